@@ -2,83 +2,874 @@
 
 //! Program subcommands for managing photo/video catalog.
 
-use std::path::Path;
+use std::{
+  fs::File,
+  io::{BufReader, stdin},
+  num::NonZeroUsize,
+  path::{Path, PathBuf},
+  thread,
+  time::{Duration, SystemTime},
+};
 
-use crate::{io, org::Organizer};
+use chrono::TimeDelta;
 
-pub fn exiftool_check() -> Result<(), String> {
-  io::exiftool_check()
+use crate::{
+  io::{self, SidecarTemplate},
+  org::{
+    CopyrightFormat,
+    ExtensionMap,
+    FileNameDatePattern,
+    FormatToKeep,
+    Geocoder,
+    IndexFormat,
+    MediaDirs,
+    MissingDatePolicy,
+    Organizer,
+    PromptConfirm,
+    ScanConfig,
+    Warning,
+  },
+};
+
+/// Settings shared across subcommands.
+#[allow(clippy::struct_excessive_bools)]
+pub struct Options {
+  /// Directory removed files are moved to.
+  pub trash: PathBuf,
+  /// How removed files are laid out in trash.
+  pub trash_layout: io::TrashLayout,
+  pub scan: ScanConfig,
+  pub sidecar_template: SidecarTemplate,
+  /// Bytes to leave free on the destination drive when moving, if checked.
+  pub min_free_space: Option<u64>,
+  /// File types whose metadata is only written to sidecars, never the media.
+  pub sidecar_only_types: Vec<String>,
+  /// Digits of sub-seconds in file names.
+  pub sub_sec_digits: u8,
+  /// Whether to name files by their local time, instead of UTC.
+  pub local_time: bool,
+  /// Style of the counter for files at the same time.
+  pub counter: io::CounterStyle,
+  /// Date tag to name files by.
+  pub rename_date_source: io::RenameDateSource,
+  /// What to do with media without a date to name it by when moving.
+  pub missing_date: MissingDatePolicy,
+  /// Whether to rename files within their current directories, rather than
+  /// moving them into date directories.
+  pub rename_only: bool,
+  /// Extensions to give moved media and their sidecars.
+  pub extension_map: ExtensionMap,
+  /// Whether to keep only one frame from each burst.
+  pub collapse_bursts: bool,
+  /// Directory media failing validation are moved to, if any.
+  pub quarantine: Option<PathBuf>,
+  /// GPX track log to write GPS coordinates from, if any.
+  pub geotag: Option<PathBuf>,
+  /// Whether geotagging overwrites existing GPS coordinates.
+  pub geotag_overwrite: bool,
+  /// `GeoNames` cities file to find locations from GPS coordinates with,
+  /// offline, instead of `ExifTool`, if any.
+  pub geocode_db: Option<PathBuf>,
+  /// Tag to write the SHA-256 of each media file's image data to, in its
+  /// sidecar, if any.
+  pub checksum_tag: Option<String>,
+  /// Patterns to infer dates of files without from their names, if any.
+  pub file_name_dates: Vec<FileNameDatePattern>,
+  /// Minimum image resolution to pass validation, if any.
+  pub min_megapixels: Option<f32>,
+  /// Additional tags required to pass validation.
+  pub required_tags: Vec<String>,
+  /// Whether `Copyright` must include the capture year to pass validation.
+  pub copyright_year: bool,
+  /// Format of `Copyright` to write and require, if not the default.
+  pub copyright_format: Option<CopyrightFormat>,
+  /// Whether a color label is required to pass validation.
+  pub require_label: bool,
+  /// Seconds after now capture dates may be to pass validation, if checked.
+  pub future_date_tolerance: Option<u32>,
+  /// Seconds apart media and sidecar capture dates may be to pass validation,
+  /// if checked.
+  pub sidecar_date_tolerance: Option<u32>,
+  /// Whether to validate sidecars and their media together.
+  pub merged_validation: bool,
+  /// Whether to validate media extensions match their detected file types.
+  pub format_check: bool,
+  /// Whether images must be stored upright to pass validation.
+  pub orientation_check: bool,
+  /// Whether the stored time zone must agree with the GPS location to pass
+  /// validation.
+  pub time_zone_check: bool,
+  /// Whether to correct mismatched extensions when moving, rather than failing
+  /// validation.
+  pub correct_extensions: bool,
+  /// Whether to relink leftover Live Photo videos to nearby images, instead of
+  /// removing them.
+  pub relink_leftover_live_photos: bool,
+  /// Whether to keep leftover sidecars instead of removing them.
+  pub keep_leftover_sidecars: bool,
+  /// Whether to ask before moving files removed by cleanup to trash.
+  pub confirm: bool,
+  /// Whether to skip all stages if no media files were loaded.
+  pub no_op_if_empty: bool,
+  /// Whether to skip removing duplicates and leftovers.
+  pub skip_cleanup: bool,
+  /// Whether to skip validation, and with it quarantine. Media are then moved
+  /// regardless of their metadata, as if forced.
+  pub skip_validate: bool,
+  /// Whether to skip moving and renaming files, leaving them in place.
+  pub skip_move: bool,
+  /// Whether to remove duplicate sidecars with the same tags as the initial.
+  pub dedupe_sidecars: bool,
+  /// Format to keep of raw+JPEG pairs, removing the other, if deduplicated.
+  pub dedupe_across_formats: Option<FormatToKeep>,
+  /// Whether to hash media up front, in parallel, and remove media identical
+  /// to others.
+  pub parallel_hash: bool,
+  /// Bits perceptual hashes may differ by for images to be reported as near
+  /// duplicates, if checked.
+  pub near_duplicates: Option<u32>,
+  /// Whether to remove near duplicates, rather than only reporting them.
+  pub remove_near_duplicates: bool,
+  /// Directory to copy original media to before writing metadata, if any.
+  pub archive: Option<PathBuf>,
+  /// Metadata copies to run at once, if not one per CPU. With one, files are
+  /// also moved in order of path, for reproducible runs.
+  pub workers: Option<NonZeroUsize>,
+  /// Tags copied from Live Photo images to videos, if not all.
+  pub live_photo_sync_tags: Vec<String>,
+  /// Seconds apart Live Photo components may be captured without a warning, if
+  /// checked.
+  pub live_photo_time_tolerance: Option<u32>,
+  /// Whether moved files keep their modify dates.
+  pub preserve_modify_dates: bool,
+  /// Whether to remove directories left empty after moving files.
+  pub remove_empty_dirs: bool,
+  /// Whether to trash media identical to another file at the same time,
+  /// rather than moving it with a counter.
+  pub trash_identical: bool,
+  /// Whether to check all files moved are at their destinations afterwards.
+  pub verify_moves: bool,
+  /// Separate directories to move photos and videos into, if any.
+  pub media_dirs: Option<MediaDirs>,
+  /// Index to load unchanged files' metadata from when organizing, if any.
+  pub cache: Option<PathBuf>,
+  /// Other catalogs to merge into the catalog when organizing.
+  pub merge: Vec<PathBuf>,
+  /// File listing the files to organize, one per line (`-` for stdin), rather
+  /// than all files in the catalog.
+  pub files_from: Option<PathBuf>,
+}
+
+impl Default for Options {
+  fn default() -> Self {
+    Self {
+      trash: PathBuf::new(),
+      trash_layout: io::TrashLayout::default(),
+      scan: ScanConfig::default(),
+      sidecar_template: SidecarTemplate::default(),
+      sidecar_only_types: Vec::new(),
+      min_free_space: None,
+      sub_sec_digits: io::SUB_SEC_DIGITS_DEFAULT,
+      local_time: false,
+      counter: io::CounterStyle::default(),
+      rename_date_source: io::RenameDateSource::default(),
+      missing_date: MissingDatePolicy::default(),
+      rename_only: false,
+      extension_map: ExtensionMap::default(),
+      collapse_bursts: false,
+      quarantine: None,
+      geotag: None,
+      geotag_overwrite: false,
+      geocode_db: None,
+      checksum_tag: None,
+      file_name_dates: Vec::new(),
+      min_megapixels: None,
+      required_tags: Vec::new(),
+      copyright_year: false,
+      copyright_format: None,
+      require_label: false,
+      future_date_tolerance: None,
+      sidecar_date_tolerance: None,
+      merged_validation: false,
+      format_check: false,
+      orientation_check: false,
+      time_zone_check: false,
+      correct_extensions: false,
+      relink_leftover_live_photos: false,
+      keep_leftover_sidecars: false,
+      confirm: false,
+      no_op_if_empty: false,
+      skip_cleanup: false,
+      skip_validate: false,
+      skip_move: false,
+      dedupe_sidecars: false,
+      dedupe_across_formats: None,
+      parallel_hash: false,
+      near_duplicates: None,
+      remove_near_duplicates: false,
+      archive: None,
+      workers: None,
+      live_photo_sync_tags: Vec::new(),
+      live_photo_time_tolerance: None,
+      preserve_modify_dates: false,
+      remove_empty_dirs: false,
+      trash_identical: false,
+      verify_moves: false,
+      media_dirs: None,
+      cache: None,
+      merge: Vec::new(),
+      files_from: None,
+    }
+  }
+}
+
+impl Options {
+  /// Trash, if it exists. For read-only commands, where trash is only needed
+  /// to skip it when scanning.
+  fn trash_if_exists(&self) -> Option<&Path> {
+    self.trash.exists().then_some(&self.trash)
+  }
+
+  /// Scan configuration for `dir`, additionally skipping quarantine if within
+  /// it, so that quarantined files aren't picked up again.
+  fn scan_for(&self, dir: impl AsRef<Path>) -> ScanConfig {
+    self.scan_for_all(&[dir])
+  }
+
+  /// Scan configuration for each of `dirs`, as `scan_for`.
+  fn scan_for_all(&self, dirs: &[impl AsRef<Path>]) -> ScanConfig {
+    let mut scan = self.scan.clone();
+    scan.exclude.extend(
+      self
+        .quarantine
+        .iter()
+        .chain(&self.archive)
+        .filter(|q| dirs.iter().any(|d| q.starts_with(d)))
+        .cloned(),
+    );
+    scan
+  }
+}
+
+pub fn exiftool_check(exiftool: &io::ExifToolConfig) -> Result<(), String> {
+  Ok(io::exiftool_check(exiftool)?)
+}
+
+/// Makes the configuration for running `ExifTool` at `path` (if not the
+/// default), decoding legacy metadata with `charsets`, and with extra `args`.
+pub fn make_exiftool_config(
+  path: Option<PathBuf>,
+  charsets: Vec<String>,
+  args: Vec<String>,
+) -> Result<io::ExifToolConfig, String> {
+  let mut exiftool = io::ExifToolConfig::default();
+
+  if let Some(path) = path {
+    exiftool.set_path(path);
+  }
+
+  if !charsets.is_empty() {
+    log::info!("Decoding metadata with charsets {}.", charsets.join(", "));
+    exiftool.set_charsets(charsets)?;
+  }
+
+  if !args.is_empty() {
+    log::warn!("Passing extra ExifTool arguments {}.", args.join(" "));
+    exiftool.set_extra_args(args);
+  }
+
+  Ok(exiftool)
 }
 
 /// Scans all files under `catalog`, performing various cleanup tasks. This will
-/// move files that are to be deleted to `catalog/.trash`.
-pub fn org(catalog: impl AsRef<Path>) -> Result<(), String> {
+/// move files that are to be deleted to trash. Cleanup, validation and moving
+/// may each be skipped (see `Options`), e.g. to only update and synchronize
+/// metadata.
+pub fn org(catalog: impl AsRef<Path>, options: &Options) -> Result<(), String> {
   log::info!("{}: Organizing.", catalog.as_ref().display());
 
-  let trash = catalog.as_ref().join(".trash");
-  let organizer = Organizer::load_catalog(&catalog, Some(trash))?;
+  let scan = with_geolocation(options, options.scan_for(&catalog));
+  let mut organizer = match (&options.files_from, &options.cache) {
+    (Some(list), _) if list.as_os_str() == "-" => {
+      Organizer::from_file_list(&catalog, Some(&options.trash), stdin().lock(), &scan)?
+    }
+    (Some(list), _) => {
+      let reader = File::open(list)
+        .map(BufReader::new)
+        .map_err(|e| format!("{}: Failed to open file list ({e}).", list.display()))?;
+      Organizer::from_file_list(&catalog, Some(&options.trash), reader, &scan)?
+    }
+    (None, Some(cache)) => {
+      Organizer::load_catalog_incremental(&catalog, Some(&options.trash), cache, &scan)?
+    }
+    (None, None) => Organizer::load_catalog(&catalog, Some(&options.trash), &scan)?,
+  };
 
-  run(organizer, catalog, true)
+  for other in &options.merge {
+    organizer.merge_catalog(other, &with_geolocation(options, options.scan_for(other)))?;
+  }
+
+  run(organizer, catalog, options, true)
 }
 
-/// Performs cleanup on `import` and then moves all *good* files to `catalog`.
-/// Other files will remain in place.
-pub fn import(catalog: impl AsRef<Path>, import: impl AsRef<Path>) -> Result<(), String> {
-  let catalog = catalog.as_ref();
-  let import = import.as_ref();
+/// Re-applies automatic metadata updates (copyright, location & time zone) to
+/// all files under `catalog`, without any cleanup or moving. Files already
+/// conforming are skipped, so this is cheap to re-run.
+pub fn metadata(catalog: impl AsRef<Path>, options: &Options) -> Result<(), String> {
+  log::info!("{}: Updating metadata.", catalog.as_ref().display());
+
+  let mut organizer = Organizer::load_catalog(
+    &catalog,
+    Some(&options.trash),
+    &with_geolocation(options, options.scan_for(&catalog)),
+  )?;
+
+  if let Some(archive) = &options.archive {
+    organizer.archive_originals(archive)?;
+  }
+
+  organizer.enable_set_copyrights_from_creator();
+  if let Some(format) = &options.copyright_format {
+    organizer.set_copyright_format(format.clone());
+  }
+  organizer.enable_set_location_from_gps();
+  organizer.enable_set_time_zone_from_gps();
+  set_geocoder(&mut organizer, options)?;
+  Ok(organizer.apply_metadata_updates()?)
+}
+
+/// Reports inconsistencies under `catalog` that `org` would act upon or flag,
+/// without changing anything. Errors if any are found.
+pub fn doctor(catalog: impl AsRef<Path>, options: &Options) -> Result<(), String> {
+  log::info!("{}: Checking.", catalog.as_ref().display());
+
+  let organizer = Organizer::load_catalog(
+    &catalog,
+    options.trash_if_exists(),
+    &options.scan_for(&catalog),
+  )?;
+
+  let issues = organizer.diagnose();
+  for issue in &issues {
+    log::warn!("{issue}");
+  }
+
+  if issues.is_empty() {
+    Ok(())
+  } else {
+    Err(format!("Found {} issue(s).", issues.len()))
+  }
+}
 
-  if import.starts_with(catalog) {
-    return Err("Cannot import into self.".to_string());
+/// Prints a consolidated report of everything wrong or noteworthy about
+/// `catalog`, as human-readable text or JSON. Makes no changes.
+pub fn health(catalog: impl AsRef<Path>, options: &Options, json: bool) -> Result<(), String> {
+  log::info!("{}: Checking health.", catalog.as_ref().display());
+
+  let mut organizer = Organizer::load_catalog(
+    &catalog,
+    options.trash_if_exists(),
+    &options.scan_for(&catalog),
+  )?;
+
+  let mut report = organizer.health();
+  report.environment.push(format!(
+    "ExifTool {}",
+    io::exiftool_version(&options.scan.exiftool)?
+  ));
+
+  if json {
+    let json = serde_json::to_string_pretty(&report)
+      .map_err(|e| format!("Unable to serialize health report ({e})."))?;
+    println!("{json}");
+  } else {
+    print!("{report}");
   }
 
+  Ok(())
+}
+
+/// Prints how many media under `catalog` were captured on each day, and how
+/// many have no capture date. Makes no changes.
+pub fn histogram(catalog: impl AsRef<Path>, options: &Options) -> Result<(), String> {
+  log::info!("{}: Counting media by day.", catalog.as_ref().display());
+
+  let organizer = Organizer::load_catalog(
+    &catalog,
+    options.trash_if_exists(),
+    &options.scan_for(&catalog),
+  )?;
+
+  print!("{}", organizer.count_by_day());
+
+  Ok(())
+}
+
+/// Moves all media under `catalog`, with their sidecars, into a directory per
+/// year of capture under `dst`.
+pub fn split_by_year(
+  catalog: impl AsRef<Path>,
+  options: &Options,
+  dst: impl AsRef<Path>,
+) -> Result<(), String> {
   log::info!(
-    "{}: Importing into {}.",
-    import.display(),
-    catalog.display()
+    "{}: Splitting by year into {}.",
+    catalog.as_ref().display(),
+    dst.as_ref().display()
   );
 
-  let organizer = Organizer::import(import)?;
+  let mut organizer =
+    Organizer::load_catalog(&catalog, Some(&options.trash), &options.scan_for(&catalog))?;
+  if options.remove_empty_dirs {
+    organizer.enable_remove_empty_dirs();
+  }
+
+  let warnings = organizer.split_by_year(dst)?;
+  log_skipped(&warnings);
+
+  Ok(())
+}
+
+/// Writes a searchable index of all media under `catalog` to `index`, without
+/// changing the catalog.
+pub fn export(
+  catalog: impl AsRef<Path>,
+  options: &Options,
+  index: impl AsRef<Path>,
+  format: IndexFormat,
+) -> Result<(), String> {
+  let organizer = Organizer::load_catalog(
+    &catalog,
+    options.trash_if_exists(),
+    &options.scan_for(&catalog),
+  )?;
+
+  Ok(organizer.export_index(index, format)?)
+}
+
+/// Catalogs all files under `path` in place, writing their metadata to `index`
+/// without moving or removing anything. Re-running only re-reads files changed
+/// since.
+pub fn index(
+  path: impl AsRef<Path>,
+  index: impl AsRef<Path>,
+  options: &Options,
+) -> Result<(), String> {
+  log::info!("{}: Indexing in place.", path.as_ref().display());
+
+  Organizer::index_only(&path, index, &options.scan_for(&path))?;
+
+  Ok(())
+}
+
+/// Prints where `org` would move each file under `catalog`, as tab-separated
+/// current and new paths, without changing anything. Assumes no other changes
+/// to the catalog (e.g. removals or metadata updates) beforehand.
+pub fn preview(catalog: impl AsRef<Path>, options: &Options) -> Result<(), String> {
+  let mut organizer = Organizer::load_catalog(
+    &catalog,
+    options.trash_if_exists(),
+    &options.scan_for(&catalog),
+  )?;
+
+  organizer.set_sub_sec_digits(options.sub_sec_digits);
+  organizer.set_counter_style(options.counter);
+  organizer.set_rename_date_source(options.rename_date_source);
+  organizer.set_missing_date_policy(options.missing_date);
+  organizer.set_extension_map(options.extension_map.clone());
+  if options.local_time {
+    organizer.enable_local_time_file_names();
+  }
+  if options.rename_only {
+    organizer.enable_rename_only();
+  }
+
+  for (src, dst) in organizer.rename_preview(&catalog, true)? {
+    println!("{}\t{}", src.display(), dst.display());
+  }
+
+  Ok(())
+}
+
+/// Moves all files under `catalog` out of their subdirectories into `catalog`
+/// itself, e.g. before re-organizing with a different file name format.
+pub fn flatten(catalog: impl AsRef<Path>, options: &Options) -> Result<(), String> {
+  log::info!("{}: Flattening.", catalog.as_ref().display());
+
+  let mut organizer =
+    Organizer::load_catalog(&catalog, Some(&options.trash), &options.scan_for(&catalog))?;
+  organizer.set_counter_style(options.counter);
+  if options.preserve_modify_dates {
+    organizer.enable_preserve_modify_dates();
+  }
+
+  let warnings = organizer.flatten_directory(&catalog)?;
+  log_skipped(&warnings);
+
+  Ok(())
+}
 
-  run(organizer, catalog, false)
+/// Permanently deletes files in trash trashed more than `older_than` ago,
+/// or all files if `None`. Only the trash directory is pruned, never the
+/// catalog.
+pub fn prune_trash(
+  catalog: impl AsRef<Path>,
+  options: &Options,
+  older_than: Option<Duration>,
+) -> Result<(), String> {
+  if !options.trash.exists() {
+    log::info!("{}: No trash to prune.", options.trash.display());
+    return Ok(());
+  }
+
+  log::info!("{}: Pruning trash.", options.trash.display());
+
+  let cutoff = older_than.map(|d| {
+    SystemTime::now()
+      .checked_sub(d)
+      .unwrap_or(SystemTime::UNIX_EPOCH)
+  });
+  let pruned = io::prune_trash(&options.trash, catalog, cutoff)?;
+  log::info!("Deleted {} file(s) from trash.", pruned.len());
+
+  Ok(())
+}
+
+/// Moves `path` (absolute, or relative to trash) from trash back to where it
+/// was removed from under `catalog`. Refuses if a file is already there.
+pub fn restore(
+  catalog: impl AsRef<Path>,
+  options: &Options,
+  path: impl AsRef<Path>,
+) -> Result<(), String> {
+  Organizer::relocate_trash(catalog, &options.trash, path)?;
+
+  Ok(())
+}
+
+/// Performs cleanup on `import` and then moves all *good* files to `catalog`.
+/// Other files will remain in place.
+pub fn import(
+  catalog: impl AsRef<Path>,
+  imports: &[impl AsRef<Path>],
+  options: &Options,
+) -> Result<(), String> {
+  let catalog = catalog.as_ref();
+
+  for import in imports {
+    let import = import.as_ref();
+
+    if import.starts_with(catalog) {
+      return Err("Cannot import into self.".to_string());
+    }
+
+    log::info!(
+      "{}: Importing into {}.",
+      import.display(),
+      catalog.display()
+    );
+  }
+
+  let organizer = Organizer::import(
+    imports,
+    &with_geolocation(options, options.scan_for_all(imports)),
+  )?;
+
+  run(organizer, catalog, options, false)
 }
 
 /// Runs `organizer` with output to `catalog`.
-fn run(mut organizer: Organizer, catalog: impl AsRef<Path>, force_move: bool) -> Result<(), String> {
+fn run(
+  mut organizer: Organizer,
+  catalog: impl AsRef<Path>,
+  options: &Options,
+  force_move: bool,
+) -> Result<(), String> {
+  if options.no_op_if_empty && !organizer.has_media() {
+    log::info!("No media files found.");
+    return Ok(());
+  }
+  configure(&mut organizer, options);
+
+  organizer.set_trash_layout(options.trash_layout)?;
+
   // 1. Remove duplicates and leftovers.
 
-  organizer.remove_live_photo_leftovers()?;
-  organizer.remove_live_photo_duplicates()?;
-  organizer.remove_sidecar_leftovers()?;
+  if !options.skip_cleanup {
+    clean_up(&mut organizer, options)?;
+  }
 
   // 2. Create sidecars for files without.
 
   organizer.create_missing_sidecars()?;
 
+  if let Some(archive) = &options.archive {
+    organizer.archive_originals(archive)?;
+  }
+
   // 3. Automatic metadata adjustments.
 
+  if let Some(track) = &options.geotag {
+    organizer.geotag_from_gpx(track, options.geotag_overwrite)?;
+  }
+
+  if !options.file_name_dates.is_empty() {
+    organizer.infer_dates_from_filename(&options.file_name_dates)?;
+  }
+
   organizer.enable_align_mwg_tags();
   organizer.enable_set_copyrights_from_creator();
   organizer.enable_set_location_from_gps();
   organizer.enable_set_time_zone_from_gps();
+  set_geocoder(&mut organizer, options)?;
   organizer.apply_metadata_updates()?;
 
   // 4. Metadata synchronization across files.
 
   organizer.sync_live_photo_metadata()?;
   organizer.sync_dupe_metadata()?;
-  // organizer.sync_media_metadata()?;
+  // organizer.sync_media_metadata(&[Codec::Raw])?;
+  if let Some(tag) = &options.checksum_tag {
+    organizer.write_checksums(tag)?;
+  }
 
   // 5. Validate metadata.
 
+  if !options.skip_validate {
+    enable_validation(&mut organizer, options);
+    organizer.validate();
+
+    if let Some(quarantine) = &options.quarantine {
+      organizer.quarantine_invalid(quarantine)?;
+    }
+  }
+
+  let mut warnings = organizer.take_warnings();
+  if !warnings.is_empty() {
+    log::warn!("{} warning(s) before moving files.", warnings.len());
+  }
+
+  // 6. Move/rename files.
+
+  if options.skip_move {
+    log_skipped(&warnings);
+    return Ok(());
+  }
+
+  // Without validation, no media would pass to be moved.
+  let force_move = force_move || options.skip_validate;
+  organizer.set_progress_callback(|moved, total, path| {
+    log::debug!("{}: Moved ({moved}/{total}).", path.display());
+  });
+  let planned = options
+    .verify_moves
+    .then(|| organizer.rename_preview(&catalog, force_move))
+    .transpose()?;
+  warnings.extend(organizer.move_and_rename_files(&catalog, force_move)?);
+
+  log_skipped(&warnings);
+
+  if let Some(planned) = planned {
+    let missing = Organizer::verify_moves(&planned);
+    for path in &missing {
+      log::error!("{}: Missing after move.", path.display());
+    }
+    if !missing.is_empty() {
+      return Err(format!(
+        "{} moved file(s) missing at their destination.",
+        missing.len()
+      ));
+    }
+  }
+
+  Ok(())
+}
+
+/// Removes duplicates and leftovers from `organizer`, as configured by
+/// `options`, confirming first if set.
+fn clean_up(organizer: &mut Organizer, options: &Options) -> Result<(), String> {
+  if options.confirm {
+    organizer.enable_confirm_removals();
+  }
+  if options.collapse_bursts {
+    organizer.collapse_bursts()?;
+  }
+  if options.keep_leftover_sidecars {
+    organizer.enable_keep_leftover_sidecars();
+  }
+  if options.relink_leftover_live_photos {
+    organizer.enable_relink_leftover_live_photos();
+  }
+  organizer.remove_live_photo_leftovers()?;
+  organizer.remove_live_photo_duplicates()?;
+  organizer.remove_sidecar_leftovers()?;
+  if options.dedupe_sidecars {
+    organizer.deduplicate_sidecars()?;
+  }
+  if let Some(keep) = options.dedupe_across_formats {
+    organizer.dedupe_across_formats(keep)?;
+  }
+  if options.parallel_hash {
+    organizer.remove_identical_media()?;
+  }
+  if let Some(max_distance) = options.near_duplicates {
+    organizer.dedupe_by_perceptual_hash(max_distance, options.remove_near_duplicates)?;
+  }
+  organizer.confirm_removals(&mut PromptConfirm)?;
+
+  Ok(())
+}
+
+/// Enables the validation checks in `options` on `organizer`.
+fn enable_validation(organizer: &mut Organizer, options: &Options) {
   organizer.enable_attribution_validation();
+  if options.copyright_year {
+    organizer.enable_copyright_year_validation();
+  }
   organizer.enable_camera_validation();
   organizer.enable_date_time_validation();
   organizer.enable_location_validation();
-  organizer.validate();
+  if options.time_zone_check {
+    organizer.enable_time_zone_validation();
+  }
+  if let Some(min_megapixels) = options.min_megapixels {
+    organizer.enable_resolution_validation(min_megapixels);
+  }
+  organizer.enable_required_tags_validation(options.required_tags.iter().cloned());
+  if options.require_label {
+    organizer.enable_label_validation();
+  }
+  if let Some(tolerance) = options.future_date_tolerance {
+    organizer.enable_future_date_validation(TimeDelta::seconds(tolerance.into()));
+  }
+  if let Some(tolerance) = options.sidecar_date_tolerance {
+    organizer.enable_sidecar_date_validation(TimeDelta::seconds(tolerance.into()));
+  }
+  if options.merged_validation {
+    organizer.enable_merged_validation();
+  }
+  if options.orientation_check {
+    organizer.enable_orientation_validation();
+  }
+  if options.correct_extensions {
+    organizer.enable_format_correction();
+  } else if options.format_check {
+    organizer.enable_format_validation();
+  }
+}
 
-  // 6. Move/rename files.
+/// Returns `scan` reading the location `ExifTool` derives from GPS coordinates,
+/// for `enable_set_location_from_gps`, unless an offline geocoder is used
+/// instead.
+fn with_geolocation(options: &Options, mut scan: ScanConfig) -> ScanConfig {
+  if options.geocode_db.is_none() {
+    scan.exiftool.enable_geolocation();
+  }
+  scan
+}
+
+/// Loads the offline geocoding database in `options` into `organizer`, if set.
+fn set_geocoder(organizer: &mut Organizer, options: &Options) -> Result<(), String> {
+  if let Some(db) = &options.geocode_db {
+    organizer.set_geocoder(Geocoder::load(db)?);
+  }
+
+  Ok(())
+}
+
+/// Applies the settings in `options` that are used across stages.
+fn configure(organizer: &mut Organizer, options: &Options) {
+  organizer.set_sidecar_template(options.sidecar_template.clone());
+  organizer.set_sidecar_only_types(options.sidecar_only_types.iter().cloned());
+  if let Some(format) = &options.copyright_format {
+    organizer.set_copyright_format(format.clone());
+  }
+  organizer.set_sub_sec_digits(options.sub_sec_digits);
+  organizer.set_counter_style(options.counter);
+  organizer.set_rename_date_source(options.rename_date_source);
+  organizer.set_missing_date_policy(options.missing_date);
+  organizer.set_extension_map(options.extension_map.clone());
+  if options.local_time {
+    organizer.enable_local_time_file_names();
+  }
+  if options.rename_only {
+    organizer.enable_rename_only();
+  }
+  if options.preserve_modify_dates {
+    organizer.enable_preserve_modify_dates();
+  }
+  if options.remove_empty_dirs {
+    organizer.enable_remove_empty_dirs();
+  }
+  if options.trash_identical {
+    organizer.enable_trash_identical_collisions();
+  }
+  if let Some(dirs) = &options.media_dirs {
+    organizer.set_media_dirs(dirs.clone());
+  }
+  if let Some(headroom) = options.min_free_space {
+    organizer.enable_free_space_check(headroom);
+  }
+  organizer.set_workers(
+    options
+      .workers
+      .unwrap_or_else(|| thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)),
+  );
+  if options.workers == Some(NonZeroUsize::MIN) {
+    organizer.enable_ordered_moves();
+  }
+  organizer.set_live_photo_sync_tags(options.live_photo_sync_tags.iter().cloned());
+  if let Some(tolerance) = options.live_photo_time_tolerance {
+    organizer.enable_live_photo_time_check(TimeDelta::seconds(tolerance.into()));
+  }
+}
+
+/// Logs how many files were skipped after errors, if any.
+fn log_skipped(warnings: &[Warning]) {
+  let skipped = warnings
+    .iter()
+    .filter(|w| matches!(w, Warning::SkippedAfterError(_)))
+    .count();
+  if skipped > 0 {
+    log::warn!("{skipped} file(s) skipped after errors.");
+  }
+}
+
+#[cfg(test)]
+mod test_doctor {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn errors_if_issues_found() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image_leftover.jpg.xmp": {},
+    );
+
+    assert_err!(
+      doctor(d.root(), &Options {
+        trash: d.trash().to_path_buf(),
+        ..Default::default()
+      }),
+      "Found 2 issue(s)."
+    );
+    assert_dir!(d, ["image.jpg", "image_leftover.jpg.xmp"]);
+  }
 
-  organizer.move_and_rename_files(catalog, force_move)
+  #[test]
+  fn succeeds_if_no_issues_found() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+    );
+
+    doctor(d.root(), &Options {
+      trash: d.trash().to_path_buf(),
+      ..Default::default()
+    })
+    .unwrap();
+  }
 }
 
 #[cfg(test)]
@@ -93,8 +884,250 @@ mod test_import {
     );
 
     assert_err!(
-      import(d.root(), d.get_path("import")),
+      import(d.root(), &[d.get_path("import")], &Options::default()),
       "Cannot import into self."
     );
   }
+
+  #[test]
+  fn moves_unvalidated_files_if_skip_validate() {
+    let d = test_dir!(
+      "import/image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "OffsetTimeOriginal": "+00:00" },
+    );
+
+    import(d.get_path("catalog"), &[d.get_path("import")], &Options {
+      trash: d.trash().to_path_buf(),
+      required_tags: vec!["Artist".to_string()],
+      skip_validate: true,
+      ..Default::default()
+    })
+    .unwrap();
+
+    assert_dir!(d, [
+      "catalog/2000/01/000101_000000000.jpg",
+      "catalog/2000/01/000101_000000000.jpg.xmp",
+    ]);
+  }
+}
+
+#[cfg(test)]
+mod test_prune_trash {
+  use std::fs;
+
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn prunes_only_old_files() {
+    let d = test_dir!();
+    let old = d.get_trash("old.jpg");
+    let recent = d.get_trash("recent.jpg");
+    fs::write(&old, "").unwrap();
+    fs::write(&recent, "").unwrap();
+    io::set_modify_time(&old, SystemTime::now() - Duration::from_hours(48)).unwrap();
+
+    let options = Options {
+      trash: d.trash().to_path_buf(),
+      ..Default::default()
+    };
+    prune_trash(d.root(), &options, Some(Duration::from_hours(24))).unwrap();
+
+    assert!(!old.exists());
+    assert!(recent.exists());
+  }
+
+  #[test]
+  fn succeeds_if_trash_missing() {
+    let d = test_dir!();
+
+    let options = Options {
+      trash: d.get_path("missing"),
+      ..Default::default()
+    };
+    prune_trash(d.root(), &options, None).unwrap();
+  }
+}
+
+#[cfg(test)]
+mod test_metadata {
+  use std::fs;
+
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn skips_conforming_files_on_rerun() {
+    let d = test_dir!(
+      "image.jpg": {
+        "Creator": "Creator",
+        "GPSLatitude": "47.6061",
+        "GPSLatitudeRef": "N",
+        "GPSLongitude": "122.3328",
+        "GPSLongitudeRef": "W",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+        "OffsetTimeOriginal": "+00:00",
+      },
+    );
+
+    metadata(d.root(), &Options {
+      trash: d.trash().to_path_buf(),
+      ..Default::default()
+    })
+    .unwrap();
+
+    assert_tag!(d, "image.jpg", "Copyright", "Copyright Creator");
+    assert_tag!(d, "image.jpg", "City", "Seattle");
+    assert_tag!(d, "image.jpg", "OffsetTimeOriginal", "-08:00");
+
+    let modified = fs::metadata(d.get_path("image.jpg"))
+      .and_then(|m| m.modified())
+      .unwrap();
+
+    metadata(d.root(), &Options {
+      trash: d.trash().to_path_buf(),
+      ..Default::default()
+    })
+    .unwrap();
+
+    assert_eq!(
+      fs::metadata(d.get_path("image.jpg"))
+        .and_then(|m| m.modified())
+        .unwrap(),
+      modified
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_org {
+  use std::fs;
+
+  use super::*;
+  use crate::{org::ErrorPolicy, testing::*};
+
+  #[test]
+  fn continues_past_unreadable_file() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "OffsetTimeOriginal": "+00:00" },
+      "image2.jpg": {},
+      "image3.jpg": { "DateTimeOriginal": "2000-01-03T00:00:00", "OffsetTimeOriginal": "+00:00" },
+    );
+    fs::write(d.get_path("image2.jpg"), "Not an image.").unwrap();
+
+    org(d.root(), &Options {
+      trash: d.trash().to_path_buf(),
+      scan: ScanConfig {
+        on_error: ErrorPolicy::Continue,
+        ..Default::default()
+      },
+      ..Default::default()
+    })
+    .unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000.jpg.xmp",
+      "2000/01/000103_000000000.jpg",
+      "2000/01/000103_000000000.jpg.xmp",
+      "image2.jpg",
+    ]);
+  }
+
+  #[test]
+  fn succeeds_on_empty_dir_if_no_op_if_empty() {
+    let d = test_dir!();
+
+    org(d.root(), &Options {
+      trash: d.trash().to_path_buf(),
+      no_op_if_empty: true,
+      ..Default::default()
+    })
+    .unwrap();
+
+    assert_dir!(d, []);
+  }
+
+  #[test]
+  fn skips_stages_if_no_op_if_empty() {
+    let d = test_dir!(
+      "image.jpg.xmp": {},
+    );
+
+    org(d.root(), &Options {
+      trash: d.trash().to_path_buf(),
+      no_op_if_empty: true,
+      ..Default::default()
+    })
+    .unwrap();
+
+    assert_dir!(d, ["image.jpg.xmp"]);
+  }
+
+  #[test]
+  fn keeps_leftovers_if_skip_cleanup() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "OffsetTimeOriginal": "+00:00" },
+      "other.jpg.xmp": {},
+    );
+
+    org(d.root(), &Options {
+      trash: d.trash().to_path_buf(),
+      skip_cleanup: true,
+      ..Default::default()
+    })
+    .unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000.jpg.xmp",
+      "other.jpg.xmp",
+    ]);
+    assert_trash!(d, []);
+  }
+
+  #[test]
+  fn leaves_files_in_place_if_skip_move() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "OffsetTimeOriginal": "+00:00" },
+    );
+
+    org(d.root(), &Options {
+      trash: d.trash().to_path_buf(),
+      skip_move: true,
+      ..Default::default()
+    })
+    .unwrap();
+
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp"]);
+  }
+}
+
+#[cfg(test)]
+mod test_scan_for {
+  use super::*;
+
+  #[test]
+  fn excludes_quarantine_within_dir() {
+    let options = Options {
+      quarantine: Some(PathBuf::from("/catalog/quarantine")),
+      ..Default::default()
+    };
+
+    assert_eq!(options.scan_for("/catalog").exclude, [PathBuf::from(
+      "/catalog/quarantine"
+    )]);
+    assert!(options.scan_for("/import").exclude.is_empty());
+  }
+
+  #[test]
+  fn excludes_quarantine_within_any_dir() {
+    let options = Options {
+      quarantine: Some(PathBuf::from("/catalog/quarantine")),
+      ..Default::default()
+    };
+
+    assert_eq!(options.scan_for_all(&["/import", "/catalog"]).exclude, [
+      PathBuf::from("/catalog/quarantine")
+    ]);
+  }
 }
@@ -0,0 +1,98 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Catalog errors: Kinds of errors returned by the `Organizer` and file
+//! functions, so that callers can match on them rather than on messages.
+
+use std::{
+  error::Error,
+  fmt::{self, Display, Formatter},
+  path::PathBuf,
+};
+
+/// An error organizing a catalog. Each variant carries the full message (or,
+/// for paths, what the path is for), so that `Display` is unchanged from when
+/// errors were plain strings.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CatalogError {
+  /// `ExifTool` failed to run, or did not do what was asked.
+  ExifTool(String),
+  /// Filesystem operation failed, or would overwrite another file.
+  Io(String),
+  /// Path which must be absolute is not, with what it is (e.g. "Catalog
+  /// path").
+  PathNotAbsolute(PathBuf, &'static str),
+  /// Path which must exist does not, with what it is.
+  PathNotFound(PathBuf, &'static str),
+  /// Output, index or metadata could not be parsed.
+  Parse(String),
+  /// File is not of a type or configuration which can be organized (e.g. a
+  /// Live Photo with an unknown codec).
+  InvalidFile(String),
+  /// Errors from several files in a batch, each of which was attempted.
+  Multiple(Vec<CatalogError>),
+}
+
+impl Display for CatalogError {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Self::ExifTool(e) | Self::Io(e) | Self::Parse(e) | Self::InvalidFile(e) => write!(f, "{e}"),
+      Self::PathNotAbsolute(p, what) => write!(f, "{}: {what} is not absolute.", p.display()),
+      Self::PathNotFound(p, what) => write!(f, "{}: {what} does not exist.", p.display()),
+      Self::Multiple(errors) => {
+        write!(f, "{} errors:", errors.len())?;
+        for e in errors {
+          write!(f, "\n{e}")?;
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+impl Error for CatalogError {}
+
+/// For the command line, where errors are only reported.
+impl From<CatalogError> for String {
+  fn from(e: CatalogError) -> Self {
+    e.to_string()
+  }
+}
+
+#[cfg(test)]
+mod test_display {
+  use std::path::Path;
+
+  use super::*;
+
+  #[test]
+  fn formats_paths_with_what_they_are() {
+    assert_eq!(
+      CatalogError::PathNotAbsolute(Path::new("a/b").to_path_buf(), "Catalog path").to_string(),
+      "a/b: Catalog path is not absolute."
+    );
+    assert_eq!(
+      CatalogError::PathNotFound(Path::new("/a/b").to_path_buf(), "GPX track").to_string(),
+      "/a/b: GPX track does not exist."
+    );
+  }
+
+  #[test]
+  fn formats_each_of_multiple_on_own_line() {
+    assert_eq!(
+      CatalogError::Multiple(vec![
+        CatalogError::Io("First.".to_string()),
+        CatalogError::ExifTool("Second.".to_string()),
+      ])
+      .to_string(),
+      "2 errors:\nFirst.\nSecond."
+    );
+  }
+
+  #[test]
+  fn formats_messages_as_is() {
+    assert_eq!(
+      CatalogError::Io("Message.".to_string()).to_string(),
+      "Message."
+    );
+  }
+}
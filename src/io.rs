@@ -3,15 +3,25 @@
 //! Functions for manipulating files.
 
 use std::{
+  collections::HashMap,
+  env,
   ffi::{OsStr, OsString},
+  fmt::{self, Display, Formatter},
   fs,
+  io::{self as std_io, BufRead, BufReader, Read, Write},
   path::{Path, PathBuf},
-  process::Command,
+  process::{Child, ChildStdout, Command, Output, Stdio},
+  thread::{self, JoinHandle},
+  time::{Duration, SystemTime},
 };
 
-use regex::Regex;
+use chrono::{FixedOffset, Local, NaiveDateTime, Timelike};
+use serde::Deserialize;
 
-use crate::prim::Metadata;
+use crate::{
+  error::CatalogError,
+  prim::{self, CopyrightFormat, Metadata},
+};
 
 /// All `ExifTool` operations will use this format when extracting date & time.
 /// Follows RFC 3339 format for easy parsing with `chrono`.
@@ -26,99 +36,511 @@ pub const DATETIME_READ_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%f%:z";
 /// Input: January 1st, 2024 at 12:30:01.050, second image at this exact time.
 /// Output: `2024/01/240101_123001050_b.jpg`.
 /// darktable duplicate: `2024/01/240101_123001050_b_01.jpg.xmp`.
-const DATETIME_WRITE_FORMAT: &str = "%Y/%m/%y%m%d_%H%M%S%-3f%+lc";
+///
+/// Sub-seconds are written with `sub_sec_digits` digits, e.g. 6 to keep burst
+/// shots with microsecond differences distinct. The counter for files at the
+/// same time is written in the `counter` style. Without `date_dirs`, the
+/// `yyyy/mm/` directories are left out.
+fn make_date_time_write_format(format: FileNameFormat) -> String {
+  let sub_sec_digits = format.sub_sec_digits;
+  let counter = match format.counter {
+    CounterStyle::Letter => "%+lc",
+    CounterStyle::Numeric => "%+3c",
+  };
+  let dirs = if format.date_dirs { "%Y/%m/" } else { "" };
+  format!("{dirs}%y%m%d_%H%M%S%-{sub_sec_digits}f{counter}")
+}
+
+/// Style of the counter appended to names of files at the same time.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum CounterStyle {
+  /// Letter, e.g. `_b` for the second file.
+  #[default]
+  Letter,
+  /// Three-digit number, e.g. `_001` for the second file. Kept at three digits
+  /// so as not to be mistaken for darktable's two-digit duplicate number.
+  Numeric,
+}
+
+/// Date tag to name moved files by, each with its own fallbacks for files
+/// without it.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum RenameDateSource {
+  /// `DateTimeOriginal`, i.e. when the shutter was actuated.
+  #[default]
+  Capture,
+  /// `CreateDate`, i.e. when the file was created (e.g. when scanning film).
+  Creation,
+  /// `ModifyDate`, i.e. when the file was last edited, else `FileModifyDate`.
+  Modify,
+  /// Only `FileModifyDate`, for media without any other date (see
+  /// `org::MissingDatePolicy`).
+  #[value(skip)]
+  FileModify,
+}
+
+impl RenameDateSource {
+  /// Tags to name files by, in order of precedence.
+  fn tags(self) -> &'static [&'static str] {
+    match self {
+      Self::Capture => &[
+        "SubSecDateTimeOriginal",
+        "DateTimeOriginal",
+        "SubSecCreateDate",
+        "CreateDate",
+        "MediaCreateDate",
+        "TrackCreateDate",
+      ],
+      Self::Creation => &[
+        "SubSecCreateDate",
+        "CreateDate",
+        "MediaCreateDate",
+        "TrackCreateDate",
+        "SubSecDateTimeOriginal",
+        "DateTimeOriginal",
+      ],
+      Self::Modify => &["SubSecModifyDate", "ModifyDate", "FileModifyDate"],
+      Self::FileModify => &["FileModifyDate"],
+    }
+  }
+}
+
+/// How files moved to trash are laid out within it.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum TrashLayout {
+  /// Directly under trash, maintaining their directory structure. Files
+  /// trashed under the same path as earlier ones collide.
+  #[default]
+  Flat,
+  /// Under a directory per run, named by its start time (e.g.
+  /// `2024-01-02T1530`), so that runs never collide.
+  Timestamped,
+}
+
+/// Format of directories per run in trash, for `TrashLayout::Timestamped`.
+const TRASH_RUN_FORMAT: &str = "%Y-%m-%dT%H%M";
+
+/// Default number of sub-second digits in file names (i.e. milliseconds).
+pub const SUB_SEC_DIGITS_DEFAULT: u8 = 3;
+
+/// Options for the date and time in names of moved files.
+#[derive(Clone, Copy)]
+pub struct FileNameFormat {
+  /// Digits of sub-seconds.
+  pub sub_sec_digits: u8,
+  /// Whether to use the file's own time zone, rather than UTC, so that e.g. a
+  /// late evening photo is named (and foldered) by its local date.
+  pub local_time:     bool,
+  /// Style of the counter for files at the same time.
+  pub counter:        CounterStyle,
+  /// Date tag to name files by.
+  pub date_source:    RenameDateSource,
+  /// Whether to nest files under `yyyy/mm` directories, rather than naming them
+  /// directly in the destination.
+  pub date_dirs:      bool,
+}
+
+impl Default for FileNameFormat {
+  fn default() -> Self {
+    Self {
+      sub_sec_digits: SUB_SEC_DIGITS_DEFAULT,
+      local_time:     false,
+      counter:        CounterStyle::default(),
+      date_source:    RenameDateSource::default(),
+      date_dirs:      true,
+    }
+  }
+}
 
 /// When using `ExifTool` to read metadata, this converts the time zone to UTC
 /// in RFC 3339 format, and puts the output into JSON for easy parsing with
 /// `serde_json`.
 const READ_ARGS: [&str; 3] = ["-d", DATETIME_READ_FORMAT, "-json"];
 
+/// Arguments for reading the location `ExifTool` derives from GPS coordinates
+/// (`Geolocation*`), so it can be compared against what is stored.
+const GEOLOCATION_ARGS: [&str; 2] = ["-api", "geolocation"];
+
 /// Arguments for converting metadata from EXIF to XMP format.
 const COPY_EXIF_2_XMP: &str = include_str!("../third_party/exiftool/arg_files/exif2xmp.args");
 
 /// Arguments for converting metadata from XMP to EXIF format.
 const COPY_XMP_2_EXIF: &str = include_str!("../third_party/exiftool/arg_files/xmp2exif.args");
 
+/// Log target for `ExifTool` command lines, enabled separately from trace.
+pub const LOG_TARGET_EXIFTOOL: &str = "exiftool";
+
 /// Minimum supported (tested) version of `ExifTool`.
 const EXIFTOOL_MIN_VERSION: (u32, u32) = (13, 29);
 
-/// Returns an iterator over the `ExifTool` arguments needed to supporting
-/// copying between any two files containing XMP and/or EXIF metadata.
-fn make_copy_args<'a>() -> impl Iterator<Item = &'a OsStr> {
-  // Using `-all:all < X:all` to preserve family 1 group (e.g. `XMP-exif`).
-  // See <https://exiftool.org/metafiles.html>.
-  COPY_EXIF_2_XMP
-    .lines()
-    .filter(|l| l.trim_start().starts_with('-'))
-    .chain(
-      COPY_XMP_2_EXIF
+/// Environment variable with the path of an `ExifTool` to run instead of the
+/// bundled one (e.g. a system install).
+pub const EXIFTOOL_PATH_VAR: &str = "CATALOG_EXIFTOOL";
+
+/// Settings for running `ExifTool`, passed to each function running it, so
+/// that every `Organizer` (or command) runs it as configured, regardless of any
+/// other.
+#[derive(Clone, Debug, Default)]
+pub struct ExifToolConfig {
+  /// `ExifTool` to run, if not that in `EXIFTOOL_PATH_VAR` or the bundled one.
+  path:         Option<PathBuf>,
+  /// `-charset` arguments for decoding legacy metadata (e.g. Latin-1 IPTC),
+  /// passed whenever metadata is read or copied.
+  charset_args: Vec<OsString>,
+  /// Raw arguments appended to every `ExifTool` command.
+  extra_args:   Vec<OsString>,
+  /// Whether metadata is read with `GEOLOCATION_ARGS`.
+  geolocation:  bool,
+}
+
+impl ExifToolConfig {
+  /// Runs the `ExifTool` at `path`, rather than that in `EXIFTOOL_PATH_VAR` if
+  /// set, else the bundled one.
+  pub fn set_path(&mut self, path: PathBuf) {
+    self.path = Some(path);
+  }
+
+  /// Sets the character sets `ExifTool` decodes metadata of each type with, as
+  /// `TYPE=CHARSET` (e.g. `IPTC=Latin1`), for files written without marking
+  /// theirs. See `-charset` in <https://exiftool.org/exiftool_pod.html>.
+  pub fn set_charsets(
+    &mut self,
+    charsets: impl IntoIterator<Item = String>,
+  ) -> Result<(), CatalogError> {
+    self.charset_args = make_charset_args(charsets)?;
+    Ok(())
+  }
+
+  /// Sets raw arguments appended to every `ExifTool` command (e.g. `-api
+  /// LargeFileSupport=1`). These are not checked, so may break parsing of its
+  /// output or write to files unexpectedly.
+  pub fn set_extra_args(&mut self, args: impl IntoIterator<Item = String>) {
+    self.extra_args = args.into_iter().map(OsString::from).collect();
+  }
+
+  /// Reads the location `ExifTool` derives from GPS coordinates along with
+  /// metadata (e.g. for `Organizer::enable_set_location_from_gps`). This slows
+  /// reading, so is off unless enabled.
+  pub fn enable_geolocation(&mut self) {
+    self.geolocation = true;
+  }
+
+  /// Returns the path of the `ExifTool` to run.
+  fn program(&self) -> PathBuf {
+    resolve_exiftool_path(self.path.clone(), env::var_os(EXIFTOOL_PATH_VAR))
+  }
+
+  /// Returns the `-charset` arguments, if any.
+  fn charset_args(&self) -> impl Iterator<Item = &OsStr> {
+    self.charset_args.iter().map(OsString::as_os_str)
+  }
+
+  /// Returns the `ExifTool` arguments for reading metadata (`READ_ARGS`), with
+  /// any charsets, and geolocation if enabled.
+  fn read_args(&self) -> impl Iterator<Item = &OsStr> {
+    let geolocation: &[&str] = if self.geolocation {
+      &GEOLOCATION_ARGS
+    } else {
+      &[]
+    };
+    self
+      .charset_args()
+      .chain(geolocation.iter().map(OsStr::new))
+      .chain(READ_ARGS.map(OsStr::new))
+  }
+
+  /// Returns an iterator over the `ExifTool` arguments needed to supporting
+  /// copying between any two files containing XMP and/or EXIF metadata, with
+  /// any charsets.
+  fn copy_args(&self) -> impl Iterator<Item = &OsStr> {
+    // Using `-all:all < X:all` to preserve family 1 group (e.g. `XMP-exif`).
+    // See <https://exiftool.org/metafiles.html>.
+    self.charset_args().chain(
+      COPY_EXIF_2_XMP
         .lines()
-        .filter(|l| l.trim_start().starts_with('-')),
+        .filter(|l| l.trim_start().starts_with('-'))
+        .chain(
+          COPY_XMP_2_EXIF
+            .lines()
+            .filter(|l| l.trim_start().starts_with('-')),
+        )
+        .chain(["-all:all<XMP:all", "-all:all<EXIF:all"])
+        .map(OsStr::new),
     )
-    .chain(["-all:all<XMP:all", "-all:all<EXIF:all"])
-    .map(OsStr::new)
+  }
+}
+
+/// Picks the `ExifTool` to run: `path` if given, else `var` if set and not
+/// empty, else the bundled one.
+fn resolve_exiftool_path(path: Option<PathBuf>, var: Option<OsString>) -> PathBuf {
+  path
+    .or_else(|| var.filter(|v| !v.is_empty()).map(PathBuf::from))
+    .unwrap_or_else(|| PathBuf::from(env!("OUT_DIR")).join("exiftool"))
+}
+
+/// Makes `-charset` arguments for each of `charsets`. Output (`ExifTool`) and
+/// bare charsets are refused, as output must stay UTF-8 for parsing.
+fn make_charset_args(
+  charsets: impl IntoIterator<Item = String>,
+) -> Result<Vec<OsString>, CatalogError> {
+  let mut args = Vec::new();
+
+  for charset in charsets {
+    match charset.split_once('=') {
+      Some((kind, name))
+        if !kind.is_empty() && !name.is_empty() && !kind.eq_ignore_ascii_case("exiftool") => {}
+      _ => {
+        return Err(CatalogError::Parse(format!(
+          "{charset}: Charset must be as `TYPE=CHARSET` (e.g. `IPTC=Latin1`), for a type other \
+           than `ExifTool`."
+        )));
+      }
+    }
+
+    args.extend([OsString::from("-charset"), OsString::from(charset)]);
+  }
+
+  Ok(args)
 }
 
 /// Copies metadata from `file_src` to `file_dst`, and returns the new metadata
 /// from `file_dst`.
 pub fn copy_metadata(
+  exiftool: &ExifToolConfig,
   file_src: impl AsRef<Path>,
   file_dst: impl AsRef<Path>,
-) -> Result<Metadata, String> {
+) -> Result<Metadata, CatalogError> {
   let file_src = make_canonical(file_src)?;
   let file_dst = make_canonical(file_dst)?;
 
   let mut args = Vec::from([OsStr::new("-tagsFromFile"), file_src.as_os_str()]);
-  args.extend(make_copy_args());
+  args.extend(exiftool.copy_args());
   args.push(file_dst.as_os_str());
-  run_exiftool(None::<&Path>, args)?;
+  run_exiftool(exiftool, None::<&Path>, args)?;
+
+  read_metadata(exiftool, &file_dst)
+}
+
+/// Copies only `tags` (by `ExifTool` name, e.g. `DateTimeOriginal`) from
+/// `file_src` to `file_dst`, leaving all others unchanged, and returns the new
+/// metadata from `file_dst`. Tags are copied by name, so both files should
+/// hold the same kind of metadata (e.g. both XMP sidecars).
+pub fn copy_metadata_tags(
+  exiftool: &ExifToolConfig,
+  file_src: impl AsRef<Path>,
+  file_dst: impl AsRef<Path>,
+  tags: &[String],
+) -> Result<Metadata, CatalogError> {
+  let file_src = make_canonical(file_src)?;
+  let file_dst = make_canonical(file_dst)?;
 
-  read_metadata(&file_dst)
+  if tags.is_empty() {
+    return read_metadata(exiftool, &file_dst);
+  }
+
+  let mut args = exiftool
+    .charset_args()
+    .map(OsString::from)
+    .collect::<Vec<_>>();
+  args.extend([OsString::from("-tagsFromFile"), file_src.into()]);
+  args.extend(tags.iter().map(|t| OsString::from(format!("-{t}<{t}"))));
+  args.push(file_dst.clone().into());
+  run_exiftool(exiftool, None::<&Path>, args)?;
+
+  read_metadata(exiftool, &file_dst)
+}
+
+/// Tags to seed newly created XMP sidecars with, where not already copied
+/// from the media file.
+#[derive(Clone, Default)]
+pub struct SidecarTemplate {
+  /// Written to `Creator`, with `Copyright` derived from it.
+  pub creator:          Option<String>,
+  /// Format `Copyright` is derived as, with the media's capture year, else
+  /// `CopyrightFormat::default()`. Skipped if it needs a year the media lacks.
+  pub copyright_format: Option<CopyrightFormat>,
 }
 
-/// Creates XMP for `file_media`, and reads back its metadata.
-pub fn create_xmp(file_media: impl AsRef<Path>) -> Result<Metadata, String> {
+/// Creates XMP for `file_media`, seeded from `template`, and reads back its
+/// metadata.
+pub fn create_xmp(
+  exiftool: &ExifToolConfig,
+  file_media: impl AsRef<Path>,
+  template: &SidecarTemplate,
+) -> Result<Metadata, CatalogError> {
   let file_media = make_canonical(file_media)?;
 
   if file_media.extension().is_none_or(|e| e == "xmp") {
-    return Err(format!(
+    return Err(CatalogError::InvalidFile(format!(
       "{}: Cannot create XMP (invalid extension).",
       file_media.display()
-    ));
+    )));
   }
 
   let mut file_xmp = file_media.clone();
   file_xmp.add_extension("xmp");
 
   if file_xmp.exists() {
-    return Err(format!(
+    return Err(CatalogError::Io(format!(
       "{}: Cannot create XMP (file already exists).",
       file_xmp.display()
-    ));
+    )));
   }
 
   let mut args = Vec::from([OsStr::new("-tagsFromFile"), file_media.as_os_str()]);
-  args.extend(make_copy_args());
+  args.extend(exiftool.copy_args());
   args.push(file_xmp.as_os_str());
-  run_exiftool(None::<&Path>, args)?;
+  run_exiftool(exiftool, None::<&Path>, args)?;
+
+  let metadata = read_metadata(exiftool, &file_xmp)?;
 
-  read_metadata(file_xmp)
+  let Some(creator) = template
+    .creator
+    .as_deref()
+    .filter(|_| metadata.creator.is_none())
+  else {
+    return Ok(metadata);
+  };
+
+  let mut seeded = metadata.clone();
+  seeded.creator = Some(creator.to_string());
+  if seeded.copyright.is_none() {
+    let format = template.copyright_format.clone().unwrap_or_default();
+    seeded.copyright = format.render_for(creator, &metadata);
+  }
+
+  write_metadata(exiftool, file_xmp, seeded.to_exiftool_args(&metadata))
+}
+
+/// Gets the version of `ExifTool` in use.
+pub fn exiftool_version(exiftool: &ExifToolConfig) -> Result<String, CatalogError> {
+  let version = run_exiftool(exiftool, None::<&Path>, ["-ver"])?;
+  Ok(String::from_utf8_lossy(&version).trim().to_string())
 }
 
 /// Check that `ExifTool` is present and new enough.
-pub fn exiftool_check() -> Result<(), String> {
-  version_check(run_exiftool(None::<&Path>, ["-ver"])?, EXIFTOOL_MIN_VERSION)
+pub fn exiftool_check(exiftool: &ExifToolConfig) -> Result<(), CatalogError> {
+  check_exiftool_program(&exiftool.program())
+}
+
+/// Checks that `program` runs as `ExifTool`, and is new enough.
+fn check_exiftool_program(program: &Path) -> Result<(), CatalogError> {
+  let output = Command::new(program).arg("-ver").output().map_err(|e| {
+    CatalogError::ExifTool(format!(
+      "{}: ExifTool failed to run: {e}",
+      program.display()
+    ))
+  })?;
+
+  if !output.status.success() {
+    return Err(CatalogError::ExifTool(format!(
+      "{}: ExifTool did not run successfully.\nstderr:\n{}",
+      program.display(),
+      String::from_utf8_lossy(&output.stderr)
+    )));
+  }
+
+  version_check(output.stdout, EXIFTOOL_MIN_VERSION)
+}
+
+/// Formats the name `move_file` gives a file with `metadata`, as
+/// `yyyy/mm/yymmdd_hhmmssfff` (or without `yyyy/mm/` if not `date_dirs`),
+/// without the counter or extension. Mirrors how
+/// `ExifTool` picks and converts the date and time, without running it.
+pub fn format_file_name_date(
+  metadata: &Metadata,
+  format: FileNameFormat,
+) -> Result<String, CatalogError> {
+  // Of the tags in `move_file`, the first one set takes precedence.
+  let (date_time, offset) = format
+    .date_source
+    .tags()
+    .iter()
+    .filter_map(|tag| match *tag {
+      "SubSecDateTimeOriginal" => metadata.sub_sec_date_time_original.as_deref(),
+      "DateTimeOriginal" => metadata.date_time_original.as_deref(),
+      "SubSecCreateDate" => metadata.sub_sec_create_date.as_deref(),
+      "CreateDate" => metadata.create_date.as_deref(),
+      "MediaCreateDate" => metadata.media_create_date.as_deref(),
+      "TrackCreateDate" => metadata.track_create_date.as_deref(),
+      "SubSecModifyDate" => metadata.sub_sec_modify_date.as_deref(),
+      "ModifyDate" => metadata.modify_date.as_deref(),
+      "FileModifyDate" => Some(metadata.file_modify_date.as_str()).filter(|d| !d.is_empty()),
+      _ => None,
+    })
+    .find_map(|d| prim::parse_date_time(d).transpose())
+    .ok_or(CatalogError::InvalidFile(format!(
+      "{metadata}: No date and time to name file by."
+    )))??;
+
+  // Dates without time zone are used as-is. Otherwise, `ExifTool` converts to
+  // its time zone, which `move_file` sets to the file's own for local time.
+  let date_time = match (offset, format.local_time.then(|| metadata.get_time_zone())) {
+    (None, _) => date_time,
+    (Some(offset), Some(Some(time_zone))) => date_time
+      .and_local_timezone(offset)
+      .unwrap()
+      .with_timezone(&time_zone)
+      .naive_local(),
+    (Some(offset), _) => date_time
+      .and_local_timezone(offset)
+      .unwrap()
+      .with_timezone(&Local)
+      .naive_local(),
+  };
+
+  let sub_secs = format!("{:09}", date_time.nanosecond());
+
+  let date_time_format = if format.date_dirs {
+    "%Y/%m/%y%m%d_%H%M%S"
+  } else {
+    "%y%m%d_%H%M%S"
+  };
+
+  Ok(format!(
+    "{}{}",
+    date_time.format(date_time_format),
+    &sub_secs[..usize::from(format.sub_sec_digits.min(9))]
+  ))
+}
+
+/// Formats the counter `move_file` appends to the name of the `copy`th file
+/// at the same time, counting from 0 (which has none).
+#[must_use]
+pub fn format_counter(style: CounterStyle, copy: u32) -> String {
+  if copy == 0 {
+    return String::new();
+  }
+
+  match style {
+    CounterStyle::Letter => {
+      let mut letters = Vec::new();
+      let mut n = copy;
+      while n > 0 || letters.is_empty() {
+        letters.push(char::from(b'a' + u8::try_from(n % 26).unwrap()));
+        n /= 26;
+      }
+      format!("_{}", letters.into_iter().rev().collect::<String>())
+    }
+    CounterStyle::Numeric => format!("_{copy:03}"),
+  }
 }
 
-/// Moves `file_src` to `yyyy/mm/yymmdd_hhmmssfff_c.ext` under `dir_dst`.
-/// Optionally, if `metadata_src` is `Some`, uses its metadata for the date and
-/// time instead. Returns the path to the new file.
+/// Moves `file_src` to `yyyy/mm/yymmdd_hhmmssfff_c.ext` under `dir_dst` (or
+/// `yymmdd_hhmmssfff_c.ext` if not `format.date_dirs`), as configured by
+/// `format`. Optionally, if `metadata_src` is `Some`, uses its
+/// metadata for the date and time instead. If `format.local_time`, names in
+/// `time_zone`, the named file's own (see `Metadata::get_time_zone`), or in
+/// UTC if `None`. Returns the path to the new file.
 pub fn move_file(
+  exiftool: &ExifToolConfig,
   file_src: impl AsRef<Path>,
   metadata_src: Option<impl AsRef<Path>>,
   dir_dst: impl AsRef<Path>,
   ext: impl AsRef<OsStr>,
-) -> Result<PathBuf, String> {
+  format: FileNameFormat,
+  time_zone: Option<FixedOffset>,
+) -> Result<PathBuf, CatalogError> {
   let file_src = make_canonical(file_src)?;
   let metadata_src = metadata_src.map(make_canonical).transpose()?;
   let dir_dst = make_canonical(dir_dst)?;
@@ -126,6 +548,18 @@ pub fn move_file(
 
   let mut args = Vec::new();
 
+  // ExifTool converts dates to its local time zone when formatting, so set that
+  // to the file's own time zone to keep its local date and time.
+  let time_zone_arg;
+  if format.local_time {
+    if let Some(offset) = time_zone {
+      time_zone_arg = format!("TimeZone={}", make_posix_time_zone(offset));
+      args.extend([OsStr::new("-api"), OsStr::new(&time_zone_arg)]);
+    } else {
+      log::debug!("{}: No time zone. Naming in UTC.", file_src.display());
+    }
+  }
+
   let metadata_src_path;
   if let Some(metadata_src) = metadata_src {
     metadata_src_path = metadata_src.clone();
@@ -133,19 +567,18 @@ pub fn move_file(
   }
 
   // `-v` needed to report renaming.
-  args.extend(["-v", "-d", DATETIME_WRITE_FORMAT].map(OsStr::new));
+  let date_time_write_format = make_date_time_write_format(format);
+  args.extend(["-v", "-d", &date_time_write_format].map(OsStr::new));
 
   let mut args_rename = Vec::new();
 
-  for date_time_tag in [
-    "CreateDate",
-    "SubSecCreateDate",
-    "DateTimeOriginal",
-    "SubSecDateTimeOriginal",
-  ] {
+  // Later arguments take precedence, so tags are given from last to first.
+  // Unset dates (see `prim::is_date_time_unset`) are kept as stored when
+  // formatting, so are made undefined to be skipped, as if missing.
+  for date_time_tag in format.date_source.tags().iter().rev() {
     let mut rename_format = OsString::from("-FileName<");
     rename_format.push(dir_dst.as_os_str());
-    rename_format.push(format!("/${{{date_time_tag}}}"));
+    rename_format.push(format!("/${{{date_time_tag};$_=undef if /^0000:00:00/}}"));
     rename_format.push(ext);
 
     args_rename.push(rename_format);
@@ -157,973 +590,3904 @@ pub fn move_file(
 
   args.push(file_src.as_os_str());
 
-  let stdout = String::from_utf8(run_exiftool(Some(&dir_dst), args)?)
-    .map_err(|e| format!("Could not parse ExifTool output as UTF-8 ({e})."))?;
+  let stdout = String::from_utf8(run_exiftool(exiftool, Some(&dir_dst), args)?)
+    .map_err(|e| CatalogError::Parse(format!("Could not parse ExifTool output as UTF-8 ({e}).")))?;
 
   if stdout.contains("0 image files updated") {
-    return Err(format!("{}: Failed to move file.", file_src.display()));
+    return Err(CatalogError::ExifTool(format!(
+      "{}: Failed to move file.",
+      file_src.display()
+    )));
+  }
+
+  make_canonical(dir_dst.join(extract_destination(&stdout, &file_src)?))
+}
+
+/// Extracts an embedded JPEG from `file` (e.g. a HEIC or raw image), if any,
+/// preferring a raw file's full-size JPEG, then its preview, then thumbnail.
+pub fn extract_preview(
+  exiftool: &ExifToolConfig,
+  file: impl AsRef<Path>,
+) -> Result<Option<Vec<u8>>, CatalogError> {
+  let file = make_canonical(file)?;
+
+  for tag in ["-JpgFromRaw", "-PreviewImage", "-ThumbnailImage"] {
+    let stdout = run_exiftool(exiftool, None::<&Path>, [
+      OsStr::new("-b"),
+      OsStr::new(tag),
+      file.as_os_str(),
+    ])?;
+    if stdout.starts_with(&[0xff, 0xd8]) {
+      return Ok(Some(stdout));
+    }
   }
 
-  make_canonical(dir_dst.join(extract_destination(&stdout)?))
+  Ok(None)
 }
 
 /// Gets metadata for `file`.
-pub fn read_metadata(file: impl AsRef<Path>) -> Result<Metadata, String> {
+pub fn read_metadata(
+  exiftool: &ExifToolConfig,
+  file: impl AsRef<Path>,
+) -> Result<Metadata, CatalogError> {
   let file = make_canonical(file)?;
 
-  let mut args = Vec::from(READ_ARGS.map(OsStr::new));
+  let mut args = exiftool.read_args().collect::<Vec<_>>();
   args.push(file.as_os_str());
 
-  Ok(parse_vec(run_exiftool(None::<&Path>, args)?)?.remove(0))
+  Ok(parse_vec(run_exiftool(exiftool, None::<&Path>, args)?)?.remove(0))
 }
 
-/// Reads metadata from `dir_root` and all subdirectories, excluding `exclude`
-/// (e.g. `trash/`).
+/// Reads metadata from `dir_root` and all subdirectories, excluding
+/// `dirs_exclude` (e.g. `trash/`), and hidden files (e.g. `.DS_Store`, or
+/// macOS resource forks like `._image.jpg`) unless `include_hidden`. Symlinks
+/// to directories are followed unless `skip_symlinks`, skipping any which point
+/// to a directory containing them. Each file `ExifTool` cannot read gets
+/// an `Err`, rather than failing the whole read.
 pub fn read_metadata_recursive(
+  exiftool: &ExifToolConfig,
   dir_root: impl AsRef<Path>,
-  dir_exclude: Option<impl AsRef<Path>>,
-) -> Result<Vec<Metadata>, String> {
+  dirs_exclude: impl IntoIterator<Item = impl AsRef<Path>>,
+  include_hidden: bool,
+  skip_symlinks: bool,
+) -> Result<Vec<Result<Metadata, CatalogError>>, CatalogError> {
   let dir_root = make_canonical(dir_root)?;
-  let dir_exclude = dir_exclude.map(make_canonical).transpose()?;
-
-  let mut args = Vec::from(READ_ARGS.map(OsStr::new));
-  args.extend(["-r", "."].map(OsStr::new));
-
-  let exclude_relative;
 
-  if let Some(exclude_path) = dir_exclude {
-    exclude_relative = exclude_path
-      .strip_prefix(&dir_root)
-      .map_err(|_| {
-        format!(
-          "{}: Exclude path must be within the read directory ({}).",
-          exclude_path.display(),
-          dir_root.display()
-        )
-      })?
-      .to_path_buf();
-
-    args.extend([OsStr::new("-i"), exclude_relative.as_os_str()]);
-  }
+  let mut args = exiftool.read_args().map(OsString::from).collect::<Vec<_>>();
+  args.extend(["-r", "."].map(OsString::from));
+  args.extend(make_exclude_args(
+    &dir_root,
+    dirs_exclude,
+    include_hidden,
+    skip_symlinks,
+  )?);
 
-  parse_vec(run_exiftool(Some(dir_root), args)?)
+  read_metadata_per_file(exiftool, dir_root, args, None)
 }
 
-/// Moves `file` under `dir_trash`, maintaining its directory structure relative
-/// to `dir_root`
-pub fn remove_file(
+/// Reads metadata as `read_metadata_recursive`, but parses `ExifTool`'s output
+/// file by file as it is written, so that the output is never held in full.
+/// Items are as from `read_metadata_recursive`, with an `Err` item if
+/// `ExifTool` fails without output.
+pub fn read_metadata_recursive_stream(
+  exiftool: &ExifToolConfig,
   dir_root: impl AsRef<Path>,
-  dir_trash: impl AsRef<Path>,
-  file: impl AsRef<Path>,
-) -> Result<(), String> {
+  dirs_exclude: impl IntoIterator<Item = impl AsRef<Path>>,
+  include_hidden: bool,
+  skip_symlinks: bool,
+) -> Result<MetadataStream, CatalogError> {
   let dir_root = make_canonical(dir_root)?;
-  let dir_trash = make_canonical(dir_trash)?;
-  let file = make_canonical(file)?;
 
-  if file.starts_with(&dir_trash) {
-    return Err(format!(
-      "{}: Cannot remove file already in trash ({}).",
-      file.display(),
-      dir_trash.display()
-    ));
-  }
+  let mut args = exiftool.read_args().map(OsString::from).collect::<Vec<_>>();
+  args.extend(["-r", "."].map(OsString::from));
+  args.extend(make_exclude_args(
+    &dir_root,
+    dirs_exclude,
+    include_hidden,
+    skip_symlinks,
+  )?);
 
-  let path_relative = file.strip_prefix(&dir_root).map_err(|_| {
-    format!(
-      "{}: Cannot remove file outside root directory ({}).",
-      file.display(),
-      dir_root.display()
-    )
-  })?;
+  MetadataStream::spawn(exiftool, dir_root, args)
+}
 
-  let path_trash = dir_trash.join(path_relative);
+/// Gets metadata for `files`, given relative to `dir_root` (e.g. as
+/// `./image.jpg`, to match `read_metadata_recursive`). As there, each file
+/// `ExifTool` cannot read gets an `Err`. Files are passed as an argument file
+/// (`-@`) through stdin, so that lists too long for the command line (e.g.
+/// from `find`) can be read.
+pub fn read_metadata_files(
+  exiftool: &ExifToolConfig,
+  dir_root: impl AsRef<Path>,
+  files: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> Result<Vec<Result<Metadata, CatalogError>>, CatalogError> {
+  let mut list = Vec::new();
+  for file in files {
+    list.extend(file.as_ref().as_os_str().as_encoded_bytes());
+    list.push(b'\n');
+  }
 
-  if path_trash.exists() {
-    return Err(format!(
-      "{}: Cannot remove file due to name collision in trash ({}).",
-      file.display(),
-      path_trash.display()
-    ));
+  // ExifTool errors without any files.
+  if list.is_empty() {
+    return Ok(Vec::new());
   }
 
-  fs::create_dir_all(path_trash.parent().unwrap()).unwrap();
-  fs::rename(file, path_trash).unwrap();
+  let mut args = exiftool.read_args().map(OsString::from).collect::<Vec<_>>();
+  args.extend(["-@", "-"].map(OsString::from));
 
-  Ok(())
+  read_metadata_per_file(exiftool, dir_root, args, Some(&list))
 }
 
-/// Runs `ExifTool` with `args`, from optional working directory `dir_root`.
-/// Panics if `ExifTool` fails.
-pub fn run_exiftool<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
-  dir_root: Option<impl AsRef<Path>>,
-  args: I,
-) -> Result<Vec<u8>, String> {
-  let dir_root = dir_root.map(make_canonical).transpose()?;
-
-  let mut cmd = Command::new(PathBuf::from(env!("OUT_DIR")).join("exiftool"));
-  if let Some(dir_root) = dir_root {
-    cmd.current_dir(dir_root);
+/// Runs `ExifTool` with `args` to read metadata, parsing the output per file.
+/// `ExifTool` reports failure if any file cannot be read, so its output is
+/// parsed regardless, with only the unreadable files as errors.
+fn read_metadata_per_file(
+  exiftool: &ExifToolConfig,
+  dir_root: impl AsRef<Path>,
+  args: Vec<OsString>,
+  stdin: Option<&[u8]>,
+) -> Result<Vec<Result<Metadata, CatalogError>>, CatalogError> {
+  let (output, args) = run_exiftool_unchecked(exiftool, Some(dir_root), args, stdin)?;
+
+  if output.stdout.is_empty() {
+    if output.status.success() {
+      return Ok(Vec::new());
+    }
+    return Err(CatalogError::ExifTool(format!(
+      "ExifTool did not run successfully.\nArgs:\n{args}\nstderr:\n{}",
+      String::from_utf8_lossy(&output.stderr)
+    )));
   }
-  cmd.args(args);
 
-  let output = cmd.output().map_err(|e| {
-    format!(
-      "ExifTool failed to run.\nArgs:\n{}\nError:\n{e}",
-      cmd
-        .get_args()
-        .collect::<Vec<_>>()
-        .join(OsStr::new(" "))
-        .display(),
-    )
+  let values: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).map_err(|e| {
+    CatalogError::Parse(format!(
+      "Failed to parse ExifTool output as metadata ({e}).\nstdout:\n{}",
+      String::from_utf8_lossy(&output.stdout)
+    ))
   })?;
 
-  if !output.status.success() {
-    return Err(format!(
-      "ExifTool did not run successfully.\nArgs:\n{}\nstderr:\n{}",
-      cmd
-        .get_args()
-        .collect::<Vec<_>>()
-        .join(OsStr::new(" "))
-        .display(),
-      String::from_utf8_lossy(&output.stderr)
-    ));
+  Ok(values.into_iter().map(parse_file_metadata).collect())
+}
+
+/// Parses a single file's metadata from `ExifTool`'s JSON output, failing if
+/// `ExifTool` reported an error reading it.
+fn parse_file_metadata(value: serde_json::Value) -> Result<Metadata, CatalogError> {
+  let file = value
+    .get("SourceFile")
+    .and_then(serde_json::Value::as_str)
+    .unwrap_or_default()
+    .to_string();
+
+  if let Some(error) = value.get("Error").and_then(serde_json::Value::as_str) {
+    return Err(CatalogError::ExifTool(format!(
+      "{file}: Unable to read metadata ({error})."
+    )));
   }
 
-  Ok(output.stdout)
+  serde_json::from_value(value)
+    .map_err(|e| CatalogError::Parse(format!("{file}: Unable to parse metadata ({e}).")))
 }
 
-/// Given a byte stream `stdout` from `ExifTool`, extracts the destination of a
-/// rename or move. Expects the format: 'OLDNAME.jpg' --> 'NEWNAME.jpg'.
-fn extract_destination(stdout: &str) -> Result<PathBuf, String> {
-  let re = Regex::new(r"'.+' --> '(.+)'").unwrap();
+/// Metadata parsed from a running `ExifTool`'s output, file by file, from
+/// `read_metadata_recursive_stream`.
+pub struct MetadataStream {
+  elements: JsonArrayElements<BufReader<ChildStdout>>,
+  child:    Child,
+  /// Collects stderr, so that `ExifTool` never blocks writing it.
+  stderr:   Option<JoinHandle<Vec<u8>>>,
+  /// Arguments quoted, for error messages.
+  args:     String,
+  read:     usize,
+}
+
+impl MetadataStream {
+  fn spawn(
+    exiftool: &ExifToolConfig,
+    dir_root: impl AsRef<Path>,
+    args: Vec<OsString>,
+  ) -> Result<Self, CatalogError> {
+    let mut cmd = exiftool_command(exiftool, Some(dir_root), args)?;
+    let args = format_args_quoted(cmd.get_args());
+
+    let mut child = cmd
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|e| {
+        CatalogError::ExifTool(format!(
+          "ExifTool failed to run.\nArgs:\n{args}\nError:\n{e}"
+        ))
+      })?;
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().map(|mut stderr| {
+      thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stderr.read_to_end(&mut buf);
+        buf
+      })
+    });
+
+    Ok(Self {
+      elements: JsonArrayElements::new(BufReader::new(stdout)),
+      child,
+      stderr,
+      args,
+      read: 0,
+    })
+  }
 
-  let caps = re.captures(stdout).ok_or(format!(
-    "ExifTool output did not match regex.\nstdout:\n{stdout}"
-  ))?;
+  /// Waits for `ExifTool` to exit, returning an error if it failed without
+  /// output (as with `read_metadata_per_file`).
+  fn finish(&mut self) -> Option<CatalogError> {
+    let status = self.child.wait();
+    let stderr = self
+      .stderr
+      .take()
+      .and_then(|h| h.join().ok())
+      .unwrap_or_default();
+
+    match status {
+      Ok(status) if status.success() || self.read > 0 => None,
+      Ok(_) => Some(CatalogError::ExifTool(format!(
+        "ExifTool did not run successfully.\nArgs:\n{}\nstderr:\n{}",
+        self.args,
+        String::from_utf8_lossy(&stderr)
+      ))),
+      Err(e) => Some(CatalogError::ExifTool(format!(
+        "ExifTool failed to run.\nArgs:\n{}\nError:\n{e}",
+        self.args
+      ))),
+    }
+  }
+}
 
-  let file_dst = caps.get(1).ok_or(format!(
-    "ExifTool output did not contain a destination.\nstdout:\n{stdout}"
-  ))?;
+impl Iterator for MetadataStream {
+  type Item = Result<Metadata, CatalogError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    match self.elements.next() {
+      Some(Ok(element)) => {
+        self.read += 1;
+        Some(
+          serde_json::from_slice(&element)
+            .map_err(|e| {
+              CatalogError::Parse(format!(
+                "Failed to parse ExifTool output as metadata ({e}).\nstdout:\n{}",
+                String::from_utf8_lossy(&element)
+              ))
+            })
+            .and_then(parse_file_metadata),
+        )
+      }
+      Some(Err(e)) => {
+        // Output is unusable past an error, so stop reading it.
+        self.elements.done = true;
+        Some(Err(CatalogError::Parse(format!(
+          "Failed to read ExifTool output ({e})."
+        ))))
+      }
+      None if self.stderr.is_some() => self.finish().map(Err),
+      None => None,
+    }
+  }
+}
 
-  Ok(PathBuf::from(file_dst.as_str()))
+impl Drop for MetadataStream {
+  fn drop(&mut self) {
+    // If dropped before the end, `ExifTool` is stopped rather than left running.
+    if self.stderr.is_some() {
+      let _ = self.child.kill();
+      let _ = self.finish();
+    }
+  }
 }
 
-/// Converts a path to an absolute, canonical form. Panics if `path` is not
-/// absolute or does not point to a real file or directory.
-fn make_canonical(path: impl AsRef<Path>) -> Result<PathBuf, String> {
-  let path = path.as_ref();
+/// Splits a JSON array read from `reader` into the raw bytes of each of its
+/// elements (objects or arrays), without reading the array in full.
+struct JsonArrayElements<R> {
+  bytes:   std_io::Bytes<R>,
+  started: bool,
+  done:    bool,
+}
 
-  if !path.is_absolute() {
-    return Err(format!("{}: Path is not absolute.", path.display()));
+impl<R: BufRead> JsonArrayElements<R> {
+  fn new(reader: R) -> Self {
+    Self {
+      bytes:   reader.bytes(),
+      started: false,
+      done:    false,
+    }
   }
-  if !path.exists() {
-    return Err(format!("{}: Path does not exist.", path.display()));
+
+  /// Reads the rest of an element after its opening `first` byte.
+  fn read_element(&mut self, first: u8) -> std_io::Result<Vec<u8>> {
+    let mut element = vec![first];
+    let mut depth = 1;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    while depth > 0 {
+      let b = self.bytes.next().ok_or_else(|| {
+        std_io::Error::new(std_io::ErrorKind::UnexpectedEof, "unterminated element")
+      })??;
+      element.push(b);
+
+      if in_string {
+        match b {
+          _ if escaped => escaped = false,
+          b'\\' => escaped = true,
+          b'"' => in_string = false,
+          _ => {}
+        }
+      } else {
+        match b {
+          b'"' => in_string = true,
+          b'{' | b'[' => depth += 1,
+          b'}' | b']' => depth -= 1,
+          _ => {}
+        }
+      }
+    }
+
+    Ok(element)
   }
+}
 
-  path
-    .canonicalize()
-    .map_err(|e| format!("{}: Path failed to canonicalize ({e}).", path.display()))
+impl<R: BufRead> Iterator for JsonArrayElements<R> {
+  type Item = std_io::Result<Vec<u8>>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    while !self.done {
+      let b = match self.bytes.next() {
+        Some(Ok(b)) => b,
+        Some(Err(e)) => return Some(Err(e)),
+        // No output at all (e.g. no files) is an empty array.
+        None => break,
+      };
+
+      match b {
+        _ if b.is_ascii_whitespace() => {}
+        b'[' if !self.started => self.started = true,
+        b',' if self.started => {}
+        b']' if self.started => break,
+        b'{' | b'[' if self.started => return Some(self.read_element(b)),
+        _ => {
+          return Some(Err(std_io::Error::new(
+            std_io::ErrorKind::InvalidData,
+            format!("unexpected `{}` between elements", char::from(b)),
+          )));
+        }
+      }
+    }
+
+    self.done = true;
+    None
+  }
 }
 
-/// Parses `ExifTool`'s JSON-formatted output `metadata` into Rust types.
-fn parse_vec(metadata: impl AsRef<[u8]>) -> Result<Vec<Metadata>, String> {
-  // `serde_json` doesn't handle the empty case.
-  if metadata.as_ref().is_empty() {
+/// Gets only `FileModifyDate` for all files in `dir_root` (recursively),
+/// skipping `dirs_exclude` (and hidden files unless `include_hidden`, and
+/// following symlinks unless `skip_symlinks`, as in `read_metadata_recursive`),
+/// as pairs of path (relative to `dir_root`) and
+/// date. This is much faster than reading all metadata, as files are not
+/// opened.
+pub fn read_file_modify_dates_recursive(
+  exiftool: &ExifToolConfig,
+  dir_root: impl AsRef<Path>,
+  dirs_exclude: impl IntoIterator<Item = impl AsRef<Path>>,
+  include_hidden: bool,
+  skip_symlinks: bool,
+) -> Result<Vec<(PathBuf, String)>, CatalogError> {
+  #[derive(Deserialize)]
+  #[serde(rename_all = "PascalCase")]
+  struct FileModifyDate {
+    source_file:      PathBuf,
+    file_modify_date: String,
+  }
+
+  let dir_root = make_canonical(dir_root)?;
+
+  let mut args = [
+    "-fast3",
+    "-d",
+    DATETIME_READ_FORMAT,
+    "-json",
+    "-FileModifyDate",
+  ]
+  .map(OsString::from)
+  .to_vec();
+  args.extend(["-r", "."].map(OsString::from));
+  args.extend(make_exclude_args(
+    &dir_root,
+    dirs_exclude,
+    include_hidden,
+    skip_symlinks,
+  )?);
+
+  let stdout = run_exiftool(exiftool, Some(dir_root), args)?;
+  if stdout.is_empty() {
     return Ok(Vec::new());
   }
 
-  serde_json::from_slice(metadata.as_ref()).map_err(|e| {
-    format!(
-      "Failed to parsed ExifTool output as metadata ({e}).\nstdout:\n{}",
-      String::from_utf8_lossy(metadata.as_ref())
-    )
-  })
+  let dates: Vec<FileModifyDate> = serde_json::from_slice(&stdout).map_err(|e| {
+    CatalogError::Parse(format!(
+      "Failed to parse ExifTool output as modify dates ({e}).\nstdout:\n{}",
+      String::from_utf8_lossy(&stdout)
+    ))
+  })?;
+
+  Ok(
+    dates
+      .into_iter()
+      .map(|d| (d.source_file, d.file_modify_date))
+      .collect(),
+  )
 }
 
-/// Returns whether `version` is as new or newer than `version_required_min`,
-/// where `version` is from `ExifTool`'s stdout.
-fn version_check(version: Vec<u8>, version_required_min: (u32, u32)) -> Result<(), String> {
-  let version = String::from_utf8(version).unwrap();
-  let Some((major, minor)) = version.trim().split_once('.') else {
-    return Err(format!("Unexpected ExifTool version string: \"{version}\""));
-  };
+/// Makes `ExifTool` arguments to skip `dirs_exclude`, which must be within
+/// `dir_root`, hidden files unless `include_hidden`, and symlinks if
+/// `skip_symlinks` (or, if following, those which would loop forever).
+fn make_exclude_args(
+  dir_root: &Path,
+  dirs_exclude: impl IntoIterator<Item = impl AsRef<Path>>,
+  include_hidden: bool,
+  skip_symlinks: bool,
+) -> Result<Vec<OsString>, CatalogError> {
+  let mut args = Vec::new();
+  let mut excluded = Vec::new();
 
-  let major = major.parse::<u32>();
-  let minor = minor.parse::<u32>();
-  let (Ok(major), Ok(minor)) = (major, minor) else {
-    return Err(format!("Unexpected ExifTool version: {version}"));
-  };
+  // Files with names starting with `.`, which includes macOS resource forks
+  // (`._*`).
+  if !include_hidden {
+    args.extend(["-i", "HIDDEN"].map(OsString::from));
+  }
 
-  if major > version_required_min.0
-    || (major == version_required_min.0 && minor >= version_required_min.1)
-  {
-    Ok(())
+  for exclude_path in dirs_exclude {
+    let exclude_path = make_canonical(exclude_path)?;
+    let exclude_relative = exclude_path.strip_prefix(dir_root).map_err(|_| {
+      CatalogError::Io(format!(
+        "{}: Exclude path must be within the read directory ({}).",
+        exclude_path.display(),
+        dir_root.display()
+      ))
+    })?;
+
+    args.extend([OsString::from("-i"), exclude_relative.into()]);
+    excluded.push(exclude_relative.to_path_buf());
+  }
+
+  if skip_symlinks {
+    args.extend(["-i", "SYMLINKS"].map(OsString::from));
   } else {
-    Err(format!(
-      "ExifTool version {major}.{minor} is too old (needs {}.{} or newer).",
-      version_required_min.0, version_required_min.1
+    // Matched against the path as `ExifTool` builds it from `.`, so that only
+    // the symlink itself is skipped, not others of the same name.
+    for cycle in find_symlink_cycles(dir_root, &excluded)? {
+      args.extend([OsString::from("-i"), Path::new(".").join(cycle).into()]);
+    }
+  }
+
+  Ok(args)
+}
+
+/// Finds symlinks within `dir_root` (skipping `dirs_exclude`, relative to it)
+/// pointing to a directory they're within, possibly via other symlinks, which
+/// would otherwise be followed forever. These are returned relative to
+/// `dir_root`, as reached by following symlinks.
+fn find_symlink_cycles(
+  dir_root: &Path,
+  dirs_exclude: &[PathBuf],
+) -> Result<Vec<PathBuf>, CatalogError> {
+  let read_error = |dir: &Path, e: std_io::Error| {
+    CatalogError::Io(format!(
+      "{}: Unable to read directory ({e}).",
+      dir.display()
     ))
+  };
+
+  let mut cycles = Vec::new();
+  // Directories to visit, relative to `dir_root`, with the canonical paths of
+  // the directories they're within (including themselves).
+  let mut stack = vec![(PathBuf::new(), vec![dir_root.to_path_buf()])];
+
+  while let Some((relative, ancestors)) = stack.pop() {
+    let dir = dir_root.join(&relative);
+    for entry in fs::read_dir(&dir).map_err(|e| read_error(&dir, e))? {
+      let entry = entry.map_err(|e| read_error(&dir, e))?;
+      let file_type = entry.file_type().map_err(|e| read_error(&dir, e))?;
+      let entry_relative = relative.join(entry.file_name());
+
+      if dirs_exclude.contains(&entry_relative) {
+        continue;
+      }
+
+      let canonical = if file_type.is_symlink() {
+        // Broken symlinks, and those to files, aren't traversed.
+        match fs::canonicalize(entry.path()) {
+          Ok(canonical) if canonical.is_dir() => canonical,
+          _ => continue,
+        }
+      } else if file_type.is_dir() {
+        ancestors[ancestors.len() - 1].join(entry.file_name())
+      } else {
+        continue;
+      };
+
+      if ancestors.contains(&canonical) {
+        log::warn!(
+          "{}: Skipping symlink to a directory containing it.",
+          entry.path().display()
+        );
+        cycles.push(entry_relative);
+        continue;
+      }
+
+      let mut ancestors = ancestors.clone();
+      ancestors.push(canonical);
+      stack.push((entry_relative, ancestors));
+    }
   }
+
+  Ok(cycles)
 }
 
-#[cfg(test)]
-mod test_copy_metadata {
-  use super::*;
-  use crate::testing::*;
+/// Creates a directory in `dir_trash` for files trashed by a run at `time`, as
+/// for `TrashLayout::Timestamped`. Runs within the same minute get a counter
+/// (e.g. `2024-01-02T1530_2`), so each has its own directory.
+pub fn make_trash_run_dir(
+  dir_trash: impl AsRef<Path>,
+  time: NaiveDateTime,
+) -> Result<PathBuf, CatalogError> {
+  let name = time.format(TRASH_RUN_FORMAT).to_string();
+
+  let mut copy = 1;
+  loop {
+    let dir = match copy {
+      1 => dir_trash.as_ref().join(&name),
+      _ => dir_trash.as_ref().join(format!("{name}_{copy}")),
+    };
+
+    match fs::create_dir(&dir) {
+      Ok(()) => return Ok(dir),
+      Err(e) if e.kind() == std_io::ErrorKind::AlreadyExists => copy += 1,
+      Err(e) => {
+        return Err(CatalogError::Io(format!(
+          "{}: Unable to create trash directory ({e}).",
+          dir.display()
+        )));
+      }
+    }
+  }
+}
 
-  #[test]
-  fn converts_exif_tags_to_quicktime() {
-    let d = test_dir!(
-      "image.jpg": {
-        "DateTimeOriginal": "2000-01-01T00:00:00",
-        "SubSecTimeOriginal": "999",
-        "OffsetTimeOriginal": "-08:00",
-        "GPSLatitude": "47.6061",
-        "GPSLatitudeRef": "N",
-        "GPSLongitude": "122.3328",
-        "GPSLongitudeRef": "W",
-      },
-      "video.mov": { "CompressorID": "avc1" },
-    );
+/// Name of the file in each trash directory `remove_file` moves files into
+/// (e.g. per run, for `TrashLayout::Timestamped`), recording when each was
+/// trashed. Each line holds seconds since the Unix epoch and the path relative
+/// to that directory, separated by a tab.
+pub const TRASH_RECORD: &str = ".trashed";
+
+/// Permanently deletes files under `dir_trash` trashed before `cutoff`, or all
+/// files if `None`, then removes directories left empty, except `dir_trash`
+/// itself. When each file was trashed is read from its `TRASH_RECORD`, else
+/// (e.g. if trashed by hand) taken as its modify date. Refuses to prune a trash
+/// directory containing `dir_catalog`, so that the catalog is never deleted.
+/// Returns the files deleted.
+pub fn prune_trash(
+  dir_trash: impl AsRef<Path>,
+  dir_catalog: impl AsRef<Path>,
+  cutoff: Option<SystemTime>,
+) -> Result<Vec<PathBuf>, CatalogError> {
+  let dir_trash = make_canonical(dir_trash)?;
+  let dir_catalog = make_canonical(dir_catalog)?;
+
+  if dir_catalog.starts_with(&dir_trash) {
+    return Err(CatalogError::Io(format!(
+      "{}: Cannot prune trash containing the catalog ({}).",
+      dir_trash.display(),
+      dir_catalog.display()
+    )));
+  }
 
-    copy_metadata(d.get_path("image.jpg"), d.get_path("video.mov")).unwrap();
+  let mut pruned = Vec::new();
+  prune_dir(&dir_trash, cutoff, &mut HashMap::new(), &mut pruned)?;
 
-    let metadata = read_metadata(d.get_path("video.mov")).unwrap();
-    assert_eq!(
-      metadata.date_time_original,
-      Some("2000-01-01T00:00:00.999-08:00".to_string())
-    );
-    assert_eq!(
-      metadata.gps_position,
-      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
-    );
+  Ok(pruned)
+}
+
+/// Deletes files under `dir` as for `prune_trash`, adding them to `pruned`.
+/// `trashed` holds when files were trashed, by absolute path, as read from
+/// records in `dir` and above. Symbolic links without a record are deleted by
+/// their own modify date, never followed. Returns whether `dir` is left empty.
+fn prune_dir(
+  dir: &Path,
+  cutoff: Option<SystemTime>,
+  trashed: &mut HashMap<PathBuf, SystemTime>,
+  pruned: &mut Vec<PathBuf>,
+) -> Result<bool, CatalogError> {
+  let read_error = |e| {
+    CatalogError::Io(format!(
+      "{}: Unable to read directory ({e}).",
+      dir.display()
+    ))
+  };
+
+  let path_record = dir.join(TRASH_RECORD);
+  let mut record = read_trash_record(&path_record)?;
+  if let Some(record) = &record {
+    trashed.extend(record.iter().map(|(file, secs)| {
+      (
+        dir.join(file),
+        SystemTime::UNIX_EPOCH + Duration::from_secs(*secs),
+      )
+    }));
   }
 
-  #[test]
-  fn converts_exif_tags_to_xmp() {
-    let d = test_dir!(
+  let mut empty = true;
+  for entry in fs::read_dir(dir).map_err(read_error)? {
+    let entry = entry.map_err(read_error)?;
+    let path = entry.path();
+
+    if path == path_record {
+      continue;
+    }
+
+    if entry.file_type().map_err(read_error)?.is_dir() {
+      if prune_dir(&path, cutoff, trashed, pruned)? {
+        fs::remove_dir(&path).map_err(|e| {
+          CatalogError::Io(format!(
+            "{}: Unable to remove empty directory ({e}).",
+            path.display()
+          ))
+        })?;
+      } else {
+        empty = false;
+      }
+      continue;
+    }
+
+    let trashed_at = match trashed.get(&path) {
+      Some(trashed_at) => *trashed_at,
+      None => entry.metadata().and_then(|m| m.modified()).map_err(|e| {
+        CatalogError::Io(format!(
+          "{}: Unable to read modify date ({e}).",
+          path.display()
+        ))
+      })?,
+    };
+    if cutoff.is_some_and(|c| trashed_at >= c) {
+      empty = false;
+      continue;
+    }
+
+    fs::remove_file(&path)
+      .map_err(|e| CatalogError::Io(format!("{}: Unable to delete file ({e}).", path.display())))?;
+    log::debug!("{}: Deleted from trash.", path.display());
+    pruned.push(path);
+  }
+
+  // Files no longer in trash (pruned or restored) are dropped from the record,
+  // which is removed once empty.
+  if let Some(record) = &mut record {
+    let len = record.len();
+    record.retain(|file, _| dir.join(file).symlink_metadata().is_ok());
+    if record.is_empty() {
+      fs::remove_file(&path_record).map_err(|e| {
+        CatalogError::Io(format!(
+          "{}: Unable to delete trash record ({e}).",
+          path_record.display()
+        ))
+      })?;
+    } else {
+      empty = false;
+      if record.len() != len {
+        write_trash_record(&path_record, record)?;
+      }
+    }
+  }
+
+  Ok(empty)
+}
+
+/// Reads the `TRASH_RECORD` at `path`, if it exists, as when each file
+/// (relative to its directory) was trashed, in seconds since the Unix epoch.
+/// Later lines take precedence, and unparseable lines are skipped.
+fn read_trash_record(path: &Path) -> Result<Option<HashMap<PathBuf, u64>>, CatalogError> {
+  let contents = match fs::read_to_string(path) {
+    Ok(contents) => contents,
+    Err(e) if e.kind() == std_io::ErrorKind::NotFound => return Ok(None),
+    Err(e) => {
+      return Err(CatalogError::Io(format!(
+        "{}: Unable to read trash record ({e}).",
+        path.display()
+      )));
+    }
+  };
+
+  Ok(Some(
+    contents
+      .lines()
+      .filter_map(|line| {
+        let (secs, file) = line.split_once('\t')?;
+        Some((PathBuf::from(file), secs.parse().ok()?))
+      })
+      .collect(),
+  ))
+}
+
+/// Writes `record` to the `TRASH_RECORD` at `path`, replacing it.
+fn write_trash_record(path: &Path, record: &HashMap<PathBuf, u64>) -> Result<(), CatalogError> {
+  let lines = record
+    .iter()
+    .map(|(file, secs)| format!("{secs}\t{}\n", file.display()))
+    .collect::<Vec<_>>();
+
+  fs::write(path, lines.concat()).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Unable to write trash record ({e}).",
+      path.display()
+    ))
+  })
+}
+
+/// Appends to the `TRASH_RECORD` in `dir_trash` that `file` (relative to it)
+/// was trashed now.
+fn record_trashed(dir_trash: &Path, file: &Path) -> Result<(), CatalogError> {
+  let path = dir_trash.join(TRASH_RECORD);
+  let secs = SystemTime::now()
+    .duration_since(SystemTime::UNIX_EPOCH)
+    .map_or(0, |d| d.as_secs());
+
+  fs::OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(&path)
+    .and_then(|mut f| writeln!(f, "{secs}\t{}", file.display()))
+    .map_err(|e| {
+      CatalogError::Io(format!(
+        "{}: Unable to record trashed file ({e}).",
+        path.display()
+      ))
+    })
+}
+
+/// Moves `file` under `dir_trash`, maintaining its directory structure relative
+/// to `dir_root`. `dir_trash` need not be within `dir_root`. When it was
+/// trashed is recorded in `dir_trash`'s `TRASH_RECORD` for `prune_trash`,
+/// leaving the file itself unchanged. Errors if the file is not under
+/// `dir_root`, is already trashed, collides with a trashed file, or cannot be
+/// moved.
+pub fn remove_file(
+  dir_root: impl AsRef<Path>,
+  dir_trash: impl AsRef<Path>,
+  file: impl AsRef<Path>,
+) -> Result<(), CatalogError> {
+  let dir_root = make_canonical(dir_root)?;
+  let dir_trash = make_canonical(dir_trash)?;
+  let file = make_canonical(file)?;
+
+  if file.starts_with(&dir_trash) {
+    return Err(CatalogError::Io(format!(
+      "{}: Cannot remove file already in trash ({}).",
+      file.display(),
+      dir_trash.display()
+    )));
+  }
+
+  let path_relative = file.strip_prefix(&dir_root).map_err(|_| {
+    CatalogError::Io(format!(
+      "{}: Cannot remove file outside root directory ({}).",
+      file.display(),
+      dir_root.display()
+    ))
+  })?;
+
+  let path_trash = dir_trash.join(path_relative);
+
+  if path_trash.exists() {
+    return Err(CatalogError::Io(format!(
+      "{}: Cannot remove file due to name collision in trash ({}).",
+      file.display(),
+      path_trash.display()
+    )));
+  }
+
+  let dir_parent = path_trash.parent().unwrap_or(&dir_trash);
+  fs::create_dir_all(dir_parent).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Unable to create directory in trash ({e}).",
+      dir_parent.display()
+    ))
+  })?;
+
+  rename_or_copy(&file, &path_trash).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Cannot move file to trash ({}): {e}",
+      file.display(),
+      path_trash.display()
+    ))
+  })?;
+
+  // The file is already trashed, so only falls back to its modify date if this
+  // fails.
+  if let Err(e) = record_trashed(&dir_trash, path_relative) {
+    log::warn!("{e}");
+  }
+
+  Ok(())
+}
+
+/// Moves `file` from `dir_trash` back under `dir_root`, at its path relative to
+/// `dir_trash`, reversing `remove_file`. A leading directory per run (as for
+/// `TrashLayout::Timestamped`) is dropped. Returns the restored path, which
+/// must not already exist.
+pub fn restore_file(
+  dir_root: impl AsRef<Path>,
+  dir_trash: impl AsRef<Path>,
+  file: impl AsRef<Path>,
+) -> Result<PathBuf, CatalogError> {
+  let dir_root = make_canonical(dir_root)?;
+  let dir_trash = make_canonical(dir_trash)?;
+  let file = make_canonical(file)?;
+
+  let path_relative = file.strip_prefix(&dir_trash).map_err(|_| {
+    CatalogError::Io(format!(
+      "{}: Cannot restore file outside trash ({}).",
+      file.display(),
+      dir_trash.display()
+    ))
+  })?;
+
+  let mut components = path_relative.components();
+  let path_relative = match components.next() {
+    Some(run) if is_trash_run_dir(run.as_os_str()) && components.clone().next().is_some() => {
+      components.as_path()
+    }
+    _ => path_relative,
+  };
+
+  let path_dst = dir_root.join(path_relative);
+
+  if path_dst.exists() {
+    return Err(CatalogError::Io(format!(
+      "{}: Cannot restore file due to name collision ({}).",
+      file.display(),
+      path_dst.display()
+    )));
+  }
+
+  fs::create_dir_all(path_dst.parent().unwrap()).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Unable to create directory ({e}).",
+      path_dst.display()
+    ))
+  })?;
+
+  rename_or_copy(&file, &path_dst).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Cannot restore file from trash ({}): {e}",
+      file.display(),
+      path_dst.display()
+    ))
+  })?;
+
+  Ok(path_dst)
+}
+
+/// Whether `name` is that of a directory made by `make_trash_run_dir` (e.g.
+/// `2024-01-02T1530` or `2024-01-02T1530_2`).
+fn is_trash_run_dir(name: &OsStr) -> bool {
+  let Some(name) = name.to_str() else {
+    return false;
+  };
+  let name = name
+    .rsplit_once('_')
+    .filter(|(_, copy)| copy.parse::<u32>().is_ok())
+    .map_or(name, |(name, _)| name);
+
+  NaiveDateTime::parse_from_str(name, TRASH_RUN_FORMAT).is_ok()
+}
+
+/// Moves `file` to `file_dst` as-is, without `ExifTool`. `file_dst` must not
+/// already exist.
+pub fn rename_file(file: impl AsRef<Path>, file_dst: impl AsRef<Path>) -> Result<(), CatalogError> {
+  let file = make_canonical(file)?;
+  let file_dst = file_dst.as_ref();
+
+  if file_dst.exists() {
+    return Err(CatalogError::Io(format!(
+      "{}: Cannot move file due to name collision ({}).",
+      file.display(),
+      file_dst.display()
+    )));
+  }
+
+  rename_or_copy(&file, file_dst).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Cannot move file ({}): {e}",
+      file.display(),
+      file_dst.display()
+    ))
+  })
+}
+
+/// Gets the bytes available (to unprivileged users) on the drive holding
+/// `dir`.
+pub fn get_available_space(dir: impl AsRef<Path>) -> Result<u64, CatalogError> {
+  let dir = dir.as_ref();
+
+  fs4::available_space(dir).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Unable to get available space ({e}).",
+      dir.display()
+    ))
+  })
+}
+
+/// Gets when `file` was last modified (i.e. its `FileModifyDate`).
+pub fn get_modify_time(file: impl AsRef<Path>) -> Result<SystemTime, CatalogError> {
+  let file = file.as_ref();
+
+  fs::metadata(file).and_then(|m| m.modified()).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Unable to read modify date ({e}).",
+      file.display()
+    ))
+  })
+}
+
+/// Sets when `file` was last modified, e.g. to restore it after a move which
+/// copied the file, as a copy is modified at the time of the move.
+pub fn set_modify_time(file: impl AsRef<Path>, time: SystemTime) -> Result<(), CatalogError> {
+  let file = file.as_ref();
+
+  fs::File::options()
+    .write(true)
+    .open(file)
+    .and_then(|f| f.set_modified(time))
+    .map_err(|e| {
+      CatalogError::Io(format!(
+        "{}: Unable to set modify date ({e}).",
+        file.display()
+      ))
+    })
+}
+
+/// Renames `file` to `file_dst`, falling back to copying and removing, as
+/// renaming fails across file systems (e.g. to an external drive).
+fn rename_or_copy(file: &Path, file_dst: &Path) -> std::io::Result<()> {
+  fs::rename(file, file_dst)
+    .or_else(|_| fs::copy(file, file_dst).and_then(|_| fs::remove_file(file)))
+}
+
+/// Runs `ExifTool` with `args`, from optional working directory `dir_root`,
+/// returning its stdout. Errors if `ExifTool` fails to run or reports errors.
+pub fn run_exiftool<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
+  exiftool: &ExifToolConfig,
+  dir_root: Option<impl AsRef<Path>>,
+  args: I,
+) -> Result<Vec<u8>, CatalogError> {
+  let (output, args) = run_exiftool_unchecked(exiftool, dir_root, args, None)?;
+
+  check_stderr(output.status.success(), &output.stderr, &args)?;
+
+  Ok(output.stdout)
+}
+
+/// Message `ExifTool` printed to stderr, as `Warning: MESSAGE - FILE` or
+/// `Error: MESSAGE - FILE`, with the file only if about a single one.
+#[derive(Debug, PartialEq)]
+struct StderrMessage<'a> {
+  is_error: bool,
+  message:  &'a str,
+  file:     Option<&'a str>,
+}
+
+impl Display for StderrMessage<'_> {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self.file {
+      Some(file) => write!(f, "{file}: {}", self.message),
+      None => write!(f, "{}", self.message),
+    }
+  }
+}
+
+/// Parses the warnings and errors `ExifTool` printed to `stderr`, skipping any
+/// other lines. Files are split at the last ` - `, as messages may contain it
+/// too.
+fn parse_stderr(stderr: &str) -> Vec<StderrMessage<'_>> {
+  stderr
+    .lines()
+    .filter_map(|line| {
+      let (is_error, rest) = line
+        .strip_prefix("Error: ")
+        .map(|r| (true, r))
+        .or_else(|| line.strip_prefix("Warning: ").map(|r| (false, r)))?;
+      let (message, file) = rest
+        .rsplit_once(" - ")
+        .map_or((rest, None), |(m, f)| (m, Some(f)));
+      Some(StderrMessage {
+        is_error,
+        message: message.trim(),
+        file,
+      })
+    })
+    .collect()
+}
+
+/// Checks `ExifTool`'s `stderr` after running with `args` (quoted), failing if
+/// it printed any errors, even if it exited successfully (e.g. for some files
+/// in a batch), or if it failed, including any warnings to explain why.
+/// Warnings alone are logged instead, with those `ExifTool` marks as minor at
+/// debug.
+fn check_stderr(success: bool, stderr: &[u8], args: &str) -> Result<(), CatalogError> {
+  let stderr = String::from_utf8_lossy(stderr);
+  let (errors, warnings): (Vec<_>, Vec<_>) =
+    parse_stderr(&stderr).into_iter().partition(|m| m.is_error);
+
+  if !errors.is_empty() {
+    return Err(CatalogError::ExifTool(format!(
+      "ExifTool reported {} error(s).\nArgs:\n{args}\nErrors:\n{}",
+      errors.len(),
+      errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+    )));
+  }
+
+  if !success {
+    if warnings.is_empty() {
+      return Err(CatalogError::ExifTool(format!(
+        "ExifTool did not run successfully.\nArgs:\n{args}\nstderr:\n{stderr}"
+      )));
+    }
+    return Err(CatalogError::ExifTool(format!(
+      "ExifTool did not run successfully, reporting {} warning(s).\nArgs:\n{args}\nWarnings:\n{}",
+      warnings.len(),
+      warnings
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+    )));
+  }
+
+  for warning in warnings {
+    if warning.message.starts_with("[minor]") {
+      log::debug!("ExifTool: {warning}");
+    } else {
+      log::warn!("ExifTool: {warning}");
+    }
+  }
+
+  Ok(())
+}
+
+/// Runs `ExifTool` as `run_exiftool`, but returns its output regardless of
+/// success, along with its arguments quoted for error messages. `stdin`, if
+/// any, is written to `ExifTool` (e.g. for `-@ -`).
+fn run_exiftool_unchecked<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
+  exiftool: &ExifToolConfig,
+  dir_root: Option<impl AsRef<Path>>,
+  args: I,
+  stdin: Option<&[u8]>,
+) -> Result<(Output, String), CatalogError> {
+  let mut cmd = exiftool_command(exiftool, dir_root, args)?;
+
+  let output = match stdin {
+    None => cmd.output(),
+    // ExifTool reads argument files before any output, so all of stdin can be
+    // written before reading stdout.
+    Some(stdin) => cmd
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .and_then(|mut child| {
+        if let Some(mut pipe) = child.stdin.take() {
+          pipe.write_all(stdin)?;
+        }
+        child.wait_with_output()
+      }),
+  }
+  .map_err(|e| {
+    CatalogError::ExifTool(format!(
+      "ExifTool failed to run.\nArgs:\n{}\nError:\n{e}",
+      format_args_quoted(cmd.get_args()),
+    ))
+  })?;
+
+  Ok((output, format_args_quoted(cmd.get_args())))
+}
+
+/// Makes the command to run `ExifTool` with `args`, followed by any extra
+/// arguments, from optional working directory `dir_root`, logging it.
+fn exiftool_command<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(
+  exiftool: &ExifToolConfig,
+  dir_root: Option<impl AsRef<Path>>,
+  args: I,
+) -> Result<Command, CatalogError> {
+  let dir_root = dir_root.map(make_canonical).transpose()?;
+
+  let mut cmd = Command::new(exiftool.program());
+  if let Some(dir_root) = dir_root {
+    cmd.current_dir(dir_root);
+  }
+  cmd.args(args);
+  cmd.args(&exiftool.extra_args);
+
+  log::trace!(
+    target: LOG_TARGET_EXIFTOOL,
+    "exiftool {}",
+    format_args_quoted(cmd.get_args())
+  );
+
+  Ok(cmd)
+}
+
+/// Writes tags to `file` as `ExifTool` arguments (e.g. from
+/// `Metadata::to_exiftool_args`), and returns its new metadata. Without any
+/// arguments, `file` is left unchanged.
+pub fn write_metadata<S: AsRef<OsStr>>(
+  exiftool: &ExifToolConfig,
+  file: impl AsRef<Path>,
+  args: impl IntoIterator<Item = S>,
+) -> Result<Metadata, CatalogError> {
+  let file = make_canonical(file)?;
+
+  let mut args = args
+    .into_iter()
+    .map(|a| a.as_ref().to_os_string())
+    .collect::<Vec<_>>();
+
+  if !args.is_empty() {
+    args.extend([OsString::from("-overwrite_original"), file.clone().into()]);
+    run_exiftool(exiftool, None::<&Path>, args)?;
+  }
+
+  read_metadata(exiftool, file)
+}
+
+/// Formats `offset` as a POSIX `TZ` value, which counts hours *west* of UTC
+/// (e.g. `-08:00` is `UTC+08:00`).
+fn make_posix_time_zone(offset: FixedOffset) -> String {
+  let seconds_west = -offset.local_minus_utc();
+  let sign = if seconds_west < 0 { '-' } else { '+' };
+  let seconds_west = seconds_west.abs();
+
+  format!(
+    "UTC{sign}{:02}:{:02}",
+    seconds_west / 3600,
+    seconds_west % 3600 / 60
+  )
+}
+
+/// Joins `args` with spaces, single-quoting any containing whitespace or quotes
+/// so that the result can be pasted into a shell.
+fn format_args_quoted<'a>(args: impl IntoIterator<Item = &'a OsStr>) -> String {
+  args
+    .into_iter()
+    .map(|a| {
+      let a = a.to_string_lossy();
+      if a.is_empty() || a.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"') {
+        format!("'{}'", a.replace('\'', r"'\''"))
+      } else {
+        a.into_owned()
+      }
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// Given a byte stream `stdout` from `ExifTool`, extracts the destination of a
+/// rename or move of `file_src`. Expects a line of the format:
+/// 'OLDNAME.jpg' --> 'NEWNAME.jpg', among any others (e.g. directories
+/// created). Quotes within names are not escaped, so lines are split after
+/// `file_src` if it matches, else at the last ' --> ', as destinations are
+/// generated names.
+fn extract_destination(stdout: &str, file_src: &Path) -> Result<PathBuf, CatalogError> {
+  let prefix = format!("'{}' --> '", file_src.display());
+
+  let mut destinations = stdout
+    .lines()
+    .filter_map(|line| {
+      let line = line.trim().strip_suffix('\'')?;
+      line.strip_prefix(&prefix).or_else(|| {
+        let (src, dst) = line.rsplit_once("' --> '")?;
+        src.starts_with('\'').then_some(dst)
+      })
+    })
+    .collect::<Vec<_>>();
+  // ExifTool may repeat a line, which is still a single rename.
+  destinations.dedup();
+
+  match destinations.as_slice() {
+    [] => Err(CatalogError::Parse(format!(
+      "ExifTool output did not contain a destination.\nstdout:\n{stdout}"
+    ))),
+    [file_dst] => Ok(PathBuf::from(file_dst)),
+    _ => Err(CatalogError::Parse(format!(
+      "ExifTool output contained multiple renames.\nstdout:\n{stdout}"
+    ))),
+  }
+}
+
+/// Converts a path to an absolute, canonical form. Errors if `path` is not
+/// absolute or does not point to a real file or directory.
+fn make_canonical(path: impl AsRef<Path>) -> Result<PathBuf, CatalogError> {
+  let path = path.as_ref();
+
+  if !path.is_absolute() {
+    return Err(CatalogError::PathNotAbsolute(path.to_path_buf(), "Path"));
+  }
+  if !path.exists() {
+    return Err(CatalogError::PathNotFound(path.to_path_buf(), "Path"));
+  }
+
+  path.canonicalize().map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Path failed to canonicalize ({e}).",
+      path.display()
+    ))
+  })
+}
+
+/// Parses `ExifTool`'s JSON-formatted output `metadata` into Rust types.
+fn parse_vec(metadata: impl AsRef<[u8]>) -> Result<Vec<Metadata>, CatalogError> {
+  // `serde_json` doesn't handle the empty case.
+  if metadata.as_ref().is_empty() {
+    return Ok(Vec::new());
+  }
+
+  serde_json::from_slice(metadata.as_ref()).map_err(|e| {
+    CatalogError::Parse(format!(
+      "Failed to parse ExifTool output as metadata ({e}).\nstdout:\n{}",
+      String::from_utf8_lossy(metadata.as_ref())
+    ))
+  })
+}
+
+/// Returns whether `version` is as new or newer than `version_required_min`,
+/// where `version` is from `ExifTool`'s stdout. Anything following the minor
+/// version (e.g. `.1`, `_beta` or ` (production)`) is ignored.
+fn version_check(version: Vec<u8>, version_required_min: (u32, u32)) -> Result<(), CatalogError> {
+  let version = String::from_utf8(version).unwrap();
+  let Some((major, minor)) = version.trim().split_once('.') else {
+    return Err(CatalogError::ExifTool(format!(
+      "Unexpected ExifTool version string: \"{version}\""
+    )));
+  };
+
+  let minor = minor
+    .find(|c: char| !c.is_ascii_digit())
+    .map_or(minor, |end| &minor[..end]);
+
+  let major = major.parse::<u32>();
+  let minor = minor.parse::<u32>();
+  let (Ok(major), Ok(minor)) = (major, minor) else {
+    return Err(CatalogError::ExifTool(format!(
+      "Unexpected ExifTool version: {version}"
+    )));
+  };
+
+  if major > version_required_min.0
+    || (major == version_required_min.0 && minor >= version_required_min.1)
+  {
+    Ok(())
+  } else {
+    Err(CatalogError::ExifTool(format!(
+      "ExifTool version {major}.{minor} is too old (needs {}.{} or newer).",
+      version_required_min.0, version_required_min.1
+    )))
+  }
+}
+
+#[cfg(test)]
+mod test_copy_metadata {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn converts_exif_tags_to_quicktime() {
+    let d = test_dir!(
+      "image.jpg": {
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+        "SubSecTimeOriginal": "999",
+        "OffsetTimeOriginal": "-08:00",
+        "GPSLatitude": "47.6061",
+        "GPSLatitudeRef": "N",
+        "GPSLongitude": "122.3328",
+        "GPSLongitudeRef": "W",
+      },
+      "video.mov": { "CompressorID": "avc1" },
+    );
+
+    copy_metadata(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      d.get_path("video.mov"),
+    )
+    .unwrap();
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("video.mov")).unwrap();
+    assert_eq!(
+      metadata.date_time_original,
+      Some("2000-01-01T00:00:00.999-08:00".to_string())
+    );
+    assert_eq!(
+      metadata.gps_position,
+      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
+    );
+  }
+
+  #[test]
+  fn converts_exif_tags_to_xmp() {
+    let d = test_dir!(
+      "image.jpg": {
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+        "SubSecTimeOriginal": "999",
+        "OffsetTimeOriginal": "-08:00",
+        "GPSLatitude": "47.6061",
+        "GPSLatitudeRef": "N",
+        "GPSLongitude": "122.3328",
+        "GPSLongitudeRef": "W",
+      },
+      "image.jpg.xmp": {},
+    );
+
+    copy_metadata(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      d.get_path("image.jpg.xmp"),
+    )
+    .unwrap();
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg.xmp")).unwrap();
+    assert_eq!(
+      metadata.date_time_original,
+      Some("2000-01-01T00:00:00.999-08:00".to_string())
+    );
+    assert_eq!(
+      metadata.gps_position,
+      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
+    );
+  }
+
+  #[test]
+  fn converts_quicktime_tags_to_exif() {
+    let d = test_dir!(
+      "video.mov": {
+        "CompressorID": "avc1",
+        "DateTimeOriginal": "2000-01-01T00:00:00.999-08:00",
+        "GPSLatitude": "47.6061 N",
+        "GPSLongitude": "122.3328 W",
+      },
+      "image.jpg": {},
+    );
+
+    copy_metadata(
+      &ExifToolConfig::default(),
+      d.get_path("video.mov"),
+      d.get_path("image.jpg"),
+    )
+    .unwrap();
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg")).unwrap();
+    assert_eq!(
+      metadata.sub_sec_date_time_original,
+      Some("2000-01-01T00:00:00.999-08:00".to_string())
+    );
+    assert_eq!(
+      metadata.gps_position,
+      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
+    );
+  }
+
+  #[test]
+  fn converts_quicktime_tags_to_xmp() {
+    let d = test_dir!(
+      "video.mov": {
+        "CompressorID": "avc1",
+        "DateTimeOriginal": "2000-01-01T00:00:00.999-08:00",
+        "GPSLatitude": "47.6061 N",
+        "GPSLongitude": "122.3328 W",
+      },
+      "video.mov.xmp": {},
+    );
+
+    copy_metadata(
+      &ExifToolConfig::default(),
+      d.get_path("video.mov"),
+      d.get_path("video.mov.xmp"),
+    )
+    .unwrap();
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("video.mov.xmp")).unwrap();
+    assert_eq!(
+      metadata.date_time_original,
+      Some("2000-01-01T00:00:00.999-08:00".to_string())
+    );
+    assert_eq!(
+      metadata.gps_position,
+      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
+    );
+  }
+
+  #[test]
+  fn converts_xmp_tags_to_exif() {
+    let d = test_dir!(
+      "image.jpg.xmp": {
+        "DateTimeOriginal": "2000-01-01T00:00:00.999-08:00",
+        "GPSLatitude": "47.6061 N",
+        "GPSLongitude": "122.3328 W",
+      },
+      "image.jpg": {},
+    );
+
+    copy_metadata(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg.xmp"),
+      d.get_path("image.jpg"),
+    )
+    .unwrap();
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg")).unwrap();
+    assert_eq!(
+      metadata.sub_sec_date_time_original,
+      Some("2000-01-01T00:00:00.999-08:00".to_string())
+    );
+    assert_eq!(
+      metadata.gps_position,
+      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
+    );
+  }
+
+  #[test]
+  fn converts_xmp_tags_to_quicktime() {
+    let d = test_dir!(
+      "video.mov.xmp": {
+        "DateTimeOriginal": "2000-01-01T00:00:00.999-08:00",
+        "GPSLatitude": "47.6061 N",
+        "GPSLongitude": "122.3328 W",
+      },
+      "video.mov": { "CompressorID": "avc1" },
+    );
+
+    copy_metadata(
+      &ExifToolConfig::default(),
+      d.get_path("video.mov.xmp"),
+      d.get_path("video.mov"),
+    )
+    .unwrap();
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("video.mov")).unwrap();
+    assert_eq!(
+      metadata.date_time_original,
+      Some("2000-01-01T00:00:00.999-08:00".to_string())
+    );
+    assert_eq!(
+      metadata.gps_position,
+      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
+    );
+  }
+
+  /// Check that metadata copies over.
+  #[test]
+  fn copies_tag() {
+    let d = test_dir!(
+      "image1.jpg": { "Creator": "Creator" },
+      "image2.jpg": {},
+    );
+
+    copy_metadata(
+      &ExifToolConfig::default(),
+      d.get_path("image1.jpg"),
+      d.get_path("image2.jpg"),
+    )
+    .unwrap();
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("image2.jpg")).unwrap();
+    assert_eq!(metadata.creator, Some("Creator".to_string()));
+  }
+
+  #[test]
+  fn returns_destination_metadata() {
+    let d = test_dir!(
+      "image.jpg": { "Creator": "Creator" },
+      "image.jpg.xmp": {},
+    );
+
+    let metadata_returned = copy_metadata(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      d.get_path("image.jpg.xmp"),
+    )
+    .unwrap();
+
+    let metadata_read =
+      read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg.xmp")).unwrap();
+    assert_eq!(metadata_returned.source_file, metadata_read.source_file);
+    assert_eq!(metadata_returned.creator, metadata_read.creator);
+  }
+}
+
+#[cfg(test)]
+mod test_copy_metadata_tags {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn copies_only_listed_tags() {
+    let d = test_dir!(
+      "image.heic.xmp": {
+        "Creator": "Image",
+        "Subject": "Keyword",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      },
+      "video.mov.xmp": { "Creator": "Video" },
+    );
+
+    let metadata = copy_metadata_tags(
+      &ExifToolConfig::default(),
+      d.get_path("image.heic.xmp"),
+      d.get_path("video.mov.xmp"),
+      &["DateTimeOriginal".to_string()],
+    )
+    .unwrap();
+
+    assert!(metadata.date_time_original.is_some());
+    assert_eq!(metadata.creator, Some("Video".to_string()));
+    assert!(metadata.subject.is_empty());
+    assert_tag!(d, "video.mov.xmp", "Creator", "Video");
+  }
+
+  #[test]
+  fn leaves_unchanged_without_tags() {
+    let d = test_dir!(
+      "image.heic.xmp": { "Creator": "Image" },
+      "video.mov.xmp": { "Creator": "Video" },
+    );
+
+    copy_metadata_tags(
+      &ExifToolConfig::default(),
+      d.get_path("image.heic.xmp"),
+      d.get_path("video.mov.xmp"),
+      &[],
+    )
+    .unwrap();
+
+    assert_tag!(d, "video.mov.xmp", "Creator", "Video");
+  }
+}
+
+#[cfg(test)]
+mod test_create_xmp {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn converts_exif_tags_to_xmp() {
+    let d = test_dir!(
+      "image.jpg": {
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+        "SubSecTimeOriginal": "999",
+        "OffsetTimeOriginal": "-08:00",
+        "GPSLatitude": "47.6061",
+        "GPSLatitudeRef": "N",
+        "GPSLongitude": "122.3328",
+        "GPSLongitudeRef": "W",
+      },
+    );
+
+    create_xmp(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      &SidecarTemplate::default(),
+    )
+    .unwrap();
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg.xmp")).unwrap();
+    assert_eq!(
+      metadata.date_time_original,
+      Some("2000-01-01T00:00:00.999-08:00".to_string())
+    );
+    assert_eq!(
+      metadata.gps_position,
+      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
+    );
+  }
+
+  #[test]
+  fn converts_quicktime_tags_to_xmp() {
+    let d = test_dir!(
+      "video.mov": {
+        "CompressorID": "avc1",
+        "DateTimeOriginal": "2000-01-01T00:00:00.999-08:00",
+        "GPSLatitude": "47.6061 N",
+        "GPSLongitude": "122.3328 W",
+      },
+    );
+
+    create_xmp(
+      &ExifToolConfig::default(),
+      d.get_path("video.mov"),
+      &SidecarTemplate::default(),
+    )
+    .unwrap();
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("video.mov.xmp")).unwrap();
+    assert_eq!(
+      metadata.date_time_original,
+      Some("2000-01-01T00:00:00.999-08:00".to_string())
+    );
+    assert_eq!(
+      metadata.gps_position,
+      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
+    );
+  }
+
+  #[test]
+  fn copies_metadata_to_xmp() {
+    let d = test_dir!(
+      "image.jpg": { "Creator": "Creator" },
+    );
+
+    create_xmp(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      &SidecarTemplate::default(),
+    )
+    .unwrap();
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg.xmp")).unwrap();
+    assert_eq!(metadata.source_file, d.get_path("image.jpg.xmp"));
+    assert_eq!(metadata.creator, Some("Creator".to_string()));
+  }
+
+  #[test]
+  fn creates_xmp() {
+    let d = test_dir!(
+      "image.jpg": { "Creator": "Creator" },
+    );
+
+    create_xmp(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      &SidecarTemplate::default(),
+    )
+    .unwrap();
+
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp"]);
+  }
+
+  #[test]
+  fn errors_if_extension_is_xmp() {
+    let d = test_dir!(
+      "image.jpg.xmp": {},
+    );
+
+    assert_err!(
+      create_xmp(
+        &ExifToolConfig::default(),
+        d.get_path("image.jpg.xmp"),
+        &SidecarTemplate::default()
+      ),
+      "Cannot create XMP (invalid extension)."
+    );
+  }
+
+  #[test]
+  fn errors_if_xmp_already_exists() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+    );
+
+    assert_err!(
+      create_xmp(
+        &ExifToolConfig::default(),
+        d.get_path("image.jpg"),
+        &SidecarTemplate::default()
+      ),
+      "Cannot create XMP (file already exists)."
+    );
+  }
+
+  #[test]
+  fn keeps_copied_creator_over_template() {
+    let d = test_dir!(
+      "image.jpg": { "Creator": "Creator" },
+    );
+
+    let template = SidecarTemplate {
+      creator: Some("Template".to_string()),
+      ..Default::default()
+    };
+    create_xmp(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      &template,
+    )
+    .unwrap();
+
+    assert_tag!(d, "image.jpg.xmp", "Creator", "Creator");
+  }
+
+  #[test]
+  fn seeds_creator_and_copyright_from_template() {
+    let d = test_dir!(
+      "image.jpg": {},
+    );
+
+    let template = SidecarTemplate {
+      creator: Some("Creator".to_string()),
+      ..Default::default()
+    };
+    let metadata = create_xmp(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      &template,
+    )
+    .unwrap();
+
+    assert_eq!(metadata.creator, Some("Creator".to_string()));
+    assert_tag!(d, "image.jpg.xmp", "Creator", "Creator");
+    assert_tag!(d, "image.jpg.xmp", "Copyright", "Copyright Creator");
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp"]);
+  }
+
+  #[test]
+  fn seeds_copyright_with_format() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2024-06-01T12:00:00" },
+    );
+
+    let template = SidecarTemplate {
+      creator:          Some("Creator".to_string()),
+      copyright_format: Some(CopyrightFormat::new("© ${Year} ${Creator}").unwrap()),
+    };
+    create_xmp(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      &template,
+    )
+    .unwrap();
+
+    assert_tag!(d, "image.jpg.xmp", "Copyright", "© 2024 Creator");
+  }
+
+  #[test]
+  fn returns_xmp_metadata() {
+    let d = test_dir!(
+      "image.jpg": { "Creator": "Creator" },
+    );
+
+    let metadata_returned = create_xmp(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      &SidecarTemplate::default(),
+    )
+    .unwrap();
+
+    let metadata_read =
+      read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg.xmp")).unwrap();
+    assert_eq!(metadata_returned.source_file, metadata_read.source_file);
+    assert_eq!(metadata_returned.creator, metadata_read.creator);
+  }
+}
+
+#[cfg(test)]
+mod test_extract_destination {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn parses_destination() {
+    let d = test_dir!(
+      "image.jpg": {}
+    );
+
+    let stdout = String::from_utf8(
+      run_exiftool(&ExifToolConfig::default(), Some(d.root()), [
+        "image.jpg",
+        "-TestName=image_new.jpg",
+      ])
+      .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(
+      extract_destination(&stdout, Path::new("image.jpg")).unwrap(),
+      PathBuf::from("image_new.jpg")
+    );
+  }
+
+  #[test]
+  fn parses_names_with_quotes() {
+    let stdout = "'/src/it's here.jpg' --> '/dst/2000/it's.jpg'\n";
+
+    assert_eq!(
+      extract_destination(stdout, Path::new("/src/it's here.jpg")).unwrap(),
+      PathBuf::from("/dst/2000/it's.jpg")
+    );
+  }
+
+  #[test]
+  fn parses_source_with_arrow() {
+    let stdout = "'/src/a' --> 'b.jpg' --> '/dst/2000/a.jpg'\n";
+
+    assert_eq!(
+      extract_destination(stdout, Path::new("/src/a' --> 'b.jpg")).unwrap(),
+      PathBuf::from("/dst/2000/a.jpg")
+    );
+  }
+
+  #[test]
+  fn parses_source_with_arrow_if_not_matching() {
+    // E.g. if ExifTool prints the source differently than given.
+    let stdout = "'a' --> 'b.jpg' --> '/dst/2000/a.jpg'\n";
+
+    assert_eq!(
+      extract_destination(stdout, Path::new("/src/a' --> 'b.jpg")).unwrap(),
+      PathBuf::from("/dst/2000/a.jpg")
+    );
+  }
+
+  #[test]
+  fn skips_other_lines() {
+    let stdout = "\
+======== /src/image.jpg
+Created directory /dst/2000
+  Created directory /dst/2000/01
+'/src/image.jpg' --> '/dst/2000/01/image.jpg'
+    1 image files updated
+";
+
+    assert_eq!(
+      extract_destination(stdout, Path::new("/src/image.jpg")).unwrap(),
+      PathBuf::from("/dst/2000/01/image.jpg")
+    );
+  }
+
+  #[test]
+  fn parses_repeated_rename() {
+    let stdout = "'/src/a.jpg' --> '/dst/a.jpg'\r\n'/src/a.jpg' --> '/dst/a.jpg'\r\n";
+
+    assert_eq!(
+      extract_destination(stdout, Path::new("/src/a.jpg")).unwrap(),
+      PathBuf::from("/dst/a.jpg")
+    );
+  }
+
+  #[test]
+  fn errors_if_multiple_renames() {
+    let stdout = "'/src/a.jpg' --> '/dst/a.jpg'\n'/src/a.jpg' --> '/dst/b.jpg'\n";
+
+    assert_err!(
+      extract_destination(stdout, Path::new("/src/a.jpg")),
+      "multiple renames"
+    );
+  }
+
+  #[test]
+  fn errors_if_no_rename() {
+    assert_err!(
+      extract_destination("    0 image files updated\n", Path::new("/src/a.jpg")),
+      "did not contain a destination"
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_parse_stderr {
+  use super::*;
+
+  #[test]
+  fn parses_warnings_and_errors_with_files() {
+    let stderr = "Warning: [minor] Maker notes could not be parsed - ./a/image.jpg\nError: Not a \
+                  valid JPG (looks more like a PNG) - ./b/image.jpg\n";
+
+    assert_eq!(parse_stderr(stderr), [
+      StderrMessage {
+        is_error: false,
+        message:  "[minor] Maker notes could not be parsed",
+        file:     Some("./a/image.jpg"),
+      },
+      StderrMessage {
+        is_error: true,
+        message:  "Not a valid JPG (looks more like a PNG)",
+        file:     Some("./b/image.jpg"),
+      },
+    ]);
+  }
+
+  #[test]
+  fn splits_file_at_last_separator() {
+    let stderr = "Error: File not found - ./a - b.jpg\n";
+
+    assert_eq!(parse_stderr(stderr), [StderrMessage {
+      is_error: true,
+      message:  "File not found - ./a",
+      file:     Some("b.jpg"),
+    }]);
+  }
+
+  #[test]
+  fn parses_messages_without_file() {
+    let stderr = "Warning: Tag 'Foo' is not defined\n";
+
+    assert_eq!(parse_stderr(stderr), [StderrMessage {
+      is_error: false,
+      message:  "Tag 'Foo' is not defined",
+      file:     None,
+    }]);
+  }
+
+  #[test]
+  fn skips_other_lines() {
+    let stderr = "    1 image files updated\nNothing to do.\n";
+
+    assert!(parse_stderr(stderr).is_empty());
+  }
+}
+
+#[cfg(test)]
+mod test_check_stderr {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn succeeds_without_stderr() {
+    check_stderr(true, b"", "").unwrap();
+  }
+
+  #[test]
+  fn succeeds_with_only_warnings() {
+    check_stderr(
+      true,
+      b"Warning: [minor] Maker notes could not be parsed - image.jpg\n",
+      "",
+    )
+    .unwrap();
+  }
+
+  #[test]
+  fn errors_on_failure_with_only_warnings() {
+    assert_err!(
+      check_stderr(
+        false,
+        b"Warning: Nothing to write - image.jpg\n",
+        "image.jpg"
+      ),
+      "ExifTool did not run successfully, reporting 1 \
+       warning(s).\nArgs:\nimage.jpg\nWarnings:\nimage.jpg: Nothing to write"
+    );
+  }
+
+  #[test]
+  fn errors_on_error_despite_success() {
+    assert_err!(
+      check_stderr(
+        true,
+        b"Warning: Nothing to write - a.jpg\n\
+          Error: File format error - b.jpg\n",
+        "a.jpg b.jpg",
+      ),
+      "ExifTool reported 1 error(s).\nArgs:\na.jpg b.jpg\nErrors:\nb.jpg: File format error"
+    );
+  }
+
+  #[test]
+  fn errors_on_failure_without_warnings() {
+    assert_err!(
+      check_stderr(false, b"Can't locate Image/ExifTool.pm\n", "-ver"),
+      "ExifTool did not run successfully."
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_format_counter {
+  use super::*;
+
+  #[test]
+  fn omits_first_copy() {
+    assert_eq!(format_counter(CounterStyle::Letter, 0), "");
+    assert_eq!(format_counter(CounterStyle::Numeric, 0), "");
+  }
+
+  #[test]
+  fn formats_letters() {
+    assert_eq!(format_counter(CounterStyle::Letter, 1), "_b");
+    assert_eq!(format_counter(CounterStyle::Letter, 25), "_z");
+    assert_eq!(format_counter(CounterStyle::Letter, 26), "_ba");
+  }
+
+  #[test]
+  fn formats_numbers() {
+    assert_eq!(format_counter(CounterStyle::Numeric, 1), "_001");
+    assert_eq!(format_counter(CounterStyle::Numeric, 1000), "_1000");
+  }
+}
+
+#[cfg(test)]
+mod test_format_args_quoted {
+  use super::*;
+
+  #[test]
+  fn leaves_plain_args() {
+    assert_eq!(
+      format_args_quoted(["-json", "-r", "dir/image.jpg"].map(OsStr::new)),
+      "-json -r dir/image.jpg"
+    );
+  }
+
+  #[test]
+  fn quotes_args_with_spaces() {
+    assert_eq!(
+      format_args_quoted(["-json", "my dir/image 1.jpg"].map(OsStr::new)),
+      "-json 'my dir/image 1.jpg'"
+    );
+  }
+
+  #[test]
+  fn quotes_args_with_quotes() {
+    assert_eq!(
+      format_args_quoted(["-Copyright<Copyright ${Creator}", "it's.jpg", ""].map(OsStr::new)),
+      r"'-Copyright<Copyright ${Creator}' 'it'\''s.jpg' ''"
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_format_file_name_date {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn errors_if_no_date_time() {
+    assert_err!(
+      format_file_name_date(&metadata!(), FileNameFormat::default()),
+      "No date and time to name file by."
+    );
+  }
+
+  #[test]
+  fn errors_if_only_unset_date_time() {
+    let metadata = metadata!(
+      "DateTimeOriginal": "0000:00:00 00:00:00",
+    );
+
+    assert_err!(
+      format_file_name_date(&metadata, FileNameFormat::default()),
+      "No date and time to name file by."
+    );
+  }
+
+  #[test]
+  fn skips_unset_date_time_original() {
+    let metadata = metadata!(
+      "DateTimeOriginal": "0000:00:00 00:00:00",
+      "CreateDate": "2000-01-01T00:00:00",
+    );
+
+    assert_eq!(
+      format_file_name_date(&metadata, FileNameFormat::default()).unwrap(),
+      "2000/01/000101_000000000"
+    );
+  }
+
+  #[test]
+  fn falls_back_to_create_date() {
+    let metadata = metadata!(
+      "CreateDate": "2000-01-01T00:00:00",
+    );
+
+    assert_eq!(
+      format_file_name_date(&metadata, FileNameFormat::default()).unwrap(),
+      "2000/01/000101_000000000"
+    );
+  }
+
+  #[test]
+  fn falls_back_to_video_dates() {
+    let metadata = metadata!(
+      "MediaCreateDate": "2000-01-01T00:00:00",
+      "TrackCreateDate": "2010-01-01T00:00:00",
+    );
+
+    assert_eq!(
+      format_file_name_date(&metadata, FileNameFormat::default()).unwrap(),
+      "2000/01/000101_000000000"
+    );
+  }
+
+  #[test]
+  fn prefers_sub_sec_date_time_original() {
+    let metadata = metadata!(
+      "CreateDate": "2010-01-01T00:00:00",
+      "DateTimeOriginal": "2000-01-01T00:00:00",
+      "SubSecDateTimeOriginal": "2000-01-01T00:00:00.123456+00:00",
+    );
+
+    assert_eq!(
+      format_file_name_date(&metadata, FileNameFormat::default()).unwrap(),
+      "2000/01/000101_000000123"
+    );
+    assert_eq!(
+      format_file_name_date(&metadata, FileNameFormat {
+        sub_sec_digits: 6,
+        ..Default::default()
+      })
+      .unwrap(),
+      "2000/01/000101_000000123456"
+    );
+  }
+
+  #[test]
+  fn uses_date_source() {
+    let metadata = metadata!(
+      "FileModifyDate": "2020-01-01T00:00:00+00:00",
+      "CreateDate": "2010-01-01T00:00:00",
+      "DateTimeOriginal": "2000-01-01T00:00:00",
+    );
+
+    let format = |date_source| FileNameFormat {
+      date_source,
+      ..Default::default()
+    };
+
+    assert_eq!(
+      format_file_name_date(&metadata, format(RenameDateSource::Capture)).unwrap(),
+      "2000/01/000101_000000000"
+    );
+    assert_eq!(
+      format_file_name_date(&metadata, format(RenameDateSource::Creation)).unwrap(),
+      "2010/01/100101_000000000"
+    );
+    assert_eq!(
+      format_file_name_date(&metadata, format(RenameDateSource::Modify)).unwrap(),
+      "2020/01/200101_000000000"
+    );
+  }
+
+  #[test]
+  fn falls_back_to_date_time_original_if_no_create_date() {
+    let metadata = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:00",
+    );
+
+    assert_eq!(
+      format_file_name_date(&metadata, FileNameFormat {
+        date_source: RenameDateSource::Creation,
+        ..Default::default()
+      })
+      .unwrap(),
+      "2000/01/000101_000000000"
+    );
+  }
+
+  #[test]
+  fn uses_own_time_zone_if_local_time() {
+    let metadata = metadata!(
+      "FileType": "XMP",
+      "DateTimeOriginal": "2000-01-31T23:00:00-08:00",
+    );
+
+    assert_eq!(
+      format_file_name_date(&metadata, FileNameFormat::default()).unwrap(),
+      "2000/02/000201_070000000"
+    );
+    assert_eq!(
+      format_file_name_date(&metadata, FileNameFormat {
+        local_time: true,
+        ..Default::default()
+      })
+      .unwrap(),
+      "2000/01/000131_230000000"
+    );
+  }
+
+  #[test]
+  fn omits_date_dirs_if_disabled() {
+    let metadata = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:00",
+    );
+
+    assert_eq!(
+      format_file_name_date(&metadata, FileNameFormat {
+        date_dirs: false,
+        ..Default::default()
+      })
+      .unwrap(),
+      "000101_000000000"
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_make_canonical {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn errors_if_path_does_not_exist() {
+    assert_err!(
+      make_canonical("/path/does/not/exist"),
+      "Path does not exist."
+    );
+  }
+
+  #[test]
+  fn errors_if_path_is_relative() {
+    assert_err!(make_canonical("relative/path"), "Path is not absolute.");
+  }
+
+  #[test]
+  fn errors_by_kind() {
+    assert!(matches!(
+      make_canonical("/path/does/not/exist"),
+      Err(CatalogError::PathNotFound(p, _)) if p == Path::new("/path/does/not/exist")
+    ));
+    assert!(matches!(
+      make_canonical("relative/path"),
+      Err(CatalogError::PathNotAbsolute(p, _)) if p == Path::new("relative/path")
+    ));
+  }
+
+  #[test]
+  fn returns_canonical_path() {
+    let d = test_dir!(
+      "image.jpg": {},
+    );
+
+    let path = make_canonical(d.get_path("image.jpg")).unwrap();
+
+    assert!(path.is_absolute());
+    assert!(path.exists());
+    assert!(!path.is_symlink());
+  }
+}
+
+#[cfg(test)]
+mod test_exiftool_command {
+  use super::*;
+
+  #[test]
+  fn appends_extra_args() {
+    let mut exiftool = ExifToolConfig::default();
+    exiftool.set_extra_args(["-api", "LargeFileSupport=1"].map(String::from));
+
+    let cmd = exiftool_command(&exiftool, None::<&Path>, ["-json", "image.jpg"]).unwrap();
+
+    assert_eq!(cmd.get_args().collect::<Vec<_>>(), [
+      "-json",
+      "image.jpg",
+      "-api",
+      "LargeFileSupport=1"
+    ]);
+  }
+
+  #[test]
+  fn adds_nothing_without_extra_args() {
+    let cmd = exiftool_command(&ExifToolConfig::default(), None::<&Path>, ["-ver"]).unwrap();
+
+    assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["-ver"]);
+  }
+
+  #[test]
+  fn runs_configured_program() {
+    let mut exiftool = ExifToolConfig::default();
+    exiftool.set_path(PathBuf::from("/a/exiftool"));
+
+    let cmd = exiftool_command(&exiftool, None::<&Path>, ["-ver"]).unwrap();
+
+    assert_eq!(cmd.get_program(), "/a/exiftool");
+  }
+}
+
+#[cfg(test)]
+mod test_read_args {
+  use super::*;
+
+  #[test]
+  fn reads_geolocation_only_if_enabled() {
+    let mut exiftool = ExifToolConfig::default();
+    assert!(!exiftool.read_args().any(|a| a == "geolocation"));
+
+    exiftool.enable_geolocation();
+    assert!(exiftool.read_args().any(|a| a == "geolocation"));
+  }
+
+  #[test]
+  fn keeps_configs_independent() {
+    let mut latin1 = ExifToolConfig::default();
+    latin1.set_charsets(["IPTC=Latin1".to_string()]).unwrap();
+
+    assert!(latin1.read_args().any(|a| a == "IPTC=Latin1"));
+    assert!(
+      !ExifToolConfig::default()
+        .read_args()
+        .any(|a| a == "IPTC=Latin1")
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_make_charset_args {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn errors_if_not_by_type() {
+    assert_err!(
+      make_charset_args(["Latin1".to_string()]),
+      "Charset must be as `TYPE=CHARSET`"
+    );
+    assert_err!(
+      make_charset_args(["IPTC=".to_string()]),
+      "Charset must be as `TYPE=CHARSET`"
+    );
+  }
+
+  #[test]
+  fn errors_if_output() {
+    assert_err!(
+      make_charset_args(["exiftool=Latin1".to_string()]),
+      "Charset must be as `TYPE=CHARSET`"
+    );
+  }
+
+  #[test]
+  fn makes_arg_per_charset() {
+    assert_eq!(
+      make_charset_args(["IPTC=Latin1".to_string(), "EXIF=UTF8".to_string()]).unwrap(),
+      ["-charset", "IPTC=Latin1", "-charset", "EXIF=UTF8"].map(OsString::from)
+    );
+  }
+
+  #[test]
+  fn decodes_latin1_iptc() {
+    let d = test_dir!(
+      "image.jpg": {},
+    );
+    run_exiftool(&ExifToolConfig::default(), None::<&Path>, [
+      "-charset",
+      "IPTC=Latin1",
+      "-IPTC:By-line=José Muñoz",
+      "-overwrite_original",
+      d.get_path("image.jpg").to_str().unwrap(),
+    ])
+    .unwrap();
+
+    let mut exiftool = ExifToolConfig::default();
+    exiftool.set_charsets(["IPTC=Latin1".to_string()]).unwrap();
+    let metadata = read_metadata(&exiftool, d.get_path("image.jpg")).unwrap();
+
+    assert_eq!(
+      metadata.other_tags.get("By-line"),
+      Some(&serde_json::Value::from("José Muñoz"))
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_make_posix_time_zone {
+  use super::*;
+
+  #[test]
+  fn inverts_sign() {
+    assert_eq!(
+      make_posix_time_zone(FixedOffset::west_opt(8 * 3600).unwrap()),
+      "UTC+08:00"
+    );
+    assert_eq!(
+      make_posix_time_zone(FixedOffset::east_opt(5 * 3600 + 1800).unwrap()),
+      "UTC-05:30"
+    );
+    assert_eq!(
+      make_posix_time_zone(FixedOffset::east_opt(0).unwrap()),
+      "UTC+00:00"
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_make_trash_run_dir {
+  use chrono::NaiveDate;
+
+  use super::*;
+  use crate::testing::*;
+
+  fn time() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, 1, 2)
+      .unwrap()
+      .and_hms_opt(15, 30, 45)
+      .unwrap()
+  }
+
+  #[test]
+  fn names_by_time() {
+    let d = test_dir!();
+
+    let dir = make_trash_run_dir(d.trash(), time()).unwrap();
+
+    assert_eq!(dir, d.get_trash("2024-01-02T1530"));
+    assert!(dir.is_dir());
+  }
+
+  #[test]
+  fn adds_counter_if_taken() {
+    let d = test_dir!();
+
+    make_trash_run_dir(d.trash(), time()).unwrap();
+    make_trash_run_dir(d.trash(), time()).unwrap();
+    let dir = make_trash_run_dir(d.trash(), time()).unwrap();
+
+    assert_eq!(dir, d.get_trash("2024-01-02T1530_3"));
+    assert!(d.get_trash("2024-01-02T1530_2").is_dir());
+  }
+
+  #[test]
+  fn errors_if_trash_missing() {
+    let d = test_dir!();
+
+    assert_err!(
+      make_trash_run_dir(d.get_path("missing"), time()),
+      "Unable to create trash directory"
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_prune_trash {
+  use std::{collections::HashSet, time::Duration};
+
+  use super::*;
+  use crate::testing::*;
+
+  const DAY: Duration = Duration::from_hours(24);
+
+  /// Creates `file` in trash, last modified `age` ago.
+  fn create_trashed(d: &TestDir, file: &str, age: Duration) {
+    let path = d.get_trash(file);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, "").unwrap();
+    set_modify_time(&path, SystemTime::now() - age).unwrap();
+  }
+
+  #[test]
+  fn prunes_old_files_and_keeps_recent() {
+    let d = test_dir!();
+    create_trashed(&d, "old.jpg", 40 * DAY);
+    create_trashed(&d, "dir/old.jpg.xmp", 40 * DAY);
+    create_trashed(&d, "recent.jpg", DAY);
+
+    let pruned = prune_trash(d.trash(), d.root(), Some(SystemTime::now() - 30 * DAY)).unwrap();
+
+    assert_eq!(pruned.len(), 2);
+    assert_eq!(d.files_trash(), HashSet::from([d.get_trash("recent.jpg")]));
+    assert!(!d.get_trash("dir").exists());
+  }
+
+  #[test]
+  fn keeps_dirs_with_recent_files() {
+    let d = test_dir!();
+    create_trashed(&d, "dir/old.jpg", 40 * DAY);
+    create_trashed(&d, "dir/recent.jpg", DAY);
+
+    prune_trash(d.trash(), d.root(), Some(SystemTime::now() - 30 * DAY)).unwrap();
+
+    assert_eq!(
+      d.files_trash(),
+      HashSet::from([d.get_trash("dir/recent.jpg")])
+    );
+  }
+
+  #[test]
+  fn keeps_old_files_trashed_recently() {
+    let d = test_dir!();
+    fs::write(d.get_path("image.jpg"), "").unwrap();
+    set_modify_time(d.get_path("image.jpg"), SystemTime::now() - 400 * DAY).unwrap();
+    remove_file(d.root(), d.trash(), d.get_path("image.jpg")).unwrap();
+
+    let pruned = prune_trash(d.trash(), d.root(), Some(SystemTime::now() - 30 * DAY)).unwrap();
+
+    assert!(pruned.is_empty());
+    assert_trash!(d, ["image.jpg"]);
+  }
+
+  #[test]
+  fn prunes_recent_files_trashed_long_ago() {
+    let d = test_dir!();
+    create_trashed(&d, "dir/image.jpg", DAY);
+    let trashed = SystemTime::now() - 40 * DAY;
+    let secs = trashed
+      .duration_since(SystemTime::UNIX_EPOCH)
+      .unwrap()
+      .as_secs();
+    fs::write(
+      d.get_trash(TRASH_RECORD),
+      format!("{secs}\tdir/image.jpg\n"),
+    )
+    .unwrap();
+
+    let pruned = prune_trash(d.trash(), d.root(), Some(SystemTime::now() - 30 * DAY)).unwrap();
+
+    assert_eq!(pruned, vec![d.get_trash("dir/image.jpg")]);
+    assert!(!d.get_trash(TRASH_RECORD).exists());
+  }
+
+  #[test]
+  fn prunes_all_without_cutoff() {
+    let d = test_dir!();
+    create_trashed(&d, "old.jpg", 40 * DAY);
+    create_trashed(&d, "recent.jpg", Duration::ZERO);
+
+    prune_trash(d.trash(), d.root(), None).unwrap();
+
+    assert!(d.files_trash().is_empty());
+    assert!(d.trash().is_dir());
+  }
+
+  #[test]
+  fn errors_if_trash_contains_catalog() {
+    let d = test_dir!();
+    fs::create_dir(d.get_trash("catalog")).unwrap();
+    create_trashed(&d, "old.jpg", 40 * DAY);
+
+    assert_err!(
+      prune_trash(d.trash(), d.get_trash("catalog"), None),
+      "Cannot prune trash containing the catalog"
+    );
+    assert_err!(
+      prune_trash(d.root(), d.root(), None),
+      "Cannot prune trash containing the catalog"
+    );
+    assert!(d.get_trash("old.jpg").exists());
+  }
+
+  #[test]
+  fn errors_if_trash_missing() {
+    let d = test_dir!();
+
+    assert!(prune_trash(d.get_path("missing"), d.root(), None).is_err());
+  }
+}
+
+#[cfg(test)]
+mod test_move_file {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn adds_counter_when_same_time() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "OffsetTimeOriginal": "+00:00" },
+      "image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "OffsetTimeOriginal": "+00:00" },
+    );
+
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image1.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image2.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000_b.jpg",
+    ]);
+  }
+
+  #[test]
+  fn names_in_destination_without_date_dirs() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "OffsetTimeOriginal": "+00:00" },
+      "image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "OffsetTimeOriginal": "+00:00" },
+    );
+    let format = FileNameFormat {
+      date_dirs: false,
+      ..Default::default()
+    };
+
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image1.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      format,
+      None,
+    )
+    .unwrap();
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image2.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      format,
+      None,
+    )
+    .unwrap();
+
+    assert_dir!(d, ["000101_000000000.jpg", "000101_000000000_b.jpg"]);
+  }
+
+  #[test]
+  fn adds_numeric_counter_when_same_time() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "OffsetTimeOriginal": "+00:00" },
+      "image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "OffsetTimeOriginal": "+00:00" },
+    );
+
+    let format = FileNameFormat {
+      counter: CounterStyle::Numeric,
+      ..Default::default()
+    };
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image1.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      format,
+      None,
+    )
+    .unwrap();
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image2.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      format,
+      None,
+    )
+    .unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000_001.jpg",
+    ]);
+  }
+
+  #[test]
+  fn distinguishes_microseconds_when_widened() {
+    let d = test_dir!(
+      "image1.jpg": {
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+        "SubSecTimeOriginal": "123456",
+        "OffsetTimeOriginal": "+00:00",
+      },
+      "image2.jpg": {
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+        "SubSecTimeOriginal": "123789",
+        "OffsetTimeOriginal": "+00:00",
+      },
+    );
+
+    let format = FileNameFormat {
+      sub_sec_digits: 6,
+      ..Default::default()
+    };
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image1.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      format,
+      None,
+    )
+    .unwrap();
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image2.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      format,
+      None,
+    )
+    .unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000123456.jpg",
+      "2000/01/000101_000000123789.jpg",
+    ]);
+  }
+
+  #[test]
+  fn errors_if_no_date_time_tags() {
+    let d = test_dir!(
+      "image.jpg": {},
+    );
+
+    assert_err!(
+      move_file(
+        &ExifToolConfig::default(),
+        d.get_path("image.jpg"),
+        None::<&Path>,
+        d.root(),
+        ".jpg",
+        FileNameFormat::default(),
+        None
+      ),
+      "Failed to move file."
+    );
+  }
+
+  #[test]
+  fn uses_create_date_if_date_source_creation() {
+    let d = test_dir!(
+      "image.jpg": {
+        "CreateDate": "2025-01-01T00:00:00",
+        "OffsetTimeDigitized": "+00:00",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+        "OffsetTimeOriginal": "+00:00",
+      },
+    );
+
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      FileNameFormat {
+        date_source: RenameDateSource::Creation,
+        ..Default::default()
+      },
+      None,
+    )
+    .unwrap();
+
+    assert_dir!(d, ["2025/01/250101_000000000.jpg"]);
+  }
+
+  #[test]
+  fn uses_create_date_as_fallback_from_exif() {
+    let d = test_dir!(
+      "image.jpg": {
+        "CreateDate": "2025-01-01T00:00:00",
+        "OffsetTimeDigitized": "+00:00",
+      },
+    );
+
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
+
+    assert_dir!(d, ["2025/01/250101_000000000.jpg"]);
+  }
+
+  #[test]
+  fn uses_create_date_as_fallback_from_xmp() {
+    let d = test_dir!(
+      "image.jpg.xmp": {
+        "CreateDate": "2025-01-01T00:00:00+00:00",
+      },
+    );
+
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg.xmp"),
+      None::<&Path>,
+      d.root(),
+      ".jpg.xmp",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
+
+    assert_dir!(d, ["2025/01/250101_000000000.jpg.xmp"]);
+  }
+
+  #[test]
+  fn uses_date_time_original_as_primary_from_exif() {
+    let d = test_dir!(
+      "image.jpg": {
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+        "OffsetTimeOriginal": "+00:00",
+        "CreateDate": "2025-01-01T00:00:00",
+        "OffsetTimeDigitized": "+00:00",
+      },
+    );
+
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
+
+    assert_dir!(d, ["2000/01/000101_000000000.jpg"]);
+  }
+
+  #[test]
+  fn uses_date_time_original_as_primary_from_xmp() {
+    let d = test_dir!(
+      "image.jpg.xmp": {
+        "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
+        "CreateDate": "2025-01-01T00:00:00+00:00",
+      },
+    );
+
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg.xmp"),
+      None::<&Path>,
+      d.root(),
+      ".jpg.xmp",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
+
+    assert_dir!(d, ["2000/01/000101_000000000.jpg.xmp"]);
+  }
+
+  #[test]
+  fn renames_in_utc_from_exif() {
+    let d = test_dir!(
+      "image.jpg": {
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+        "OffsetTimeOriginal": "-08:00",
+      },
+    );
+
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
+
+    assert_dir!(d, ["2000/01/000101_080000000.jpg"]);
+  }
+
+  #[test]
+  fn renames_in_utc_from_xmp() {
+    let d = test_dir!(
+      "image.jpg": {
+        "DateTimeOriginal": "2000-01-01T00:00:00-08:00",
+      },
+    );
+
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
+
+    assert_dir!(d, ["2000/01/000101_080000000.jpg"]);
+  }
+
+  #[test]
+  fn renames_in_local_time_from_exif() {
+    let d = test_dir!(
       "image.jpg": {
-        "DateTimeOriginal": "2000-01-01T00:00:00",
-        "SubSecTimeOriginal": "999",
+        "DateTimeOriginal": "2000-01-31T23:00:00",
         "OffsetTimeOriginal": "-08:00",
-        "GPSLatitude": "47.6061",
-        "GPSLatitudeRef": "N",
-        "GPSLongitude": "122.3328",
-        "GPSLongitudeRef": "W",
       },
-      "image.jpg.xmp": {},
     );
 
-    copy_metadata(d.get_path("image.jpg"), d.get_path("image.jpg.xmp")).unwrap();
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      FileNameFormat {
+        local_time: true,
+        ..Default::default()
+      },
+      Some(FixedOffset::west_opt(8 * 3600).unwrap()),
+    )
+    .unwrap();
 
-    let metadata = read_metadata(d.get_path("image.jpg.xmp")).unwrap();
-    assert_eq!(
-      metadata.date_time_original,
-      Some("2000-01-01T00:00:00.999-08:00".to_string())
-    );
-    assert_eq!(
-      metadata.gps_position,
-      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
-    );
+    assert_dir!(d, ["2000/01/000131_230000000.jpg"]);
   }
 
   #[test]
-  fn converts_quicktime_tags_to_exif() {
+  fn renames_in_local_time_from_xmp() {
     let d = test_dir!(
-      "video.mov": {
-        "CompressorID": "avc1",
-        "DateTimeOriginal": "2000-01-01T00:00:00.999-08:00",
-        "GPSLatitude": "47.6061 N",
-        "GPSLongitude": "122.3328 W",
-      },
       "image.jpg": {},
+      "image.jpg.xmp": {
+        "DateTimeOriginal": "2000-01-31T23:00:00-08:00",
+      },
     );
 
-    copy_metadata(d.get_path("video.mov"), d.get_path("image.jpg")).unwrap();
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      Some(d.get_path("image.jpg.xmp")),
+      d.root(),
+      ".jpg",
+      FileNameFormat {
+        local_time: true,
+        ..Default::default()
+      },
+      Some(FixedOffset::west_opt(8 * 3600).unwrap()),
+    )
+    .unwrap();
 
-    let metadata = read_metadata(d.get_path("image.jpg")).unwrap();
-    assert_eq!(
-      metadata.sub_sec_date_time_original,
-      Some("2000-01-01T00:00:00.999-08:00".to_string())
-    );
-    assert_eq!(
-      metadata.gps_position,
-      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
-    );
+    assert_dir!(d, ["2000/01/000131_230000000.jpg", "image.jpg.xmp"]);
   }
 
   #[test]
-  fn converts_quicktime_tags_to_xmp() {
+  fn renames_in_utc_by_default() {
     let d = test_dir!(
-      "video.mov": {
-        "CompressorID": "avc1",
-        "DateTimeOriginal": "2000-01-01T00:00:00.999-08:00",
-        "GPSLatitude": "47.6061 N",
-        "GPSLongitude": "122.3328 W",
+      "image.jpg": {
+        "DateTimeOriginal": "2000-01-31T23:00:00",
+        "OffsetTimeOriginal": "-08:00",
       },
-      "video.mov.xmp": {},
     );
 
-    copy_metadata(d.get_path("video.mov"), d.get_path("video.mov.xmp")).unwrap();
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
 
-    let metadata = read_metadata(d.get_path("video.mov.xmp")).unwrap();
-    assert_eq!(
-      metadata.date_time_original,
-      Some("2000-01-01T00:00:00.999-08:00".to_string())
-    );
-    assert_eq!(
-      metadata.gps_position,
-      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
-    );
+    assert_dir!(d, ["2000/02/000201_070000000.jpg"]);
   }
 
   #[test]
-  fn converts_xmp_tags_to_exif() {
+  fn renames_with_subseconds_from_exif() {
     let d = test_dir!(
-      "image.jpg.xmp": {
-        "DateTimeOriginal": "2000-01-01T00:00:00.999-08:00",
-        "GPSLatitude": "47.6061 N",
-        "GPSLongitude": "122.3328 W",
+      "image.jpg": {
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+        "SubSecTimeOriginal": "999",
+        "OffsetTimeOriginal": "+00:00",
       },
-      "image.jpg": {},
     );
 
-    copy_metadata(d.get_path("image.jpg.xmp"), d.get_path("image.jpg")).unwrap();
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
 
-    let metadata = read_metadata(d.get_path("image.jpg")).unwrap();
-    assert_eq!(
-      metadata.sub_sec_date_time_original,
-      Some("2000-01-01T00:00:00.999-08:00".to_string())
-    );
-    assert_eq!(
-      metadata.gps_position,
-      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
-    );
+    assert_dir!(d, ["2000/01/000101_000000999.jpg"]);
   }
 
   #[test]
-  fn converts_xmp_tags_to_quicktime() {
+  fn renames_with_subseconds_from_xmp() {
     let d = test_dir!(
-      "video.mov.xmp": {
-        "DateTimeOriginal": "2000-01-01T00:00:00.999-08:00",
-        "GPSLatitude": "47.6061 N",
-        "GPSLongitude": "122.3328 W",
+      "image.jpg.xmp": {
+        "DateTimeOriginal": "2000-01-01T00:00:00.999+00:00",
       },
-      "video.mov": { "CompressorID": "avc1" },
     );
 
-    copy_metadata(d.get_path("video.mov.xmp"), d.get_path("video.mov")).unwrap();
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg.xmp"),
+      None::<&Path>,
+      d.root(),
+      ".jpg.xmp",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
+
+    assert_dir!(d, ["2000/01/000101_000000999.jpg.xmp"]);
+  }
 
-    let metadata = read_metadata(d.get_path("video.mov")).unwrap();
-    assert_eq!(
-      metadata.date_time_original,
-      Some("2000-01-01T00:00:00.999-08:00".to_string())
-    );
-    assert_eq!(
-      metadata.gps_position,
-      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
+  #[test]
+  fn returns_new_path() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
     );
+
+    let p = move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      None::<&Path>,
+      d.root(),
+      ".jpg",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
+
+    assert_eq!(p, d.get_path("2000/01/000101_000000000.jpg"));
   }
 
-  /// Check that metadata copies over.
   #[test]
-  fn copies_tag() {
+  fn uses_tag_source() {
     let d = test_dir!(
-      "image1.jpg": { "Creator": "Creator" },
-      "image2.jpg": {},
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.jpg.xmp": { "DateTimeOriginal": "2025-01-01T00:00:00" },
     );
 
-    copy_metadata(d.get_path("image1.jpg"), d.get_path("image2.jpg")).unwrap();
+    move_file(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg"),
+      Some(d.get_path("image.jpg.xmp")),
+      d.root(),
+      ".jpg",
+      FileNameFormat::default(),
+      None,
+    )
+    .unwrap();
 
-    let metadata = read_metadata(d.get_path("image2.jpg")).unwrap();
-    assert_eq!(metadata.creator, Some("Creator".to_string()));
+    assert_dir!(d, ["2025/01/250101_000000000.jpg", "image.jpg.xmp"]);
   }
+}
+
+#[cfg(test)]
+mod test_read_file_modify_dates_recursive {
+  use super::*;
+  use crate::testing::*;
 
   #[test]
-  fn returns_destination_metadata() {
+  fn reads_all_files_except_excluded() {
     let d = test_dir!(
-      "image.jpg": { "Creator": "Creator" },
-      "image.jpg.xmp": {},
+      "image.jpg": {},
+      "dir/image.jpg": {},
     );
 
-    let metadata_returned =
-      copy_metadata(d.get_path("image.jpg"), d.get_path("image.jpg.xmp")).unwrap();
+    let dates = read_file_modify_dates_recursive(
+      &ExifToolConfig::default(),
+      d.root(),
+      [d.trash(), &d.get_path("dir")],
+      false,
+      false,
+    )
+    .unwrap();
 
-    let metadata_read = read_metadata(d.get_path("image.jpg.xmp")).unwrap();
-    assert_eq!(metadata_returned.source_file, metadata_read.source_file);
-    assert_eq!(metadata_returned.creator, metadata_read.creator);
+    assert_eq!(dates.len(), 1);
+    assert_eq!(d.get_path(&dates[0].0), d.get_path("image.jpg"));
+    assert!(!dates[0].1.is_empty());
   }
 }
 
 #[cfg(test)]
-mod test_create_xmp {
+mod test_read_metadata {
+  use chrono::NaiveDate;
+
   use super::*;
-  use crate::testing::*;
+  use crate::{prim, testing::*};
 
   #[test]
-  fn converts_exif_tags_to_xmp() {
+  fn errors_if_file_does_not_exist() {
+    let d = test_dir!();
+    assert_err!(
+      read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg")),
+      "Path does not exist."
+    );
+  }
+
+  #[test]
+  fn reads_no_time_zone_as_local() {
     let d = test_dir!(
-      "image.jpg": {
-        "DateTimeOriginal": "2000-01-01T00:00:00",
-        "SubSecTimeOriginal": "999",
-        "OffsetTimeOriginal": "-08:00",
-        "GPSLatitude": "47.6061",
-        "GPSLatitudeRef": "N",
-        "GPSLongitude": "122.3328",
-        "GPSLongitudeRef": "W",
-      },
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
     );
 
-    create_xmp(d.get_path("image.jpg")).unwrap();
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg.xmp")).unwrap();
+
+    let local_date_time = NaiveDate::from_ymd_opt(2000, 1, 1)
+      .and_then(|d| d.and_hms_opt(0, 0, 0))
+      .unwrap();
+    let local_offset = prim::get_offset_local(&local_date_time).to_string();
 
-    let metadata = read_metadata(d.get_path("image.jpg.xmp")).unwrap();
     assert_eq!(
       metadata.date_time_original,
-      Some("2000-01-01T00:00:00.999-08:00".to_string())
+      Some(format!("2000-01-01T00:00:00{local_offset}"))
+    );
+  }
+
+  #[test]
+  fn reads_tag() {
+    let d = test_dir!(
+      "image.jpg": { "Creator": "Creator" },
+    );
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg")).unwrap();
+
+    assert_eq!(metadata.creator, Some("Creator".to_string()));
+  }
+
+  #[test]
+  fn reads_time_zone() {
+    let d = test_dir!(
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00-08:00" },
     );
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg.xmp")).unwrap();
+
     assert_eq!(
-      metadata.gps_position,
-      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
+      metadata.date_time_original,
+      Some("2000-01-01T00:00:00-08:00".to_string())
     );
   }
 
   #[test]
-  fn converts_quicktime_tags_to_xmp() {
+  fn returns_source_file_relative_to_root() {
     let d = test_dir!(
-      "video.mov": {
-        "CompressorID": "avc1",
-        "DateTimeOriginal": "2000-01-01T00:00:00.999-08:00",
-        "GPSLatitude": "47.6061 N",
-        "GPSLongitude": "122.3328 W",
-      },
+      "dir/image.jpg": {},
+    );
+
+    let metadata = read_metadata(&ExifToolConfig::default(), d.get_path("dir/image.jpg")).unwrap();
+
+    assert!(d.root().join(metadata.source_file).exists());
+  }
+}
+
+#[cfg(test)]
+mod test_read_metadata_files {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn reads_only_given_files() {
+    let d = test_dir!(
+      "image1.jpg": {},
+      "image2.jpg": {},
     );
 
-    create_xmp(d.get_path("video.mov")).unwrap();
+    let metadata =
+      read_metadata_files(&ExifToolConfig::default(), d.root(), ["./image2.jpg"]).unwrap();
 
-    let metadata = read_metadata(d.get_path("video.mov.xmp")).unwrap();
-    assert_eq!(
-      metadata.date_time_original,
-      Some("2000-01-01T00:00:00.999-08:00".to_string())
-    );
+    assert_eq!(metadata.len(), 1);
     assert_eq!(
-      metadata.gps_position,
-      Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".to_string())
+      metadata[0].as_ref().unwrap().source_file,
+      Path::new("./image2.jpg")
     );
   }
 
   #[test]
-  fn copies_metadata_to_xmp() {
-    let d = test_dir!(
-      "image.jpg": { "Creator": "Creator" },
+  fn returns_empty_vec_if_no_files() {
+    let d = test_dir!();
+
+    assert!(
+      read_metadata_files(&ExifToolConfig::default(), d.root(), None::<&Path>)
+        .unwrap()
+        .is_empty()
     );
+  }
+}
 
-    create_xmp(d.get_path("image.jpg")).unwrap();
+#[cfg(test)]
+mod test_read_metadata_recursive {
+  use std::collections::HashSet;
 
-    let metadata = read_metadata(d.get_path("image.jpg.xmp")).unwrap();
-    assert_eq!(metadata.source_file, d.get_path("image.jpg.xmp"));
-    assert_eq!(metadata.creator, Some("Creator".to_string()));
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn errors_if_directory_does_not_exist() {
+    let d = test_dir!();
+    assert_err!(
+      read_metadata_recursive(
+        &ExifToolConfig::default(),
+        d.root().join("dir"),
+        None::<&Path>,
+        false,
+        false
+      ),
+      "Path does not exist."
+    );
   }
 
   #[test]
-  fn creates_xmp() {
+  fn reads_all_files() {
     let d = test_dir!(
-      "image.jpg": { "Creator": "Creator" },
+      "image1.jpg": {},
+      "image2.jpg": {},
+      "dir/image3.jpg": {},
     );
 
-    create_xmp(d.get_path("image.jpg")).unwrap();
+    let metadata = read_metadata_recursive(
+      &ExifToolConfig::default(),
+      d.root(),
+      None::<&Path>,
+      false,
+      false,
+    )
+    .unwrap();
 
-    assert_dir!(d, ["image.jpg", "image.jpg.xmp"]);
+    assert_eq!(
+      metadata
+        .into_iter()
+        .map(|m| d.get_path(m.unwrap().source_file))
+        .collect::<HashSet<_>>(),
+      HashSet::from(["image1.jpg", "image2.jpg", "dir/image3.jpg"].map(|p| d.get_path(p)))
+    );
   }
 
   #[test]
-  fn errors_if_extension_is_xmp() {
-    let d = test_dir!(
-      "image.jpg.xmp": {},
-    );
+  fn returns_empty_vec_if_directory_empty() {
+    let d = test_dir!();
 
-    assert_err!(
-      create_xmp(d.get_path("image.jpg.xmp")),
-      "Cannot create XMP (invalid extension)."
-    );
+    let metadata = read_metadata_recursive(
+      &ExifToolConfig::default(),
+      d.root(),
+      None::<&Path>,
+      false,
+      false,
+    )
+    .unwrap();
+
+    assert!(metadata.is_empty());
   }
 
   #[test]
-  fn errors_if_xmp_already_exists() {
+  fn errors_if_excluded_directory_outside_root() {
     let d = test_dir!(
-      "image.jpg": {},
-      "image.jpg.xmp": {},
+      "dir/image.jpg": {},
     );
 
     assert_err!(
-      create_xmp(d.get_path("image.jpg")),
-      "Cannot create XMP (file already exists)."
+      read_metadata_recursive(
+        &ExifToolConfig::default(),
+        d.get_path("dir"),
+        d.some_trash(),
+        false,
+        false
+      ),
+      "Exclude path must be within the read directory"
     );
   }
 
   #[test]
-  fn returns_xmp_metadata() {
+  fn skips_excluded_subdirectories() {
     let d = test_dir!(
-      "image.jpg": { "Creator": "Creator" },
+      "image.jpg": {},
+      "_working/image.jpg": {},
+      "originals/image.jpg": {},
     );
 
-    let metadata_returned = create_xmp(d.get_path("image.jpg")).unwrap();
+    let metadata = read_metadata_recursive(
+      &ExifToolConfig::default(),
+      d.root(),
+      [d.get_path("_working"), d.get_path("originals")],
+      false,
+      false,
+    )
+    .unwrap();
 
-    let metadata_read = read_metadata(d.get_path("image.jpg.xmp")).unwrap();
-    assert_eq!(metadata_returned.source_file, metadata_read.source_file);
-    assert_eq!(metadata_returned.creator, metadata_read.creator);
+    assert_eq!(
+      metadata
+        .into_iter()
+        .map(|m| d.get_path(m.unwrap().source_file))
+        .collect::<HashSet<_>>(),
+      HashSet::from([d.get_path("image.jpg")])
+    );
   }
-}
-
-#[cfg(test)]
-mod test_extract_destination {
-  use super::*;
-  use crate::testing::*;
 
   #[test]
-  fn parses_destination() {
+  fn skips_excluded_subdirectory() {
     let d = test_dir!(
-      "image.jpg": {}
+      "image1.jpg": {},
+      "image2.jpg": {},
     );
+    fs::copy(d.get_path("image1.jpg"), d.trash().join("image3.jpg")).unwrap();
 
-    let stdout = String::from_utf8(
-      run_exiftool(Some(d.root()), ["image.jpg", "-TestName=image_new.jpg"]).unwrap(),
+    let metadata = read_metadata_recursive(
+      &ExifToolConfig::default(),
+      d.root(),
+      d.some_trash(),
+      false,
+      false,
     )
     .unwrap();
 
     assert_eq!(
-      extract_destination(&stdout).unwrap(),
-      PathBuf::from("image_new.jpg")
+      metadata
+        .into_iter()
+        .map(|m| d.get_path(m.unwrap().source_file))
+        .collect::<HashSet<_>>(),
+      HashSet::from(["image1.jpg", "image2.jpg"].map(|p| d.get_path(p)))
     );
   }
 }
 
 #[cfg(test)]
-mod test_make_canonical {
-  use super::make_canonical;
+mod test_find_symlink_cycles {
+  use std::os::unix::fs::symlink;
+
+  use super::*;
   use crate::testing::*;
 
   #[test]
-  fn errors_if_path_does_not_exist() {
-    assert_err!(
-      make_canonical("/path/does/not/exist"),
-      "Path does not exist."
-    );
+  fn finds_symlink_to_ancestor() {
+    let d = test_dir!();
+    fs::create_dir_all(d.get_path("a/b")).unwrap();
+    symlink(d.root(), d.get_path("a/b/root")).unwrap();
+
+    assert_eq!(find_symlink_cycles(d.root(), &[]).unwrap(), [
+      PathBuf::from("a/b/root")
+    ]);
   }
 
   #[test]
-  fn errors_if_path_is_relative() {
-    assert_err!(make_canonical("relative/path"), "Path is not absolute.");
+  fn finds_cycle_through_other_symlinks() {
+    let d = test_dir!();
+    let root = d.get_path("catalog");
+    fs::create_dir_all(&root).unwrap();
+    fs::create_dir_all(d.get_path("archive")).unwrap();
+    symlink(d.get_path("archive"), root.join("archive")).unwrap();
+    symlink(&root, d.get_path("archive/catalog")).unwrap();
+
+    assert_eq!(find_symlink_cycles(&root, &[]).unwrap(), [PathBuf::from(
+      "archive/catalog"
+    )]);
   }
 
   #[test]
-  fn returns_canonical_path() {
-    let d = test_dir!(
-      "image.jpg": {},
-    );
+  fn ignores_symlinks_elsewhere() {
+    let d = test_dir!();
+    fs::create_dir_all(d.get_path("a")).unwrap();
+    fs::create_dir_all(d.get_path("b")).unwrap();
+    symlink(d.get_path("a"), d.get_path("b/a")).unwrap();
+    symlink(d.get_path("missing"), d.get_path("b/missing")).unwrap();
 
-    let path = make_canonical(d.get_path("image.jpg")).unwrap();
+    assert!(find_symlink_cycles(d.root(), &[]).unwrap().is_empty());
+  }
 
-    assert!(path.is_absolute());
-    assert!(path.exists());
-    assert!(!path.is_symlink());
+  #[test]
+  fn skips_excluded_directories() {
+    let d = test_dir!();
+    fs::create_dir_all(d.get_path("a")).unwrap();
+    symlink(d.root(), d.get_path("a/root")).unwrap();
+
+    assert!(
+      find_symlink_cycles(d.root(), &[PathBuf::from("a")])
+        .unwrap()
+        .is_empty()
+    );
   }
 }
 
 #[cfg(test)]
-mod test_move_file {
+mod test_read_metadata_recursive_stream {
   use super::*;
   use crate::testing::*;
 
   #[test]
-  fn adds_counter_when_same_time() {
-    let d = test_dir!(
-      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "OffsetTimeOriginal": "+00:00" },
-      "image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "OffsetTimeOriginal": "+00:00" },
+  fn errors_if_directory_does_not_exist() {
+    let d = test_dir!();
+    assert_err!(
+      read_metadata_recursive_stream(
+        &ExifToolConfig::default(),
+        d.root().join("dir"),
+        None::<&Path>,
+        false,
+        false
+      ),
+      "Path does not exist."
     );
-
-    move_file(d.get_path("image1.jpg"), None::<&Path>, d.root(), ".jpg").unwrap();
-    move_file(d.get_path("image2.jpg"), None::<&Path>, d.root(), ".jpg").unwrap();
-
-    assert_dir!(d, [
-      "2000/01/000101_000000000.jpg",
-      "2000/01/000101_000000000_b.jpg",
-    ]);
   }
 
   #[test]
-  fn errors_if_no_date_time_tags() {
+  fn matches_batch_read() {
     let d = test_dir!(
-      "image.jpg": {},
+      "image1.jpg": { "Creator": "Creator {with} \"braces\"" },
+      "image1.jpg.xmp": { "Rating": "3" },
+      "dir/image2.heic": {},
+      "dir/video.mov": { "CompressorID": "avc1" },
     );
+    fs::write(d.get_path("unreadable.jpg"), "Not an image.").unwrap();
 
-    assert_err!(
-      move_file(d.get_path("image.jpg"), None::<&Path>, d.root(), ".jpg"),
-      "Failed to move file."
+    let to_values = |metadata: Vec<Result<Metadata, CatalogError>>| {
+      let mut values = metadata
+        .into_iter()
+        .map(|m| m.map(|m| serde_json::to_value(m).unwrap()))
+        .collect::<Vec<_>>();
+      values.sort_by_key(|v| format!("{v:?}"));
+      values
+    };
+
+    let streamed = to_values(
+      read_metadata_recursive_stream(
+        &ExifToolConfig::default(),
+        d.root(),
+        None::<&Path>,
+        false,
+        false,
+      )
+      .unwrap()
+      .collect(),
     );
-  }
-
-  #[test]
-  fn uses_create_date_as_fallback_from_exif() {
-    let d = test_dir!(
-      "image.jpg": {
-        "CreateDate": "2025-01-01T00:00:00",
-        "OffsetTimeDigitized": "+00:00",
-      },
+    let batch = to_values(
+      read_metadata_recursive(
+        &ExifToolConfig::default(),
+        d.root(),
+        None::<&Path>,
+        false,
+        false,
+      )
+      .unwrap(),
     );
 
-    move_file(d.get_path("image.jpg"), None::<&Path>, d.root(), ".jpg").unwrap();
-
-    assert_dir!(d, ["2025/01/250101_000000000.jpg"]);
+    assert_eq!(streamed.len(), 5);
+    assert_eq!(streamed, batch);
   }
 
   #[test]
-  fn uses_create_date_as_fallback_from_xmp() {
-    let d = test_dir!(
-      "image.jpg.xmp": {
-        "CreateDate": "2025-01-01T00:00:00+00:00",
-      },
-    );
+  fn yields_nothing_if_directory_empty() {
+    let d = test_dir!();
 
-    move_file(
-      d.get_path("image.jpg.xmp"),
-      None::<&Path>,
+    let metadata = read_metadata_recursive_stream(
+      &ExifToolConfig::default(),
       d.root(),
-      ".jpg.xmp",
+      None::<&Path>,
+      false,
+      false,
     )
     .unwrap();
 
-    assert_dir!(d, ["2025/01/250101_000000000.jpg.xmp"]);
+    assert_eq!(metadata.count(), 0);
   }
+}
 
-  #[test]
-  fn uses_date_time_original_as_primary_from_exif() {
-    let d = test_dir!(
-      "image.jpg": {
-        "DateTimeOriginal": "2000-01-01T00:00:00",
-        "OffsetTimeOriginal": "+00:00",
-        "CreateDate": "2025-01-01T00:00:00",
-        "OffsetTimeDigitized": "+00:00",
-      },
-    );
-
-    move_file(d.get_path("image.jpg"), None::<&Path>, d.root(), ".jpg").unwrap();
+#[cfg(test)]
+mod test_json_array_elements {
+  use super::*;
 
-    assert_dir!(d, ["2000/01/000101_000000000.jpg"]);
+  fn split(json: &str) -> Vec<std_io::Result<String>> {
+    JsonArrayElements::new(json.as_bytes())
+      .map(|e| e.map(|e| String::from_utf8(e).unwrap()))
+      .collect()
   }
 
   #[test]
-  fn uses_date_time_original_as_primary_from_xmp() {
-    let d = test_dir!(
-      "image.jpg.xmp": {
-        "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
-        "CreateDate": "2025-01-01T00:00:00+00:00",
-      },
+  fn splits_elements() {
+    let elements = split("[{\"A\": 1},\n {\"B\": [{}, \"}\\\"]\"]}\n]\n");
+
+    assert_eq!(
+      elements.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+      ["{\"A\": 1}", "{\"B\": [{}, \"}\\\"]\"]}"]
     );
+  }
 
-    move_file(
-      d.get_path("image.jpg.xmp"),
-      None::<&Path>,
-      d.root(),
-      ".jpg.xmp",
-    )
-    .unwrap();
+  #[test]
+  fn splits_nothing_if_empty() {
+    assert!(split("").is_empty());
+    assert!(split("[]").is_empty());
+  }
 
-    assert_dir!(d, ["2000/01/000101_000000000.jpg.xmp"]);
+  #[test]
+  fn errors_if_not_array() {
+    assert!(split("{}")[0].is_err());
   }
 
   #[test]
-  fn renames_in_utc_from_exif() {
+  fn errors_if_element_unterminated() {
+    assert!(split("[{\"A\": [1}")[0].is_err());
+  }
+}
+
+#[cfg(test)]
+mod test_remove_file {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn errors_if_file_already_in_trash() {
     let d = test_dir!(
-      "image.jpg": {
-        "DateTimeOriginal": "2000-01-01T00:00:00",
-        "OffsetTimeOriginal": "-08:00",
-      },
+      "image.jpg": {},
     );
+    fs::rename(d.get_path("image.jpg"), d.trash().join("image.jpg")).unwrap();
 
-    move_file(d.get_path("image.jpg"), None::<&Path>, d.root(), ".jpg").unwrap();
-
-    assert_dir!(d, ["2000/01/000101_080000000.jpg"]);
+    assert_err!(
+      remove_file(d.root(), d.trash(), d.trash().join("image.jpg")),
+      "Cannot remove file already in trash"
+    );
   }
 
   #[test]
-  fn renames_in_utc_from_xmp() {
+  fn errors_if_name_collision_in_trash() {
     let d = test_dir!(
-      "image.jpg": {
-        "DateTimeOriginal": "2000-01-01T00:00:00-08:00",
-      },
+      "image.jpg": {},
     );
+    fs::copy(d.get_path("image.jpg"), d.trash().join("image.jpg")).unwrap();
 
-    move_file(d.get_path("image.jpg"), None::<&Path>, d.root(), ".jpg").unwrap();
-
-    assert_dir!(d, ["2000/01/000101_080000000.jpg"]);
+    assert_err!(
+      remove_file(d.root(), d.trash(), d.get_path("image.jpg")),
+      "Cannot remove file due to name collision in trash"
+    );
   }
 
   #[test]
-  fn renames_with_subseconds_from_exif() {
+  fn errors_if_not_under_root() {
     let d = test_dir!(
-      "image.jpg": {
-        "DateTimeOriginal": "2000-01-01T00:00:00",
-        "SubSecTimeOriginal": "999",
-        "OffsetTimeOriginal": "+00:00",
-      },
+      "image1.jpg": {},
+      "dir/image2.jpg": {}
     );
 
-    move_file(d.get_path("image.jpg"), None::<&Path>, d.root(), ".jpg").unwrap();
-
-    assert_dir!(d, ["2000/01/000101_000000999.jpg"]);
+    assert_err!(
+      remove_file(d.root().join("dir"), d.trash(), d.root().join("image1.jpg")),
+      "Cannot remove file outside root directory"
+    );
   }
 
   #[test]
-  fn renames_with_subseconds_from_xmp() {
+  fn moves_file_to_external_trash() {
     let d = test_dir!(
-      "image.jpg.xmp": {
-        "DateTimeOriginal": "2000-01-01T00:00:00.999+00:00",
-      },
+      "catalog/dir/image.jpg": {},
     );
 
-    move_file(
-      d.get_path("image.jpg.xmp"),
-      None::<&Path>,
-      d.root(),
-      ".jpg.xmp",
+    remove_file(
+      d.get_path("catalog"),
+      d.trash(),
+      d.get_path("catalog/dir/image.jpg"),
     )
     .unwrap();
 
-    assert_dir!(d, ["2000/01/000101_000000999.jpg.xmp"]);
+    assert_dir!(d, []);
+    assert_trash!(d, ["dir/image.jpg"]);
   }
 
   #[test]
-  fn returns_new_path() {
+  fn moves_file_to_trash() {
     let d = test_dir!(
-      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.jpg": {},
     );
 
-    let p = move_file(d.get_path("image.jpg"), None::<&Path>, d.root(), ".jpg").unwrap();
+    remove_file(d.root(), d.trash(), d.get_path("image.jpg")).unwrap();
 
-    assert_eq!(p, d.get_path("2000/01/000101_000000000.jpg"));
+    assert_dir!(d, []);
+    assert_trash!(d, ["image.jpg"]);
   }
 
   #[test]
-  fn uses_tag_source() {
+  fn preserves_subdirectory_structure() {
     let d = test_dir!(
-      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
-      "image.jpg.xmp": { "DateTimeOriginal": "2025-01-01T00:00:00" },
+      "dir/image.jpg": {},
     );
 
-    move_file(
-      d.get_path("image.jpg"),
-      Some(d.get_path("image.jpg.xmp")),
-      d.root(),
-      ".jpg",
-    )
-    .unwrap();
+    remove_file(d.root(), d.trash(), d.get_path("dir/image.jpg")).unwrap();
 
-    assert_dir!(d, ["2025/01/250101_000000000.jpg", "image.jpg.xmp"]);
+    assert_dir!(d, []);
+    assert_trash!(d, ["dir/image.jpg"]);
+  }
+
+  #[test]
+  fn preserves_modify_date() {
+    let d = test_dir!();
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    fs::write(d.get_path("image.jpg"), "").unwrap();
+    set_modify_time(d.get_path("image.jpg"), modified).unwrap();
+
+    remove_file(d.root(), d.trash(), d.get_path("image.jpg")).unwrap();
+
+    let metadata = fs::metadata(d.get_trash("image.jpg")).unwrap();
+    assert_eq!(metadata.modified().unwrap(), modified);
+    assert!(d.get_trash(TRASH_RECORD).exists());
   }
 }
 
 #[cfg(test)]
-mod test_read_metadata {
-  use chrono::NaiveDate;
-
+mod test_restore_file {
   use super::*;
-  use crate::{prim, testing::*};
+  use crate::testing::*;
 
   #[test]
-  fn errors_if_file_does_not_exist() {
+  fn restores_trashed_file() {
     let d = test_dir!();
-    assert_err!(
-      read_metadata(d.get_path("image.jpg")),
-      "Path does not exist."
+    fs::create_dir(d.get_path("dir")).unwrap();
+    fs::write(d.get_path("dir/image.jpg"), "image").unwrap();
+    remove_file(d.root(), d.trash(), d.get_path("dir/image.jpg")).unwrap();
+
+    let restored = restore_file(d.root(), d.trash(), d.get_trash("dir/image.jpg")).unwrap();
+
+    assert_eq!(
+      restored,
+      make_canonical(d.get_path("dir/image.jpg")).unwrap()
     );
+    assert_eq!(fs::read_to_string(&restored).unwrap(), "image");
+    assert_trash!(d, []);
   }
 
   #[test]
-  fn reads_no_time_zone_as_local() {
-    let d = test_dir!(
-      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
-    );
+  fn restores_from_run_dir() {
+    let d = test_dir!();
+    let run = make_trash_run_dir(d.trash(), NaiveDateTime::default()).unwrap();
+    fs::write(d.get_path("image.jpg"), "image").unwrap();
+    remove_file(d.root(), &run, d.get_path("image.jpg")).unwrap();
 
-    let metadata = read_metadata(d.get_path("image.jpg.xmp")).unwrap();
+    let restored = restore_file(d.root(), d.trash(), run.join("image.jpg")).unwrap();
 
-    let local_date_time = NaiveDate::from_ymd_opt(2000, 1, 1)
-      .and_then(|d| d.and_hms_opt(0, 0, 0))
-      .unwrap();
-    let local_offset = prim::get_offset_local(&local_date_time).to_string();
+    assert_eq!(restored, make_canonical(d.get_path("image.jpg")).unwrap());
+  }
+
+  #[test]
+  fn keeps_run_dir_name_of_file() {
+    let d = test_dir!();
+    fs::write(d.get_trash("2024-01-02T1530"), "image").unwrap();
+
+    let restored = restore_file(d.root(), d.trash(), d.get_trash("2024-01-02T1530")).unwrap();
 
     assert_eq!(
-      metadata.date_time_original,
-      Some(format!("2000-01-01T00:00:00{local_offset}"))
+      restored,
+      make_canonical(d.get_path("2024-01-02T1530")).unwrap()
     );
   }
 
   #[test]
-  fn reads_tag() {
-    let d = test_dir!(
-      "image.jpg": { "Creator": "Creator" },
+  fn errors_if_name_collision() {
+    let d = test_dir!();
+    fs::write(d.get_path("image.jpg"), "image").unwrap();
+    fs::write(d.get_trash("image.jpg"), "trashed").unwrap();
+
+    assert_err!(
+      restore_file(d.root(), d.trash(), d.get_trash("image.jpg")),
+      "Cannot restore file due to name collision"
+    );
+    assert_eq!(
+      fs::read_to_string(d.get_path("image.jpg")).unwrap(),
+      "image"
     );
+  }
 
-    let metadata = read_metadata(d.get_path("image.jpg")).unwrap();
+  #[test]
+  fn errors_if_not_in_trash() {
+    let d = test_dir!();
+    fs::write(d.get_path("image.jpg"), "image").unwrap();
 
-    assert_eq!(metadata.creator, Some("Creator".to_string()));
+    assert_err!(
+      restore_file(d.root(), d.trash(), d.get_path("image.jpg")),
+      "Cannot restore file outside trash"
+    );
   }
+}
+
+#[cfg(test)]
+mod test_rename_file {
+  use super::*;
+  use crate::testing::*;
 
   #[test]
-  fn reads_time_zone() {
+  fn errors_if_name_collision() {
     let d = test_dir!(
-      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00-08:00" },
+      "image1.jpg": {},
+      "image2.jpg": {},
     );
 
-    let metadata = read_metadata(d.get_path("image.jpg.xmp")).unwrap();
-
-    assert_eq!(
-      metadata.date_time_original,
-      Some("2000-01-01T00:00:00-08:00".to_string())
+    assert_err!(
+      rename_file(d.get_path("image1.jpg"), d.get_path("image2.jpg")),
+      "Cannot move file due to name collision"
     );
   }
 
   #[test]
-  fn returns_source_file_relative_to_root() {
+  fn moves_file() {
     let d = test_dir!(
       "dir/image.jpg": {},
     );
 
-    let metadata = read_metadata(d.get_path("dir/image.jpg")).unwrap();
+    rename_file(d.get_path("dir/image.jpg"), d.get_path("image.jpg")).unwrap();
 
-    assert!(d.root().join(metadata.source_file).exists());
+    assert_dir!(d, ["image.jpg"]);
   }
 }
 
 #[cfg(test)]
-mod test_read_metadata_recursive {
-  use std::collections::HashSet;
-
+mod test_get_available_space {
   use super::*;
   use crate::testing::*;
 
   #[test]
-  fn errors_if_directory_does_not_exist() {
+  fn gets_space_of_directory() {
     let d = test_dir!();
+
+    assert!(get_available_space(d.root()).unwrap() > 0);
+  }
+
+  #[test]
+  fn errors_if_directory_does_not_exist() {
     assert_err!(
-      read_metadata_recursive(d.root().join("dir"), None::<&Path>),
-      "Path does not exist."
+      get_available_space("/path/does/not/exist"),
+      "Unable to get available space"
     );
   }
+}
+
+#[cfg(test)]
+mod test_set_modify_time {
+  use std::time::Duration;
+
+  use super::*;
+  use crate::testing::*;
 
   #[test]
-  fn reads_all_files() {
+  fn round_trips_modify_time() {
     let d = test_dir!(
-      "image1.jpg": {},
-      "image2.jpg": {},
-      "dir/image3.jpg": {},
+      "image.jpg": {},
     );
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(978_307_201);
 
-    let metadata = read_metadata_recursive(d.root(), None::<&Path>).unwrap();
+    set_modify_time(d.get_path("image.jpg"), time).unwrap();
 
-    assert_eq!(
-      metadata
-        .into_iter()
-        .map(|m| d.get_path(m.source_file))
-        .collect::<HashSet<_>>(),
-      HashSet::from(["image1.jpg", "image2.jpg", "dir/image3.jpg"].map(|p| d.get_path(p)))
+    assert_eq!(get_modify_time(d.get_path("image.jpg")).unwrap(), time);
+  }
+
+  #[test]
+  fn errors_if_file_does_not_exist() {
+    assert_err!(
+      set_modify_time("/path/does/not/exist", SystemTime::UNIX_EPOCH),
+      "Unable to set modify date"
     );
   }
+}
+
+#[cfg(test)]
+mod test_write_metadata {
+  use super::*;
+  use crate::testing::*;
 
   #[test]
-  fn returns_empty_vec_if_directory_empty() {
-    let d = test_dir!();
+  fn writes_changed_and_cleared_tags() {
+    let d = test_dir!(
+      "image.jpg.xmp": { "Creator": "Old", "Copyright": "Copyright Old", "Rating": "3" },
+    );
 
-    let metadata = read_metadata_recursive(d.root(), None::<&Path>).unwrap();
+    let original = read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg.xmp")).unwrap();
+    let mut metadata = original.clone();
+    metadata.creator = Some("New".to_string());
+    metadata.copyright = None;
 
-    assert!(metadata.is_empty());
+    let written = write_metadata(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg.xmp"),
+      metadata.to_exiftool_args(&original),
+    )
+    .unwrap();
+
+    assert_eq!(written.creator.as_deref(), Some("New"));
+    assert_eq!(written.copyright, None);
+    assert_tag!(d, "image.jpg.xmp", "Creator", "New");
+    assert_tag!(d, "image.jpg.xmp", "Copyright", None);
+    assert_tag!(d, "image.jpg.xmp", "Rating", "3");
   }
 
   #[test]
-  fn skips_excluded_subdirectory() {
+  fn leaves_file_unchanged_without_args() {
     let d = test_dir!(
-      "image1.jpg": {},
-      "image2.jpg": {},
+      "image.jpg.xmp": { "Creator": "Creator" },
     );
-    fs::copy(d.get_path("image1.jpg"), d.trash().join("image3.jpg")).unwrap();
 
-    let metadata = read_metadata_recursive(d.root(), d.some_trash()).unwrap();
+    let original = read_metadata(&ExifToolConfig::default(), d.get_path("image.jpg.xmp")).unwrap();
+    let written = write_metadata(
+      &ExifToolConfig::default(),
+      d.get_path("image.jpg.xmp"),
+      Vec::<String>::new(),
+    )
+    .unwrap();
 
-    assert_eq!(
-      metadata
-        .into_iter()
-        .map(|m| d.get_path(m.source_file))
-        .collect::<HashSet<_>>(),
-      HashSet::from(["image1.jpg", "image2.jpg"].map(|p| d.get_path(p)))
-    );
+    assert_eq!(written.file_modify_date, original.file_modify_date);
+    assert_tag!(d, "image.jpg.xmp", "Creator", "Creator");
   }
 }
 
 #[cfg(test)]
-mod test_remove_file {
+mod test_resolve_exiftool_path {
   use super::*;
-  use crate::testing::*;
 
   #[test]
-  fn errors_if_file_already_in_trash() {
-    let d = test_dir!(
-      "image.jpg": {},
+  fn prefers_path() {
+    let path = resolve_exiftool_path(
+      Some(PathBuf::from("/a/exiftool")),
+      Some(OsString::from("/b/exiftool")),
     );
-    fs::rename(d.get_path("image.jpg"), d.trash().join("image.jpg")).unwrap();
 
-    assert_err!(
-      remove_file(d.root(), d.trash(), d.trash().join("image.jpg")),
-      "Cannot remove file already in trash"
-    );
+    assert_eq!(path, PathBuf::from("/a/exiftool"));
   }
 
   #[test]
-  fn errors_if_name_collision_in_trash() {
-    let d = test_dir!(
-      "image.jpg": {},
-    );
-    fs::copy(d.get_path("image.jpg"), d.trash().join("image.jpg")).unwrap();
+  fn uses_var_without_path() {
+    let path = resolve_exiftool_path(None, Some(OsString::from("/b/exiftool")));
 
-    assert_err!(
-      remove_file(d.root(), d.trash(), d.get_path("image.jpg")),
-      "Cannot remove file due to name collision in trash"
-    );
+    assert_eq!(path, PathBuf::from("/b/exiftool"));
   }
 
   #[test]
-  fn errors_if_not_under_root() {
-    let d = test_dir!(
-      "image1.jpg": {},
-      "dir/image2.jpg": {}
-    );
+  fn falls_back_to_bundled() {
+    let bundled = PathBuf::from(env!("OUT_DIR")).join("exiftool");
 
-    assert_err!(
-      remove_file(d.root().join("dir"), d.trash(), d.root().join("image1.jpg")),
-      "Cannot remove file outside root directory"
-    );
+    assert_eq!(resolve_exiftool_path(None, None), bundled);
+    assert_eq!(resolve_exiftool_path(None, Some(OsString::new())), bundled);
   }
+}
 
-  #[test]
-  fn moves_file_to_trash() {
-    let d = test_dir!(
-      "image.jpg": {},
-    );
+#[cfg(test)]
+mod test_check_exiftool_program {
+  use std::os::unix::fs::PermissionsExt;
 
-    remove_file(d.root(), d.trash(), d.get_path("image.jpg")).unwrap();
+  use super::*;
+  use crate::testing::*;
 
-    assert_dir!(d, []);
-    assert_trash!(d, ["image.jpg"]);
+  /// Writes a script standing in for `ExifTool` that records its arguments
+  /// next to itself, and reports `version`.
+  fn write_wrapper(path: &Path, version: &str) {
+    fs::write(
+      path,
+      format!("#!/bin/sh\necho \"$@\" > \"$0.args\"\necho {version}\n"),
+    )
+    .unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
   }
 
   #[test]
-  fn preserves_subdirectory_structure() {
-    let d = test_dir!(
-      "dir/image.jpg": {},
+  fn invokes_program() {
+    let d = test_dir!();
+    let wrapper = d.get_path("exiftool");
+    write_wrapper(&wrapper, "13.30");
+
+    check_exiftool_program(&wrapper).unwrap();
+
+    assert_eq!(
+      fs::read_to_string(d.get_path("exiftool.args")).unwrap(),
+      "-ver\n"
     );
+  }
 
-    remove_file(d.root(), d.trash(), d.get_path("dir/image.jpg")).unwrap();
+  #[test]
+  fn errors_if_too_old() {
+    let d = test_dir!();
+    let wrapper = d.get_path("exiftool");
+    write_wrapper(&wrapper, "12.00");
 
-    assert_dir!(d, []);
-    assert_trash!(d, ["dir/image.jpg"]);
+    assert_err!(check_exiftool_program(&wrapper), "too old");
+  }
+
+  #[test]
+  fn errors_if_missing() {
+    let d = test_dir!();
+
+    assert_err!(
+      check_exiftool_program(&d.get_path("exiftool")),
+      "ExifTool failed to run"
+    );
   }
 }
 
@@ -1145,6 +4509,20 @@ mod test_version_check {
     assert!(version_check(version, (13, 29)).is_err());
   }
 
+  #[test]
+  fn fails_older_minor_with_patch() {
+    let version = "13.28.5".as_bytes().to_vec();
+
+    assert!(version_check(version, (13, 29)).is_err());
+  }
+
+  #[test]
+  fn fails_missing_minor() {
+    let version = "13.beta".as_bytes().to_vec();
+
+    assert!(version_check(version, (13, 29)).is_err());
+  }
+
   #[test]
   fn fails_older_minor() {
     let version = "13.28".as_bytes().to_vec();
@@ -1172,4 +4550,32 @@ mod test_version_check {
 
     assert!(version_check(version, (13, 29)).is_ok());
   }
+
+  #[test]
+  fn passes_newer_minor_with_patch() {
+    let version = "13.30.1".as_bytes().to_vec();
+
+    assert!(version_check(version, (13, 29)).is_ok());
+  }
+
+  #[test]
+  fn passes_newer_minor_with_suffix() {
+    let version = "13.30_beta".as_bytes().to_vec();
+
+    assert!(version_check(version, (13, 29)).is_ok());
+  }
+
+  #[test]
+  fn passes_newer_minor_with_description() {
+    let version = "13.30 (production)".as_bytes().to_vec();
+
+    assert!(version_check(version, (13, 29)).is_ok());
+  }
+
+  #[test]
+  fn passes_newer_minor_with_newline() {
+    let version = "13.30\n".as_bytes().to_vec();
+
+    assert!(version_check(version, (13, 29)).is_ok());
+  }
 }
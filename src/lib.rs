@@ -0,0 +1,83 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Library for organizing a photo catalog, acting as a wrapper around
+//! `ExifTool`. The `c1a` binary is a command-line front end to this.
+//!
+//! The supported API is the `Organizer`, loaded from a catalog (or files to
+//! import) and then driven through its stages in order, as documented on the
+//! type. Each stage is configured by its `enable_*` and `set_*` methods before
+//! it runs. Stages return `Err` only for errors stopping the whole run;
+//! problems with individual files are collected as `Warning`s, per the
+//! `ErrorPolicy` in `ScanConfig`.
+//!
+//! Other modules are public for the binary, and may change between versions.
+//!
+//! # Example
+//!
+//! Cleaning up, then moving and renaming a catalog with a single photo:
+//!
+//! ```
+//! use std::fs;
+//!
+//! use catalog_1a::{Organizer, ScanConfig};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let root = std::env::temp_dir().join("catalog_1a_example");
+//! let (catalog, trash) = (root.join("catalog"), root.join("trash"));
+//! let _ = fs::remove_dir_all(&root);
+//! fs::create_dir_all(&catalog)?;
+//! fs::create_dir_all(&trash)?;
+//! fs::copy("assets/test.jpg", catalog.join("photo.jpg"))?;
+//!
+//! let mut organizer = Organizer::load_catalog(&catalog, Some(&trash), &ScanConfig::default())?;
+//!
+//! // 1. Cleanup.
+//! organizer.remove_live_photo_leftovers()?;
+//! organizer.remove_live_photo_duplicates()?;
+//! organizer.remove_sidecar_leftovers()?;
+//!
+//! // 2. Sidecars.
+//! organizer.create_missing_sidecars()?;
+//!
+//! // 3. Metadata updates.
+//! organizer.enable_align_mwg_tags();
+//! organizer.apply_metadata_updates()?;
+//!
+//! // 4. Synchronization.
+//! organizer.sync_live_photo_metadata()?;
+//! organizer.sync_dupe_metadata()?;
+//!
+//! // 5. Validation.
+//! organizer.enable_date_time_validation();
+//! organizer.validate();
+//!
+//! // 6. Organization, moving even media which failed validation.
+//! for warning in organizer.move_and_rename_files(&catalog, true)? {
+//!   eprintln!("{warning}");
+//! }
+//!
+//! assert!(!catalog.join("photo.jpg").exists());
+//! # fs::remove_dir_all(&root)?;
+//! # Ok(())
+//! # }
+//! ```
+
+#![feature(path_add_extension)]
+// Errors and panics are described in each function's docs, rather than in
+// separate sections.
+#![allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+
+#[doc(hidden)]
+pub mod commands;
+pub mod error;
+pub mod io;
+pub mod org;
+mod prim;
+#[doc(hidden)]
+pub mod setup;
+#[cfg(test)]
+mod testing;
+
+pub use error::CatalogError;
+pub use org::{ErrorPolicy, Organizer, ScanConfig, Warning};
+pub use prim::Codec;
@@ -3,31 +3,376 @@
 //! This is a program for organizing my photo catalog, acting as a wrapper
 //! around `ExifTool`.
 
-#![feature(path_add_extension)]
+use std::{env, ffi::OsString, iter, num::NonZeroUsize, path::PathBuf, time::Duration};
 
-mod commands;
-mod io;
-mod org;
-mod prim;
-mod setup;
-#[cfg(test)]
-mod testing;
-
-use std::path::PathBuf;
-
-use clap::{ArgAction, Parser, Subcommand};
+use catalog_1a::{commands, io, org, setup};
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand, parser::ValueSource};
 
 /// Command-line arguments.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Parser)]
 struct Args {
   /// Directory of multimedia catalog. Updates default in `XDG_CONFIG_HOME`.
   #[arg(short, global = true)]
   catalog: Option<PathBuf>,
 
-  /// Verbosity level. Max: 2.
+  /// Directory to move removed files to. Defaults to `.trash` in the catalog.
+  #[arg(long, global = true)]
+  trash_dir: Option<PathBuf>,
+
+  /// How removed files are laid out in trash: Directly, or under a directory
+  /// per run named by its time, so that runs never collide.
+  #[arg(long, global = true, value_enum, default_value_t = io::TrashLayout::Flat)]
+  trash_layout: io::TrashLayout,
+
+  /// Verbosity level. Max: 3 (which also logs `ExifTool` commands).
   #[arg(short, action = ArgAction::Count, global = true)]
   verbose: u8,
 
+  /// File to also write logs to, replacing any existing file.
+  #[arg(long, global = true)]
+  log_file: Option<PathBuf>,
+
+  /// Directory to skip when scanning, relative to the scanned directory or
+  /// absolute (repeatable).
+  #[arg(long, global = true)]
+  exclude: Vec<PathBuf>,
+
+  /// Levels of subdirectories to descend into when scanning, e.g. 0 for only
+  /// files directly within the scanned directory.
+  #[arg(long, global = true)]
+  max_depth: Option<usize>,
+
+  /// Load hidden files when scanning (with names starting with `.`, e.g.
+  /// `.DS_Store` or macOS resource forks like `._image.jpg`), which are skipped
+  /// by default.
+  #[arg(long, global = true)]
+  include_hidden: bool,
+
+  /// Skip symlinks when scanning, rather than following those to directories
+  /// (e.g. to external archives). Symlinks looping back to a directory
+  /// containing them are skipped regardless.
+  #[arg(long, global = true)]
+  skip_symlinks: bool,
+
+  /// `ExifTool` to run instead of the bundled one (e.g. a system install).
+  /// Overrides the `CATALOG_EXIFTOOL` environment variable.
+  #[arg(long, global = true)]
+  exiftool: Option<PathBuf>,
+
+  /// Raw argument appended to every `ExifTool` command (repeatable, once per
+  /// argument, e.g. `--exiftool-args -api --exiftool-args LargeFileSupport=1`).
+  /// Use with care: these are passed unchecked, and may break parsing of
+  /// `ExifTool`'s output, or modify files in unexpected ways.
+  #[arg(long = "exiftool-args", global = true, allow_hyphen_values = true)]
+  exiftool_arg: Vec<String>,
+
+  /// Character set to decode metadata of a type with, as `TYPE=CHARSET` (e.g.
+  /// `IPTC=Latin1` for legacy scans; repeatable). See `ExifTool`'s `-charset`.
+  #[arg(long, global = true)]
+  charset: Vec<String>,
+
+  /// Creator to seed newly created sidecars with, along with a copyright
+  /// derived as `--copyright-format`.
+  #[arg(long, global = true)]
+  creator: Option<String>,
+
+  /// Free space to leave on the destination drive, in MB, checked before
+  /// moving anything. Moving is aborted if files copied from other drives
+  /// would leave less.
+  #[arg(long, global = true)]
+  min_free_space: Option<u64>,
+
+  /// File type (as `ExifTool`'s `FileType`, e.g. `PNG`) to only ever write
+  /// metadata to sidecars for, never into the media itself (repeatable). For
+  /// formats `ExifTool` can't write, or which should stay untouched.
+  #[arg(long, global = true)]
+  sidecar_only_type: Vec<String>,
+
+  /// Digits of sub-seconds in file names (e.g. 6 to keep microsecond burst
+  /// shots distinct).
+  #[arg(
+    long,
+    global = true,
+    default_value_t = io::SUB_SEC_DIGITS_DEFAULT,
+    value_parser = clap::value_parser!(u8).range(1..=9),
+  )]
+  sub_sec_digits: u8,
+
+  /// Name and folder files by the date and time in their own time zone, rather
+  /// than UTC.
+  #[arg(long, global = true)]
+  local_time: bool,
+
+  /// Style of the counter appended to names of files at the same time. Numeric
+  /// counters are three digits, to stay distinct from darktable's duplicates.
+  #[arg(long, global = true, value_enum, default_value_t = io::CounterStyle::Letter)]
+  counter: io::CounterStyle,
+
+  /// Date tag to name files by, falling back to others if not set. Creation
+  /// (`CreateDate`) suits e.g. scanned film, where capture is unknown.
+  #[arg(long, global = true, value_enum, default_value_t = io::RenameDateSource::Capture)]
+  rename_date_source: io::RenameDateSource,
+
+  /// What to do with media without a date to name it by when moving: Leave it
+  /// in place, move it into `undated` keeping its name, or name it by its file
+  /// modify date.
+  #[arg(long, global = true, value_enum, default_value_t = org::MissingDatePolicy::Skip)]
+  missing_date: org::MissingDatePolicy,
+
+  /// Rename files in place within their current directories, rather than
+  /// moving them into `yyyy/mm` directories.
+  #[arg(long, global = true)]
+  rename_only: bool,
+
+  /// Extension to give moved media of another extension, as `FROM=TO` (e.g.
+  /// `jpeg=jpg`), along with their sidecars (repeatable).
+  #[arg(long, global = true, value_parser = parse_extension)]
+  extension: Vec<(String, String)>,
+
+  /// Force extensions of moved files to lowercase.
+  #[arg(long, global = true)]
+  lowercase_extensions: bool,
+
+  /// Keep the modify date of moved files, which may otherwise be reset to the
+  /// time of the move (e.g. on network drives).
+  #[arg(long, global = true)]
+  preserve_modify_dates: bool,
+
+  /// Remove directories left empty after moving files out, other than trash
+  /// and excluded directories.
+  #[arg(long, global = true)]
+  remove_empty_dirs: bool,
+
+  /// Move media with the same capture time and contents as another file to
+  /// trash when moving, rather than keeping both with a counter.
+  #[arg(long, global = true)]
+  trash_identical: bool,
+
+  /// After moving, check that every file is at its planned destination, and
+  /// fail if any are missing. Media trashed by `--trash-identical` are reported
+  /// as missing.
+  #[arg(long, global = true)]
+  verify_moves: bool,
+
+  /// Move photos into this directory under the destination (e.g. `Photos`),
+  /// apart from videos.
+  #[arg(long, global = true, requires = "videos_dir")]
+  photos_dir: Option<PathBuf>,
+
+  /// Move videos into this directory under the destination (e.g. `Videos`),
+  /// apart from photos.
+  #[arg(long, global = true, requires = "photos_dir")]
+  videos_dir: Option<PathBuf>,
+
+  /// Directory to keep Live Photos (images and videos together) in, with
+  /// `--photos-dir` and `--videos-dir`.
+  #[arg(long, global = true, value_enum, default_value_t = org::MediaKind::Photo)]
+  live_photos_in: org::MediaKind,
+
+  /// Keep only the highest rated (or first) frame of each burst.
+  #[arg(long, global = true)]
+  collapse_bursts: bool,
+
+  /// Directory to move media failing validation to, along with their sidecars.
+  #[arg(long, global = true)]
+  quarantine: Option<PathBuf>,
+
+  /// Directory to copy original media to, before writing any metadata.
+  /// Files already archived (by contents) are skipped.
+  #[arg(long, global = true)]
+  archive: Option<PathBuf>,
+
+  /// GPX track log to write GPS coordinates from, for files without.
+  #[arg(long, global = true)]
+  geotag: Option<PathBuf>,
+
+  /// Also overwrite existing GPS coordinates when geotagging.
+  #[arg(long, global = true, requires = "geotag")]
+  geotag_overwrite: bool,
+
+  /// `GeoNames` cities file (e.g. `cities1000.txt`, with
+  /// `admin1CodesASCII.txt` and `countryInfo.txt` alongside) to find the city,
+  /// state and country of GPS coordinates with offline, instead of `ExifTool`.
+  #[arg(long, global = true)]
+  geocode_db: Option<PathBuf>,
+
+  /// Write the SHA-256 of each media file's image data to this tag in its
+  /// sidecar (e.g. `XMP-et:OriginalImageHash`), to detect later corruption.
+  /// Checksums already stored are never overwritten; a mismatch is reported.
+  #[arg(long, global = true)]
+  checksum_tag: Option<String>,
+
+  /// Write `DateTimeOriginal` from file names (e.g. `IMG_20230115_143000.jpg`)
+  /// for files without any date and time.
+  #[arg(long, global = true)]
+  infer_dates_from_filename: bool,
+
+  /// Pattern for dates in file names, as `REGEX=FORMAT`, with the date in the
+  /// first capture group and a `chrono` format (repeatable). Replaces the
+  /// defaults.
+  #[arg(long, global = true, requires = "infer_dates_from_filename")]
+  file_name_date_pattern: Vec<org::FileNameDatePattern>,
+
+  /// Minimum resolution for images to pass validation, in megapixels.
+  #[arg(long, global = true)]
+  min_megapixels: Option<f32>,
+
+  /// Tag which must be set to pass validation, by `ExifTool` name (e.g.
+  /// `Label`; repeatable).
+  #[arg(long, global = true)]
+  require_tag: Vec<String>,
+
+  /// Require the capture year in `Copyright` to pass validation (e.g.
+  /// `Copyright 2023 Creator`).
+  #[arg(long, global = true)]
+  copyright_year: bool,
+
+  /// Format of `Copyright` to write from `Creator` (including when seeding
+  /// sidecars) and require to pass validation, with placeholders `${Creator}`
+  /// and `${Year}` (of capture), e.g. `© ${Year} ${Creator}, All Rights
+  /// Reserved`.
+  #[arg(long, global = true)]
+  copyright_format: Option<org::CopyrightFormat>,
+
+  /// Require a color label (`Label`, as set by darktable) to pass validation.
+  #[arg(long, global = true)]
+  require_label: bool,
+
+  /// Reject capture dates more than this many seconds after now (e.g. from a
+  /// camera with a dead clock).
+  #[arg(long, global = true)]
+  future_date_tolerance: Option<u32>,
+
+  /// Reject media whose sidecar's capture date is more than this many seconds
+  /// from the media's own, as files are named by the sidecar's.
+  #[arg(long, global = true)]
+  sidecar_date_tolerance: Option<u32>,
+
+  /// Validate each sidecar together with its media, so that tags may be set in
+  /// either (e.g. GPS only in the media, creator only in the sidecar).
+  #[arg(long, global = true)]
+  merged_validation: bool,
+
+  /// Check that media extensions match the file types detected from their
+  /// contents (e.g. a PNG named `.jpg`), failing validation otherwise.
+  #[arg(long, global = true)]
+  format_check: bool,
+
+  /// With `--format-check`, pass media with mismatched extensions, giving them
+  /// the extension of their detected type when moved.
+  #[arg(long, global = true, requires = "format_check")]
+  correct_extensions: bool,
+
+  /// Check that images are stored upright, failing validation for those
+  /// rotated or mirrored by their EXIF `Orientation`, which some viewers
+  /// ignore.
+  #[arg(long, global = true)]
+  orientation_check: bool,
+
+  /// Check that the stored time zone agrees with the one at the GPS location,
+  /// within an hour, failing validation otherwise (e.g. for a camera left set
+  /// to the wrong time zone).
+  #[arg(long, global = true)]
+  time_zone_check: bool,
+
+  /// Link Live Photo videos without an image to a nearby image without a video
+  /// (e.g. after converting the image), instead of removing them.
+  #[arg(long, global = true)]
+  relink_leftover_live_photos: bool,
+
+  /// Keep sidecars without media files (e.g. for media stored elsewhere),
+  /// instead of removing them.
+  #[arg(long, global = true)]
+  keep_leftover_sidecars: bool,
+
+  /// List files cleanup would move to trash, and ask before moving them. If
+  /// declined, they are left in place.
+  #[arg(long, global = true)]
+  confirm: bool,
+
+  /// Skip all stages, with a message, if no media files are found (e.g. in an
+  /// empty directory).
+  #[arg(long, global = true)]
+  no_op_if_empty: bool,
+
+  /// Skip removing duplicates and leftovers to trash.
+  #[arg(long, global = true)]
+  skip_cleanup: bool,
+
+  /// Skip metadata validation (and quarantine). Files are then moved regardless
+  /// of their metadata, as if all were valid.
+  #[arg(long, global = true)]
+  skip_validate: bool,
+
+  /// Skip moving and renaming files, e.g. to only update and synchronize
+  /// metadata.
+  #[arg(long, global = true)]
+  skip_move: bool,
+
+  /// Remove duplicate sidecars with the same tags as the initial sidecar.
+  #[arg(long, global = true)]
+  dedupe_sidecars: bool,
+
+  /// Remove one image of each raw+JPEG pair captured together (by stem, else
+  /// capture time), keeping this format. Live Photos are not affected.
+  #[arg(long, global = true, value_enum)]
+  dedupe_across_formats: Option<org::FormatToKeep>,
+
+  /// Hash media contents up front (with `--workers` threads) and remove media
+  /// identical to others, keeping the first by path. Hashes are reused when
+  /// trashing identical media while moving.
+  #[arg(long, global = true)]
+  parallel_hash: bool,
+
+  /// Report images which look alike (e.g. resized or re-exported copies), by
+  /// perceptual hashes differing by at most this many of 64 bits (e.g. 10).
+  /// HEIC and raw images are compared by their embedded previews.
+  #[arg(long, global = true)]
+  near_duplicates: Option<u32>,
+
+  /// With `--near-duplicates`, keep only the image with the most pixels of
+  /// each group, and remove the rest along with their sidecars.
+  #[arg(long, global = true, requires = "near_duplicates")]
+  remove_near_duplicates: bool,
+
+  /// Metadata copies to run at once when synchronizing, and files to hash at
+  /// once with `--parallel-hash`. Defaults to the number of CPUs. With 1, runs
+  /// are also deterministic: files are moved in order of path, so files at the
+  /// same time get the same counters every run.
+  #[arg(long, global = true, visible_alias = "threads")]
+  workers: Option<NonZeroUsize>,
+
+  /// Tag to copy from Live Photo images to videos, by `ExifTool` name (e.g.
+  /// `DateTimeOriginal`; repeatable). Defaults to all tags.
+  #[arg(long, global = true)]
+  live_photo_sync_tag: Vec<String>,
+
+  /// Warn about Live Photo images and videos captured further apart than this
+  /// many seconds, as likely mismatched.
+  #[arg(long, global = true)]
+  live_photo_time_tolerance: Option<u32>,
+
+  /// Index of the catalog's metadata for `org`, so that unchanged files are
+  /// not re-read on reruns. Rewritten on each run.
+  #[arg(long, global = true)]
+  cache: Option<PathBuf>,
+
+  /// Other catalog to merge into the catalog for `org`, moving its files in
+  /// (repeatable).
+  #[arg(long, global = true)]
+  merge: Vec<PathBuf>,
+
+  /// File listing the files for `org` to organize, one per line (e.g. from
+  /// `find`), rather than scanning the whole catalog. `-` reads from stdin.
+  #[arg(long, global = true, conflicts_with = "cache")]
+  files_from: Option<PathBuf>,
+
+  /// What to do on an error with a single file: Stop, or skip it and report
+  /// the error with other warnings.
+  #[arg(long, global = true, value_enum, default_value_t = org::ErrorPolicy::FailFast)]
+  on_error: org::ErrorPolicy,
+
   /// Function to run.
   #[command(subcommand)]
   command: Commands,
@@ -38,22 +383,253 @@ struct Args {
 enum Commands {
   /// Clean catalog.
   Org,
-  /// Import photos from path into the catalog.
-  Import { path: PathBuf },
+  /// Import photos from paths (e.g. several memory cards) into the catalog.
+  Import {
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+    /// Instead of importing, leave files in place and only write an index of
+    /// their metadata to this path. Unchanged files are not re-read on reruns.
+    #[arg(long)]
+    index: Option<PathBuf>,
+  },
+  /// Re-apply automatic metadata updates to catalog, without cleanup or moving.
+  Metadata,
+  /// Report catalog inconsistencies, without making any changes.
+  Doctor,
+  /// Print where `org` would move and rename each file, without making any
+  /// changes.
+  Preview,
+  /// Move all files in the catalog out of their subdirectories into the
+  /// catalog root.
+  Flatten,
+  /// Move media, with their sidecars, under a directory per year of capture
+  /// in path (e.g. `path/2023/`), such as for archiving to separate drives.
+  SplitByYear { path: PathBuf },
+  /// Export a searchable index of the catalog's metadata to path.
+  Export {
+    path:   PathBuf,
+    /// Format of the index.
+    #[arg(long, value_enum, default_value_t = org::IndexFormat::Csv)]
+    format: org::IndexFormat,
+  },
+  /// Report everything wrong or noteworthy about the catalog, without making
+  /// any changes.
+  Health {
+    /// Output as JSON.
+    #[arg(long)]
+    json: bool,
+  },
+  /// Print how many media were captured on each day, to find gaps in imports,
+  /// without making any changes.
+  Histogram,
+  /// Permanently delete files in trash, only those trashed longer ago than the
+  /// given age if set. Never touches the catalog.
+  PruneTrash {
+    /// Age of files to delete, as a number and unit of `d`, `h`, `m` or `s`
+    /// (e.g. `30d`).
+    #[arg(long, value_parser = parse_age)]
+    older_than: Option<Duration>,
+  },
+  /// Move a file from trash back to where it was removed from in the catalog,
+  /// unless a file is already there.
+  Restore {
+    /// File in trash, absolute or relative to trash. Any directory per run
+    /// (with `--trash-layout timestamped`) is dropped.
+    path: PathBuf,
+  },
 }
 
-fn run() -> Result<(), String> {
-  commands::exiftool_check()?;
+/// Parses `FROM=TO` for `--extension`.
+fn parse_extension(s: &str) -> Result<(String, String), String> {
+  let (from, to) = s
+    .split_once('=')
+    .ok_or_else(|| format!("{s}: Expected extension as FROM=TO."))?;
+  Ok((from.to_string(), to.to_string()))
+}
 
-  let args = Args::parse();
+/// Parses an age like `30d` for `--older-than`.
+fn parse_age(s: &str) -> Result<Duration, String> {
+  let error = || format!("{s}: Expected age as a number and unit of d, h, m or s (e.g. 30d).");
 
-  setup::configure_logging(args.verbose);
+  let (n, unit_secs) = [('d', 24 * 60 * 60), ('h', 60 * 60), ('m', 60), ('s', 1)]
+    .into_iter()
+    .find_map(|(unit, secs)| s.strip_suffix(unit).map(|n| (n, secs)))
+    .ok_or_else(error)?;
+
+  n.parse::<u64>()
+    .ok()
+    .and_then(|n| n.checked_mul(unit_secs))
+    .map(Duration::from_secs)
+    .ok_or_else(error)
+}
+
+/// Parses `argv` as `Args`, with defaults for flags not given from the config
+/// file, as read by `read_config` (e.g. `setup::read_config_args`) from
+/// whether each flag is given by ID.
+fn parse_args(
+  argv: &[OsString],
+  read_config: impl FnOnce(&dyn Fn(&str) -> bool) -> Result<Vec<String>, String>,
+) -> Result<Args, String> {
+  let matches = Args::command()
+    .try_get_matches_from(argv)
+    .unwrap_or_else(|e| e.exit());
+  let defaults = read_config(&|id| matches.value_source(id) == Some(ValueSource::CommandLine))?;
+  if defaults.is_empty() {
+    return Args::from_arg_matches(&matches).map_err(|e| e.to_string());
+  }
+
+  let (bin, argv) = argv.split_first().ok_or("Missing program name.")?;
+  Args::try_parse_from(
+    iter::once(bin.clone())
+      .chain(defaults.into_iter().map(OsString::from))
+      .chain(argv.iter().cloned()),
+  )
+  .map_err(|e| format!("Invalid config ({}).", e.kind()))
+}
+
+/// Makes the map of extensions for moved files, from `--extension` and
+/// `--lowercase-extensions`.
+fn make_extension_map(extensions: Vec<(String, String)>, lowercase: bool) -> org::ExtensionMap {
+  let mut extension_map = org::ExtensionMap::default();
+  for (from, to) in extensions {
+    extension_map.insert(&from, to);
+  }
+  if lowercase {
+    extension_map.enable_lowercase();
+  }
+  extension_map
+}
+
+/// Makes the patterns to infer dates from file names with, from
+/// `--infer-dates-from-filename` and `--file-name-date-pattern`, with the
+/// defaults if none are given.
+fn make_file_name_dates(
+  infer: bool,
+  patterns: Vec<org::FileNameDatePattern>,
+) -> Vec<org::FileNameDatePattern> {
+  match (infer, patterns) {
+    (false, _) => Vec::new(),
+    (true, patterns) if patterns.is_empty() => org::FileNameDatePattern::defaults(),
+    (true, patterns) => patterns,
+  }
+}
+
+/// Makes the separate directories for photos and videos, from `--photos-dir`,
+/// `--videos-dir` and `--live-photos-in`, if both directories are given.
+fn make_media_dirs(
+  photos: Option<PathBuf>,
+  videos: Option<PathBuf>,
+  live_photos: org::MediaKind,
+) -> Option<org::MediaDirs> {
+  photos.zip(videos).map(|(photos, videos)| org::MediaDirs {
+    photos,
+    videos,
+    live_photos,
+  })
+}
+
+fn run() -> Result<(), String> {
+  let args = parse_args(&env::args_os().collect::<Vec<_>>(), setup::read_config_args)?;
+
+  setup::configure_logging(args.verbose, args.log_file.as_deref())?;
+  let exiftool_config =
+    commands::make_exiftool_config(args.exiftool, args.charset, args.exiftool_arg)?;
+  commands::exiftool_check(&exiftool_config)?;
 
   let catalog = setup::get_or_update_catalog_path(args.catalog)?;
 
+  let options = commands::Options {
+    trash: args.trash_dir.unwrap_or_else(|| catalog.join(".trash")),
+    trash_layout: args.trash_layout,
+    scan: org::ScanConfig {
+      exclude:        args.exclude,
+      max_depth:      args.max_depth,
+      on_error:       args.on_error,
+      include_hidden: args.include_hidden,
+      skip_symlinks:  args.skip_symlinks,
+      exiftool:       exiftool_config,
+    },
+    sidecar_template: io::SidecarTemplate {
+      creator:          args.creator,
+      copyright_format: args.copyright_format.clone(),
+    },
+    sidecar_only_types: args.sidecar_only_type,
+    min_free_space: args.min_free_space.map(|mb| mb.saturating_mul(1_000_000)),
+    sub_sec_digits: args.sub_sec_digits,
+    local_time: args.local_time,
+    counter: args.counter,
+    rename_date_source: args.rename_date_source,
+    missing_date: args.missing_date,
+    rename_only: args.rename_only,
+    extension_map: make_extension_map(args.extension, args.lowercase_extensions),
+    collapse_bursts: args.collapse_bursts,
+    quarantine: args.quarantine,
+    geotag: args.geotag,
+    geotag_overwrite: args.geotag_overwrite,
+    geocode_db: args.geocode_db,
+    checksum_tag: args.checksum_tag,
+    file_name_dates: make_file_name_dates(
+      args.infer_dates_from_filename,
+      args.file_name_date_pattern,
+    ),
+    min_megapixels: args.min_megapixels,
+    required_tags: args.require_tag,
+    copyright_year: args.copyright_year,
+    copyright_format: args.copyright_format,
+    require_label: args.require_label,
+    future_date_tolerance: args.future_date_tolerance,
+    sidecar_date_tolerance: args.sidecar_date_tolerance,
+    merged_validation: args.merged_validation,
+    format_check: args.format_check,
+    orientation_check: args.orientation_check,
+    time_zone_check: args.time_zone_check,
+    correct_extensions: args.correct_extensions,
+    relink_leftover_live_photos: args.relink_leftover_live_photos,
+    keep_leftover_sidecars: args.keep_leftover_sidecars,
+    confirm: args.confirm,
+    no_op_if_empty: args.no_op_if_empty,
+    skip_cleanup: args.skip_cleanup,
+    skip_validate: args.skip_validate,
+    skip_move: args.skip_move,
+    dedupe_sidecars: args.dedupe_sidecars,
+    dedupe_across_formats: args.dedupe_across_formats,
+    parallel_hash: args.parallel_hash,
+    near_duplicates: args.near_duplicates,
+    remove_near_duplicates: args.remove_near_duplicates,
+    archive: args.archive,
+    workers: args.workers,
+    live_photo_sync_tags: args.live_photo_sync_tag,
+    live_photo_time_tolerance: args.live_photo_time_tolerance,
+    preserve_modify_dates: args.preserve_modify_dates,
+    remove_empty_dirs: args.remove_empty_dirs,
+    trash_identical: args.trash_identical,
+    verify_moves: args.verify_moves,
+    media_dirs: make_media_dirs(args.photos_dir, args.videos_dir, args.live_photos_in),
+    cache: args.cache,
+    merge: args.merge,
+    files_from: args.files_from,
+  };
+
   match args.command {
-    Commands::Org => commands::org(&catalog),
-    Commands::Import { path } => commands::import(&catalog, &path),
+    Commands::Org => commands::org(&catalog, &options),
+    Commands::Import { paths, index: None } => commands::import(&catalog, &paths, &options),
+    Commands::Import {
+      paths,
+      index: Some(index),
+    } => match paths.as_slice() {
+      [path] => commands::index(path, &index, &options),
+      _ => Err("Can only index one path at a time.".to_string()),
+    },
+    Commands::Metadata => commands::metadata(&catalog, &options),
+    Commands::Doctor => commands::doctor(&catalog, &options),
+    Commands::Preview => commands::preview(&catalog, &options),
+    Commands::Flatten => commands::flatten(&catalog, &options),
+    Commands::SplitByYear { path } => commands::split_by_year(&catalog, &options, &path),
+    Commands::Export { path, format } => commands::export(&catalog, &options, &path, format),
+    Commands::Health { json } => commands::health(&catalog, &options, json),
+    Commands::Histogram => commands::histogram(&catalog, &options),
+    Commands::PruneTrash { older_than } => commands::prune_trash(&catalog, &options, older_than),
+    Commands::Restore { path } => commands::restore(&catalog, &options, &path),
   }
 }
 
@@ -63,3 +639,47 @@ fn main() {
     std::process::exit(1);
   };
 }
+
+#[cfg(test)]
+mod test_parse_args {
+  use std::fs;
+
+  use super::*;
+
+  fn parse_with_config(args: &[&str], config: &str) -> Args {
+    let path = env::temp_dir().join(format!(
+      "{}_tests/main_test_parse_args.json",
+      env!("CARGO_PKG_NAME")
+    ));
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, config).unwrap();
+
+    let command_line = args.iter().map(OsString::from).collect::<Vec<_>>();
+    parse_args(&command_line, |given| setup::load_config_args(&path, given)).unwrap()
+  }
+
+  #[test]
+  fn sets_defaults_from_config() {
+    let args = parse_with_config(
+      &["c1a", "org"],
+      r#"{ "min-megapixels": 12, "copyright-year": true, "counter": "numeric", "exclude": ["tmp"] }"#,
+    );
+
+    assert_eq!(args.min_megapixels, Some(12.0));
+    assert!(args.copyright_year);
+    assert!(matches!(args.counter, io::CounterStyle::Numeric));
+    assert_eq!(args.exclude, [PathBuf::from("tmp")]);
+  }
+
+  #[test]
+  fn overrides_config_with_flags() {
+    let args = parse_with_config(
+      &["c1a", "org", "--min-megapixels", "8", "--exclude", "other"],
+      r#"{ "min-megapixels": 12, "exclude": ["tmp"], "require-label": true }"#,
+    );
+
+    assert_eq!(args.min_megapixels, Some(8.0));
+    assert_eq!(args.exclude, [PathBuf::from("other")]);
+    assert!(args.require_label);
+  }
+}
@@ -0,0 +1,353 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Archive of originals: Verbatim copies of media files, made before any
+//! metadata is written to them.
+
+use std::{
+  collections::HashMap,
+  fs::{self, File},
+  io::{BufRead, BufReader, Read},
+  path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use super::{Organizer, to_abs_path};
+use crate::error::CatalogError;
+
+impl Organizer {
+  /// Copies each media file (but not sidecars) verbatim under `dir`,
+  /// maintaining its directory structure. Files with the same contents as any
+  /// already archived (e.g. before being renamed) are skipped. Run before
+  /// stages 3 and 4, which write metadata.
+  pub fn archive_originals(&mut self, dir: impl AsRef<Path>) -> Result<(), CatalogError> {
+    let dir = dir.as_ref();
+
+    if dir.is_relative() {
+      return Err(CatalogError::PathNotAbsolute(
+        dir.to_path_buf(),
+        "Archive path",
+      ));
+    }
+
+    log::info!("Archiving originals.");
+
+    fs::create_dir_all(dir).map_err(|e| {
+      CatalogError::Io(format!(
+        "{}: Unable to create archive directory ({e}).",
+        dir.display()
+      ))
+    })?;
+
+    let mut archived = Archived::new(dir)?;
+
+    for media in self.media.iter_data() {
+      let path = to_abs_path(&self.source, media);
+      let path_archive = dir.join(media.as_ref().strip_prefix(".").unwrap_or(media.as_ref()));
+
+      let result = archived.contains(&path).and_then(|contains| {
+        if contains {
+          log::debug!("{media}: Already archived.");
+          return Ok(());
+        }
+
+        archived.insert(&path, &path_archive)
+      });
+
+      self.warnings.check(result)?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Files in an archive, by size, so that only files of the same size need be
+/// hashed to check whether a file is already archived.
+struct Archived {
+  by_size: HashMap<u64, Vec<(PathBuf, Option<FileHash>)>>,
+}
+
+impl Archived {
+  /// Lists all files under `dir`.
+  fn new(dir: &Path) -> Result<Self, CatalogError> {
+    let mut by_size = HashMap::<_, Vec<_>>::new();
+    let mut dirs = Vec::from([dir.to_path_buf()]);
+
+    while let Some(dir) = dirs.pop() {
+      let entries = fs::read_dir(&dir).map_err(|e| {
+        CatalogError::Io(format!("{}: Unable to read archive ({e}).", dir.display()))
+      })?;
+
+      for entry in entries {
+        let entry = entry.map_err(|e| {
+          CatalogError::Io(format!("{}: Unable to read archive ({e}).", dir.display()))
+        })?;
+        let path = entry.path();
+
+        if path.is_dir() {
+          dirs.push(path);
+        } else {
+          by_size
+            .entry(file_size(&path)?)
+            .or_default()
+            .push((path, None));
+        }
+      }
+    }
+
+    Ok(Self { by_size })
+  }
+
+  /// Whether a file with the same contents as `file` is archived.
+  fn contains(&mut self, file: &Path) -> Result<bool, CatalogError> {
+    let Some(same_size) = self.by_size.get_mut(&file_size(file)?) else {
+      return Ok(false);
+    };
+
+    let hash = hash_file(file)?;
+
+    for (path, hash_archived) in same_size {
+      let hash_archived = match hash_archived {
+        Some(h) => *h,
+        None => *hash_archived.insert(hash_file(path)?),
+      };
+
+      if hash_archived == hash {
+        return Ok(true);
+      }
+    }
+
+    Ok(false)
+  }
+
+  /// Copies `file` to `file_archive`, which must not already exist.
+  fn insert(&mut self, file: &Path, file_archive: &Path) -> Result<(), CatalogError> {
+    if file_archive.exists() {
+      return Err(CatalogError::Io(format!(
+        "{}: Cannot archive due to name collision ({}).",
+        file.display(),
+        file_archive.display()
+      )));
+    }
+
+    log::debug!("{}: Archiving.", file.display());
+
+    fs::create_dir_all(file_archive.parent().unwrap())
+      .and_then(|()| fs::copy(file, file_archive))
+      .map_err(|e| {
+        CatalogError::Io(format!(
+          "{}: Unable to archive ({}): {e}",
+          file.display(),
+          file_archive.display()
+        ))
+      })?;
+
+    self
+      .by_size
+      .entry(file_size(file_archive)?)
+      .or_default()
+      .push((file_archive.to_path_buf(), None));
+
+    Ok(())
+  }
+}
+
+pub fn file_size(file: &Path) -> Result<u64, CatalogError> {
+  fs::metadata(file).map(|m| m.len()).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Unable to read file size ({e}).",
+      file.display()
+    ))
+  })
+}
+
+/// SHA-256 digest of a file's contents.
+pub type FileHash = [u8; 32];
+
+/// Hashes the contents of `file` with SHA-256, reading it in chunks as media
+/// may be large. Wide enough that files with equal hashes may be taken as
+/// identical.
+pub fn hash_file(file: &Path) -> Result<FileHash, CatalogError> {
+  let map_err = |e| CatalogError::Io(format!("{}: Unable to hash file ({e}).", file.display()));
+
+  let mut reader = File::open(file).map_err(map_err)?;
+  let mut hasher = Sha256::new();
+  let mut buffer = vec![0; 1 << 16];
+
+  loop {
+    let n = reader.read(&mut buffer).map_err(map_err)?;
+    if n == 0 {
+      return Ok(hasher.finalize().into());
+    }
+    hasher.update(&buffer[..n]);
+  }
+}
+
+/// Whether `file` and `other` have the same contents, comparing them byte by
+/// byte, e.g. to confirm matching hashes before removing either.
+pub fn same_contents(file: &Path, other: &Path) -> Result<bool, CatalogError> {
+  if file_size(file)? != file_size(other)? {
+    return Ok(false);
+  }
+
+  let open = |f: &Path| {
+    File::open(f)
+      .map(|f| BufReader::with_capacity(1 << 16, f))
+      .map_err(|e| CatalogError::Io(format!("{}: Unable to read file ({e}).", f.display())))
+  };
+  let mut reader = open(file)?;
+  let mut reader_other = open(other)?;
+
+  loop {
+    let buffer = reader
+      .fill_buf()
+      .map_err(|e| CatalogError::Io(format!("{}: Unable to read file ({e}).", file.display())))?;
+    let buffer_other = reader_other
+      .fill_buf()
+      .map_err(|e| CatalogError::Io(format!("{}: Unable to read file ({e}).", other.display())))?;
+
+    let n = buffer.len().min(buffer_other.len());
+    if n == 0 {
+      return Ok(buffer.is_empty() && buffer_other.is_empty());
+    }
+    if buffer[..n] != buffer_other[..n] {
+      return Ok(false);
+    }
+
+    reader.consume(n);
+    reader_other.consume(n);
+  }
+}
+
+#[cfg(test)]
+mod test_archive_originals {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn errors_if_archive_path_is_relative() {
+    let d = test_dir!();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    assert_err!(
+      o.archive_originals("relative/path"),
+      "Archive path is not absolute."
+    );
+  }
+
+  #[test]
+  fn copies_media_verbatim() {
+    let d = test_dir!(
+      "image.jpg": { "Creator": "Creator" },
+      "image.jpg.xmp": {},
+      "dir/video.mov": { "CompressorID": "avc1" },
+    );
+    let archive = d.trash().join("archive");
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.archive_originals(&archive).unwrap();
+
+    assert_trash!(d, ["archive/image.jpg", "archive/dir/video.mov"]);
+    for file in ["image.jpg", "dir/video.mov"] {
+      assert_eq!(
+        fs::read(archive.join(file)).unwrap(),
+        fs::read(d.get_path(file)).unwrap()
+      );
+    }
+  }
+
+  #[test]
+  fn keeps_original_after_metadata_write() {
+    let d = test_dir!(
+      "image.jpg": { "Creator": "Original" },
+    );
+    let archive = d.trash().join("archive");
+    let original = fs::read(d.get_path("image.jpg")).unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.archive_originals(&archive).unwrap();
+    crate::io::run_exiftool(&crate::io::ExifToolConfig::default(), Some(d.root()), [
+      "-Creator=Changed",
+      "-overwrite_original",
+      "image.jpg",
+    ])
+    .unwrap();
+
+    assert_eq!(fs::read(archive.join("image.jpg")).unwrap(), original);
+    assert_tag!(d, "image.jpg", "Creator", "Changed");
+  }
+
+  #[test]
+  fn skips_already_archived() {
+    let d = test_dir!(
+      "2000/01/000101_000000000.jpg": {},
+    );
+    let archive = d.trash().join("archive");
+    fs::create_dir_all(&archive).unwrap();
+    fs::copy(
+      d.get_path("2000/01/000101_000000000.jpg"),
+      archive.join("image.jpg"),
+    )
+    .unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.archive_originals(&archive).unwrap();
+
+    assert_trash!(d, ["archive/image.jpg"]);
+  }
+
+  #[test]
+  fn errors_if_name_collision_with_other_contents() {
+    let d = test_dir!(
+      "image.jpg": { "Creator": "New" },
+    );
+    let archive = d.trash().join("archive");
+    fs::create_dir_all(&archive).unwrap();
+    fs::write(archive.join("image.jpg"), "Other contents.").unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    assert_err!(
+      o.archive_originals(&archive),
+      "Cannot archive due to name collision"
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_hash_file {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn hashes_with_sha256() {
+    let d = test_dir!();
+    fs::write(d.get_path("file"), "abc").unwrap();
+
+    let hash = hash_file(&d.get_path("file")).unwrap();
+
+    assert_eq!(hash, [
+      0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22,
+      0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00,
+      0x15, 0xad,
+    ]);
+  }
+}
+
+#[cfg(test)]
+mod test_same_contents {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn compares_bytes() {
+    let d = test_dir!();
+    fs::write(d.get_path("a"), "contents").unwrap();
+    fs::write(d.get_path("b"), "contents").unwrap();
+    fs::write(d.get_path("c"), "contenTs").unwrap();
+    fs::write(d.get_path("d"), "contents, longer").unwrap();
+
+    assert!(same_contents(&d.get_path("a"), &d.get_path("b")).unwrap());
+    assert!(!same_contents(&d.get_path("a"), &d.get_path("c")).unwrap());
+    assert!(!same_contents(&d.get_path("a"), &d.get_path("d")).unwrap());
+  }
+}
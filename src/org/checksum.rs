@@ -0,0 +1,226 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Checksums of media files, written to their sidecars so that later
+//! corruption (e.g. bit rot) can be detected.
+
+use std::{ffi::OsStr, path::Path};
+
+use super::{Organizer, Warning, to_abs_path};
+use crate::{error::CatalogError, io, prim::Sidecar};
+
+impl Organizer {
+  /// Writes the SHA-256 of each media file's image data (`ExifTool`'s
+  /// `ImageDataHash`, which excludes metadata) to `tag` (e.g.
+  /// `XMP-et:OriginalImageHash`) in its sidecar, as lowercase hex. Checksums
+  /// are only written where `tag` is unset. Where it is set but differs, the
+  /// media may be corrupt, so it is reported and the stored checksum kept as
+  /// evidence. Files without sidecars are skipped. Run after stages 3 and 4, so
+  /// that any metadata written to media is settled.
+  pub fn write_checksums(&mut self, tag: &str) -> Result<(), CatalogError> {
+    // `ExifTool` reports tags by name only (e.g. `OriginalImageHash`).
+    let tag_name = tag.rsplit(':').next().unwrap_or_default();
+    if tag_name.is_empty() {
+      return Err(CatalogError::Parse(format!(
+        "Invalid checksum tag ({tag})."
+      )));
+    }
+
+    log::info!("Writing checksums to {tag}.");
+
+    for media in self.media.iter_data() {
+      let Some(sidecar) = media.get_sidecar().map(|h| &mut self.sidecars[h]) else {
+        log::warn!("{media}: Not writing checksum. File has no sidecar.");
+        continue;
+      };
+
+      let Some(checksum) = self.warnings.check(hash_image_data(
+        &self.exiftool,
+        &to_abs_path(&self.source, media),
+      ))?
+      else {
+        continue;
+      };
+
+      match sidecar.get_metadata().other_tags.get(tag_name) {
+        Some(stored) if stored.as_str() == Some(checksum.as_str()) => {
+          log::debug!("{media}: Checksum is current.");
+          continue;
+        }
+        Some(_) => {
+          self
+            .warnings
+            .push(Warning::ChecksumMismatch(media.as_ref().to_path_buf()));
+          continue;
+        }
+        None => {}
+      }
+
+      log::trace!("{media}: Writing checksum.");
+
+      let path = to_abs_path(&self.source, &*sidecar);
+      let arg = format!("-{tag}={checksum}");
+
+      let updated = self.warnings.check(
+        io::run_exiftool(&self.exiftool, Some(&self.source), [
+          OsStr::new(&arg),
+          path.as_os_str(),
+        ])
+        .and_then(|_| io::read_metadata(&self.exiftool, &path)),
+      )?;
+
+      if let Some(metadata) = updated {
+        sidecar.update_metadata(metadata);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Computes the SHA-256 of the image data of `file`, as lowercase hex, with
+/// `ExifTool`'s `ImageDataHash`. Unlike a hash of the whole file, this is
+/// unchanged by writing metadata to it.
+pub fn hash_image_data(exiftool: &io::ExifToolConfig, file: &Path) -> Result<String, CatalogError> {
+  let stdout = io::run_exiftool(exiftool, None::<&Path>, [
+    OsStr::new("-api"),
+    OsStr::new("ImageHashType=SHA256"),
+    OsStr::new("-ImageDataHash"),
+    OsStr::new("-s3"),
+    file.as_os_str(),
+  ])?;
+
+  let checksum = String::from_utf8_lossy(&stdout).trim().to_string();
+  if checksum.is_empty() {
+    return Err(CatalogError::InvalidFile(format!(
+      "{}: Unable to compute checksum (no image data).",
+      file.display()
+    )));
+  }
+
+  Ok(checksum)
+}
+
+#[cfg(test)]
+mod test_write_checksums {
+  use std::path::PathBuf;
+
+  use super::*;
+  use crate::{
+    org::ScanConfig,
+    testing::{self, *},
+  };
+
+  const TAG: &str = "XMP-et:OriginalImageHash";
+
+  #[test]
+  fn writes_checksum_to_sidecar() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.write_checksums(TAG).unwrap();
+
+    let expected =
+      hash_image_data(&io::ExifToolConfig::default(), &d.get_path("image.jpg")).unwrap();
+    let actual = testing::read_tag(d.root(), "image.jpg.xmp", None, "OriginalImageHash");
+
+    assert_eq!(actual.as_deref(), Some(expected.as_str()));
+    assert_tag!(d, "image.jpg", "OriginalImageHash", None);
+  }
+
+  #[test]
+  fn skips_current_checksum() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.write_checksums(TAG).unwrap();
+
+    let modified = d
+      .get_path("image.jpg.xmp")
+      .metadata()
+      .unwrap()
+      .modified()
+      .unwrap();
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.write_checksums(TAG).unwrap();
+
+    assert_eq!(
+      d.get_path("image.jpg.xmp")
+        .metadata()
+        .unwrap()
+        .modified()
+        .unwrap(),
+      modified
+    );
+  }
+
+  #[test]
+  fn reports_mismatched_checksum_without_overwriting() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {
+        "XMP-et:OriginalImageHash": "0000",
+      },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.write_checksums(TAG).unwrap();
+
+    assert_eq!(o.take_warnings(), [Warning::ChecksumMismatch(
+      PathBuf::from("./image.jpg")
+    )]);
+    assert_eq!(
+      testing::read_tag(d.root(), "image.jpg.xmp", None, "OriginalImageHash").as_deref(),
+      Some("0000")
+    );
+  }
+
+  #[test]
+  fn keeps_checksum_after_metadata_written_to_media() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.write_checksums(TAG).unwrap();
+    io::run_exiftool(&io::ExifToolConfig::default(), Some(d.root()), [
+      "-Creator=Creator",
+      "-overwrite_original",
+      "image.jpg",
+    ])
+    .unwrap();
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.write_checksums(TAG).unwrap();
+
+    assert!(o.take_warnings().is_empty());
+  }
+
+  #[test]
+  fn skips_media_without_sidecar() {
+    let d = test_dir!(
+      "image.jpg": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.write_checksums(TAG).unwrap();
+
+    assert_tag!(d, "image.jpg", "OriginalImageHash", None);
+  }
+
+  #[test]
+  fn errors_if_tag_empty() {
+    let d = test_dir!();
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+
+    assert_err!(o.write_checksums("XMP:"), "Invalid checksum tag");
+  }
+}
@@ -0,0 +1,374 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Organizer diagnostics: Read-only reporting of catalog inconsistencies.
+
+use std::{
+  collections::BTreeSet,
+  fmt::{self, Display, Formatter},
+  path::PathBuf,
+};
+
+use serde::Serialize;
+
+use super::{Organizer, to_abs_path};
+use crate::prim::{self, Handle, LivePhotoLinker, Media, Metadata, Sidecar};
+
+/// An inconsistency found in a catalog, with the path of the offending file.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub enum Issue {
+  /// Sidecar without an associated media file.
+  LeftoverSidecar(PathBuf),
+  /// Live Photo video without any associated image.
+  LeftoverLivePhotoVideo(PathBuf),
+  /// Media file without an initial sidecar.
+  MissingSidecar(PathBuf),
+  /// Media file still referenced by a sidecar or Live Photo, but gone.
+  MissingMedia(PathBuf),
+  /// `DateTimeOriginal` tag is set, but cannot be parsed.
+  UnparseableDate(PathBuf),
+  /// Live Photo component which would be removed in favor of a duplicate with a
+  /// better codec or more recent modification.
+  LivePhotoDuplicate(PathBuf),
+}
+
+impl Display for Issue {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Self::LeftoverSidecar(p) => write!(f, "{}: Leftover sidecar.", p.display()),
+      Self::LeftoverLivePhotoVideo(p) => {
+        write!(f, "{}: Live Photo video without image.", p.display())
+      }
+      Self::MissingSidecar(p) => write!(f, "{}: Missing sidecar.", p.display()),
+      Self::MissingMedia(p) => write!(f, "{}: Missing media file.", p.display()),
+      Self::UnparseableDate(p) => write!(f, "{}: Unable to parse `DateTimeOriginal`.", p.display()),
+      Self::LivePhotoDuplicate(p) => write!(f, "{}: Duplicate Live Photo component.", p.display()),
+    }
+  }
+}
+
+impl Organizer {
+  /// Finds everything stages 1 through 5 would act upon or reject, without
+  /// changing any files.
+  pub fn diagnose(&self) -> Vec<Issue> {
+    log::info!("Diagnosing catalog.");
+
+    let mut issues = Vec::new();
+
+    issues.extend(
+      self
+        .sidecars
+        .iter_data()
+        .filter(|s| s.is_leftover())
+        .map(|s| Issue::LeftoverSidecar(to_abs_path(&self.source, s))),
+    );
+    issues.extend(
+      self
+        .dupes
+        .iter_data()
+        .filter(|s| s.is_leftover())
+        .map(|s| Issue::LeftoverSidecar(to_abs_path(&self.source, s))),
+    );
+
+    for link in self.live_photo_map.values() {
+      if link.is_leftover_videos() {
+        issues.extend(
+          link
+            .iter_videos()
+            .map(|h| Issue::LeftoverLivePhotoVideo(self.media_path(h))),
+        );
+        continue;
+      }
+
+      issues.extend(
+        iter_duplicates(
+          link,
+          LivePhotoLinker::has_duplicate_images,
+          LivePhotoLinker::get_image_best,
+          LivePhotoLinker::iter_images,
+        )
+        .chain(iter_duplicates(
+          link,
+          LivePhotoLinker::has_duplicate_videos,
+          LivePhotoLinker::get_video_best,
+          LivePhotoLinker::iter_videos,
+        ))
+        .map(|h| Issue::LivePhotoDuplicate(self.media_path(h))),
+      );
+    }
+
+    issues.extend(
+      self
+        .report_missing_sidecars()
+        .into_iter()
+        .map(Issue::MissingSidecar),
+    );
+
+    issues.extend(
+      self
+        .media
+        .iter_data()
+        .map(Media::get_metadata)
+        .chain(self.sidecars.iter_data().map(Sidecar::get_metadata))
+        .filter(|m| is_date_unparseable(m))
+        .map(|m| Issue::UnparseableDate(to_abs_path(&self.source, m))),
+    );
+
+    issues.extend(
+      self
+        .find_orphan_media()
+        .into_iter()
+        .map(Issue::MissingMedia),
+    );
+
+    issues.sort_by_key(ToString::to_string);
+    issues
+  }
+
+  /// Finds media which are still referenced, by a sidecar or Live Photo, but no
+  /// longer exist. Either because they were removed (e.g. as a duplicate) or
+  /// deleted from disk since loading. Leftover sidecars, which never had media,
+  /// are reported by `diagnose` instead.
+  pub fn find_orphan_media(&self) -> Vec<PathBuf> {
+    let sidecar_refs = self
+      .sidecars
+      .iter_data()
+      .filter_map(Sidecar::get_media_handle)
+      .chain(self.dupes.iter_data().filter_map(Sidecar::get_media_handle));
+
+    let live_photo_refs = self
+      .live_photo_map
+      .values()
+      .flat_map(|l| l.iter_images().chain(l.iter_videos()));
+
+    sidecar_refs
+      .chain(live_photo_refs)
+      .filter(|h| {
+        self
+          .media
+          .get(*h)
+          .is_none_or(|m| !to_abs_path(&self.source, m).exists())
+      })
+      .filter_map(|h| self.media.get_path(h).map(PathBuf::from))
+      .collect::<BTreeSet<_>>()
+      .into_iter()
+      .collect()
+  }
+
+  fn media_path(&self, handle: Handle<Media>) -> PathBuf {
+    to_abs_path(&self.source, &self.media[handle])
+  }
+}
+
+/// Iterates over the components that `remove_live_photo_duplicates` would
+/// remove from `link`.
+fn iter_duplicates<'a, I: Iterator<Item = Handle<Media>> + 'a>(
+  link: &'a LivePhotoLinker,
+  has_duplicates: fn(&LivePhotoLinker) -> bool,
+  get: fn(&LivePhotoLinker) -> Handle<Media>,
+  iter: fn(&'a LivePhotoLinker) -> I,
+) -> impl Iterator<Item = Handle<Media>> + 'a {
+  let best = has_duplicates(link).then(|| get(link));
+  best
+    .into_iter()
+    .flat_map(move |best| iter(link).filter(move |h| *h != best))
+}
+
+/// Whether `DateTimeOriginal` is set, but cannot be parsed (e.g. is missing a
+/// time).
+fn is_date_unparseable(metadata: &Metadata) -> bool {
+  metadata
+    .date_time_original
+    .as_deref()
+    .is_some_and(|d| !prim::is_date_time_unset(d))
+    && metadata.get_date_time_original().is_none()
+}
+
+#[cfg(test)]
+mod test_diagnose {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn reports_nothing_for_clean_catalog() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID" },
+      "image.heic.xmp": {},
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+      "video.mov.xmp": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert_eq!(o.diagnose(), []);
+  }
+
+  #[test]
+  fn reports_leftover_sidecars() {
+    let d = test_dir!(
+      "image.jpg.xmp": {},
+      "image_01.jpg.xmp": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert_eq!(o.diagnose(), [
+      Issue::LeftoverSidecar(d.get_path("image.jpg.xmp")),
+      Issue::LeftoverSidecar(d.get_path("image_01.jpg.xmp")),
+    ]);
+  }
+
+  #[test]
+  fn reports_leftover_live_photo_video() {
+    let d = test_dir!(
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+      "video.mov.xmp": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert_eq!(o.diagnose(), [Issue::LeftoverLivePhotoVideo(
+      d.get_path("video.mov")
+    )]);
+  }
+
+  #[test]
+  fn reports_missing_sidecar() {
+    let d = test_dir!(
+      "image.jpg": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert_eq!(o.diagnose(), [Issue::MissingSidecar(
+      d.get_path("image.jpg")
+    )]);
+  }
+
+  #[test]
+  fn reports_unparseable_date() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": { "DateTimeOriginal": "2000" },
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert_eq!(o.diagnose(), [Issue::UnparseableDate(
+      d.get_path("image.jpg.xmp")
+    )]);
+  }
+
+  #[test]
+  fn reports_live_photo_duplicates() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID" },
+      "image.heic.xmp": {},
+      "image.jpg": { "ContentIdentifier": "ID" },
+      "image.jpg.xmp": {},
+      "video_good.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+      "video_good.mov.xmp": {},
+      "video_bad.mov": { "ContentIdentifier": "ID", "CompressorID": "avc1" },
+      "video_bad.mov.xmp": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert_eq!(o.diagnose(), [
+      Issue::LivePhotoDuplicate(d.get_path("image.jpg")),
+      Issue::LivePhotoDuplicate(d.get_path("video_bad.mov")),
+    ]);
+  }
+
+  #[test]
+  fn leaves_files_unchanged() {
+    let d = test_dir!(
+      "image.jpg.xmp": {},
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.diagnose();
+
+    assert_dir!(d, ["image.jpg.xmp", "video.mov"]);
+    assert_trash!(d, []);
+  }
+}
+
+#[cfg(test)]
+mod test_is_date_unparseable {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn is_unparseable_if_invalid() {
+    let metadata = metadata!(
+      "DateTimeOriginal": "2000",
+    );
+
+    assert!(is_date_unparseable(&metadata));
+  }
+
+  #[test]
+  fn treats_unset_date_as_missing() {
+    let metadata = metadata!(
+      "DateTimeOriginal": "0000:00:00 00:00:00",
+    );
+
+    assert!(!is_date_unparseable(&metadata));
+  }
+}
+
+#[cfg(test)]
+mod test_find_orphan_media {
+  use std::fs;
+
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn reports_nothing_for_clean_catalog() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID" },
+      "image.heic.xmp": {},
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert!(o.find_orphan_media().is_empty());
+  }
+
+  #[test]
+  fn reports_media_deleted_from_disk() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+      "image_01.jpg.xmp": {},
+      "image.heic": { "ContentIdentifier": "ID" },
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    fs::remove_file(d.get_path("image.jpg")).unwrap();
+    fs::remove_file(d.get_path("video.mov")).unwrap();
+
+    assert_eq!(o.find_orphan_media(), [
+      d.get_path("image.jpg"),
+      d.get_path("video.mov"),
+    ]);
+  }
+
+  #[test]
+  fn reports_nothing_for_media_removed_with_sidecars() {
+    let d = test_dir!(
+      "image1.jpg": { "BurstUUID": "ID" },
+      "image2.jpg": { "BurstUUID": "ID" },
+      "image2.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.collapse_bursts().unwrap();
+
+    assert!(o.find_orphan_media().is_empty());
+  }
+}
@@ -0,0 +1,194 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Export of a searchable index of catalog metadata.
+
+use std::{fs, path::Path};
+
+use serde::Serialize;
+
+use super::Organizer;
+use crate::{
+  error::CatalogError,
+  prim::{self, Metadata, Sidecar},
+};
+
+/// File format of an exported index.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum IndexFormat {
+  Csv,
+  Json,
+}
+
+/// A single media file's entry in the index.
+#[derive(Serialize)]
+struct IndexRow<'a> {
+  path:     &'a Path,
+  date:     Option<&'a str>,
+  gps:      Option<&'a str>,
+  city:     Option<&'a str>,
+  state:    Option<&'a str>,
+  country:  Option<&'a str>,
+  creator:  Option<&'a str>,
+  keywords: &'a [String],
+  rating:   Option<f32>,
+}
+
+impl<'a> IndexRow<'a> {
+  const HEADER: &'static str = "path,date,gps,city,state,country,creator,keywords,rating";
+
+  fn new(metadata: &'a Metadata, path: &'a Path) -> Self {
+    Self {
+      path,
+      date: metadata
+        .sub_sec_date_time_original
+        .as_deref()
+        .or(metadata.date_time_original.as_deref())
+        .filter(|d| !prim::is_date_time_unset(d)),
+      gps: metadata.gps_position.as_deref(),
+      city: metadata.city.as_deref(),
+      state: metadata.state.as_deref(),
+      country: metadata.country.as_deref(),
+      creator: metadata.creator.as_deref(),
+      keywords: &metadata.subject,
+      rating: metadata.rating,
+    }
+  }
+
+  /// Formats as a CSV line. Keywords are joined by `;`.
+  fn to_csv(&self) -> String {
+    [
+      escape_csv(&self.path.to_string_lossy()),
+      escape_csv(self.date.unwrap_or_default()),
+      escape_csv(self.gps.unwrap_or_default()),
+      escape_csv(self.city.unwrap_or_default()),
+      escape_csv(self.state.unwrap_or_default()),
+      escape_csv(self.country.unwrap_or_default()),
+      escape_csv(self.creator.unwrap_or_default()),
+      escape_csv(&self.keywords.join(";")),
+      self.rating.map(|r| r.to_string()).unwrap_or_default(),
+    ]
+    .join(",")
+  }
+}
+
+impl Organizer {
+  /// Writes an index of all media to `path`, with paths relative to the
+  /// catalog. Metadata is taken from the sidecar where present.
+  pub fn export_index(
+    &self,
+    path: impl AsRef<Path>,
+    format: IndexFormat,
+  ) -> Result<(), CatalogError> {
+    log::info!("{}: Exporting index.", path.as_ref().display());
+
+    let mut media = self
+      .media
+      .iter_data()
+      .map(|m| {
+        let metadata = m
+          .get_sidecar()
+          .map_or(m.get_metadata(), |h| self.sidecars[h].get_metadata());
+        let path = m.as_ref();
+        (metadata, path.strip_prefix(".").unwrap_or(path))
+      })
+      .collect::<Vec<_>>();
+    media.sort_by_key(|(_, p)| *p);
+
+    let rows = media.iter().map(|(m, p)| IndexRow::new(m, p));
+
+    let contents = match format {
+      IndexFormat::Csv => {
+        let mut lines = vec![IndexRow::HEADER.to_string()];
+        lines.extend(rows.map(|r| r.to_csv()));
+        lines.join("\n") + "\n"
+      }
+      IndexFormat::Json => serde_json::to_string_pretty(&rows.collect::<Vec<_>>())
+        .map_err(|e| CatalogError::Parse(format!("Unable to serialize index ({e}).")))?,
+    };
+
+    fs::write(&path, contents).map_err(|e| {
+      CatalogError::Io(format!(
+        "{}: Unable to write index ({e}).",
+        path.as_ref().display()
+      ))
+    })
+  }
+}
+
+/// Quotes `field` if it contains characters special to CSV.
+fn escape_csv(field: &str) -> String {
+  if field.contains([',', '"', '\n']) {
+    format!("\"{}\"", field.replace('"', "\"\""))
+  } else {
+    field.to_string()
+  }
+}
+
+#[cfg(test)]
+mod test_export_index {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn exports_csv() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {
+        "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
+        "GPSLatitude": "47.6061 N",
+        "GPSLongitude": "122.3328 W",
+        "City": "Seattle",
+        "State": "Washington",
+        "Country": "United States",
+        "Creator": "Creator",
+        "Subject": "Family",
+        "Rating": "4",
+      },
+      "other.jpg": {},
+    );
+
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.export_index(d.trash().join("index.csv"), IndexFormat::Csv)
+      .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(d.trash().join("index.csv")).unwrap(),
+      "path,date,gps,city,state,country,creator,keywords,rating\nimage.jpg,2000-01-01T00:00:00+00:\
+       00,\"47 deg 36' 21.96\"\" N, 122 deg 19' 58.08\"\" W\",Seattle,Washington,United \
+       States,Creator,Family,4\nother.jpg,,,,,,,,\n"
+    );
+  }
+
+  #[test]
+  fn exports_json() {
+    let d = test_dir!(
+      "image.jpg": {
+        "City": "Seattle",
+        "Creator": "Creator",
+        "Subject": "Family",
+        "Rating": "5",
+      },
+    );
+
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.export_index(d.trash().join("index.json"), IndexFormat::Json)
+      .unwrap();
+
+    let index: serde_json::Value =
+      serde_json::from_str(&fs::read_to_string(d.trash().join("index.json")).unwrap()).unwrap();
+    assert_eq!(
+      index,
+      serde_json::json!([{
+        "path": "image.jpg",
+        "date": null,
+        "gps": null,
+        "city": "Seattle",
+        "state": null,
+        "country": null,
+        "creator": "Creator",
+        "keywords": ["Family"],
+        "rating": 5.0,
+      }])
+    );
+  }
+}
@@ -0,0 +1,300 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Offline reverse geocoding of GPS coordinates to the nearest city, from a
+//! `GeoNames` dump, as an alternative to `ExifTool`'s `-geolocate`.
+
+use std::{
+  collections::HashMap,
+  fs::File,
+  io::{BufRead, BufReader},
+  path::Path,
+};
+
+use crate::error::CatalogError;
+
+/// Name of the `GeoNames` file of first-level administrative divisions (e.g.
+/// states), expected alongside the cities file.
+const ADMIN1_FILE: &str = "admin1CodesASCII.txt";
+/// Name of the `GeoNames` file of countries, expected alongside the cities
+/// file.
+const COUNTRY_FILE: &str = "countryInfo.txt";
+
+/// Rings of 1° cells searched around a coordinate for the nearest city, beyond
+/// which coordinates are taken as having no city (e.g. at sea).
+const MAX_RING: i32 = 3;
+/// Mean radius of the Earth, in kilometers.
+const EARTH_RADIUS_KM: f64 = 6371.0;
+/// Length of a degree of latitude, in kilometers.
+const DEGREE_KM: f64 = 111.2;
+
+/// A city, with the names of its state and country.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Place {
+  pub city:    String,
+  pub state:   Option<String>,
+  pub country: String,
+  lat:         f64,
+  lon:         f64,
+}
+
+/// Finds the nearest city to GPS coordinates, from cities indexed by 1° cell.
+#[derive(Default)]
+pub struct Geocoder {
+  places: Vec<Place>,
+  cells:  HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl Geocoder {
+  /// Loads a `GeoNames` cities file (e.g. `cities1000.txt`), with the state and
+  /// country files (`admin1CodesASCII.txt` and `countryInfo.txt`) from the
+  /// same directory. See <https://download.geonames.org/export/dump/>.
+  pub fn load(cities: impl AsRef<Path>) -> Result<Self, CatalogError> {
+    let cities = cities.as_ref();
+    let dir = cities.parent().unwrap_or(Path::new(""));
+
+    let open = |path: &Path| {
+      File::open(path).map(BufReader::new).map_err(|e| {
+        CatalogError::Io(format!(
+          "{}: Unable to open geocoding database ({e}).",
+          path.display()
+        ))
+      })
+    };
+
+    Self::from_geonames(
+      open(cities)?,
+      open(&dir.join(ADMIN1_FILE))?,
+      open(&dir.join(COUNTRY_FILE))?,
+    )
+  }
+
+  /// Builds from `GeoNames` cities, states (`admin1`) and countries, each
+  /// tab-separated as in their dumps. Cities in unknown countries are skipped.
+  pub fn from_geonames(
+    cities: impl BufRead,
+    admin1: impl BufRead,
+    countries: impl BufRead,
+  ) -> Result<Self, CatalogError> {
+    // Columns: code (e.g. `US.WA`), name, ASCII name, ID.
+    let states = read_columns(admin1)?
+      .filter_map(|c| Some((c.first()?.clone(), c.get(1)?.clone())))
+      .collect::<HashMap<_, _>>();
+    // Columns: ISO code (e.g. `US`), ISO3, numeric, FIPS, name, ...
+    let countries = read_columns(countries)?
+      .filter_map(|c| Some((c.first()?.clone(), c.get(4)?.clone())))
+      .collect::<HashMap<_, _>>();
+
+    let mut geocoder = Self::default();
+
+    // Columns: ID, name, ASCII name, alternate names, latitude, longitude,
+    // feature class, feature code, country code, alternate country codes,
+    // admin1 code, ...
+    for (i, columns) in read_columns(cities)?.enumerate() {
+      let [
+        _,
+        city,
+        _,
+        _,
+        lat,
+        lon,
+        _,
+        _,
+        country_code,
+        _,
+        admin1_code,
+        ..,
+      ] = columns.as_slice()
+      else {
+        return Err(CatalogError::InvalidFile(format!(
+          "Geocoding database: Invalid city on line {}.",
+          i + 1
+        )));
+      };
+      let (Ok(lat), Ok(lon)) = (lat.parse::<f64>(), lon.parse::<f64>()) else {
+        return Err(CatalogError::InvalidFile(format!(
+          "Geocoding database: Invalid coordinates on line {}.",
+          i + 1
+        )));
+      };
+      let Some(country) = countries.get(country_code) else {
+        continue;
+      };
+
+      geocoder
+        .cells
+        .entry(cell_of(lat, lon))
+        .or_default()
+        .push(geocoder.places.len());
+      geocoder.places.push(Place {
+        city: city.clone(),
+        state: states
+          .get(&format!("{country_code}.{admin1_code}"))
+          .cloned(),
+        country: country.clone(),
+        lat,
+        lon,
+      });
+    }
+
+    Ok(geocoder)
+  }
+
+  /// Finds the nearest city to `lat` and `lon` (in decimal degrees), if any is
+  /// within a few degrees.
+  #[must_use]
+  pub fn find(&self, lat: f64, lon: f64) -> Option<&Place> {
+    let (cell_lat, cell_lon) = cell_of(lat, lon);
+    // Cells are narrower away from the equator, so a ring may be nearer than
+    // its number of degrees of latitude.
+    let ring_km = DEGREE_KM * lat.to_radians().cos();
+
+    let mut nearest: Option<(f64, &Place)> = None;
+
+    for ring in 0..=MAX_RING {
+      for cell in ring_cells(cell_lat, cell_lon, ring) {
+        for place in self.cells.get(&cell).into_iter().flatten() {
+          let place = &self.places[*place];
+          let distance = distance_km(lat, lon, place.lat, place.lon);
+          if nearest.is_none_or(|(d, _)| distance < d) {
+            nearest = Some((distance, place));
+          }
+        }
+      }
+
+      // Cities in further rings are at least this far away.
+      if nearest.is_some_and(|(d, _)| d <= f64::from(ring) * ring_km) {
+        break;
+      }
+    }
+
+    nearest.map(|(_, place)| place)
+  }
+}
+
+/// Reads tab-separated `lines`, skipping blank lines and comments (starting
+/// with `#`).
+fn read_columns(lines: impl BufRead) -> Result<impl Iterator<Item = Vec<String>>, CatalogError> {
+  let lines = lines
+    .lines()
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| CatalogError::Io(format!("Unable to read geocoding database ({e}).")))?;
+
+  Ok(
+    lines
+      .into_iter()
+      .filter(|l| !l.is_empty() && !l.starts_with('#'))
+      .map(|l| l.split('\t').map(ToString::to_string).collect()),
+  )
+}
+
+/// Gets the 1° cell containing `lat` and `lon`.
+#[allow(clippy::cast_possible_truncation)]
+fn cell_of(lat: f64, lon: f64) -> (i32, i32) {
+  (lat.floor() as i32, lon.floor() as i32)
+}
+
+/// Gets the cells `ring` cells away from the one at `lat` and `lon`, wrapping
+/// around the antimeridian.
+fn ring_cells(lat: i32, lon: i32, ring: i32) -> impl Iterator<Item = (i32, i32)> {
+  (-ring..=ring).flat_map(move |d_lat| {
+    (-ring..=ring)
+      .filter(move |d_lon| d_lat.abs() == ring || d_lon.abs() == ring)
+      .map(move |d_lon| (lat + d_lat, (lon + d_lon + 180).rem_euclid(360) - 180))
+  })
+}
+
+/// Gets the great-circle distance between two coordinates, in kilometers.
+fn distance_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+  let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+  let d_lat = lat2 - lat1;
+  let d_lon = (lon2 - lon1).to_radians();
+
+  let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+  2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+#[cfg(test)]
+mod test_find {
+  use super::*;
+
+  // Columns after the admin1 code are omitted.
+  const CITIES: &str = "\
+5809844\tSeattle\tSeattle\t\t47.60621\t-122.33207\tP\tPPLA2\tUS\t\tWA\t033
+5799841\tKirkland\tKirkland\t\t47.68149\t-122.20874\tP\tPPL\tUS\t\tWA\t033
+6173331\tVancouver\tVancouver\t\t49.24966\t-123.11934\tP\tPPL\tCA\t\t02
+2643743\tLondon\tLondon\t\t51.50853\t-0.12574\tP\tPPLC\tGB\t\tENG\tGLA
+";
+  const ADMIN1: &str = "\
+US.WA\tWashington\tWashington\t5815135
+CA.02\tBritish Columbia\tBritish Columbia\t5909050
+GB.ENG\tEngland\tEngland\t6269131
+";
+  const COUNTRIES: &str = "\
+# ISO\tISO3\tISO-Numeric\tfips\tCountry
+US\tUSA\t840\tUS\tUnited States
+CA\tCAN\t124\tCA\tCanada
+GB\tGBR\t826\tUK\tUnited Kingdom
+";
+
+  fn geocoder() -> Geocoder {
+    Geocoder::from_geonames(CITIES.as_bytes(), ADMIN1.as_bytes(), COUNTRIES.as_bytes()).unwrap()
+  }
+
+  #[test]
+  fn finds_nearest_city() {
+    let geocoder = geocoder();
+    let place = geocoder.find(47.6061, -122.3328).unwrap();
+
+    assert_eq!(place.city, "Seattle");
+    assert_eq!(place.state.as_deref(), Some("Washington"));
+    assert_eq!(place.country, "United States");
+  }
+
+  #[test]
+  fn finds_nearest_city_in_other_cell() {
+    // Just across 0° longitude from London.
+    let geocoder = geocoder();
+    let place = geocoder.find(51.5, 0.01).unwrap();
+
+    assert_eq!(place.city, "London");
+    assert_eq!(place.country, "United Kingdom");
+  }
+
+  #[test]
+  fn prefers_nearer_city() {
+    let geocoder = geocoder();
+    let place = geocoder.find(47.68, -122.21).unwrap();
+
+    assert_eq!(place.city, "Kirkland");
+  }
+
+  #[test]
+  fn finds_nothing_far_from_cities() {
+    assert!(geocoder().find(0.0, -140.0).is_none());
+  }
+
+  #[test]
+  fn errors_if_city_invalid() {
+    let result = Geocoder::from_geonames(
+      "5809844\tSeattle".as_bytes(),
+      ADMIN1.as_bytes(),
+      COUNTRIES.as_bytes(),
+    );
+
+    assert!(result.is_err());
+  }
+}
+
+#[cfg(test)]
+mod test_load {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn errors_if_missing() {
+    assert_err!(
+      Geocoder::load("/path/does/not/exist/cities1000.txt"),
+      "Unable to open geocoding database"
+    );
+  }
+}
@@ -0,0 +1,293 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Index of media contents by hash, computed once (optionally up front, in
+//! parallel) and shared by passes comparing contents, so that each file is
+//! hashed at most once unless it changes.
+
+use std::{
+  collections::HashMap,
+  fs,
+  path::{Path, PathBuf},
+  time::SystemTime,
+};
+
+use super::{
+  Organizer,
+  archive::{FileHash, file_size, hash_file, same_contents},
+  par_map,
+  to_abs_path,
+};
+use crate::{
+  error::CatalogError,
+  prim::{Handle, Media},
+};
+
+/// Size and hash of a file's contents. Files with equal `ContentHash` are taken
+/// as identical.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ContentHash {
+  size: u64,
+  hash: FileHash,
+}
+
+impl ContentHash {
+  /// Hashes the contents of `file`.
+  pub fn of(file: &Path) -> Result<Self, CatalogError> {
+    Ok(Self {
+      size: file_size(file)?,
+      hash: hash_file(file)?,
+    })
+  }
+}
+
+/// File found with the same contents as media, with its handle if media.
+pub type Identical = (Option<Handle<Media>>, PathBuf);
+
+/// Hash of a media file, with when it was modified, to detect changes (e.g.
+/// metadata written) since.
+struct Entry {
+  hash:     ContentHash,
+  modified: SystemTime,
+}
+
+impl Entry {
+  fn read(file: &Path) -> Result<Self, CatalogError> {
+    Ok(Self {
+      modified: modified(file)?,
+      hash:     ContentHash::of(file)?,
+    })
+  }
+
+  /// Whether `file` is unchanged since hashed.
+  fn is_current(&self, file: &Path) -> bool {
+    file_size(file).is_ok_and(|s| s == self.hash.size)
+      && modified(file).is_ok_and(|m| m == self.modified)
+  }
+}
+
+/// Content hashes of media, by handle.
+#[derive(Default)]
+pub struct HashIndex {
+  entries: HashMap<Handle<Media>, Entry>,
+}
+
+impl HashIndex {
+  /// Hashes each of `files` (media handles and absolute paths) not already
+  /// current, using up to `workers` threads. Returns any errors, in the order
+  /// of `files`.
+  pub fn index(&mut self, files: &[(Handle<Media>, PathBuf)], workers: usize) -> Vec<CatalogError> {
+    let stale = files
+      .iter()
+      .filter(|(h, path)| !self.entries.get(h).is_some_and(|e| e.is_current(path)))
+      .collect::<Vec<_>>();
+
+    let results = par_map(&stale, workers, |(handle, path)| {
+      (*handle, Entry::read(path))
+    });
+
+    let mut errors = Vec::new();
+    for (handle, entry) in results {
+      match entry {
+        Ok(entry) => {
+          self.entries.insert(handle, entry);
+        }
+        Err(e) => errors.push(e),
+      }
+    }
+
+    errors
+  }
+
+  /// Gets the hash of media `handle` at `file`, hashing it if not indexed or
+  /// changed since.
+  pub fn get(&mut self, handle: Handle<Media>, file: &Path) -> Result<ContentHash, CatalogError> {
+    if let Some(entry) = self.entries.get(&handle)
+      && entry.is_current(file)
+    {
+      return Ok(entry.hash);
+    }
+
+    let entry = Entry::read(file)?;
+    let hash = entry.hash;
+    self.entries.insert(handle, entry);
+    Ok(hash)
+  }
+
+  /// Finds the first of `files` (not media) or `media` (handles and absolute
+  /// paths) with the same contents as media `handle` at `file`, with its handle
+  /// if media. Only files of the same size are hashed, and those with the same
+  /// hash are then compared byte by byte.
+  pub fn find_identical<'a>(
+    &mut self,
+    handle: Handle<Media>,
+    file: &Path,
+    files: impl IntoIterator<Item = &'a PathBuf>,
+    media: impl IntoIterator<Item = &'a (Handle<Media>, PathBuf)>,
+  ) -> Result<Option<Identical>, CatalogError> {
+    let size = file_size(file)?;
+    let mut hash = None;
+
+    let others = files
+      .into_iter()
+      .map(|f| (None, f))
+      .chain(media.into_iter().map(|(h, f)| (Some(*h), f)));
+
+    for (other_handle, other) in others {
+      if file_size(other)? != size {
+        continue;
+      }
+
+      let hash = match hash {
+        Some(hash) => hash,
+        None => *hash.insert(self.get(handle, file)?),
+      };
+      let other_hash = match other_handle {
+        Some(h) => self.get(h, other)?,
+        None => ContentHash::of(other)?,
+      };
+      if other_hash == hash && same_contents(file, other)? {
+        return Ok(Some((other_handle, other.clone())));
+      }
+    }
+
+    Ok(None)
+  }
+}
+
+impl Organizer {
+  /// Hashes the contents of all media up front, using up to as many threads as
+  /// set by `set_workers`, for passes comparing contents (i.e.
+  /// `remove_identical_media` and trashing identical media when moving).
+  /// Otherwise, media is hashed as needed.
+  pub fn index_hashes(&mut self) -> Result<(), CatalogError> {
+    log::info!("Hashing media contents.");
+
+    let files = self
+      .media
+      .iter_data_indexed()
+      .map(|(h, m)| (h, to_abs_path(&self.source, m)))
+      .collect::<Vec<_>>();
+
+    for e in self.hash_index.index(&files, self.workers) {
+      self.warnings.check::<()>(Err(e))?;
+    }
+
+    Ok(())
+  }
+}
+
+fn modified(file: &Path) -> Result<SystemTime, CatalogError> {
+  fs::metadata(file).and_then(|m| m.modified()).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Unable to read modification time ({e}).",
+      file.display()
+    ))
+  })
+}
+
+#[cfg(test)]
+mod test_hash_index {
+  use super::*;
+  use crate::testing::*;
+
+  fn write(d: &TestDir, file: &str, contents: &str) -> PathBuf {
+    let path = d.get_path(file);
+    fs::write(&path, contents).unwrap();
+    path
+  }
+
+  #[test]
+  fn matches_identical_contents() {
+    let d = test_dir!();
+    let a = write(&d, "a.jpg", "contents");
+    let b = write(&d, "b.jpg", "contents");
+    let c = write(&d, "c.jpg", "other");
+
+    let mut index = HashIndex::default();
+
+    assert_eq!(
+      index.get(Handle::from(0), &a).unwrap(),
+      index.get(Handle::from(1), &b).unwrap()
+    );
+    assert_ne!(
+      index.get(Handle::from(0), &a).unwrap(),
+      index.get(Handle::from(2), &c).unwrap()
+    );
+  }
+
+  #[test]
+  fn indexes_in_parallel_as_in_sequence() {
+    let d = test_dir!();
+    let files = (0..8)
+      .map(|i| {
+        (
+          Handle::from(i),
+          write(&d, &format!("{i}.jpg"), &"x".repeat(i % 3)),
+        )
+      })
+      .collect::<Vec<_>>();
+
+    let mut parallel = HashIndex::default();
+    assert!(parallel.index(&files, 4).is_empty());
+
+    let mut sequential = HashIndex::default();
+    for (handle, path) in &files {
+      assert_eq!(
+        parallel.get(*handle, path).unwrap(),
+        sequential.get(*handle, path).unwrap()
+      );
+    }
+  }
+
+  #[test]
+  fn rehashes_changed_file() {
+    let d = test_dir!();
+    let a = write(&d, "a.jpg", "contents");
+
+    let mut index = HashIndex::default();
+    let before = index.get(Handle::from(0), &a).unwrap();
+    write(&d, "a.jpg", "new contents");
+
+    assert_ne!(index.get(Handle::from(0), &a).unwrap(), before);
+  }
+
+  #[test]
+  fn finds_identical_file_or_media() {
+    let d = test_dir!();
+    let a = write(&d, "a.jpg", "contents");
+    let b = write(&d, "b.jpg", "contents");
+    let c = write(&d, "c.jpg", "contents");
+    let other = write(&d, "other.jpg", "contents!");
+
+    let mut index = HashIndex::default();
+    let handle = Handle::from(0);
+
+    assert_eq!(
+      index
+        .find_identical(handle, &a, [&other, &b], &[(Handle::from(1), c.clone())])
+        .unwrap(),
+      Some((None, b))
+    );
+    assert_eq!(
+      index
+        .find_identical(handle, &a, [&other], &[(Handle::from(1), c.clone())])
+        .unwrap(),
+      Some((Some(Handle::from(1)), c))
+    );
+    assert_eq!(
+      index.find_identical(handle, &a, [&other], &[]).unwrap(),
+      None
+    );
+  }
+
+  #[test]
+  fn reports_errors() {
+    let mut index = HashIndex::default();
+    let errors = index.index(
+      &[(Handle::from(0), PathBuf::from("/does/not/exist.jpg"))],
+      2,
+    );
+
+    assert_eq!(errors.len(), 1);
+  }
+}
@@ -0,0 +1,164 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Organizer health report: Read-only summary of everything wrong or
+//! noteworthy about a catalog.
+
+use std::{
+  fmt::{self, Display, Formatter},
+  path::PathBuf,
+};
+
+use serde::Serialize;
+
+use super::{Organizer, doctor::Issue, to_abs_path};
+
+/// Consolidated results of all read-only checks on a catalog.
+#[derive(Serialize)]
+pub struct HealthReport {
+  /// Details of the tooling in use, e.g. the `ExifTool` version.
+  pub environment: Vec<String>,
+  pub stats:       Stats,
+  /// Inconsistencies that `org` would act upon.
+  pub issues:      Vec<Issue>,
+  /// Media failing metadata validation.
+  pub invalid:     Vec<PathBuf>,
+}
+
+/// Number of each kind of file in the catalog.
+#[derive(Serialize)]
+pub struct Stats {
+  pub media:       usize,
+  pub sidecars:    usize,
+  pub dupes:       usize,
+  pub live_photos: usize,
+}
+
+impl Display for HealthReport {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    writeln!(f, "Environment:")?;
+    for line in &self.environment {
+      writeln!(f, "  {line}")?;
+    }
+
+    writeln!(f, "Stats:")?;
+    writeln!(f, "  Media: {}", self.stats.media)?;
+    writeln!(f, "  Sidecars: {}", self.stats.sidecars)?;
+    writeln!(f, "  Duplicate sidecars: {}", self.stats.dupes)?;
+    writeln!(f, "  Live Photos: {}", self.stats.live_photos)?;
+
+    writeln!(f, "Issues ({}):", self.issues.len())?;
+    for issue in &self.issues {
+      writeln!(f, "  {issue}")?;
+    }
+
+    writeln!(f, "Invalid metadata ({}):", self.invalid.len())?;
+    for path in &self.invalid {
+      writeln!(f, "  {}", path.display())?;
+    }
+
+    Ok(())
+  }
+}
+
+impl Organizer {
+  /// Builds a `HealthReport` from all read-only checks. Enables all
+  /// validation, but does not otherwise change any files.
+  pub fn health(&mut self) -> HealthReport {
+    self.enable_attribution_validation();
+    self.enable_camera_validation();
+    self.enable_date_time_validation();
+    self.enable_location_validation();
+    self.enable_time_zone_validation();
+    self.validate();
+
+    let mut invalid = self
+      .media
+      .iter_data_indexed()
+      .filter(|(h, _)| !self.valid_media.contains(h))
+      .map(|(_, m)| to_abs_path(&self.source, m))
+      .collect::<Vec<_>>();
+    invalid.sort();
+
+    HealthReport {
+      environment: Vec::new(),
+      stats: Stats {
+        media:       self.media.iter_data().count(),
+        sidecars:    self.sidecars.iter_data().count(),
+        dupes:       self.dupes.iter_data().count(),
+        live_photos: self.live_photo_map.len(),
+      },
+      issues: self.diagnose(),
+      invalid,
+    }
+  }
+}
+
+#[cfg(test)]
+mod test_health {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn reports_each_section() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID" },
+      "image.jpg": { "ContentIdentifier": "ID" },
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+      "leftover.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    let mut report = o.health();
+    report.environment.push("ExifTool 0.0".to_string());
+
+    assert_eq!(report.stats.media, 3);
+    assert_eq!(report.stats.sidecars, 1);
+    assert_eq!(report.stats.live_photos, 1);
+    assert!(
+      report
+        .issues
+        .contains(&Issue::LeftoverSidecar(d.get_path("leftover.jpg.xmp")))
+    );
+    assert!(
+      report
+        .issues
+        .contains(&Issue::LivePhotoDuplicate(d.get_path("image.jpg")))
+    );
+    assert!(
+      report
+        .issues
+        .contains(&Issue::MissingSidecar(d.get_path("video.mov")))
+    );
+    assert_eq!(report.invalid.len(), 3);
+
+    let human = report.to_string();
+    for section in ["Environment:", "Stats:", "Issues (", "Invalid metadata ("] {
+      assert!(
+        human.contains(section),
+        "Missing section `{section}`:\n{human}"
+      );
+    }
+
+    let json = serde_json::to_value(&report).unwrap();
+    for section in ["environment", "stats", "issues", "invalid"] {
+      assert!(
+        json.get(section).is_some(),
+        "Missing section `{section}`:\n{json}"
+      );
+    }
+  }
+
+  #[test]
+  fn leaves_files_unchanged() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "leftover.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.health();
+
+    assert_dir!(d, ["image.jpg", "leftover.jpg.xmp"]);
+    assert_trash!(d, []);
+  }
+}
@@ -0,0 +1,117 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Counts of media per day of capture, to find gaps in imports.
+
+use std::{
+  collections::{BTreeMap, HashSet},
+  fmt::{self, Display, Formatter},
+};
+
+use chrono::NaiveDate;
+
+use super::Organizer;
+use crate::prim::LivePhotoLinker;
+
+/// Number of media captured on each day, with those without a capture date
+/// counted separately.
+#[derive(Debug, Default, PartialEq)]
+pub struct DayCounts {
+  pub days:    BTreeMap<NaiveDate, usize>,
+  pub undated: usize,
+}
+
+impl Display for DayCounts {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    for (day, count) in &self.days {
+      writeln!(f, "{day}: {count}")?;
+    }
+    writeln!(f, "Undated: {}", self.undated)
+  }
+}
+
+impl Organizer {
+  /// Counts media by the day they were captured, in their local time. Live
+  /// Photos count once, by their image.
+  #[must_use]
+  pub fn count_by_day(&self) -> DayCounts {
+    let live_photo_videos = self
+      .live_photo_map
+      .values()
+      .filter(|l| !l.is_leftover_videos())
+      .flat_map(LivePhotoLinker::iter_videos)
+      .collect::<HashSet<_>>();
+
+    let mut counts = DayCounts::default();
+    for (_, media) in self
+      .media
+      .iter_data_indexed()
+      .filter(|(h, _)| !live_photo_videos.contains(h))
+    {
+      match media.get_metadata().get_date_time_original() {
+        Some((date_time, _)) => *counts.days.entry(date_time.date()).or_default() += 1,
+        None => counts.undated += 1,
+      }
+    }
+    counts
+  }
+}
+
+#[cfg(test)]
+mod test_count_by_day {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  fn day(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+  }
+
+  #[test]
+  fn counts_media_per_day() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image2.jpg": { "DateTimeOriginal": "2000-01-01T23:59:59" },
+      "image3.jpg": { "DateTimeOriginal": "2000-01-03T12:00:00" },
+      "image3.jpg.xmp": { "DateTimeOriginal": "2000-01-03T12:00:00" },
+      "undated.jpg": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert_eq!(o.count_by_day(), DayCounts {
+      days:    BTreeMap::from([(day("2000-01-01"), 2), (day("2000-01-03"), 1)]),
+      undated: 1,
+    });
+  }
+
+  #[test]
+  fn counts_live_photo_once() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID", "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.mov": {
+        "ContentIdentifier": "ID",
+        "CompressorID": "hvc1",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      },
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert_eq!(o.count_by_day(), DayCounts {
+      days:    BTreeMap::from([(day("2000-01-01"), 1)]),
+      undated: 0,
+    });
+  }
+
+  #[test]
+  fn displays_each_day_and_undated() {
+    let counts = DayCounts {
+      days:    BTreeMap::from([(day("2000-01-01"), 2), (day("2000-01-03"), 1)]),
+      undated: 4,
+    };
+
+    assert_eq!(
+      counts.to_string(),
+      "2000-01-01: 2\n2000-01-03: 1\nUndated: 4\n"
+    );
+  }
+}
@@ -0,0 +1,321 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Catalog index: Persistent metadata of files cataloged in place (e.g. on slow
+//! archival storage) or cleaned up repeatedly, so that unchanged files need not
+//! be re-read.
+
+use std::{
+  collections::HashMap,
+  fs,
+  path::{Path, PathBuf},
+};
+
+use super::{Organizer, ScanConfig, to_abs_path};
+use crate::{error::CatalogError, io, prim::Metadata};
+
+impl Organizer {
+  /// Create a new `Organizer` cataloging all multimedia files from `path`
+  /// (recursively) in place, and write their metadata to the index at `index`.
+  /// If the index already exists, metadata of files with the same
+  /// `FileModifyDate` is taken from it, rather than re-read. Files are never
+  /// moved nor removed.
+  pub fn index_only(
+    path: impl AsRef<Path>,
+    index: impl AsRef<Path>,
+    scan: &ScanConfig,
+  ) -> Result<Self, CatalogError> {
+    Self::load_catalog_incremental(path, None::<&Path>, index, scan)
+  }
+
+  /// As `load_catalog`, but with metadata of files with the same
+  /// `FileModifyDate` as in the index at `index` taken from it, rather than
+  /// re-read. The index is then rewritten with the catalog's metadata as
+  /// loaded, so files changed by later stages are re-read on the next load.
+  pub fn load_catalog_incremental(
+    path: impl AsRef<Path>,
+    trash: Option<impl AsRef<Path>>,
+    index: impl AsRef<Path>,
+    scan: &ScanConfig,
+  ) -> Result<Self, CatalogError> {
+    let index = std::path::absolute(&index).map_err(|e| {
+      CatalogError::Io(format!(
+        "{}: Invalid index path ({e}).",
+        index.as_ref().display()
+      ))
+    })?;
+
+    Self::new_with_reader(path, trash, scan, |path, exclude| {
+      let metadata = read_metadata_with_index(path, exclude, &index, scan)?;
+      write_index(&index, metadata.iter().filter_map(|m| m.as_ref().ok()))?;
+      Ok(metadata)
+    })
+  }
+}
+
+/// Reads metadata for all files in `dir_root`, skipping `dirs_exclude` and
+/// `index` itself, using `index` for unchanged files. As with
+/// `io::read_metadata_recursive`, hidden files and symlinks are handled per
+/// `scan`, and each unreadable file gets an `Err`.
+fn read_metadata_with_index(
+  dir_root: &Path,
+  dirs_exclude: Vec<PathBuf>,
+  index: &Path,
+  scan: &ScanConfig,
+) -> Result<Vec<Result<Metadata, CatalogError>>, CatalogError> {
+  let is_index = |p: &Path| to_abs_path(dir_root, p) == index;
+
+  let Some(indexed) = read_index(index)? else {
+    log::info!("{}: No index. Reading all metadata.", index.display());
+
+    let mut metadata = io::read_metadata_recursive(
+      &scan.exiftool,
+      dir_root,
+      dirs_exclude,
+      scan.include_hidden,
+      scan.skip_symlinks,
+    )?;
+    metadata.retain(|m| m.as_ref().map_or(true, |m| !is_index(&m.source_file)));
+    return Ok(metadata);
+  };
+
+  let mut indexed = indexed
+    .into_iter()
+    .map(|m| (m.source_file.clone(), m))
+    .collect::<HashMap<_, _>>();
+
+  let mut metadata = Vec::new();
+  let mut changed = Vec::new();
+
+  for (path, date) in io::read_file_modify_dates_recursive(
+    &scan.exiftool,
+    dir_root,
+    dirs_exclude,
+    scan.include_hidden,
+    scan.skip_symlinks,
+  )? {
+    if is_index(&path) {
+      continue;
+    }
+
+    match indexed.remove(&path) {
+      Some(m) if m.file_modify_date == date => metadata.push(Ok(m)),
+      _ => changed.push(path),
+    }
+  }
+
+  log::info!(
+    "Reading metadata of {} new or changed files ({} unchanged).",
+    changed.len(),
+    metadata.len()
+  );
+
+  metadata.extend(io::read_metadata_files(&scan.exiftool, dir_root, changed)?);
+
+  Ok(metadata)
+}
+
+/// Reads the index at `path`, if it exists.
+fn read_index(path: &Path) -> Result<Option<Vec<Metadata>>, CatalogError> {
+  if !path.exists() {
+    return Ok(None);
+  }
+
+  let contents = fs::read(path)
+    .map_err(|e| CatalogError::Io(format!("{}: Unable to read index ({e}).", path.display())))?;
+
+  serde_json::from_slice(&contents)
+    .map(Some)
+    .map_err(|e| CatalogError::Parse(format!("{}: Unable to parse index ({e}).", path.display())))
+}
+
+/// Writes `metadata` to the index at `path`, in the same format as `ExifTool`'s
+/// JSON output. The index is written beside `path` first, then renamed over
+/// it, so an interrupted write leaves the previous index intact.
+fn write_index<'a>(
+  path: &Path,
+  metadata: impl IntoIterator<Item = &'a Metadata>,
+) -> Result<(), CatalogError> {
+  log::info!("{}: Writing index.", path.display());
+
+  let contents = serde_json::to_vec(&metadata.into_iter().collect::<Vec<_>>())
+    .map_err(|e| CatalogError::Parse(format!("Unable to serialize index ({e}).")))?;
+
+  let mut path_tmp = path.as_os_str().to_owned();
+  path_tmp.push(".tmp");
+  let path_tmp = PathBuf::from(path_tmp);
+
+  fs::write(&path_tmp, contents).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Unable to write index ({e}).",
+      path_tmp.display()
+    ))
+  })?;
+  fs::rename(&path_tmp, path).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Unable to replace index ({e}).",
+      path.display()
+    ))
+  })
+}
+
+#[cfg(test)]
+mod test_index_only {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn round_trips_index() {
+    let d = test_dir!(
+      "image.jpg": { "Rating": "3" },
+      "image.jpg.xmp": {},
+      "dir/video.mov": {},
+    );
+    let index = d.trash().join("index.json");
+
+    let o = Organizer::index_only(d.root(), &index, &ScanConfig::default()).unwrap();
+    let written = read_index(&index).unwrap().unwrap();
+
+    assert_eq!(written.len(), 3);
+    assert_eq!(o.media.iter_data().count(), 2);
+    assert_eq!(o.sidecars.iter_data().count(), 1);
+
+    let o = Organizer::index_only(d.root(), &index, &ScanConfig::default()).unwrap();
+    let image = &o.media[o.media.find(d.get_path("image.jpg")).unwrap()];
+
+    assert_eq!(image.get_metadata().rating, Some(3.0));
+    assert_eq!(
+      serde_json::to_value(read_index(&index).unwrap().unwrap()).unwrap(),
+      serde_json::to_value(written).unwrap()
+    );
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp", "dir/video.mov"]);
+  }
+
+  #[test]
+  fn skips_unchanged_files() {
+    let d = test_dir!(
+      "image.jpg": { "Rating": "3" },
+    );
+    let index = d.trash().join("index.json");
+
+    Organizer::index_only(d.root(), &index, &ScanConfig::default()).unwrap();
+
+    // Only the index is changed, so its metadata is used on reload.
+    let mut metadata = read_index(&index).unwrap().unwrap();
+    metadata[0].rating = Some(5.0);
+    write_index(&index, &metadata).unwrap();
+
+    let o = Organizer::index_only(d.root(), &index, &ScanConfig::default()).unwrap();
+    let image = &o.media[o.media.find(d.get_path("image.jpg")).unwrap()];
+
+    assert_eq!(image.get_metadata().rating, Some(5.0));
+  }
+
+  #[test]
+  fn rereads_changed_files() {
+    let d = test_dir!(
+      "image.jpg": { "Rating": "3" },
+    );
+    let index = d.trash().join("index.json");
+
+    Organizer::index_only(d.root(), &index, &ScanConfig::default()).unwrap();
+
+    let mut metadata = read_index(&index).unwrap().unwrap();
+    metadata[0].rating = Some(5.0);
+    metadata[0].file_modify_date = "2000-01-01T00:00:00+00:00".to_string();
+    write_index(&index, &metadata).unwrap();
+
+    let o = Organizer::index_only(d.root(), &index, &ScanConfig::default()).unwrap();
+    let image = &o.media[o.media.find(d.get_path("image.jpg")).unwrap()];
+
+    assert_eq!(image.get_metadata().rating, Some(3.0));
+  }
+
+  #[test]
+  fn skips_index_within_catalog() {
+    let d = test_dir!(
+      "image.jpg": {},
+    );
+    let index = d.get_path("index.json");
+
+    Organizer::index_only(d.root(), &index, &ScanConfig::default()).unwrap();
+    Organizer::index_only(d.root(), &index, &ScanConfig::default()).unwrap();
+
+    assert_eq!(read_index(&index).unwrap().unwrap().len(), 1);
+  }
+}
+
+#[cfg(test)]
+mod test_load_catalog_incremental {
+  use super::*;
+  use crate::{prim::Sidecar, testing::*};
+
+  #[test]
+  fn reads_nothing_if_unchanged() {
+    let d = test_dir!(
+      "image.jpg": { "Rating": "3" },
+      "image.jpg.xmp": {},
+      "dir/video.mov": { "CompressorID": "avc1" },
+    );
+    let index = d.trash().join("index.json");
+
+    Organizer::load_catalog_incremental(d.root(), d.some_trash(), &index, &ScanConfig::default())
+      .unwrap();
+
+    // Mark every indexed file, so that any re-read is visible as a lost mark.
+    let mut metadata = read_index(&index).unwrap().unwrap();
+    for m in &mut metadata {
+      m.rating = Some(5.0);
+    }
+    write_index(&index, &metadata).unwrap();
+
+    let o =
+      Organizer::load_catalog_incremental(d.root(), d.some_trash(), &index, &ScanConfig::default())
+        .unwrap();
+
+    assert!(
+      o.media
+        .iter_data()
+        .all(|m| m.get_metadata().rating == Some(5.0))
+    );
+    assert!(
+      o.sidecars
+        .iter_data()
+        .all(|s| s.get_metadata().rating == Some(5.0))
+    );
+  }
+
+  #[test]
+  fn reads_new_files() {
+    let d = test_dir!(
+      "image.jpg": {},
+    );
+    let index = d.trash().join("index.json");
+
+    Organizer::load_catalog_incremental(d.root(), d.some_trash(), &index, &ScanConfig::default())
+      .unwrap();
+    fs::copy(d.get_path("image.jpg"), d.get_path("image_new.jpg")).unwrap();
+
+    let o =
+      Organizer::load_catalog_incremental(d.root(), d.some_trash(), &index, &ScanConfig::default())
+        .unwrap();
+
+    assert!(o.media.find(d.get_path("image_new.jpg")).is_some());
+    assert_eq!(read_index(&index).unwrap().unwrap().len(), 2);
+  }
+
+  #[test]
+  fn skips_trash() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "trash/removed.jpg": {},
+    );
+    let index = d.trash().join("index.json");
+
+    let o =
+      Organizer::load_catalog_incremental(d.root(), d.some_trash(), &index, &ScanConfig::default())
+        .unwrap();
+
+    assert_eq!(o.media.iter_data().count(), 1);
+    assert_eq!(read_index(&index).unwrap().unwrap().len(), 1);
+  }
+}
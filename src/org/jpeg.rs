@@ -0,0 +1,92 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! JPEG decoding for perceptual hashing: Images are decoded to grayscale at
+//! 1/8 scale, which is all hashing needs and much faster than full size. Covers
+//! camera JPEGs and previews extracted from HEIC and raw files.
+
+use std::fmt::Display;
+
+use jpeg_decoder::{Decoder, PixelFormat};
+
+use crate::error::CatalogError;
+
+/// Grayscale image, with pixels in rows from the top left.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrayImage {
+  pub width:  usize,
+  pub height: usize,
+  pub pixels: Vec<f32>,
+}
+
+/// Decodes the JPEG `data` to grayscale, at 1/8 scale (rounded up).
+pub fn decode_gray(data: &[u8]) -> Result<GrayImage, CatalogError> {
+  let mut decoder = Decoder::new(data);
+  let (width, height) = decoder.scale(1, 1).map_err(invalid)?;
+  let bytes = decoder.decode().map_err(invalid)?;
+  let format = decoder
+    .info()
+    .ok_or_else(|| invalid("Missing image data"))?
+    .pixel_format;
+
+  let pixels = match format {
+    PixelFormat::L8 => bytes.into_iter().map(f32::from).collect(),
+    PixelFormat::L16 => bytes
+      .chunks_exact(2)
+      .map(|p| f32::from(u16::from_ne_bytes([p[0], p[1]])) / 257.0)
+      .collect(),
+    PixelFormat::RGB24 => bytes
+      .chunks_exact(3)
+      .map(|p| 0.299 * f32::from(p[0]) + 0.587 * f32::from(p[1]) + 0.114 * f32::from(p[2]))
+      .collect(),
+    // Inks subtract from white, with black scaling the rest.
+    PixelFormat::CMYK32 => bytes
+      .chunks_exact(4)
+      .map(|p| {
+        let ink = |i: usize| 1.0 - f32::from(p[i]) / 255.0;
+        let luma = 0.299 * ink(0) + 0.587 * ink(1) + 0.114 * ink(2);
+        255.0 * luma * ink(3)
+      })
+      .collect::<Vec<_>>(),
+  };
+
+  let (width, height) = (usize::from(width), usize::from(height));
+  if pixels.len() != width * height {
+    return Err(invalid("Unexpected image size"));
+  }
+
+  Ok(GrayImage {
+    width,
+    height,
+    pixels,
+  })
+}
+
+fn invalid(reason: impl Display) -> CatalogError {
+  CatalogError::InvalidFile(format!("Unable to decode JPEG ({reason})."))
+}
+
+#[cfg(test)]
+mod test_decode_gray {
+  use std::fs;
+
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn decodes_progressive() {
+    let data = fs::read("assets/test.jpg").unwrap();
+    let image = decode_gray(&data).unwrap();
+
+    assert_eq!((image.width, image.height), (2, 2));
+    assert_eq!(image.pixels.len(), 4);
+  }
+
+  #[test]
+  fn rejects_other_files() {
+    assert_err!(decode_gray(b"not a jpeg"), "Unable to decode JPEG");
+    assert_err!(
+      decode_gray(&[0xff, 0xd8, 0xff, 0xd9]),
+      "Unable to decode JPEG"
+    );
+  }
+}
@@ -0,0 +1,100 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Read-only listing of Live Photos, with their components by path.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use super::{Organizer, to_abs_path};
+use crate::prim::{Handle, Media};
+
+/// Components of a Live Photo, as linked by their `ContentIdentifier`.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct LivePhotoGroup {
+  pub id:         String,
+  /// Paths of all images, sorted.
+  pub images:     Vec<PathBuf>,
+  /// Paths of all videos, sorted.
+  pub videos:     Vec<PathBuf>,
+  /// Image kept when removing duplicates, if any images.
+  pub best_image: Option<PathBuf>,
+  /// Video kept when removing duplicates, if any videos.
+  pub best_video: Option<PathBuf>,
+}
+
+impl Organizer {
+  /// Lists all Live Photos, with their images and videos and the best of each
+  /// (as kept by `remove_live_photo_duplicates`), sorted by ID. Groups may be
+  /// missing images or videos (e.g. leftover videos).
+  #[must_use]
+  pub fn list_live_photo_groups(&self) -> Vec<LivePhotoGroup> {
+    let path = |h: Handle<Media>| to_abs_path(&self.source, &self.media[h]);
+    let sorted = |mut paths: Vec<PathBuf>| {
+      paths.sort();
+      paths
+    };
+
+    let mut groups = self
+      .live_photo_map
+      .iter()
+      .map(|(id, link)| {
+        let images = sorted(link.iter_images().map(path).collect());
+        let videos = sorted(link.iter_videos().map(path).collect());
+        LivePhotoGroup {
+          id: id.0.clone(),
+          best_image: (!images.is_empty()).then(|| path(link.get_image_best())),
+          best_video: (!videos.is_empty()).then(|| path(link.get_video_best())),
+          images,
+          videos,
+        }
+      })
+      .collect::<Vec<_>>();
+    groups.sort_by(|a, b| a.id.cmp(&b.id));
+    groups
+  }
+}
+
+#[cfg(test)]
+mod test_list_live_photo_groups {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn lists_components_and_best_of_each() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID" },
+      "image.jpg": { "ContentIdentifier": "ID" },
+      "video_avc.mov": { "ContentIdentifier": "ID", "CompressorID": "avc1" },
+      "video_hevc.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert_eq!(o.list_live_photo_groups(), [LivePhotoGroup {
+      id:         "ID".to_string(),
+      images:     vec![d.get_path("image.heic"), d.get_path("image.jpg")],
+      videos:     vec![d.get_path("video_avc.mov"), d.get_path("video_hevc.mov")],
+      best_image: Some(d.get_path("image.heic")),
+      best_video: Some(d.get_path("video_hevc.mov")),
+    }]);
+  }
+
+  #[test]
+  fn lists_leftover_videos_without_best_image() {
+    let d = test_dir!(
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+      "image.jpg": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert_eq!(o.list_live_photo_groups(), [LivePhotoGroup {
+      id:         "ID".to_string(),
+      images:     Vec::new(),
+      videos:     vec![d.get_path("video.mov")],
+      best_image: None,
+      best_video: Some(d.get_path("video.mov")),
+    }]);
+  }
+}
@@ -0,0 +1,250 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Merging catalogs: Folding the files of another catalog into one already
+//! loaded, so that both are organized together.
+
+use std::{
+  collections::HashMap,
+  path::{Component, Path, PathBuf},
+};
+
+use super::{Organizer, ScanConfig, Warning, get_root, read_catalog};
+use crate::{error::CatalogError, io, prim::FileCategory};
+
+impl Organizer {
+  /// Adds the files of the catalog at `path` (scanned as configured by `scan`)
+  /// to this one, then relinks sidecars, Live Photos and bursts across both.
+  /// Live Photo components duplicated across the catalogs are then removed by
+  /// `remove_live_photo_duplicates`, as within one. Other media at the same
+  /// path within its catalog and with the same capture time as media already
+  /// loaded are warned about, as likely copies.
+  ///
+  /// Merged files are kept by absolute path, and are trashed relative to their
+  /// own catalog. Run before any other stage.
+  pub fn merge_catalog(
+    &mut self,
+    path: impl AsRef<Path>,
+    scan: &ScanConfig,
+  ) -> Result<(), CatalogError> {
+    let path = path.as_ref();
+
+    if path.is_relative() {
+      return Err(CatalogError::PathNotAbsolute(
+        path.to_path_buf(),
+        "Merged catalog path",
+      ));
+    }
+
+    if !path.exists() {
+      return Err(CatalogError::PathNotFound(
+        path.to_path_buf(),
+        "Merged catalog path",
+      ));
+    }
+
+    if let Some(other) = [&self.source]
+      .into_iter()
+      .chain(&self.merged)
+      .find(|o| path.starts_with(o) || o.starts_with(path))
+    {
+      return Err(CatalogError::Io(format!(
+        "{}: Cannot merge catalog overlapping {}.",
+        path.display(),
+        other.display()
+      )));
+    }
+
+    log::info!("{}: Merging catalog.", path.display());
+
+    // Media already loaded, by path within its catalog, for finding copies.
+    let existing = self
+      .media
+      .iter_data()
+      .filter_map(|m| {
+        let file = m.get_metadata().source_file.as_path();
+        let root = get_root(&self.source, &self.merged, file);
+        let (date_time, _) = m.get_metadata().get_date_time_original()?;
+        Some((
+          (to_catalog_path(root, file), date_time),
+          self.source.join(file),
+        ))
+      })
+      .collect::<HashMap<_, _>>();
+
+    let mut metadata = read_catalog(path, self.trash.as_deref(), scan, |p, e| {
+      io::read_metadata_recursive(
+        &scan.exiftool,
+        p,
+        e,
+        scan.include_hidden,
+        scan.skip_symlinks,
+      )
+    })?
+    .collect::<Vec<_>>();
+
+    // Paths are made absolute, as they are not within the scanned directory.
+    for m in metadata.iter_mut().flatten() {
+      m.source_file = path.join(to_catalog_path(path, &m.source_file));
+
+      if m.get_file_category() == FileCategory::Media
+        && let Some((date_time, _)) = m.get_date_time_original()
+        && let Some(copy) = existing.get(&(to_catalog_path(path, &m.source_file), date_time))
+      {
+        self.warnings.push(Warning::DuplicateInMergedCatalog(
+          m.source_file.clone(),
+          copy.clone(),
+        ));
+      }
+    }
+
+    self.merged.push(path.to_path_buf());
+    self
+      .excluded
+      .extend(scan.exclude.iter().map(|e| path.join(e)));
+
+    self.load_metadata(metadata)?;
+    self.link_sidecars();
+    self.link_live_photos();
+    self.link_bursts();
+
+    Ok(())
+  }
+}
+
+/// Gets the path of `file` within the catalog at `root`, given either absolute
+/// or relative to `root` (e.g. `./dir/image.jpg` as `dir/image.jpg`).
+fn to_catalog_path(root: &Path, file: &Path) -> PathBuf {
+  file
+    .strip_prefix(root)
+    .unwrap_or(file)
+    .components()
+    .filter(|c| matches!(c, Component::Normal(_)))
+    .collect()
+}
+
+#[cfg(test)]
+mod test_merge_catalog {
+  use super::*;
+  use crate::{prim::LivePhotoLinker, testing::*};
+
+  #[test]
+  fn errors_if_path_invalid() {
+    let d = test_dir!(
+      "a/image.jpg": {},
+    );
+
+    let mut o =
+      Organizer::load_catalog(d.get_path("a"), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert!(matches!(
+      o.merge_catalog("relative/path", &ScanConfig::default()),
+      Err(CatalogError::PathNotAbsolute(_, "Merged catalog path"))
+    ));
+    assert!(matches!(
+      o.merge_catalog(d.get_path("b"), &ScanConfig::default()),
+      Err(CatalogError::PathNotFound(_, "Merged catalog path"))
+    ));
+  }
+
+  #[test]
+  fn errors_if_overlapping() {
+    let d = test_dir!(
+      "a/image.jpg": {},
+      "a/sub/image.jpg": {},
+    );
+
+    let mut o =
+      Organizer::load_catalog(d.get_path("a"), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert_err!(
+      o.merge_catalog(d.get_path("a/sub"), &ScanConfig::default()),
+      "Cannot merge catalog overlapping"
+    );
+    assert_err!(
+      o.merge_catalog(d.root(), &ScanConfig::default()),
+      "Cannot merge catalog overlapping"
+    );
+  }
+
+  #[test]
+  fn links_sidecars_within_merged_catalog() {
+    let d = test_dir!(
+      "a/image.jpg": {},
+      "b/image.jpg": {},
+      "b/image.jpg.xmp": {},
+      "b/image_01.jpg.xmp": {},
+    );
+
+    let mut o =
+      Organizer::load_catalog(d.get_path("a"), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.merge_catalog(d.get_path("b"), &ScanConfig::default())
+      .unwrap();
+
+    let a = o.media.find(d.get_path("a/image.jpg")).unwrap();
+    let b = o.media.find(d.get_path("b/image.jpg")).unwrap();
+
+    assert!(o.media[a].get_sidecar().is_none());
+    assert_eq!(
+      o.media[b].get_sidecar(),
+      o.sidecars.find(d.get_path("b/image.jpg.xmp"))
+    );
+    assert_eq!(o.media[b].iter_dupes().count(), 1);
+  }
+
+  #[test]
+  fn links_live_photos_across_catalogs() {
+    let d = test_dir!(
+      "a/image.heic": { "ContentIdentifier": "ID" },
+      "b/video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+    );
+
+    let mut o =
+      Organizer::load_catalog(d.get_path("a"), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.merge_catalog(d.get_path("b"), &ScanConfig::default())
+      .unwrap();
+    o.remove_live_photo_leftovers().unwrap();
+
+    assert_eq!(o.live_photo_map.len(), 1);
+    assert!(o.live_photo_map.values().all(LivePhotoLinker::is_pair));
+    assert_dir!(d, ["a/image.heic", "b/video.mov"]);
+  }
+
+  #[test]
+  fn removes_live_photo_duplicates_across_catalogs() {
+    let d = test_dir!(
+      "a/image.heic": { "ContentIdentifier": "ID" },
+      "a/video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+      "b/image.jpg": { "ContentIdentifier": "ID" },
+    );
+
+    let mut o =
+      Organizer::load_catalog(d.get_path("a"), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.merge_catalog(d.get_path("b"), &ScanConfig::default())
+      .unwrap();
+    o.remove_live_photo_duplicates().unwrap();
+
+    assert_dir!(d, ["a/image.heic", "a/video.mov"]);
+    assert_trash!(d, ["image.jpg"]);
+  }
+
+  #[test]
+  fn warns_about_copies_across_catalogs() {
+    let d = test_dir!(
+      "a/dir/image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "a/other.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "b/dir/image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "b/other.jpg": { "DateTimeOriginal": "2010-01-01T00:00:00" },
+    );
+
+    let mut o =
+      Organizer::load_catalog(d.get_path("a"), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.merge_catalog(d.get_path("b"), &ScanConfig::default())
+      .unwrap();
+
+    assert_eq!(o.media.iter_data().count(), 4);
+    assert_eq!(o.take_warnings(), [Warning::DuplicateInMergedCatalog(
+      d.get_path("b/dir/image.jpg"),
+      d.get_path("a/dir/image.jpg"),
+    )]);
+  }
+}
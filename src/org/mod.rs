@@ -3,24 +3,63 @@
 //! Core organizer module for managing a catalog of media files and their
 //! sidecars.
 
+mod archive;
+mod checksum;
+mod doctor;
+mod export;
+mod geocode;
+mod hash_index;
+mod health;
+mod histogram;
+mod index;
+mod jpeg;
+mod live_photo_groups;
+mod merge;
+mod perceptual_hash;
+mod report;
 mod stage_1_cleanup;
 mod stage_2_sidecars;
 mod stage_3_metadata;
 mod stage_4_synchronization;
 mod stage_5_validation;
 mod stage_6_organization;
+mod warnings;
 
 use std::{
   collections::{HashMap, HashSet},
-  path::{Path, PathBuf},
+  io::BufRead,
+  path::{Component, Path, PathBuf},
+  sync::{
+    LazyLock,
+    atomic::{AtomicUsize, Ordering},
+  },
+  thread,
 };
 
+use chrono::TimeDelta;
+pub use export::IndexFormat;
+pub use geocode::Geocoder;
+use hash_index::HashIndex;
+pub use histogram::DayCounts;
+pub use live_photo_groups::LivePhotoGroup;
+pub use report::FinalReport;
+pub use stage_1_cleanup::{FormatToKeep, PromptConfirm};
+pub use stage_3_metadata::FileNameDatePattern;
 use stage_3_metadata::MetadataUpdateConfig;
 use stage_5_validation::ValidationConfig;
+use stage_6_organization::FreeSpaceCheck;
+pub use stage_6_organization::{ExtensionMap, MediaDirs, MediaKind, MissingDatePolicy};
+use tzf_rs::{Finder, r#gen::tzf::v1::Timezones};
+use warnings::Warnings;
+pub use warnings::{ErrorPolicy, Warning};
 
+pub use crate::prim::CopyrightFormat;
 use crate::{
+  error::CatalogError,
   io,
   prim::{
+    Adjustment,
+    BurstID,
     FileCategory,
     FileMap,
     Handle,
@@ -35,46 +74,175 @@ use crate::{
   },
 };
 
+/// Called with the 1-based index of a group of files (e.g. a Live Photo with
+/// its sidecars), the total number of groups, and the path of the group's main
+/// media file.
+pub type ProgressCallback = Box<dyn Fn(usize, usize, &Path)>;
+
+/// Options for which files are scanned when loading an `Organizer`.
+#[derive(Clone, Default)]
+pub struct ScanConfig {
+  /// Directories to skip, either absolute or relative to the scanned
+  /// directory. These must be within the scanned directory.
+  pub exclude:        Vec<PathBuf>,
+  /// Levels of subdirectories to descend into, if limited. At 0, only files
+  /// directly within the scanned directory are loaded.
+  pub max_depth:      Option<usize>,
+  /// What to do on errors with individual files, both when scanning and in
+  /// later stages.
+  pub on_error:       ErrorPolicy,
+  /// Whether to load hidden files (with names starting with `.`, e.g.
+  /// macOS resource forks like `._image.jpg`), which are otherwise skipped as
+  /// junk.
+  pub include_hidden: bool,
+  /// Whether to skip symlinks, rather than following those to directories
+  /// (e.g. to external archives). Symlinks to a directory containing them are
+  /// skipped regardless, to avoid looping forever.
+  pub skip_symlinks:  bool,
+  /// How to run `ExifTool`, both when scanning and, as kept by the
+  /// `Organizer`, in later stages.
+  pub exiftool:       io::ExifToolConfig,
+}
+
+impl ScanConfig {
+  /// Returns a copy for scanning `dir`, without absolute exclusions outside it.
+  fn within(&self, dir: impl AsRef<Path>) -> ScanConfig {
+    ScanConfig {
+      exclude: self
+        .exclude
+        .iter()
+        .filter(|e| e.is_relative() || e.starts_with(&dir))
+        .cloned()
+        .collect(),
+      ..self.clone()
+    }
+  }
+}
+
 /// Main type for organizing a multimedia catalog.
 ///
-/// This can both clean up an existing catalog, or import new files into one.
-/// Files are processed in a multi-stage process, which includes:
+/// This can both clean up an existing catalog (`load_catalog`), or import new
+/// files into one (`import`). Files are processed in a multi-stage process,
+/// with each stage run by the methods listed, in order:
 ///
 /// 1. Automatic removal of some files to a trash directory (or skip during
-///    import).
-/// 2. Creation of sidecars for any files without.
-/// 3. Automatic updates to some basic metadata to save manual effort.
-/// 4. Synchronization of metadata across files, including Live Photos.
+///    import): `remove_live_photo_leftovers`, `remove_live_photo_duplicates`
+///    and `remove_sidecar_leftovers`, optionally `collapse_bursts`,
+///    `deduplicate_sidecars`, `dedupe_across_formats`, `remove_identical_media`
+///    and `dedupe_by_perceptual_hash`, then `confirm_removals` if enabled.
+/// 2. Creation of sidecars for any files without: `create_missing_sidecars`.
+/// 3. Automatic updates to some basic metadata to save manual effort:
+///    `apply_metadata_updates`, optionally after `geotag_from_gpx` and
+///    `infer_dates_from_filename`.
+/// 4. Synchronization of metadata across files, including Live Photos:
+///    `sync_live_photo_metadata` and `sync_dupe_metadata`, optionally
+///    `sync_media_metadata`.
 /// 5. Validation of metadata, to prevent adding files not meeting standards
-///    until fixed.
-/// 6. Automatic moving and renaming by timestamp.
+///    until fixed: `validate`, optionally followed by `quarantine_invalid`.
+/// 6. Automatic moving and renaming by timestamp: `move_and_rename_files`
+///    (previewed by `rename_preview`), which takes all files out of the
+///    `Organizer` and returns its remaining warnings.
+///
+/// Finally, `into_report` consumes the `Organizer`, summarizing what each
+/// stage did (e.g. files removed, and where files were moved).
+///
+/// Stages up to 5 return `Result<(), CatalogError>`, and collect problems with
+/// individual files for `take_warnings`. Each stage is configured by its
+/// `enable_*` and `set_*` methods, called before it runs.
 ///
 /// Note that the `Organizer` will assume that any file associations (e.g.
 /// sidecars or Live Photos) are represented within the input directory. This
 /// means, for example, that a Live Photo video imported separately from its
 /// image will not be linked correctly.
 #[derive(Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Organizer {
   source: PathBuf,
   trash:  Option<PathBuf>,
 
-  media:    FileMap<Media>,
-  sidecars: FileMap<SidecarInitial>,
-  dupes:    FileMap<SidecarDupe>,
+  media:       FileMap<Media>,
+  sidecars:    FileMap<SidecarInitial>,
+  dupes:       FileMap<SidecarDupe>,
+  adjustments: FileMap<Adjustment>,
 
   live_photo_map: HashMap<LivePhotoID, LivePhotoLinker>,
+  burst_map:      HashMap<BurstID, Vec<Handle<Media>>>,
+
+  /// How to run `ExifTool`, as scanned with.
+  exiftool: io::ExifToolConfig,
+
+  sidecar_template:   io::SidecarTemplate,
+  /// File types (by `ExifTool`'s `FileType`, e.g. `PNG`) whose metadata is only
+  /// ever written to sidecars, never into the media.
+  sidecar_only_types: Vec<String>,
 
   metadata_updates: MetadataUpdateConfig,
+  /// Format of `Copyright`, both written and validated, if set.
+  copyright_format: Option<CopyrightFormat>,
 
   validation:  ValidationConfig,
   valid_media: HashSet<Handle<Media>>,
+
+  keep_leftover_sidecars:      bool,
+  relink_leftover_live_photos: bool,
+  pending_removals:            Option<Vec<PathBuf>>,
+
+  merged:   Vec<PathBuf>,
+  /// Directories skipped when scanning the source and merged catalogs, which
+  /// are left untouched.
+  excluded: Vec<PathBuf>,
+
+  file_name_format:      io::FileNameFormat,
+  extension_map:         ExtensionMap,
+  preserve_modify_dates: bool,
+  remove_empty_dirs:     bool,
+  trash_identical:       bool,
+  /// Whether to move files in order of path, rather than as loaded.
+  ordered_moves:         bool,
+  /// Separate directories to move photos and videos into, if any.
+  media_dirs:            Option<MediaDirs>,
+  /// What to do with media without a date to name it by.
+  missing_date:          MissingDatePolicy,
+  /// Whether to rename files within their current directories, not moving them.
+  rename_only:           bool,
+  free_space_check:      Option<FreeSpaceCheck>,
+
+  /// Tags to copy from Live Photo images to videos, if not all.
+  live_photo_sync_tags:      Option<Vec<String>>,
+  /// Furthest apart Live Photo images and videos may be captured without a
+  /// warning, if checked.
+  live_photo_time_tolerance: Option<TimeDelta>,
+
+  /// Metadata copies (or files hashed) to run at once (at least one).
+  workers: usize,
+
+  /// Media contents by hash, shared by passes comparing contents.
+  hash_index: HashIndex,
+
+  /// Called as each group of files is moved, if set.
+  progress: Option<ProgressCallback>,
+
+  warnings: Warnings,
+  /// What each stage has done so far, for `into_report`.
+  report:   FinalReport,
 }
 
 impl Organizer {
-  /// Create a new `Organizer` importing all multimedia files from path
-  /// (recursively).
-  pub fn import(path: impl AsRef<Path>) -> Result<Self, String> {
-    Self::new(path, None::<&Path>)
+  /// Create a new `Organizer` importing all multimedia files from `paths`
+  /// (recursively), e.g. several memory cards at once. Sidecars, Live Photos
+  /// and bursts are linked across all of them, as by `merge_catalog`. Absolute
+  /// paths excluded by `scan` apply to whichever of `paths` they are within.
+  pub fn import(paths: &[impl AsRef<Path>], scan: &ScanConfig) -> Result<Self, CatalogError> {
+    let Some((first, rest)) = paths.split_first() else {
+      return Err(CatalogError::Io("No paths to import.".to_string()));
+    };
+
+    let mut organizer = Self::new(first, None::<&Path>, &scan.within(first))?;
+    for path in rest {
+      organizer.merge_catalog(path, &scan.within(path))?;
+    }
+
+    Ok(organizer)
   }
 
   /// Create a new `Organizer` cleaning up an existing catalog at `path`,
@@ -82,37 +250,160 @@ impl Organizer {
   pub fn load_catalog(
     path: impl AsRef<Path>,
     trash: Option<impl AsRef<Path>>,
-  ) -> Result<Self, String> {
-    Self::new(path, trash)
+    scan: &ScanConfig,
+  ) -> Result<Self, CatalogError> {
+    Self::new(path, trash, scan)
+  }
+
+  /// Create a new `Organizer` as `load_catalog`, but with only the files listed
+  /// in `reader` (one per line, e.g. from `find`), rather than all files under
+  /// `path`. Files are given absolute or relative to `path`, and must be within
+  /// it. Files in trash or excluded directories are still skipped.
+  pub fn from_file_list(
+    path: impl AsRef<Path>,
+    trash: Option<impl AsRef<Path>>,
+    reader: impl BufRead,
+    scan: &ScanConfig,
+  ) -> Result<Self, CatalogError> {
+    let files = reader
+      .lines()
+      .filter(|l| l.as_ref().is_ok_and(|l| !l.trim().is_empty()))
+      .map(|l| l.map(PathBuf::from))
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(|e| CatalogError::Io(format!("Failed to read file list ({e}).")))?;
+
+    Self::new_with_reader(path, trash, scan, |root, exclude| {
+      let mut listed = Vec::with_capacity(files.len());
+      for file in files {
+        let file = to_abs_path(root, file);
+        let Some(file_relative) = file
+          .strip_prefix(root)
+          .ok()
+          .filter(|f| !f.components().any(|c| c == Component::ParentDir))
+        else {
+          return Err(CatalogError::Io(format!(
+            "{}: Listed file is not within catalog {}.",
+            file.display(),
+            root.display()
+          )));
+        };
+        if !exclude.iter().any(|e| file.starts_with(e))
+          && (scan.include_hidden || !is_hidden(&file))
+        {
+          // Relative to the catalog as `ExifTool` reports for a recursive scan.
+          listed.push(Path::new(".").join(file_relative));
+        }
+      }
+
+      io::read_metadata_files(&scan.exiftool, root, listed)
+    })
+  }
+
+  /// Returns whether any media files were loaded (e.g. not if the catalog is
+  /// empty, or all excluded).
+  #[must_use]
+  pub fn has_media(&self) -> bool {
+    self.media.iter_data().next().is_some()
+  }
+
+  /// Checks that every handle held by media, Live Photos and bursts refers to a
+  /// file not yet taken, and that none is held twice.
+  ///
+  /// # Panics
+  ///
+  /// If any handle is taken or held twice.
+  pub fn assert_consistent(&self) {
+    fn check<T>(map: &FileMap<T>, handles: impl Iterator<Item = Handle<T>>, kind: &str) {
+      map.count_entries();
+
+      let mut seen = HashSet::new();
+      for handle in handles {
+        assert!(map.get(handle).is_some(), "{kind} {handle} is taken.");
+        assert!(seen.insert(handle), "{kind} {handle} is held twice.");
+      }
+    }
+
+    check(
+      &self.sidecars,
+      self.media.iter_data().filter_map(Media::get_sidecar),
+      "Sidecar",
+    );
+    check(
+      &self.dupes,
+      self.media.iter_data().flat_map(Media::iter_dupes),
+      "Duplicate sidecar",
+    );
+    check(
+      &self.adjustments,
+      self.media.iter_data().filter_map(Media::get_adjustment),
+      "Adjustment sidecar",
+    );
+    check(
+      &self.media,
+      self
+        .live_photo_map
+        .values()
+        .flat_map(|l| l.iter_images().chain(l.iter_videos())),
+      "Live Photo media",
+    );
+    check(
+      &self.media,
+      self.burst_map.values().flatten().copied(),
+      "Burst media",
+    );
+  }
+
+  /// Create a new `Organizer`. Metadata is loaded as `ExifTool` reads it,
+  /// rather than once all is read, to bound memory use on large catalogs.
+  fn new(
+    path: impl AsRef<Path>,
+    trash: Option<impl AsRef<Path>>,
+    scan: &ScanConfig,
+  ) -> Result<Self, CatalogError> {
+    Self::new_with_reader(path, trash, scan, |path, exclude| {
+      io::read_metadata_recursive_stream(
+        &scan.exiftool,
+        path,
+        exclude,
+        scan.include_hidden,
+        scan.skip_symlinks,
+      )
+    })
   }
 
-  /// Create a new `Organizer`.
-  fn new(path: impl AsRef<Path>, trash: Option<impl AsRef<Path>>) -> Result<Self, String> {
+  /// Create a new `Organizer`, with metadata for the catalog (minus excluded
+  /// directories) provided by `read_metadata`.
+  fn new_with_reader<I: IntoIterator<Item = Result<Metadata, CatalogError>>>(
+    path: impl AsRef<Path>,
+    trash: Option<impl AsRef<Path>>,
+    scan: &ScanConfig,
+    read_metadata: impl FnOnce(&Path, Vec<PathBuf>) -> Result<I, CatalogError>,
+  ) -> Result<Self, CatalogError> {
     if path.as_ref().is_relative() {
-      return Err(format!(
-        "{}: Catalog path is not absolute.",
-        path.as_ref().display()
+      return Err(CatalogError::PathNotAbsolute(
+        path.as_ref().to_path_buf(),
+        "Catalog path",
       ));
     }
 
     if !path.as_ref().exists() {
-      return Err(format!(
-        "{}: Catalog path does not exist.",
-        path.as_ref().display()
+      return Err(CatalogError::PathNotFound(
+        path.as_ref().to_path_buf(),
+        "Catalog path",
       ));
     }
 
     if let Some(trash) = &trash {
       if trash.as_ref().is_relative() {
-        return Err(format!(
-          "{}: Trash path is not absolute.",
-          trash.as_ref().display()
+        return Err(CatalogError::PathNotAbsolute(
+          trash.as_ref().to_path_buf(),
+          "Trash path",
         ));
       }
       if !trash.as_ref().exists() {
-        return Err(format!(
-          "{}: Trash path does not exist.",
-          trash.as_ref().display()
+        return Err(CatalogError::PathNotFound(
+          trash.as_ref().to_path_buf(),
+          "Trash path",
         ));
       }
     }
@@ -122,20 +413,36 @@ impl Organizer {
     let mut organizer = Self {
       source: path.as_ref().to_path_buf(),
       trash: trash.map(|p| p.as_ref().to_path_buf()),
+      exiftool: scan.exiftool.clone(),
       ..Default::default()
     };
+    organizer.warnings.set_policy(scan.on_error);
+    organizer.excluded = scan
+      .exclude
+      .iter()
+      .map(|e| organizer.source.join(e))
+      .collect();
 
-    let metadata = io::read_metadata_recursive(path, organizer.trash.as_ref())?;
+    let metadata = read_catalog(
+      &organizer.source,
+      organizer.trash.as_deref(),
+      scan,
+      read_metadata,
+    )?;
 
     organizer.load_metadata(metadata)?;
     organizer.link_sidecars();
     organizer.link_live_photos();
+    organizer.link_bursts();
 
     Ok(organizer)
   }
 
   /// Loads in all metadata (generally for `ExifTool`'s scan).
-  fn load_metadata(&mut self, metadata: impl IntoIterator<Item = Metadata>) -> Result<(), String> {
+  fn load_metadata(
+    &mut self,
+    metadata: impl IntoIterator<Item = Result<Metadata, CatalogError>>,
+  ) -> Result<(), CatalogError> {
     log::info!("Parsing metadata.");
 
     load_metadata(
@@ -143,7 +450,9 @@ impl Organizer {
       &mut self.media,
       &mut self.sidecars,
       &mut self.dupes,
+      &mut self.adjustments,
       metadata,
+      &mut self.warnings,
     )
   }
 
@@ -165,37 +474,140 @@ impl Organizer {
       &mut self.media,
       Media::add_dupe,
     );
+
+    // Media may have been re-encoded (e.g. JPEG to HEIC) since its sidecars
+    // were made, so relink those left over by stem instead.
+    relink_sidecars_by_stem(
+      &self.source,
+      &mut self.sidecars,
+      &mut self.media,
+      Media::set_sidecar,
+      Media::is_missing_sidecar,
+      &mut self.warnings,
+    );
+    relink_sidecars_by_stem(
+      &self.source,
+      &mut self.dupes,
+      &mut self.media,
+      Media::add_dupe,
+      |_| true,
+      &mut self.warnings,
+    );
+
+    link_adjustments(&self.source, &mut self.adjustments, &mut self.media);
   }
 
+  /// Links Live Photo components, replacing any existing links.
   fn link_live_photos(&mut self) {
     log::info!("Linking Live Photos images to videos.");
 
+    self.live_photo_map.clear();
     link_live_photos(&mut self.media, &mut self.live_photo_map);
   }
+
+  /// Groups burst frames, replacing any existing groups.
+  fn link_bursts(&mut self) {
+    log::info!("Linking burst frames.");
+
+    self.burst_map.clear();
+    link_bursts(&self.media, &mut self.burst_map);
+  }
+}
+
+/// Loads the time zone boundaries used to find the time zone of GPS
+/// coordinates.
+fn load_time_zone_finder() -> Finder {
+  Finder::from_pb(
+    Timezones::try_from(
+      include_bytes!("../../third_party/tzf-rel/combined-with-oceans.bin").to_vec(),
+    )
+    .unwrap(),
+  )
 }
 
 fn to_abs_path(dir: impl AsRef<Path>, path_rel: impl AsRef<Path>) -> PathBuf {
   dir.as_ref().join(path_rel).clone()
 }
 
-/// Converts metadata into collections of media files and sidecars.
+/// Gets the root `file` was scanned from: Whichever of the `merged` catalogs it
+/// is within, else `source`.
+fn get_root<'a>(source: &'a Path, merged: &'a [PathBuf], file: &Path) -> &'a Path {
+  merged
+    .iter()
+    .find(|m| file.starts_with(m))
+    .map_or(source, PathBuf::as_path)
+}
+
+/// Reads metadata for the catalog at `root` with `read_metadata`, skipping
+/// `trash` (if within) and as configured by `scan`.
+fn read_catalog<F, I>(
+  root: &Path,
+  trash: Option<&Path>,
+  scan: &ScanConfig,
+  read_metadata: F,
+) -> Result<impl Iterator<Item = Result<Metadata, CatalogError>> + use<F, I>, CatalogError>
+where
+  F: FnOnce(&Path, Vec<PathBuf>) -> Result<I, CatalogError>,
+  I: IntoIterator<Item = Result<Metadata, CatalogError>>,
+{
+  // Trash may be outside the catalog (e.g. on another drive).
+  let exclude = trash
+    .filter(|t| t.starts_with(root))
+    .map(Path::to_path_buf)
+    .into_iter()
+    .chain(scan.exclude.iter().map(|e| root.join(e)))
+    .collect::<Vec<_>>();
+
+  let metadata = read_metadata(root, exclude)?;
+
+  // `ExifTool` can't limit recursion, so files too deep are dropped after.
+  let root = root.to_path_buf();
+  let max_depth = scan.max_depth;
+  Ok(metadata.into_iter().filter(move |m| {
+    max_depth.is_none_or(|max_depth| {
+      !m.as_ref()
+        .is_ok_and(|m| get_depth(&root, &m.source_file) > max_depth)
+    })
+  }))
+}
+
+/// Converts metadata into collections of media files and sidecars. Files which
+/// could not be read or loaded are errors, subject to the policy of `warnings`.
 fn load_metadata(
   dir_root: impl AsRef<Path>,
   media: &mut FileMap<Media>,
   sidecars: &mut FileMap<SidecarInitial>,
   dupes: &mut FileMap<SidecarDupe>,
-  metadata: impl IntoIterator<Item = Metadata>,
-) -> Result<(), String> {
+  adjustments: &mut FileMap<Adjustment>,
+  metadata: impl IntoIterator<Item = Result<Metadata, CatalogError>>,
+  warnings: &mut Warnings,
+) -> Result<(), CatalogError> {
   for m in metadata {
+    let Some(m) = warnings.check(m)? else {
+      continue;
+    };
+    let path = to_abs_path(&dir_root, &m);
+
     match m.get_file_category() {
       FileCategory::Media => {
-        media.insert(to_abs_path(&dir_root, &m), Media::new(m)?);
+        if let Some(m) = warnings.check(Media::new(m))? {
+          media.insert(path, m);
+        }
       }
       FileCategory::SidecarInitial => {
-        sidecars.insert(to_abs_path(&dir_root, &m), SidecarInitial::new(m)?);
+        if let Some(m) = warnings.check(SidecarInitial::new(m))? {
+          sidecars.insert(path, m);
+        }
       }
       FileCategory::SidecarDupe => {
-        dupes.insert(to_abs_path(&dir_root, &m), SidecarDupe::new(m)?);
+        if let Some(m) = warnings.check(SidecarDupe::new(m))? {
+          dupes.insert(path, m);
+        }
+      }
+      FileCategory::Adjustment => {
+        if let Some(m) = warnings.check(Adjustment::new(m))? {
+          adjustments.insert(path, m);
+        }
       }
     }
   }
@@ -204,7 +616,7 @@ fn load_metadata(
 }
 
 /// Links sidecars to their associated media files. Generic over initial and
-/// duplicate sidecars.
+/// duplicate sidecars. Sidecars already linked are skipped.
 fn link_sidecars_by_type<S: Sidecar>(
   dir_root: impl AsRef<Path>,
   sidecar_map: &mut FileMap<S>,
@@ -212,6 +624,10 @@ fn link_sidecars_by_type<S: Sidecar>(
   add_sidecar: fn(&mut Media, Handle<S>),
 ) {
   for (handle_sidecar, sidecar) in sidecar_map.iter_data_mut_indexed() {
+    if !sidecar.is_leftover() {
+      continue;
+    }
+
     if let Some(handle_media) = media_map.find(to_abs_path(&dir_root, sidecar.get_media_path())) {
       add_sidecar(&mut media_map[handle_media], handle_sidecar);
       sidecar.set_media_handle(handle_media);
@@ -219,8 +635,115 @@ fn link_sidecars_by_type<S: Sidecar>(
   }
 }
 
+/// Extensions (lowercase) of video files, by which a sidecar's name tells it
+/// was written for a video.
+static VIDEO_EXTS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
+  HashSet::from([
+    "3gp", "avi", "m2ts", "m4v", "mkv", "mov", "mp4", "mpg", "mts", "webm", "wmv",
+  ])
+});
+
+/// Links leftover sidecars to media with the same path and stem, but a
+/// different extension, if there is exactly one such media file of the kind
+/// the sidecar was written for and it `can_link` the sidecar. Sidecars named
+/// without the media's extension (e.g. `IMG_0001.xmp`) go to images before
+/// videos, as a Live Photo video shares its image's stem. Each relinked sidecar
+/// is warned about.
+fn relink_sidecars_by_stem<S: Sidecar + AsRef<Path>>(
+  dir_root: impl AsRef<Path>,
+  sidecar_map: &mut FileMap<S>,
+  media_map: &mut FileMap<Media>,
+  add_sidecar: fn(&mut Media, Handle<S>),
+  can_link: fn(&Media) -> bool,
+  warnings: &mut Warnings,
+) {
+  let mut media_by_stem = HashMap::<_, Vec<_>>::new();
+  for (handle_media, media) in media_map.iter_data_indexed() {
+    media_by_stem
+      .entry((
+        to_abs_path(&dir_root, media).with_extension(""),
+        MediaKind::of(media),
+      ))
+      .or_default()
+      .push(handle_media);
+  }
+
+  for (handle_sidecar, sidecar) in sidecar_map.iter_data_mut_indexed() {
+    if !sidecar.is_leftover() {
+      continue;
+    }
+
+    let media_path = to_abs_path(&dir_root, sidecar.get_media_path());
+    let ext = media_path
+      .extension()
+      .map(|e| e.to_string_lossy().to_lowercase())
+      .unwrap_or_default();
+    let kinds: &[MediaKind] = if ext == "xmp" {
+      &[MediaKind::Photo, MediaKind::Video]
+    } else if VIDEO_EXTS.contains(ext.as_str()) {
+      &[MediaKind::Video]
+    } else {
+      &[MediaKind::Photo]
+    };
+
+    let stem = media_path.with_extension("");
+    let Some(&[handle_media]) = kinds
+      .iter()
+      .find_map(|&k| media_by_stem.get(&(stem.clone(), k)))
+      .map(Vec::as_slice)
+    else {
+      continue;
+    };
+
+    if !can_link(&media_map[handle_media]) {
+      continue;
+    }
+
+    add_sidecar(&mut media_map[handle_media], handle_sidecar);
+    sidecar.set_media_handle(handle_media);
+
+    warnings.push(Warning::RelinkedSidecar(
+      sidecar.as_ref().to_path_buf(),
+      media_map[handle_media].as_ref().to_path_buf(),
+    ));
+  }
+}
+
+/// Links adjustment sidecars to the image with the same path and stem (e.g.
+/// `IMG_0001.aae` to `IMG_0001.heic`), if there is exactly one. Videos are
+/// skipped, as Live Photo videos share their image's stem.
+fn link_adjustments(
+  dir_root: impl AsRef<Path>,
+  adjustments: &mut FileMap<Adjustment>,
+  media_map: &mut FileMap<Media>,
+) {
+  let mut images_by_stem = HashMap::<_, Vec<_>>::new();
+  for (handle_media, media) in media_map.iter_data_indexed() {
+    if media.get_metadata().is_video() || media.get_adjustment().is_some() {
+      continue;
+    }
+
+    images_by_stem
+      .entry(to_abs_path(&dir_root, media).with_extension(""))
+      .or_default()
+      .push(handle_media);
+  }
+
+  for (handle_adjustment, adjustment) in adjustments.iter_data_mut_indexed() {
+    if !adjustment.is_leftover() {
+      continue;
+    }
+
+    let stem = to_abs_path(&dir_root, adjustment.get_media_stem());
+    if let Some(&[handle_media]) = images_by_stem.get(&stem).map(Vec::as_slice) {
+      media_map[handle_media].set_adjustment(handle_adjustment);
+      adjustment.set_media_handle(handle_media);
+    }
+  }
+}
+
 /// Link Live Photo images to their videos, and vice versa. This is based on the
-/// `ContentIdentifier` tag from `ExifTool`.
+/// `ContentIdentifier` tag from `ExifTool`, falling back to `MediaGroupUUID`.
 fn link_live_photos(
   media_map: &mut FileMap<Media>,
   live_photo_map: &mut HashMap<LivePhotoID, LivePhotoLinker>,
@@ -243,15 +766,90 @@ fn link_live_photos(
   }
 }
 
+/// Group burst frames by their `BurstUUID` tag from `ExifTool`.
+fn link_bursts(media_map: &FileMap<Media>, burst_map: &mut HashMap<BurstID, Vec<Handle<Media>>>) {
+  for (media_handle, media) in media_map.iter_data_indexed() {
+    if let Some(burst_id) = media.burst_id() {
+      burst_map.entry(burst_id).or_default().push(media_handle);
+    }
+  }
+}
+
+/// Whether `file` is hidden, i.e. its name starts with `.`, as `ExifTool`
+/// skips when scanning.
+fn is_hidden(file: &Path) -> bool {
+  file
+    .file_name()
+    .is_some_and(|n| n.as_encoded_bytes().starts_with(b"."))
+}
+
+/// Whether metadata for `media` may only be written to its sidecar, as its file
+/// type is one of `sidecar_only_types` (matched ignoring case).
+fn is_sidecar_only(media: &Media, sidecar_only_types: &[String]) -> bool {
+  let file_type = &media.get_metadata().file_type;
+  sidecar_only_types
+    .iter()
+    .any(|t| t.eq_ignore_ascii_case(file_type))
+}
+
+/// Gets how many directories below `root` `file` is, given either absolute or
+/// relative to `root` (e.g. `./dir/image.jpg` is at 1).
+fn get_depth(root: &Path, file: &Path) -> usize {
+  file
+    .strip_prefix(root)
+    .unwrap_or(file)
+    .components()
+    .filter(|c| matches!(c, Component::Normal(_)))
+    .count()
+    .saturating_sub(1)
+}
+
+/// Applies `f` to each of `items` on up to `workers` threads, each taking the
+/// next item as it finishes the last. Results are in the same order as `items`.
+fn par_map<T: Sync, R: Send>(items: &[T], workers: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+  let next = AtomicUsize::new(0);
+
+  let mut results = thread::scope(|s| {
+    let threads = (0..workers.clamp(1, items.len().max(1)))
+      .map(|_| {
+        s.spawn(|| {
+          let mut results = Vec::new();
+          loop {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            let Some(item) = items.get(i) else {
+              return results;
+            };
+            results.push((i, f(item)));
+          }
+        })
+      })
+      .collect::<Vec<_>>();
+
+    threads
+      .into_iter()
+      .flat_map(|t| t.join().unwrap())
+      .collect::<Vec<_>>()
+  });
+
+  results.sort_by_key(|(i, _)| *i);
+  results.into_iter().map(|(_, r)| r).collect()
+}
+
 #[cfg(test)]
 mod test_new {
+  use std::fs;
+
   use super::*;
   use crate::testing::*;
 
   #[test]
   fn errors_if_catalog_path_does_not_exist() {
     assert_err!(
-      Organizer::new("/path/does/not/exist", None::<&Path>),
+      Organizer::new(
+        "/path/does/not/exist",
+        None::<&Path>,
+        &ScanConfig::default()
+      ),
       "Catalog path does not exist."
     );
   }
@@ -259,16 +857,36 @@ mod test_new {
   #[test]
   fn errors_if_catalog_path_is_relative() {
     assert_err!(
-      Organizer::new("relative/path", None::<&Path>),
+      Organizer::new("relative/path", None::<&Path>, &ScanConfig::default()),
       "Catalog path is not absolute."
     );
   }
 
+  #[test]
+  fn errors_by_kind_if_catalog_path_invalid() {
+    assert!(matches!(
+      Organizer::new(
+        "/path/does/not/exist",
+        None::<&Path>,
+        &ScanConfig::default()
+      ),
+      Err(CatalogError::PathNotFound(_, "Catalog path"))
+    ));
+    assert!(matches!(
+      Organizer::new("relative/path", None::<&Path>, &ScanConfig::default()),
+      Err(CatalogError::PathNotAbsolute(_, "Catalog path"))
+    ));
+  }
+
   #[test]
   fn errors_if_trash_path_does_not_exist() {
     let d = test_dir!();
     assert_err!(
-      Organizer::new(d.root(), Some("/path/does/not/exist")),
+      Organizer::new(
+        d.root(),
+        Some("/path/does/not/exist"),
+        &ScanConfig::default()
+      ),
       "Trash path does not exist."
     );
   }
@@ -277,138 +895,763 @@ mod test_new {
   fn errors_if_trash_path_is_relative() {
     let d = test_dir!();
     assert_err!(
-      Organizer::new(d.root(), Some("relative/path")),
+      Organizer::new(d.root(), Some("relative/path"), &ScanConfig::default()),
       "Trash path is not absolute."
     );
   }
+
+  #[test]
+  fn errors_by_kind_if_trash_path_invalid() {
+    let d = test_dir!();
+    assert!(matches!(
+      Organizer::new(
+        d.root(),
+        Some("/path/does/not/exist"),
+        &ScanConfig::default()
+      ),
+      Err(CatalogError::PathNotFound(_, "Trash path"))
+    ));
+    assert!(matches!(
+      Organizer::new(d.root(), Some("relative/path"), &ScanConfig::default()),
+      Err(CatalogError::PathNotAbsolute(_, "Trash path"))
+    ));
+  }
+
+  #[test]
+  fn excludes_only_trash_within_catalog() {
+    let d = test_dir!(
+      "catalog/image.jpg": {},
+    );
+    fs::copy(d.get_path("catalog/image.jpg"), d.trash().join("image.jpg")).unwrap();
+
+    let o = Organizer::new(
+      d.get_path("catalog"),
+      d.some_trash(),
+      &ScanConfig::default(),
+    )
+    .unwrap();
+
+    assert_eq!(o.media.iter_data().count(), 1);
+  }
 }
 
 #[cfg(test)]
-mod test_load_metadata {
+mod test_import {
   use super::*;
   use crate::testing::*;
 
   #[test]
-  fn loads_media() {
+  fn errors_if_no_paths() {
+    assert_err!(
+      Organizer::import(&[] as &[&Path], &ScanConfig::default()),
+      "No paths to import."
+    );
+  }
+
+  #[test]
+  fn imports_from_all_paths() {
     let d = test_dir!(
-      "image.jpg": {},
-      "image.jpg.xmp": {},
-      "image_01.jpg.xmp": {},
+      "a/image.jpg": {},
+      "a/image.jpg.xmp": {},
+      "b/image.jpg": {},
+      "b/video.mov": {},
     );
 
-    let mut media = FileMap::new();
-    let mut sidecars = FileMap::new();
-    let mut dupes = FileMap::new();
-    let metadata = io::read_metadata_recursive(d.root(), d.some_trash()).unwrap();
+    let o = Organizer::import(&[d.get_path("a"), d.get_path("b")], &ScanConfig::default()).unwrap();
 
-    load_metadata(d.root(), &mut media, &mut sidecars, &mut dupes, metadata).unwrap();
+    let mut media = o
+      .media
+      .iter_data()
+      .map(|m| to_abs_path(&o.source, m))
+      .collect::<Vec<_>>();
+    media.sort();
+    assert_eq!(media, [
+      d.get_path("a/image.jpg"),
+      d.get_path("b/image.jpg"),
+      d.get_path("b/video.mov"),
+    ]);
 
-    assert!(media.iter_data().count() == 1);
-    assert!(media.find(d.get_path("image.jpg")).is_some());
-    assert!(sidecars.iter_data().count() == 1);
-    assert!(sidecars.find(d.get_path("image.jpg.xmp")).is_some());
-    assert!(dupes.iter_data().count() == 1);
-    assert!(dupes.find(d.get_path("image_01.jpg.xmp")).is_some());
+    let a = o.media.find(d.get_path("a/image.jpg")).unwrap();
+    assert_eq!(
+      o.media[a].get_sidecar(),
+      o.sidecars.find(d.get_path("a/image.jpg.xmp"))
+    );
+  }
+
+  #[test]
+  fn applies_absolute_exclusions_within_each_path() {
+    let d = test_dir!(
+      "a/image.jpg": {},
+      "a/skip/image.jpg": {},
+      "b/image.jpg": {},
+    );
+
+    let scan = ScanConfig {
+      exclude: vec![d.get_path("a/skip")],
+      ..Default::default()
+    };
+    let o = Organizer::import(&[d.get_path("a"), d.get_path("b")], &scan).unwrap();
+
+    assert_eq!(o.media.iter_data().count(), 2);
+    assert!(o.media.find(d.get_path("a/skip/image.jpg")).is_none());
   }
 }
 
 #[cfg(test)]
-mod test_link_sidecars {
+mod test_load_catalog {
+  use std::fs;
+
   use super::*;
   use crate::testing::*;
 
   #[test]
-  fn links_sidecars() {
+  fn skips_excluded_directories() {
     let d = test_dir!(
       "image.jpg": {},
-      "image.jpg.xmp": {},
-      "image_01.jpg.xmp": {},
+      "_working/image.jpg": {},
+      "originals/image.jpg": {},
     );
 
-    let mut media = FileMap::new();
-    let mut sidecars = FileMap::new();
-    let mut dupes = FileMap::new();
-    let metadata = io::read_metadata_recursive(d.root(), d.some_trash()).unwrap();
+    let scan = ScanConfig {
+      exclude: vec![PathBuf::from("_working"), d.get_path("originals")],
+      ..Default::default()
+    };
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &scan).unwrap();
 
-    load_metadata(d.root(), &mut media, &mut sidecars, &mut dupes, metadata).unwrap();
+    assert_eq!(o.media.iter_data().count(), 1);
+    assert!(o.media.find(d.get_path("image.jpg")).is_some());
+  }
 
-    let handle_media = media.find(d.get_path("image.jpg")).unwrap();
-    let handle_sidecar = sidecars.find(d.get_path("image.jpg.xmp")).unwrap();
-    let handle_dupe = dupes.find(d.get_path("image_01.jpg.xmp")).unwrap();
+  #[test]
+  fn skips_files_below_max_depth() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "a/image.jpg": {},
+      "a/b/image.jpg": {},
+      "a/b/c/image.jpg": {},
+    );
 
-    link_sidecars_by_type(d.root(), &mut sidecars, &mut media, Media::set_sidecar);
+    let scan = ScanConfig {
+      max_depth: Some(1),
+      ..Default::default()
+    };
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &scan).unwrap();
 
-    assert_eq!(media[handle_media].get_sidecar(), Some(handle_sidecar));
-    assert!(media[handle_media].iter_dupes().count() == 0);
-    assert_eq!(
-      sidecars[handle_sidecar].get_media_handle(),
-      Some(handle_media)
-    );
-    assert!(dupes[handle_dupe].get_media_handle().is_none());
+    assert_eq!(o.media.iter_data().count(), 2);
+    assert!(o.media.find(d.get_path("image.jpg")).is_some());
+    assert!(o.media.find(d.get_path("a/image.jpg")).is_some());
   }
 
   #[test]
-  fn links_dupes() {
+  fn skips_hidden_files() {
     let d = test_dir!(
       "image.jpg": {},
-      "image.jpg.xmp": {},
-      "image_01.jpg.xmp": {},
+      ".hidden.jpg": {},
+      "a/.hidden.jpg": {},
     );
+    fs::write(d.get_path("._image.jpg"), b"\x00\x05\x16\x07").unwrap();
 
-    let mut media = FileMap::new();
-    let mut sidecars = FileMap::new();
-    let mut dupes = FileMap::new();
-    let metadata = io::read_metadata_recursive(d.root(), d.some_trash()).unwrap();
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
 
-    load_metadata(d.root(), &mut media, &mut sidecars, &mut dupes, metadata).unwrap();
+    assert_eq!(o.media.iter_data().count(), 1);
+    assert!(o.media.find(d.get_path("image.jpg")).is_some());
+  }
 
-    let handle_media = media.find(d.get_path("image.jpg")).unwrap();
-    let handle_sidecar = sidecars.find(d.get_path("image.jpg.xmp")).unwrap();
-    let handle_dupe = dupes.find(d.get_path("image_01.jpg.xmp")).unwrap();
+  #[test]
+  fn loads_hidden_files_if_include_hidden() {
+    let d = test_dir!(
+      "image.jpg": {},
+      ".hidden.jpg": {},
+    );
 
-    link_sidecars_by_type(d.root(), &mut dupes, &mut media, Media::add_dupe);
+    let scan = ScanConfig {
+      include_hidden: true,
+      ..Default::default()
+    };
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &scan).unwrap();
 
-    assert!(media[handle_media].get_sidecar().is_none());
-    assert_eq!(media[handle_media].iter_dupes().collect::<Vec<_>>(), vec![
-      handle_dupe
-    ]);
-    assert!(sidecars[handle_sidecar].get_media_handle().is_none());
-    assert_eq!(dupes[handle_dupe].get_media_handle(), Some(handle_media));
+    assert_eq!(o.media.iter_data().count(), 2);
+    assert!(o.media.find(d.get_path(".hidden.jpg")).is_some());
   }
-}
-
-#[cfg(test)]
-mod test_link_live_photos {
-  use super::*;
-  use crate::testing::*;
 
   #[test]
-  fn links_live_photo() {
+  fn loads_symlinked_directories_unless_skip_symlinks() {
     let d = test_dir!(
-      "image.heic": {
-        "ContentIdentifier": "ID",
-      },
-      "image_dupe.jpg": {
-        "ContentIdentifier": "ID",
-      },
-      "video.mov": {
-        "ContentIdentifier": "ID",
-        "CompressorID": "hvc1",
-      },
-      "video_dupe.mov": {
-        "ContentIdentifier": "ID",
-        "CompressorID": "avc1",
-      },
-      "not_live.jpg": {}
+      "catalog/image.jpg": {},
+      "archive/image.jpg": {},
     );
+    std::os::unix::fs::symlink(d.get_path("archive"), d.get_path("catalog/archive")).unwrap();
+    // Loops back to the catalog, so would be followed forever.
+    std::os::unix::fs::symlink(d.get_path("catalog"), d.get_path("archive/catalog")).unwrap();
 
-    let mut media = FileMap::new();
-    let mut sidecars = FileMap::new();
+    let scan = ScanConfig {
+      skip_symlinks: true,
+      ..Default::default()
+    };
+    let o = Organizer::load_catalog(d.get_path("catalog"), d.some_trash(), &scan).unwrap();
+    assert_eq!(o.media.iter_data().count(), 1);
+
+    let o = Organizer::load_catalog(
+      d.get_path("catalog"),
+      d.some_trash(),
+      &ScanConfig::default(),
+    )
+    .unwrap();
+
+    assert_eq!(o.media.iter_data().count(), 2);
+    assert!(
+      o.media
+        .find(d.get_path("catalog/archive/image.jpg"))
+        .is_some()
+    );
+  }
+
+  #[test]
+  fn loads_only_top_level_if_max_depth_zero() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+      "a/image.jpg": {},
+      "a/image.jpg.xmp": {},
+    );
+
+    let scan = ScanConfig {
+      max_depth: Some(0),
+      ..Default::default()
+    };
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &scan).unwrap();
+
+    assert_eq!(o.media.iter_data().count(), 1);
+    assert_eq!(o.sidecars.iter_data().count(), 1);
+  }
+
+  #[test]
+  fn errors_if_file_unreadable() {
+    let d = test_dir!(
+      "image1.jpg": {},
+      "image2.jpg": {},
+    );
+    fs::write(d.get_path("image2.jpg"), "Not an image.").unwrap();
+
+    assert_err!(
+      Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()),
+      "image2.jpg: Unable to read metadata"
+    );
+  }
+
+  #[test]
+  fn skips_unreadable_file_if_continuing() {
+    let d = test_dir!(
+      "image1.jpg": {},
+      "image2.jpg": {},
+      "image3.jpg": {},
+    );
+    fs::write(d.get_path("image2.jpg"), "Not an image.").unwrap();
+
+    let scan = ScanConfig {
+      on_error: ErrorPolicy::Continue,
+      ..Default::default()
+    };
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &scan).unwrap();
+
+    assert_eq!(o.media.iter_data().count(), 2);
+    assert!(o.media.find(d.get_path("image2.jpg")).is_none());
+    assert!(matches!(
+      o.take_warnings().as_slice(),
+      [Warning::SkippedAfterError(e)]
+        if e.to_string().contains("image2.jpg: Unable to read metadata")
+    ));
+  }
+}
+
+#[cfg(test)]
+mod test_from_file_list {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn loads_only_listed_files() {
+    let d = test_dir!(
+      "image1.jpg": {},
+      "image2.jpg": {},
+      "image2.jpg.xmp": {},
+      "a/image3.jpg": {},
+      "a/image4.jpg": {},
+    );
+
+    let list = format!(
+      "image1.jpg\n./image2.jpg.xmp\n\n{}\n",
+      d.get_path("a/image3.jpg").display()
+    );
+    let o = Organizer::from_file_list(
+      d.root(),
+      d.some_trash(),
+      list.as_bytes(),
+      &ScanConfig::default(),
+    )
+    .unwrap();
+
+    assert_eq!(o.media.iter_data().count(), 2);
+    assert_eq!(o.sidecars.iter_data().count(), 1);
+    assert!(o.media.find(d.get_path("image1.jpg")).is_some());
+    assert!(o.media.find(d.get_path("a/image3.jpg")).is_some());
+    assert!(o.sidecars.find(d.get_path("image2.jpg.xmp")).is_some());
+  }
+
+  #[test]
+  fn skips_listed_files_in_excluded_directories() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "_working/image.jpg": {},
+    );
+
+    let scan = ScanConfig {
+      exclude: vec![PathBuf::from("_working")],
+      ..Default::default()
+    };
+    let o = Organizer::from_file_list(
+      d.root(),
+      d.some_trash(),
+      "image.jpg\n_working/image.jpg\n".as_bytes(),
+      &scan,
+    )
+    .unwrap();
+
+    assert_eq!(o.media.iter_data().count(), 1);
+    assert!(o.media.find(d.get_path("image.jpg")).is_some());
+  }
+
+  #[test]
+  fn skips_listed_hidden_files() {
+    let d = test_dir!(
+      "image.jpg": {},
+      ".hidden.jpg": {},
+    );
+
+    let o = Organizer::from_file_list(
+      d.root(),
+      d.some_trash(),
+      "image.jpg\n.hidden.jpg\n".as_bytes(),
+      &ScanConfig::default(),
+    )
+    .unwrap();
+
+    assert_eq!(o.media.iter_data().count(), 1);
+    assert!(o.media.find(d.get_path("image.jpg")).is_some());
+  }
+
+  #[test]
+  fn loads_nothing_if_list_empty() {
+    let d = test_dir!(
+      "image.jpg": {},
+    );
+
+    let o = Organizer::from_file_list(
+      d.root(),
+      d.some_trash(),
+      "".as_bytes(),
+      &ScanConfig::default(),
+    )
+    .unwrap();
+
+    assert_eq!(o.media.iter_data().count(), 0);
+  }
+
+  #[test]
+  fn errors_if_listed_file_outside_catalog() {
+    let d = test_dir!(
+      "a/image.jpg": {},
+      "b/image.jpg": {},
+    );
+
+    assert_err!(
+      Organizer::from_file_list(
+        d.get_path("a"),
+        d.some_trash(),
+        "../b/image.jpg\n".as_bytes(),
+        &ScanConfig::default(),
+      ),
+      "Listed file is not within catalog"
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_load_metadata {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn loads_media() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+      "image_01.jpg.xmp": {},
+    );
+
+    let mut media = FileMap::new();
+    let mut sidecars = FileMap::new();
+    let mut dupes = FileMap::new();
+    let metadata = io::read_metadata_recursive(
+      &io::ExifToolConfig::default(),
+      d.root(),
+      d.some_trash(),
+      false,
+      false,
+    )
+    .unwrap();
+
+    load_metadata(
+      d.root(),
+      &mut media,
+      &mut sidecars,
+      &mut dupes,
+      &mut FileMap::new(),
+      metadata,
+      &mut Warnings::default(),
+    )
+    .unwrap();
+
+    assert!(media.iter_data().count() == 1);
+    assert!(media.find(d.get_path("image.jpg")).is_some());
+    assert!(sidecars.iter_data().count() == 1);
+    assert!(sidecars.find(d.get_path("image.jpg.xmp")).is_some());
+    assert!(dupes.iter_data().count() == 1);
+    assert!(dupes.find(d.get_path("image_01.jpg.xmp")).is_some());
+  }
+}
+
+#[cfg(test)]
+mod test_link_sidecars {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn links_sidecars() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+      "image_01.jpg.xmp": {},
+    );
+
+    let mut media = FileMap::new();
+    let mut sidecars = FileMap::new();
+    let mut dupes = FileMap::new();
+    let metadata = io::read_metadata_recursive(
+      &io::ExifToolConfig::default(),
+      d.root(),
+      d.some_trash(),
+      false,
+      false,
+    )
+    .unwrap();
+
+    load_metadata(
+      d.root(),
+      &mut media,
+      &mut sidecars,
+      &mut dupes,
+      &mut FileMap::new(),
+      metadata,
+      &mut Warnings::default(),
+    )
+    .unwrap();
+
+    let handle_media = media.find(d.get_path("image.jpg")).unwrap();
+    let handle_sidecar = sidecars.find(d.get_path("image.jpg.xmp")).unwrap();
+    let handle_dupe = dupes.find(d.get_path("image_01.jpg.xmp")).unwrap();
+
+    link_sidecars_by_type(d.root(), &mut sidecars, &mut media, Media::set_sidecar);
+
+    assert_eq!(media[handle_media].get_sidecar(), Some(handle_sidecar));
+    assert!(media[handle_media].iter_dupes().count() == 0);
+    assert_eq!(
+      sidecars[handle_sidecar].get_media_handle(),
+      Some(handle_media)
+    );
+    assert!(dupes[handle_dupe].get_media_handle().is_none());
+  }
+
+  #[test]
+  fn links_dupes() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+      "image_01.jpg.xmp": {},
+    );
+
+    let mut media = FileMap::new();
+    let mut sidecars = FileMap::new();
+    let mut dupes = FileMap::new();
+    let metadata = io::read_metadata_recursive(
+      &io::ExifToolConfig::default(),
+      d.root(),
+      d.some_trash(),
+      false,
+      false,
+    )
+    .unwrap();
+
+    load_metadata(
+      d.root(),
+      &mut media,
+      &mut sidecars,
+      &mut dupes,
+      &mut FileMap::new(),
+      metadata,
+      &mut Warnings::default(),
+    )
+    .unwrap();
+
+    let handle_media = media.find(d.get_path("image.jpg")).unwrap();
+    let handle_sidecar = sidecars.find(d.get_path("image.jpg.xmp")).unwrap();
+    let handle_dupe = dupes.find(d.get_path("image_01.jpg.xmp")).unwrap();
+
+    link_sidecars_by_type(d.root(), &mut dupes, &mut media, Media::add_dupe);
+
+    assert!(media[handle_media].get_sidecar().is_none());
+    assert_eq!(media[handle_media].iter_dupes().collect::<Vec<_>>(), vec![
+      handle_dupe
+    ]);
+    assert!(sidecars[handle_sidecar].get_media_handle().is_none());
+    assert_eq!(dupes[handle_dupe].get_media_handle(), Some(handle_media));
+  }
+}
+
+#[cfg(test)]
+mod test_relink_sidecars_by_stem {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn relinks_sidecars_to_media_with_other_extension() {
+    let d = test_dir!(
+      "image.heic": {},
+      "image.jpg.xmp": {},
+      "image_01.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    let handle_media = o.media.find(d.get_path("image.heic")).unwrap();
+    let handle_sidecar = o.sidecars.find(d.get_path("image.jpg.xmp")).unwrap();
+    let handle_dupe = o.dupes.find(d.get_path("image_01.jpg.xmp")).unwrap();
+
+    assert_eq!(o.media[handle_media].get_sidecar(), Some(handle_sidecar));
+    assert_eq!(o.media[handle_media].iter_dupes().collect::<Vec<_>>(), [
+      handle_dupe
+    ]);
+    assert_eq!(
+      o.sidecars[handle_sidecar].get_media_handle(),
+      Some(handle_media)
+    );
+    assert_eq!(o.take_warnings(), [
+      Warning::RelinkedSidecar(
+        Path::new("./image.jpg.xmp").to_path_buf(),
+        Path::new("./image.heic").to_path_buf()
+      ),
+      Warning::RelinkedSidecar(
+        Path::new("./image_01.jpg.xmp").to_path_buf(),
+        Path::new("./image.heic").to_path_buf()
+      ),
+    ]);
+  }
+
+  #[test]
+  fn skips_media_with_sidecar() {
+    let d = test_dir!(
+      "image.heic": {},
+      "image.heic.xmp": {},
+      "image.jpg.xmp": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    let handle_sidecar = o.sidecars.find(d.get_path("image.jpg.xmp")).unwrap();
+    assert!(o.sidecars[handle_sidecar].is_leftover());
+  }
+
+  #[test]
+  fn skips_if_ambiguous() {
+    let d = test_dir!(
+      "image.heic": {},
+      "image.png": {},
+      "image.jpg.xmp": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    let handle_sidecar = o.sidecars.find(d.get_path("image.jpg.xmp")).unwrap();
+    assert!(o.sidecars[handle_sidecar].is_leftover());
+  }
+
+  #[test]
+  fn relinks_sidecars_only_to_media_of_same_kind() {
+    let d = test_dir!(
+      "image.heic": {},
+      "image.mov": { "CompressorID": "avc1" },
+      "image.jpg.xmp": {},
+      "video.jpg": {},
+      "video.mov": { "CompressorID": "avc1" },
+      "video.mp4.xmp": {},
+      "other.mov": { "CompressorID": "avc1" },
+      "other.jpg.xmp": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    let handle_image = o.media.find(d.get_path("image.heic")).unwrap();
+    let handle_video = o.media.find(d.get_path("video.mov")).unwrap();
+    assert_eq!(
+      o.media[handle_image].get_sidecar(),
+      o.sidecars.find(d.get_path("image.jpg.xmp"))
+    );
+    assert_eq!(
+      o.media[handle_video].get_sidecar(),
+      o.sidecars.find(d.get_path("video.mp4.xmp"))
+    );
+    let handle_sidecar = o.sidecars.find(d.get_path("other.jpg.xmp")).unwrap();
+    assert!(o.sidecars[handle_sidecar].is_leftover());
+  }
+
+  #[test]
+  fn relinks_sidecar_without_extension_to_image_before_video() {
+    let d = test_dir!(
+      "IMG_0001.HEIC": {},
+      "IMG_0001.MOV": { "CompressorID": "avc1" },
+      "IMG_0001.xmp": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    let handle_media = o.media.find(d.get_path("IMG_0001.HEIC")).unwrap();
+    let handle_sidecar = o.sidecars.find(d.get_path("IMG_0001.xmp")).unwrap();
+    assert_eq!(o.media[handle_media].get_sidecar(), Some(handle_sidecar));
+    assert_eq!(
+      o.sidecars[handle_sidecar].get_media_handle(),
+      Some(handle_media)
+    );
+  }
+
+  #[test]
+  fn moves_relinked_sidecar_with_media() {
+    let d = test_dir!(
+      "image.heic": {},
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.heic",
+      "2000/01/000101_000000000.heic.xmp",
+    ]);
+  }
+}
+
+#[cfg(test)]
+mod test_link_adjustments {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn links_adjustment_to_image() {
+    let d = test_dir!(
+      "IMG_0001.heic": {},
+      "IMG_0001.aae": {},
+    );
+
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+
+    let handle_media = o.media.find(d.get_path("IMG_0001.heic")).unwrap();
+    let handle_adjustment = o.adjustments.find(d.get_path("IMG_0001.aae")).unwrap();
+    assert_eq!(
+      o.media[handle_media].get_adjustment(),
+      Some(handle_adjustment)
+    );
+    assert!(!o.adjustments[handle_adjustment].is_leftover());
+  }
+
+  #[test]
+  fn links_live_photo_image_not_video() {
+    let d = test_dir!(
+      "IMG_0001.heic": { "ContentIdentifier": "ID" },
+      "IMG_0001.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+      "IMG_0001.aae": {},
+    );
+
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+
+    let handle_image = o.media.find(d.get_path("IMG_0001.heic")).unwrap();
+    let handle_video = o.media.find(d.get_path("IMG_0001.mov")).unwrap();
+    assert!(o.media[handle_image].get_adjustment().is_some());
+    assert!(o.media[handle_video].get_adjustment().is_none());
+  }
+
+  #[test]
+  fn skips_if_multiple_images_match() {
+    let d = test_dir!(
+      "IMG_0001.dng": {},
+      "IMG_0001.jpg": {},
+      "IMG_0001.aae": {},
+    );
+
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+
+    let handle_adjustment = o.adjustments.find(d.get_path("IMG_0001.aae")).unwrap();
+    assert!(o.adjustments[handle_adjustment].is_leftover());
+  }
+}
+
+#[cfg(test)]
+mod test_link_live_photos {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn links_live_photo() {
+    let d = test_dir!(
+      "image.heic": {
+        "ContentIdentifier": "ID",
+      },
+      "image_dupe.jpg": {
+        "ContentIdentifier": "ID",
+      },
+      "video.mov": {
+        "ContentIdentifier": "ID",
+        "CompressorID": "hvc1",
+      },
+      "video_dupe.mov": {
+        "ContentIdentifier": "ID",
+        "CompressorID": "avc1",
+      },
+      "not_live.jpg": {}
+    );
+
+    let mut media = FileMap::new();
+    let mut sidecars = FileMap::new();
     let mut dupes = FileMap::new();
     let mut live_photos = HashMap::new();
-    let metadata = io::read_metadata_recursive(d.root(), d.some_trash()).unwrap();
+    let metadata = io::read_metadata_recursive(
+      &io::ExifToolConfig::default(),
+      d.root(),
+      d.some_trash(),
+      false,
+      false,
+    )
+    .unwrap();
 
-    load_metadata(d.root(), &mut media, &mut sidecars, &mut dupes, metadata).unwrap();
+    load_metadata(
+      d.root(),
+      &mut media,
+      &mut sidecars,
+      &mut dupes,
+      &mut FileMap::new(),
+      metadata,
+      &mut Warnings::default(),
+    )
+    .unwrap();
 
     let handle_image = media.find(d.get_path("image.heic")).unwrap();
     let handle_image_dupe = media.find(d.get_path("image_dupe.jpg")).unwrap();
@@ -423,4 +1666,233 @@ mod test_link_live_photos {
     assert!(link.drain_images().collect::<Vec<_>>() == vec![handle_image, handle_image_dupe]);
     assert!(link.drain_videos().collect::<Vec<_>>() == vec![handle_video, handle_video_dupe]);
   }
+
+  #[test]
+  fn links_by_media_group_uuid() {
+    let d = test_dir!(
+      "image.heic": {
+        "ContentIdentifier": "",
+        "MediaGroupUUID": "ID",
+      },
+      "video.mov": {
+        "ContentIdentifier": "ID",
+        "CompressorID": "hvc1",
+      },
+    );
+
+    let mut media = FileMap::new();
+    let mut sidecars = FileMap::new();
+    let mut dupes = FileMap::new();
+    let mut live_photos = HashMap::new();
+    let metadata = io::read_metadata_recursive(
+      &io::ExifToolConfig::default(),
+      d.root(),
+      d.some_trash(),
+      false,
+      false,
+    )
+    .unwrap();
+
+    load_metadata(
+      d.root(),
+      &mut media,
+      &mut sidecars,
+      &mut dupes,
+      &mut FileMap::new(),
+      metadata,
+      &mut Warnings::default(),
+    )
+    .unwrap();
+
+    let handle_image = media.find(d.get_path("image.heic")).unwrap();
+    let handle_video = media.find(d.get_path("video.mov")).unwrap();
+
+    link_live_photos(&mut media, &mut live_photos);
+
+    let mut link = live_photos.remove(&LivePhotoID("ID".to_string())).unwrap();
+    assert!(link.is_pair());
+    assert!(link.drain_images().collect::<Vec<_>>() == vec![handle_image]);
+    assert!(link.drain_videos().collect::<Vec<_>>() == vec![handle_video]);
+  }
+}
+
+#[cfg(test)]
+mod test_link_bursts {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn links_burst_frames() {
+    let d = test_dir!(
+      "image1.jpg": { "BurstUUID": "ID" },
+      "image2.jpg": { "BurstUUID": "ID" },
+      "image3.jpg": { "BurstUUID": "ID" },
+      "other.jpg": {},
+    );
+
+    let mut media = FileMap::new();
+    let mut sidecars = FileMap::new();
+    let mut dupes = FileMap::new();
+    let metadata = io::read_metadata_recursive(
+      &io::ExifToolConfig::default(),
+      d.root(),
+      d.some_trash(),
+      false,
+      false,
+    )
+    .unwrap();
+    load_metadata(
+      d.root(),
+      &mut media,
+      &mut sidecars,
+      &mut dupes,
+      &mut FileMap::new(),
+      metadata,
+      &mut Warnings::default(),
+    )
+    .unwrap();
+
+    let mut burst_map = HashMap::new();
+    link_bursts(&media, &mut burst_map);
+
+    assert_eq!(burst_map.len(), 1);
+    assert_eq!(burst_map[&BurstID("ID".to_string())].len(), 3);
+  }
+}
+
+#[cfg(test)]
+mod test_assert_consistent {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn passes_after_import() {
+    let d = test_dir!(
+      "burst1.jpg": { "BurstUUID": "ID" },
+      "burst2.jpg": { "BurstUUID": "ID" },
+      "image.heic": { "ContentIdentifier": "ID" },
+      "image.heic.xmp": {},
+      "image_01.heic.xmp": {},
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+    );
+
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.assert_consistent();
+  }
+
+  #[test]
+  #[should_panic(expected = "is held twice.")]
+  fn panics_if_sidecar_held_twice() {
+    let d = test_dir!(
+      "image1.jpg": {},
+      "image1.jpg.xmp": {},
+      "image2.jpg": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let handle = o.sidecars.find(d.get_path("image1.jpg.xmp")).unwrap();
+    let media = o.media.find(d.get_path("image2.jpg")).unwrap();
+    o.media[media].set_sidecar(handle);
+
+    o.assert_consistent();
+  }
+
+  #[test]
+  #[should_panic(expected = "is taken.")]
+  fn panics_if_sidecar_taken() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let handle = o.sidecars.find(d.get_path("image.jpg.xmp")).unwrap();
+    o.sidecars.get_entry_mut(handle).take();
+
+    o.assert_consistent();
+  }
+
+  #[test]
+  #[should_panic(expected = "is taken.")]
+  fn panics_if_burst_frame_taken() {
+    let d = test_dir!(
+      "burst1.jpg": { "BurstUUID": "ID" },
+      "burst2.jpg": { "BurstUUID": "ID" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let handle = o.media.find(d.get_path("burst1.jpg")).unwrap();
+    o.media.get_entry_mut(handle).take();
+
+    o.assert_consistent();
+  }
+}
+
+#[cfg(test)]
+mod test_is_hidden {
+  use super::*;
+
+  #[test]
+  fn is_hidden_if_name_starts_with_dot() {
+    assert!(is_hidden(Path::new("./.hidden.jpg")));
+    assert!(is_hidden(Path::new("/catalog/a/._image.jpg")));
+    assert!(!is_hidden(Path::new("./image.jpg")));
+    assert!(!is_hidden(Path::new("/catalog/.a/image.jpg")));
+  }
+}
+
+#[cfg(test)]
+mod test_is_sidecar_only {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn matches_file_type_ignoring_case() {
+    let media = Media::new(metadata!(
+      "SourceFile": "image.png",
+      "FileType": "PNG",
+      "MIMEType": "image/png",
+    ))
+    .unwrap();
+
+    assert!(is_sidecar_only(&media, &["png".to_string()]));
+    assert!(!is_sidecar_only(&media, &["GIF".to_string()]));
+    assert!(!is_sidecar_only(&media, &[]));
+  }
+}
+
+#[cfg(test)]
+mod test_get_depth {
+  use super::*;
+
+  #[test]
+  fn counts_directories_below_root() {
+    let root = Path::new("/catalog");
+
+    assert_eq!(get_depth(root, Path::new("./image.jpg")), 0);
+    assert_eq!(get_depth(root, Path::new("./a/b/image.jpg")), 2);
+    assert_eq!(get_depth(root, Path::new("/catalog/image.jpg")), 0);
+    assert_eq!(get_depth(root, Path::new("/catalog/a/image.jpg")), 1);
+  }
+}
+
+#[cfg(test)]
+mod test_par_map {
+  use super::*;
+
+  #[test]
+  fn keeps_order_of_items() {
+    let items = (0..100).collect::<Vec<_>>();
+
+    assert_eq!(
+      par_map(&items, 4, |i| i * 2),
+      (0..200).step_by(2).collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn handles_no_items_or_workers() {
+    assert!(par_map(&[] as &[usize], 4, |i| *i).is_empty());
+    assert_eq!(par_map(&[1, 2], 0, |i| i + 1), [2, 3]);
+  }
 }
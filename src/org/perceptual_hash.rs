@@ -0,0 +1,343 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Perceptual hashes of images, to find near duplicates (e.g. resized or
+//! re-exported copies of the same shot) which differ in bytes but look alike.
+
+use std::{fs, path::Path};
+
+use super::{
+  Organizer,
+  Warning,
+  jpeg::{GrayImage, decode_gray},
+  to_abs_path,
+};
+use crate::{error::CatalogError, io, prim::Codec};
+
+/// Width and height of the downscaled image compared by `dhash`.
+const DHASH_WIDTH: usize = 9;
+const DHASH_HEIGHT: usize = 8;
+
+/// Difference hash (dHash) of `image`: Whether each pixel of a 9×8 grayscale
+/// downscale is darker than the pixel to its right, which is unchanged by
+/// resizing, re-encoding, or adjusting brightness.
+pub fn dhash(image: &GrayImage) -> u64 {
+  let small = resize(image, DHASH_WIDTH, DHASH_HEIGHT);
+
+  let mut hash = 0;
+  for row in small.chunks_exact(DHASH_WIDTH) {
+    for pair in row.windows(2) {
+      hash = (hash << 1) | u64::from(pair[0] < pair[1]);
+    }
+  }
+  hash
+}
+
+/// Number of bits differing between hashes `a` and `b`.
+pub fn distance(a: u64, b: u64) -> u32 {
+  (a ^ b).count_ones()
+}
+
+/// Groups `hashes` into clusters, by index, where each is within
+/// `max_distance` of another in its cluster. Hashes without any near duplicate
+/// are left out.
+pub fn cluster(hashes: &[u64], max_distance: u32) -> Vec<Vec<usize>> {
+  // Union-find, with each hash's parent being the lowest index found alike.
+  let mut parents = (0..hashes.len()).collect::<Vec<_>>();
+
+  for i in 0..hashes.len() {
+    for j in i + 1..hashes.len() {
+      if distance(hashes[i], hashes[j]) <= max_distance {
+        let (a, b) = (root(&mut parents, i), root(&mut parents, j));
+        parents[a.max(b)] = a.min(b);
+      }
+    }
+  }
+
+  let mut clusters = Vec::<Vec<usize>>::new();
+  let mut cluster_of_root = vec![None::<usize>; hashes.len()];
+  for i in 0..hashes.len() {
+    let r = root(&mut parents, i);
+    if let Some(c) = cluster_of_root[r] {
+      clusters[c].push(i);
+    } else {
+      cluster_of_root[r] = Some(clusters.len());
+      clusters.push(vec![i]);
+    }
+  }
+  clusters.retain(|c| c.len() > 1);
+  clusters
+}
+
+/// Indices of `hashes` after the first within `max_distance` of it. Clusters
+/// chain (i.e. A near B near C), so not all in a cluster need be near its
+/// first.
+pub fn near_first(hashes: &[u64], max_distance: u32) -> Vec<usize> {
+  let Some(first) = hashes.first() else {
+    return Vec::new();
+  };
+
+  (1..hashes.len())
+    .filter(|i| distance(*first, hashes[*i]) <= max_distance)
+    .collect()
+}
+
+/// Finds the root of `i` in union-find `parents`, flattening the path to it.
+fn root(parents: &mut [usize], mut i: usize) -> usize {
+  while parents[i] != i {
+    parents[i] = parents[parents[i]];
+    i = parents[i];
+  }
+  i
+}
+
+/// Perceptual hash of the image at `file`, decoded directly if a JPEG, or from
+/// its embedded JPEG preview otherwise. `None` if there's nothing to decode
+/// (e.g. a HEIC without a preview).
+fn hash_image(
+  exiftool: &io::ExifToolConfig,
+  file: &Path,
+  codec: Codec,
+) -> Result<Option<u64>, CatalogError> {
+  let data = match codec {
+    Codec::JPEG => Some(
+      fs::read(file)
+        .map_err(|e| CatalogError::Io(format!("{}: Unable to read file ({e}).", file.display())))?,
+    ),
+    Codec::HEIC | Codec::Raw => io::extract_preview(exiftool, file)?,
+    _ => None,
+  };
+
+  data
+    .map(|d| decode_gray(&d).map(|i| dhash(&i)))
+    .transpose()
+    .map_err(|e| CatalogError::InvalidFile(format!("{}: {e}", file.display())))
+}
+
+/// Resizes `image` to `width` × `height` by averaging the area of `image`
+/// covered by each pixel.
+#[allow(
+  clippy::cast_precision_loss,
+  clippy::cast_possible_truncation,
+  clippy::cast_sign_loss
+)]
+fn resize(image: &GrayImage, width: usize, height: usize) -> Vec<f32> {
+  let span = |i: usize, n: usize, size: usize| {
+    let scale = size as f32 / n as f32;
+    (i as f32 * scale, (i + 1) as f32 * scale)
+  };
+  let overlap =
+    |i: usize, (start, end): (f32, f32)| (end.min(i as f32 + 1.0) - start.max(i as f32)).max(0.0);
+
+  let mut pixels = Vec::with_capacity(width * height);
+  for y in 0..height {
+    let ys = span(y, height, image.height);
+    for x in 0..width {
+      let xs = span(x, width, image.width);
+
+      let (mut sum, mut weight) = (0.0, 0.0);
+      for sy in ys.0 as usize..(ys.1.ceil() as usize).min(image.height) {
+        for sx in xs.0 as usize..(xs.1.ceil() as usize).min(image.width) {
+          let w = overlap(sy, ys) * overlap(sx, xs);
+          sum += w * image.pixels[sy * image.width + sx];
+          weight += w;
+        }
+      }
+      pixels.push(if weight > 0.0 { sum / weight } else { 0.0 });
+    }
+  }
+  pixels
+}
+
+impl Organizer {
+  /// Finds images which look alike (e.g. resized or re-exported copies of the
+  /// same shot), by perceptual hashes within `max_distance` bits of each other,
+  /// and warns about each cluster. JPEGs are decoded directly, and HEIC and raw
+  /// images by their embedded previews. Live Photo components and burst frames
+  /// are left to their own passes.
+  ///
+  /// If `remove`, the image with the most pixels (or first by path) of each
+  /// cluster is kept, and the rest within `max_distance` of it are removed
+  /// along with their sidecars. Otherwise, clusters are only reported.
+  pub fn dedupe_by_perceptual_hash(
+    &mut self,
+    max_distance: u32,
+    remove: bool,
+  ) -> Result<(), CatalogError> {
+    log::info!("Finding near-duplicate images.");
+
+    let mut handles = Vec::new();
+    let mut hashes = Vec::new();
+    for handle in self.ungrouped_media() {
+      let media = &self.media[handle];
+      let file = to_abs_path(&self.source, media);
+      if let Some(Some(hash)) =
+        self
+          .warnings
+          .check(hash_image(&self.exiftool, &file, media.get_codec()))?
+      {
+        handles.push(handle);
+        hashes.push(hash);
+      }
+    }
+
+    for cluster in cluster(&hashes, max_distance) {
+      let mut cluster = cluster
+        .into_iter()
+        .map(|i| (handles[i], hashes[i]))
+        .collect::<Vec<_>>();
+      // Stable, so ties stay sorted by path.
+      cluster.sort_by_key(|(h, _)| {
+        let metadata = self.media[*h].get_metadata();
+        let pixels = metadata
+          .image_width
+          .zip(metadata.image_height)
+          .map(|(w, h)| u64::from(w) * u64::from(h));
+        std::cmp::Reverse(pixels)
+      });
+
+      self.warnings.push(Warning::NearDuplicates(
+        cluster
+          .iter()
+          .map(|(h, _)| self.media[*h].as_ref().to_path_buf())
+          .collect(),
+      ));
+
+      if remove {
+        let hashes = cluster.iter().map(|(_, hash)| *hash).collect::<Vec<_>>();
+        for i in near_first(&hashes, max_distance) {
+          self.remove_media(cluster[i].0)?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test_dhash {
+  use super::*;
+
+  /// Image with smooth, uneven shading, so that its hash has both bits set and
+  /// unset.
+  #[allow(clippy::cast_precision_loss)]
+  fn shaded(width: usize, height: usize) -> GrayImage {
+    let pixels = (0..width * height)
+      .map(|i| {
+        let x = (i % width) as f32 / width as f32;
+        let y = (i / width) as f32 / height as f32;
+        128.0 + 100.0 * (x * 7.0).sin() * (y * 5.0).cos()
+      })
+      .collect();
+    GrayImage {
+      width,
+      height,
+      pixels,
+    }
+  }
+
+  /// Copy of `image` at half the size, averaging each 2×2 pixels.
+  fn downscale(image: &GrayImage) -> GrayImage {
+    let (width, height) = (image.width / 2, image.height / 2);
+    let at = |x: usize, y: usize| image.pixels[y * image.width + x];
+    let pixels = (0..width * height)
+      .map(|i| {
+        let (x, y) = (i % width * 2, i / width * 2);
+        (at(x, y) + at(x + 1, y) + at(x, y + 1) + at(x + 1, y + 1)) / 4.0
+      })
+      .collect();
+    GrayImage {
+      width,
+      height,
+      pixels,
+    }
+  }
+
+  fn mirror(image: &GrayImage) -> GrayImage {
+    let pixels = image
+      .pixels
+      .chunks_exact(image.width)
+      .flat_map(|row| row.iter().rev().copied())
+      .collect();
+    GrayImage {
+      pixels,
+      ..image.clone()
+    }
+  }
+
+  #[test]
+  fn matches_downscaled_copy() {
+    let image = shaded(144, 96);
+
+    assert!(distance(dhash(&image), dhash(&downscale(&image))) <= 2);
+    assert!(distance(dhash(&image), dhash(&mirror(&image))) > 20);
+  }
+
+  #[test]
+  fn clusters_image_with_downscaled_copy() {
+    let image = shaded(144, 96);
+    let hashes = [
+      dhash(&image),
+      dhash(&mirror(&image)),
+      dhash(&downscale(&image)),
+      dhash(&downscale(&downscale(&image))),
+    ];
+
+    assert_eq!(cluster(&hashes, 10), [vec![0, 2, 3]]);
+    assert!(cluster(&hashes[..2], 10).is_empty());
+  }
+}
+
+#[cfg(test)]
+mod test_near_first {
+  use super::*;
+
+  #[test]
+  fn excludes_hashes_only_chained_to_first() {
+    let hashes = [0, 0b1111, 0b1111_1111];
+
+    assert_eq!(cluster(&hashes, 4), [vec![0, 1, 2]]);
+    assert_eq!(near_first(&hashes, 4), [1]);
+  }
+}
+
+#[cfg(test)]
+mod test_dedupe_by_perceptual_hash {
+  use std::path::PathBuf;
+
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn reports_without_removing() {
+    let d = test_dir!(
+      "a.jpg": {},
+      "b.jpg": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.dedupe_by_perceptual_hash(10, false).unwrap();
+
+    assert_dir!(d, ["a.jpg", "b.jpg"]);
+    assert_eq!(o.take_warnings(), [Warning::NearDuplicates(vec![
+      PathBuf::from("./a.jpg"),
+      PathBuf::from("./b.jpg"),
+    ])]);
+  }
+
+  #[test]
+  fn removes_if_enabled() {
+    let d = test_dir!(
+      "a.jpg": {},
+      "a.jpg.xmp": {},
+      "b.jpg": {},
+      "b.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.dedupe_by_perceptual_hash(10, true).unwrap();
+
+    assert_dir!(d, ["a.jpg", "a.jpg.xmp"]);
+    assert_trash!(d, ["b.jpg", "b.jpg.xmp"]);
+  }
+}
@@ -0,0 +1,127 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Organizer final report: Summary of what each stage did over a run, for
+//! library callers.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use super::Organizer;
+
+/// Counts of what each stage did, recorded as they run, along with where files
+/// were moved to. Stages not run are left at zero.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct FinalReport {
+  /// Media files loaded, including from merged catalogs.
+  pub total_media:      usize,
+  /// Media moved to trash (along with their sidecars) by cleanup, or as
+  /// identical to others when moving.
+  pub removed:          usize,
+  /// Sidecars created for media without.
+  pub sidecars_created: usize,
+  /// Media whose metadata was updated.
+  pub metadata_updated: usize,
+  /// Files whose metadata was synchronized from another.
+  pub synchronized:     usize,
+  /// Media passing and failing validation, if run.
+  pub valid:            usize,
+  pub invalid:          usize,
+  /// Media moved and renamed.
+  pub moved:            usize,
+  /// Absolute paths of all files moved (media and sidecars), in the order
+  /// moved.
+  pub destinations:     Vec<PathBuf>,
+}
+
+impl FinalReport {
+  /// Records moving a media file, along with its sidecars, to `files_dst`.
+  pub(super) fn record_move(&mut self, files_dst: Vec<PathBuf>) {
+    self.moved += 1;
+    self.destinations.extend(files_dst);
+  }
+}
+
+impl Organizer {
+  /// Consumes the `Organizer`, returning what each stage run did, e.g. after
+  /// `move_and_rename_files`.
+  #[must_use]
+  pub fn into_report(self) -> FinalReport {
+    let (present, taken) = self.media.count_entries();
+
+    FinalReport {
+      total_media: present + taken,
+      ..self.report
+    }
+  }
+}
+
+#[cfg(test)]
+mod test_into_report {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn counts_only_loaded_media_if_no_stages_run() {
+    let d = test_dir!(
+      "image1.jpg": {},
+      "image1.jpg.xmp": {},
+      "image2.jpg": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+
+    assert_eq!(o.into_report(), FinalReport {
+      total_media: 2,
+      ..Default::default()
+    });
+  }
+
+  #[test]
+  fn counts_actions_of_each_stage() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image1.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image1_01.jpg.xmp": {},
+      "image2.jpg": { "DateTimeOriginal": "2000-01-02T00:00:00" },
+      "image3.jpg": {},
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.remove_live_photo_leftovers().unwrap();
+    o.create_missing_sidecars().unwrap();
+    o.sync_dupe_metadata().unwrap();
+    o.enable_required_tags_validation(["DateTimeOriginal".to_string()]);
+    o.validate();
+    o.enable_ordered_moves();
+    o.move_and_rename_files(d.root(), false).unwrap();
+    let report = o.into_report();
+
+    assert_eq!(
+      report
+        .destinations
+        .iter()
+        .map(|p| p.file_name().unwrap().to_str().unwrap())
+        .collect::<Vec<_>>(),
+      [
+        "000101_000000000_01.jpg.xmp",
+        "000101_000000000.jpg",
+        "000101_000000000.jpg.xmp",
+        "000102_000000000.jpg",
+        "000102_000000000.jpg.xmp",
+      ]
+    );
+    assert_eq!(report, FinalReport {
+      total_media:      4,
+      removed:          1,
+      sidecars_created: 2,
+      metadata_updated: 0,
+      synchronized:     1,
+      valid:            2,
+      invalid:          1,
+      moved:            2,
+      destinations:     report.destinations.clone(),
+    });
+  }
+}
@@ -2,14 +2,33 @@
 
 //! Organizer Stage 1: Automatic deletion of duplicates and leftovers.
 
-use std::{collections::HashMap, path::Path};
+use std::{
+  collections::{HashMap, HashSet},
+  io::{BufRead, Write},
+  path::{Path, PathBuf},
+};
+
+use chrono::{Local, TimeDelta};
 
-use super::Organizer;
+use super::{
+  Organizer,
+  Warning,
+  archive,
+  get_root,
+  hash_index::ContentHash,
+  stage_6_organization::{take_adjustment, take_dupes, take_sidecar},
+  to_abs_path,
+};
 use crate::{
+  error::CatalogError,
   io,
-  prim::{Handle, LivePhotoLinker, Media, Sidecar},
+  prim::{Codec, FileMap, Handle, LivePhotoLinker, Media, Sidecar},
 };
 
+/// Furthest apart in capture time a leftover Live Photo video and an image may
+/// be to relink them, as the video starts shortly before the image.
+const RELINK_MAX_SECONDS: i64 = 3;
+
 /// Allows using `LivePhotoLink::drain_images` and `drain_videos` as generics in
 /// `remove_live_photo_duplicates_by_type`, without calls to those functions
 /// borrowing `self` mutably past the point at which their returned iterators
@@ -33,29 +52,185 @@ where
   type Iter = I;
 }
 
+/// Which image of a raw+JPEG pair (e.g. from shooting RAW+JPEG)
+/// `Organizer::dedupe_across_formats` keeps.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum FormatToKeep {
+  #[default]
+  Raw,
+  Jpeg,
+}
+
+/// Decides whether files slated for removal are moved to trash, for
+/// `Organizer::confirm_removals`.
+pub trait Confirm {
+  /// Whether to remove `files`.
+  fn confirm(&mut self, files: &[PathBuf]) -> bool;
+}
+
+/// Lists files on stdout, and asks for `y` on stdin to remove them.
+pub struct PromptConfirm;
+
+impl Confirm for PromptConfirm {
+  fn confirm(&mut self, files: &[PathBuf]) -> bool {
+    for file in files {
+      println!("{}", file.display());
+    }
+    print!("Move {} files to trash? [y/N] ", files.len());
+    if std::io::stdout().flush().is_err() {
+      return false;
+    }
+
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+      return false;
+    }
+
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+  }
+}
+
 impl Organizer {
+  /// Lays out files moved to trash from here on as `layout`, instead of the
+  /// default of directly under trash. For `TrashLayout::Timestamped`, the
+  /// directory for this run is created now. Call after loading (and merging)
+  /// catalogs, which skip all of trash.
+  pub fn set_trash_layout(&mut self, layout: io::TrashLayout) -> Result<(), CatalogError> {
+    let (io::TrashLayout::Timestamped, Some(trash)) = (layout, &self.trash) else {
+      return Ok(());
+    };
+
+    let trash_run = io::make_trash_run_dir(trash, Local::now().naive_local())?;
+    log::info!(
+      "{}: Moving files to trash for this run.",
+      trash_run.display()
+    );
+    self.trash = Some(trash_run);
+
+    Ok(())
+  }
+
+  /// Moves `file` (absolute, or relative to `trash`) from `trash` back to where
+  /// it was removed from under `root`, e.g. to recover a file trashed by
+  /// mistake. Returns the restored path. See `io::restore_file`.
+  pub fn relocate_trash(
+    root: impl AsRef<Path>,
+    trash: impl AsRef<Path>,
+    file: impl AsRef<Path>,
+  ) -> Result<PathBuf, CatalogError> {
+    let trash = trash.as_ref();
+    let restored = io::restore_file(root, trash, to_abs_path(trash, file))?;
+    log::info!("{}: Restored from trash.", restored.display());
+
+    Ok(restored)
+  }
+
+  /// Holds files removed by later calls (e.g. `remove_sidecar_leftovers`) in
+  /// place, until moved to trash by `confirm_removals`.
+  pub fn enable_confirm_removals(&mut self) {
+    log::info!("Confirming removals before moving files to trash.");
+    self.pending_removals = Some(Vec::new());
+  }
+
+  /// Moves files held by `enable_confirm_removals` to trash, if `confirm`
+  /// accepts them. Otherwise, they are left in place, but not organized any
+  /// further.
+  pub fn confirm_removals(&mut self, confirm: &mut impl Confirm) -> Result<(), CatalogError> {
+    let Some(pending) = self.pending_removals.as_mut().map(std::mem::take) else {
+      return Ok(());
+    };
+
+    if pending.is_empty() {
+      log::debug!("No files to remove. Skipping.");
+      return Ok(());
+    }
+
+    if !confirm.confirm(&pending) {
+      log::info!("Keeping {} files slated for removal.", pending.len());
+      return Ok(());
+    }
+
+    for path in pending {
+      self.warnings.check(remove_by_path(
+        &self.source,
+        &self.merged,
+        path,
+        self.trash.as_ref(),
+        None,
+      ))?;
+    }
+
+    Ok(())
+  }
+
+  /// Before removing leftover Live Photo videos, links each to an image
+  /// without a video instead, if exactly one is in the same directory and
+  /// captured within a few seconds (e.g. after converting the image, dropping
+  /// its `ContentIdentifier`).
+  pub fn enable_relink_leftover_live_photos(&mut self) {
+    log::info!("Relinking leftover Live Photo videos to nearby images.");
+    self.relink_leftover_live_photos = true;
+  }
+
   /// Removes leftover Live Photos videos. These are video files that were part
   /// of a Live Photo, where the corresponding image no longer exists. It is
   /// assumed this means the image was purposefully deleted, and as such, so
   /// too should the video.
-  pub fn remove_live_photo_leftovers(&mut self) -> Result<(), String> {
+  pub fn remove_live_photo_leftovers(&mut self) -> Result<(), CatalogError> {
     log::info!("Removing videos from deleted Live Photo images.");
 
-    let (leftover, good): (HashMap<_, _>, HashMap<_, _>) = self
+    let (mut leftover, good): (HashMap<_, _>, HashMap<_, _>) = self
       .live_photo_map
       .drain()
       .partition(|(_, l)| l.is_leftover_videos());
 
     self.live_photo_map.extend(good);
 
+    if self.relink_leftover_live_photos {
+      let mut relinked = HashSet::new();
+
+      for link in leftover.values_mut() {
+        let video = link.get_video_best();
+        let Some(image) = find_image_to_relink(&self.media, video, &relinked) else {
+          continue;
+        };
+
+        self.warnings.push(Warning::RelinkedLivePhotoVideo(
+          self.media.get_path(video).unwrap().to_path_buf(),
+          self.media.get_path(image).unwrap().to_path_buf(),
+        ));
+        link.insert_image(image, &self.media[image]);
+        relinked.insert(image);
+      }
+
+      let relinked_links: HashMap<_, _>;
+      (leftover, relinked_links) = leftover
+        .into_iter()
+        .partition(|(_, l)| l.is_leftover_videos());
+      self.live_photo_map.extend(relinked_links);
+    }
+
     for (_, mut link) in leftover {
       for media_handle in link.drain() {
-        let media = self
-          .media
-          .get_entry_mut(media_handle)
-          .take()
-          .ok_or(format!("Cannot find media handle `{media_handle}` in map."))?;
-        remove_by_path(&self.source, media, self.trash.as_ref())?;
+        let media =
+          self
+            .media
+            .get_entry_mut(media_handle)
+            .take()
+            .ok_or(CatalogError::InvalidFile(format!(
+              "Cannot find media handle `{media_handle}` in map."
+            )))?;
+        self.report.removed += 1;
+        self.warnings.push(Warning::LeftoverLivePhotoVideo(
+          media.as_ref().to_path_buf(),
+        ));
+        self.warnings.check(remove_by_path(
+          &self.source,
+          &self.merged,
+          media,
+          self.trash.as_ref(),
+          self.pending_removals.as_mut(),
+        ))?;
       }
     }
 
@@ -68,7 +243,7 @@ impl Organizer {
   /// date, assuming that duplicates generally come from downloads
   /// being converted from their original formats (e.g. HEIC) to those more
   /// "compatible" (e.g. JPEG).
-  pub fn remove_live_photo_duplicates(&mut self) -> Result<(), String> {
+  pub fn remove_live_photo_duplicates(&mut self) -> Result<(), CatalogError> {
     log::info!("Removing Live Photo duplicates.");
 
     self.remove_live_photo_duplicates_by_type(
@@ -94,7 +269,7 @@ impl Organizer {
     get: fn(&LivePhotoLinker) -> Handle<Media>,
     drain: impl for<'a> DrainFn<'a>,
     insert: fn(&mut LivePhotoLinker, Handle<Media>, &Media),
-  ) -> Result<(), String> {
+  ) -> Result<(), CatalogError> {
     for link in self.live_photo_map.values_mut() {
       if !has_duplicates(link) {
         continue;
@@ -111,8 +286,20 @@ impl Organizer {
           .media
           .get_entry_mut(removed)
           .take()
-          .ok_or(format!("Cannot find media handle `{removed}` in map."))?;
-        remove_by_path(&self.source, media, self.trash.as_ref())?;
+          .ok_or(CatalogError::InvalidFile(format!(
+            "Cannot find media handle `{removed}` in map."
+          )))?;
+        self.report.removed += 1;
+        self
+          .warnings
+          .push(Warning::DuplicateLivePhoto(media.as_ref().to_path_buf()));
+        self.warnings.check(remove_by_path(
+          &self.source,
+          &self.merged,
+          media,
+          self.trash.as_ref(),
+          self.pending_removals.as_mut(),
+        ))?;
       }
 
       insert(link, handle, &self.media[handle]);
@@ -121,20 +308,332 @@ impl Organizer {
     Ok(())
   }
 
+  /// Removes all but one frame of each burst, as grouped by the `BurstUUID`
+  /// tag, along with their sidecars. The highest rated frame is kept, or the
+  /// first by name if tied. Rating is read from the sidecar, if present.
+  pub fn collapse_bursts(&mut self) -> Result<(), CatalogError> {
+    log::info!("Collapsing bursts.");
+
+    let mut removed = Vec::new();
+    for frames in self.burst_map.values_mut() {
+      if frames.len() < 2 {
+        continue;
+      }
+
+      let rating = |h: &Handle<Media>| {
+        let media = &self.media[*h];
+        media
+          .get_sidecar()
+          .map_or(media.get_metadata(), |s| self.sidecars[s].get_metadata())
+          .rating
+          .unwrap_or_default()
+      };
+
+      frames.sort_by(|a, b| self.media[*a].as_ref().cmp(self.media[*b].as_ref()));
+      let keep = *frames
+        .iter()
+        .min_by(|a, b| rating(b).total_cmp(&rating(a)))
+        .unwrap();
+
+      removed.extend(frames.drain(..).filter(|h| *h != keep));
+      frames.push(keep);
+    }
+
+    for handle in removed {
+      self.warnings.push(Warning::DuplicateBurstFrame(
+        self.media[handle].as_ref().to_path_buf(),
+      ));
+      self.remove_media(handle)?;
+    }
+
+    Ok(())
+  }
+
+  /// Removes media with the same contents as other media (e.g. the same photo
+  /// imported twice), along with its sidecars, keeping the first by path
+  /// unless only the other has edits (see `is_redundant_with`). Copies with
+  /// differing edits are all kept. Live Photo components and burst frames are
+  /// left to their own passes. Contents are compared by hashes indexed up front
+  /// (see `index_hashes`), which are shared with trashing identical media when
+  /// moving, then byte by byte before removing anything.
+  pub fn remove_identical_media(&mut self) -> Result<(), CatalogError> {
+    self.index_hashes()?;
+
+    log::info!("Removing media with identical contents.");
+
+    // Media kept so far, by contents.
+    let mut kept = HashMap::<ContentHash, Vec<Handle<Media>>>::new();
+
+    for handle in self.ungrouped_media() {
+      let path = to_abs_path(&self.source, &self.media[handle]);
+      let Some(hash) = self.warnings.check(self.hash_index.get(handle, &path))? else {
+        continue;
+      };
+      let kept = kept.entry(hash).or_default();
+
+      let mut removed = None;
+      for other in kept.iter_mut() {
+        let path_other = to_abs_path(&self.source, &self.media[*other]);
+        let same = archive::same_contents(&path, &path_other);
+        if self.warnings.check(same)? != Some(true) {
+          continue;
+        }
+
+        if self.is_redundant_with(handle, *other) {
+          removed = Some((handle, *other));
+        } else if self.is_redundant_with(*other, handle) {
+          removed = Some((*other, handle));
+          *other = handle;
+        } else {
+          continue;
+        }
+        break;
+      }
+
+      let Some((removed, identical)) = removed else {
+        kept.push(handle);
+        continue;
+      };
+
+      self.warnings.push(Warning::IdenticalMedia(
+        self.media[removed].as_ref().to_path_buf(),
+        self.media[identical].as_ref().to_path_buf(),
+      ));
+      self.remove_media(removed)?;
+    }
+
+    Ok(())
+  }
+
+  /// Whether removing media `handle` in favor of media `other` with the same
+  /// contents loses no edits, i.e. it has no duplicate sidecars or adjustment,
+  /// and either no initial sidecar or one with the same tags as `other`'s.
+  pub(crate) fn is_redundant_with(&self, handle: Handle<Media>, other: Handle<Media>) -> bool {
+    let media = &self.media[handle];
+    let sidecar = |m: &Media| m.get_sidecar().map(|h| self.sidecars[h].get_metadata());
+
+    media.iter_dupes().next().is_none()
+      && media.get_adjustment().is_none()
+      && sidecar(media)
+        .is_none_or(|s| sidecar(&self.media[other]).is_some_and(|s_other| s.has_same_tags(s_other)))
+  }
+
+  /// Removes one image of each raw+JPEG pair captured together (e.g. when
+  /// shooting RAW+JPEG), along with its sidecars, keeping the format `keep`.
+  /// Images are paired by stem in the same directory, else by capture time
+  /// (including sub-seconds, if any) in the same directory from the same camera
+  /// (by `Make` and `Model`, which must be set), each at most once. Live Photo
+  /// components and burst frames are left to their own passes.
+  pub fn dedupe_across_formats(&mut self, keep: FormatToKeep) -> Result<(), CatalogError> {
+    log::info!("Removing one image of each raw+JPEG pair, keeping {keep:?}.");
+
+    let (raws, jpegs): (Vec<_>, Vec<_>) = self
+      .ungrouped_media()
+      .into_iter()
+      .filter(|h| matches!(self.media[*h].get_codec(), Codec::Raw | Codec::JPEG))
+      .partition(|h| self.media[*h].get_codec() == Codec::Raw);
+
+    let stem = |h: Handle<Media>| self.media[h].as_ref().with_extension("");
+    let shot = |h: Handle<Media>| {
+      let media = &self.media[h];
+      let metadata = media.get_metadata();
+      Some((
+        media.as_ref().parent()?,
+        metadata.make.as_deref()?,
+        metadata.model.as_deref()?,
+        metadata.get_date_time_original()?.0,
+      ))
+    };
+
+    let mut by_stem = HashMap::<_, Vec<_>>::new();
+    let mut by_shot = HashMap::<_, Vec<_>>::new();
+    for jpeg in jpegs {
+      by_stem.entry(stem(jpeg)).or_default().push(jpeg);
+      if let Some(shot) = shot(jpeg) {
+        by_shot.entry(shot).or_default().push(jpeg);
+      }
+    }
+
+    let mut paired = HashSet::new();
+    let mut pairs = Vec::new();
+    for raw in raws {
+      let candidates = by_stem
+        .get(&stem(raw))
+        .into_iter()
+        .chain(shot(raw).and_then(|s| by_shot.get(&s)))
+        .flatten();
+      let Some(jpeg) = candidates.copied().find(|j| !paired.contains(j)) else {
+        continue;
+      };
+      paired.insert(jpeg);
+      pairs.push((raw, jpeg));
+    }
+
+    for (raw, jpeg) in pairs {
+      let (removed, kept) = match keep {
+        FormatToKeep::Raw => (jpeg, raw),
+        FormatToKeep::Jpeg => (raw, jpeg),
+      };
+      self.warnings.push(Warning::PairedInOtherFormat(
+        self.media[removed].as_ref().to_path_buf(),
+        self.media[kept].as_ref().to_path_buf(),
+      ));
+      self.remove_media(removed)?;
+    }
+
+    Ok(())
+  }
+
+  /// Gets media which is neither a Live Photo component nor a burst frame,
+  /// which are left to their own passes, sorted by path.
+  pub(crate) fn ungrouped_media(&self) -> Vec<Handle<Media>> {
+    let grouped = self
+      .live_photo_map
+      .values()
+      .flat_map(|l| l.iter_images().chain(l.iter_videos()))
+      .chain(self.burst_map.values().flatten().copied())
+      .collect::<HashSet<_>>();
+    let mut handles = self
+      .media
+      .iter_data_indexed()
+      .map(|(h, _)| h)
+      .filter(|h| !grouped.contains(h))
+      .collect::<Vec<_>>();
+    handles.sort_by(|a, b| self.media[*a].as_ref().cmp(self.media[*b].as_ref()));
+    handles
+  }
+
+  /// Removes media `handle` from the catalog, moving it to trash along with
+  /// its sidecars and adjustment.
+  pub(crate) fn remove_media(&mut self, handle: Handle<Media>) -> Result<(), CatalogError> {
+    let media = self.media.get_entry_mut(handle).take().unwrap();
+    self.report.removed += 1;
+    let sidecar = take_sidecar(&media, &mut self.sidecars);
+    let dupes = take_dupes(&media, &mut self.dupes);
+    let adjustment = take_adjustment(&media, &mut self.adjustments);
+
+    let files = [media.as_ref().to_path_buf()]
+      .into_iter()
+      .chain(sidecar.iter().map(|s| s.as_ref().to_path_buf()))
+      .chain(dupes.iter().map(|d| d.as_ref().to_path_buf()))
+      .chain(adjustment.iter().map(|a| a.as_ref().to_path_buf()));
+    for file in files {
+      self.warnings.check(remove_by_path(
+        &self.source,
+        &self.merged,
+        file,
+        self.trash.as_ref(),
+        self.pending_removals.as_mut(),
+      ))?;
+    }
+
+    Ok(())
+  }
+
+  /// Removes duplicate sidecars holding the same tags as their media file's
+  /// initial sidecar (e.g. after synchronization), as compared by
+  /// `Metadata::has_same_tags` rather than bytes to ignore formatting and file
+  /// properties.
+  pub fn deduplicate_sidecars(&mut self) -> Result<(), CatalogError> {
+    log::info!("Removing duplicate sidecars same as the initial sidecar.");
+
+    for media in self.media.iter_data_mut() {
+      let Some(sidecar) = media.get_sidecar().map(|h| &self.sidecars[h]) else {
+        continue;
+      };
+
+      let redundant = media
+        .iter_dupes()
+        .filter(|h| {
+          self.dupes[*h]
+            .get_metadata()
+            .has_same_tags(sidecar.get_metadata())
+        })
+        .collect::<Vec<_>>();
+
+      for handle in redundant {
+        media.remove_dupe(handle);
+
+        let dupe = self
+          .dupes
+          .get_entry_mut(handle)
+          .take()
+          .ok_or(CatalogError::InvalidFile(format!(
+            "Cannot find dupe handle `{handle}` in map."
+          )))?;
+        self
+          .warnings
+          .push(Warning::RedundantDupe(dupe.as_ref().to_path_buf()));
+        self.warnings.check(remove_by_path(
+          &self.source,
+          &self.merged,
+          dupe,
+          self.trash.as_ref(),
+          self.pending_removals.as_mut(),
+        ))?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Keeps leftover XMP sidecars in place (e.g. for media stored elsewhere),
+  /// only reporting them, instead of removing them.
+  pub fn enable_keep_leftover_sidecars(&mut self) {
+    log::info!("Keeping leftover XMP sidecars.");
+    self.keep_leftover_sidecars = true;
+  }
+
   /// Removes leftover XMP sidecars. These are sidecars that no longer have a
   /// corresponding media file, assumably because it was deleted on purpose.
-  pub fn remove_sidecar_leftovers(&mut self) -> Result<(), String> {
+  pub fn remove_sidecar_leftovers(&mut self) -> Result<(), CatalogError> {
+    if self.keep_leftover_sidecars {
+      log::info!("Reporting XMP sidecars missing associated media files.");
+
+      for sidecar in self.sidecars.iter_data().filter(|s| s.is_leftover()) {
+        self
+          .warnings
+          .push(Warning::LeftoverSidecar(sidecar.as_ref().to_path_buf()));
+      }
+
+      for sidecar in self.dupes.iter_data().filter(|s| s.is_leftover()) {
+        self
+          .warnings
+          .push(Warning::LeftoverSidecar(sidecar.as_ref().to_path_buf()));
+      }
+
+      return Ok(());
+    }
+
     log::info!("Removing XMP sidecars missing associated media files.");
 
     for sidecar in self.sidecars.iter_entries_mut() {
       if let Some(sidecar) = sidecar.take_if(|s| s.is_leftover()) {
-        remove_by_path(&self.source, sidecar, self.trash.as_ref())?;
+        self
+          .warnings
+          .push(Warning::LeftoverSidecar(sidecar.as_ref().to_path_buf()));
+        self.warnings.check(remove_by_path(
+          &self.source,
+          &self.merged,
+          sidecar,
+          self.trash.as_ref(),
+          self.pending_removals.as_mut(),
+        ))?;
       }
     }
 
     for sidecar in self.dupes.iter_entries_mut() {
       if let Some(sidecar) = sidecar.take_if(|s| s.is_leftover()) {
-        remove_by_path(&self.source, sidecar, self.trash.as_ref())?;
+        self
+          .warnings
+          .push(Warning::LeftoverSidecar(sidecar.as_ref().to_path_buf()));
+        self.warnings.check(remove_by_path(
+          &self.source,
+          &self.merged,
+          sidecar,
+          self.trash.as_ref(),
+          self.pending_removals.as_mut(),
+        ))?;
       }
     }
 
@@ -142,25 +641,129 @@ impl Organizer {
   }
 }
 
+/// Finds the image to link leftover Live Photo `video` to: The only image in
+/// the same directory which could be a Live Photo image, but is not part of
+/// one (nor already `relinked`), captured within `RELINK_MAX_SECONDS`.
+fn find_image_to_relink(
+  media_map: &FileMap<Media>,
+  video: Handle<Media>,
+  relinked: &HashSet<Handle<Media>>,
+) -> Option<Handle<Media>> {
+  let dir = media_map.get_path(video)?.parent();
+  let video_metadata = media_map[video].get_metadata();
+
+  let mut candidates = media_map.iter_data_indexed().filter(|(handle, media)| {
+    if relinked.contains(handle)
+      || media.content_id().is_some()
+      || !matches!(media.get_codec(), Codec::HEIC | Codec::JPEG)
+      || media_map.get_path(*handle).unwrap().parent() != dir
+    {
+      return false;
+    }
+
+    video_metadata
+      .get_capture_time_difference(media.get_metadata())
+      .is_some_and(|d| d.abs() <= TimeDelta::seconds(RELINK_MAX_SECONDS))
+  });
+
+  let (image, _) = candidates.next()?;
+  candidates.next().is_none().then_some(image)
+}
+
 /// Remove a file to `trash`, if `Some`, preserving relative path from the
-/// scanned input directory.
-fn remove_by_path(
+/// scanned input directory (or merged catalog, if within one of `merged`). If
+/// `pending` is `Some`, the file is only added to it, to be removed once
+/// confirmed.
+pub fn remove_by_path(
   root: impl AsRef<Path>,
+  merged: &[PathBuf],
   path_relative: impl AsRef<Path>,
   trash: Option<impl AsRef<Path>>,
-) -> Result<(), String> {
+  pending: Option<&mut Vec<PathBuf>>,
+) -> Result<(), CatalogError> {
   if let Some(trash) = trash {
-    log::warn!("{}: Moving to trash.", path_relative.as_ref().display());
-    io::remove_file(&root, trash, root.as_ref().join(path_relative))?;
+    let path = root.as_ref().join(&path_relative);
+
+    if let Some(pending) = pending {
+      pending.push(path);
+      return Ok(());
+    }
+
+    log::info!("{}: Moving to trash.", path_relative.as_ref().display());
+    io::remove_file(get_root(root.as_ref(), merged, &path), trash, &path)?;
   }
 
   Ok(())
 }
 
+#[cfg(test)]
+mod test_deduplicate_sidecars {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn keeps_differing_dupe() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": { "Rating": "1" },
+      "image_01.jpg.xmp": { "Rating": "5" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.deduplicate_sidecars().unwrap();
+
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp", "image_01.jpg.xmp"]);
+  }
+
+  #[test]
+  fn keeps_dupe_differing_in_other_tag() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": { "Rating": "1", "Title": "Title" },
+      "image_01.jpg.xmp": { "Rating": "1", "Title": "Other" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.deduplicate_sidecars().unwrap();
+
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp", "image_01.jpg.xmp"]);
+    assert_trash!(d, []);
+  }
+
+  #[test]
+  fn keeps_dupe_without_initial_sidecar() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image_01.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.deduplicate_sidecars().unwrap();
+
+    assert_dir!(d, ["image.jpg", "image_01.jpg.xmp"]);
+  }
+
+  #[test]
+  fn removes_identical_dupe() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": { "Rating": "1", "Creator": "Creator" },
+      "image_01.jpg.xmp": { "Rating": "1", "Creator": "Creator" },
+      "image_02.jpg.xmp": { "Rating": "1", "Creator": "Other" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.deduplicate_sidecars().unwrap();
+
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp", "image_02.jpg.xmp"]);
+    assert_trash!(d, ["image_01.jpg.xmp"]);
+  }
+}
+
 #[cfg(test)]
 mod test_remove_live_photo_leftovers {
   use super::*;
-  use crate::testing::*;
+  use crate::{org::ScanConfig, testing::*};
 
   #[test]
   fn keeps_non_live_photo() {
@@ -168,7 +771,7 @@ mod test_remove_live_photo_leftovers {
       "image_not_live.jpg": {},
     );
 
-    let mut o = Organizer::load_catalog(d.root(), d.some_trash()).unwrap();
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
     o.remove_live_photo_leftovers().unwrap();
 
     assert_dir!(d, ["image_not_live.jpg"]);
@@ -181,7 +784,7 @@ mod test_remove_live_photo_leftovers {
       "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
     );
 
-    let mut o = Organizer::load_catalog(d.root(), d.some_trash()).unwrap();
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
     o.remove_live_photo_leftovers().unwrap();
 
     assert_dir!(d, ["image.heic", "video.mov"]);
@@ -193,7 +796,7 @@ mod test_remove_live_photo_leftovers {
       "image_leftover.heic": { "ContentIdentifier": "ID" },
     );
 
-    let mut o = Organizer::load_catalog(d.root(), d.some_trash()).unwrap();
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
     o.remove_live_photo_leftovers().unwrap();
 
     assert_dir!(d, ["image_leftover.heic"]);
@@ -205,18 +808,100 @@ mod test_remove_live_photo_leftovers {
       "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
     );
 
-    let mut o = Organizer::load_catalog(d.root(), d.some_trash()).unwrap();
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
     o.remove_live_photo_leftovers().unwrap();
 
     assert_dir!(d, []);
     assert_trash!(d, ["video.mov"]);
   }
+
+  #[test]
+  fn relinks_to_nearby_image_if_enabled() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:02" },
+      "video.mov": {
+        "ContentIdentifier": "ID",
+        "CompressorID": "hvc1",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_relink_leftover_live_photos();
+    o.remove_live_photo_leftovers().unwrap();
+
+    assert_dir!(d, ["image.jpg", "video.mov"]);
+    assert_trash!(d, []);
+    assert_eq!(o.take_warnings(), [Warning::RelinkedLivePhotoVideo(
+      d.root().join("video.mov"),
+      d.root().join("image.jpg"),
+    )]);
+    assert_eq!(o.live_photo_map.len(), 1);
+  }
+
+  #[test]
+  fn removes_if_no_nearby_image() {
+    let d = test_dir!(
+      "dir/image_other_dir.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image_later.jpg": { "DateTimeOriginal": "2000-01-01T00:01:00" },
+      "video.mov": {
+        "ContentIdentifier": "ID",
+        "CompressorID": "hvc1",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_relink_leftover_live_photos();
+    o.remove_live_photo_leftovers().unwrap();
+
+    assert_dir!(d, ["dir/image_other_dir.jpg", "image_later.jpg"]);
+    assert_trash!(d, ["video.mov"]);
+  }
+
+  #[test]
+  fn removes_if_multiple_nearby_images() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:01" },
+      "image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:02" },
+      "video.mov": {
+        "ContentIdentifier": "ID",
+        "CompressorID": "hvc1",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_relink_leftover_live_photos();
+    o.remove_live_photo_leftovers().unwrap();
+
+    assert_dir!(d, ["image1.jpg", "image2.jpg"]);
+    assert_trash!(d, ["video.mov"]);
+  }
+
+  #[test]
+  fn removes_without_relinking_by_default() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "video.mov": {
+        "ContentIdentifier": "ID",
+        "CompressorID": "hvc1",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.remove_live_photo_leftovers().unwrap();
+
+    assert_dir!(d, ["image.jpg"]);
+    assert_trash!(d, ["video.mov"]);
+  }
 }
 
 #[cfg(test)]
 mod test_remove_live_photo_duplicates {
   use super::*;
-  use crate::testing::*;
+  use crate::{org::ScanConfig, testing::*};
 
   #[test]
   fn keeps_heic_over_jpg() {
@@ -226,7 +911,7 @@ mod test_remove_live_photo_duplicates {
       "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
     );
 
-    let mut o = Organizer::load_catalog(d.root(), d.some_trash()).unwrap();
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
     o.remove_live_photo_duplicates().unwrap();
 
     assert_dir!(d, ["image.heic", "video.mov"]);
@@ -241,7 +926,7 @@ mod test_remove_live_photo_duplicates {
       "video_bad.mov": { "ContentIdentifier": "ID", "CompressorID": "avc1" },
     );
 
-    let mut o = Organizer::load_catalog(d.root(), d.some_trash()).unwrap();
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
     o.remove_live_photo_duplicates().unwrap();
 
     assert_dir!(d, ["image.heic", "video_good.mov"]);
@@ -265,7 +950,7 @@ mod test_remove_live_photo_duplicates {
       },
     );
 
-    let mut o = Organizer::load_catalog(d.root(), d.some_trash()).unwrap();
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
     o.remove_live_photo_duplicates().unwrap();
 
     assert_dir!(d, ["image_new.heic", "video_new.mov"]);
@@ -283,17 +968,97 @@ mod test_remove_live_photo_duplicates {
       },
     );
 
-    let mut o = Organizer::load_catalog(d.root(), d.some_trash()).unwrap();
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
     o.remove_live_photo_duplicates().unwrap();
 
     assert_dir!(d, ["image.heic", "video.mov"]);
   }
+
+  #[test]
+  fn keeps_raw_alongside_live_photo() {
+    let d = test_dir!(
+      "image.dng": {},
+      "image.jpg": { "ContentIdentifier": "ID" },
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.remove_live_photo_duplicates().unwrap();
+
+    assert_dir!(d, ["image.dng", "image.jpg", "video.mov"]);
+  }
+}
+
+#[cfg(test)]
+mod test_collapse_bursts {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn keeps_first_frame_if_unrated() {
+    let d = test_dir!(
+      "image1.jpg": { "BurstUUID": "ID" },
+      "image2.jpg": { "BurstUUID": "ID" },
+      "image3.jpg": { "BurstUUID": "ID" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.collapse_bursts().unwrap();
+
+    assert_dir!(d, ["image1.jpg"]);
+    assert_trash!(d, ["image2.jpg", "image3.jpg"]);
+  }
+
+  #[test]
+  fn keeps_highest_rated_frame() {
+    let d = test_dir!(
+      "image1.jpg": { "BurstUUID": "ID" },
+      "image2.jpg": { "BurstUUID": "ID" },
+      "image2.jpg.xmp": { "Rating": "5" },
+      "image3.jpg": { "BurstUUID": "ID", "Rating": "3" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.collapse_bursts().unwrap();
+
+    assert_dir!(d, ["image2.jpg", "image2.jpg.xmp"]);
+    assert_trash!(d, ["image1.jpg", "image3.jpg"]);
+  }
+
+  #[test]
+  fn removes_sidecars_of_removed_frames() {
+    let d = test_dir!(
+      "image1.jpg": { "BurstUUID": "ID" },
+      "image2.jpg": { "BurstUUID": "ID" },
+      "image2.jpg.xmp": {},
+      "image2_01.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.collapse_bursts().unwrap();
+
+    assert_dir!(d, ["image1.jpg"]);
+    assert_trash!(d, ["image2.jpg", "image2.jpg.xmp", "image2_01.jpg.xmp"]);
+  }
+
+  #[test]
+  fn keeps_separate_bursts() {
+    let d = test_dir!(
+      "image1.jpg": { "BurstUUID": "ID1" },
+      "image2.jpg": { "BurstUUID": "ID2" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.collapse_bursts().unwrap();
+
+    assert_dir!(d, ["image1.jpg", "image2.jpg"]);
+  }
 }
 
 #[cfg(test)]
 mod test_remove_sidecar_leftovers {
   use super::*;
-  use crate::testing::*;
+  use crate::{org::ScanConfig, testing::*};
 
   #[test]
   fn keeps_paired_sidecar() {
@@ -302,7 +1067,7 @@ mod test_remove_sidecar_leftovers {
       "image.jpg.xmp": {},
     );
 
-    let mut o = Organizer::load_catalog(d.root(), d.some_trash()).unwrap();
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
     o.remove_sidecar_leftovers().unwrap();
 
     assert_dir!(d, ["image.jpg", "image.jpg.xmp"]);
@@ -315,19 +1080,53 @@ mod test_remove_sidecar_leftovers {
       "image_01.jpg.xmp": {},
     );
 
-    let mut o = Organizer::load_catalog(d.root(), d.some_trash()).unwrap();
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
     o.remove_sidecar_leftovers().unwrap();
 
     assert_dir!(d, ["image.jpg", "image_01.jpg.xmp",]);
   }
 
+  #[test]
+  fn keeps_leftovers_if_enabled() {
+    let d = test_dir!(
+      "image.jpg.xmp": {},
+      "image_01.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_keep_leftover_sidecars();
+    o.remove_sidecar_leftovers().unwrap();
+
+    assert_dir!(d, ["image.jpg.xmp", "image_01.jpg.xmp"]);
+    assert_trash!(d, []);
+    assert_eq!(o.take_warnings().len(), 2);
+  }
+
   #[test]
   fn removes_leftover_sidecar() {
     let d = test_dir!(
       "image.jpg.xmp": {},
     );
 
-    let mut o = Organizer::load_catalog(d.root(), d.some_trash()).unwrap();
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.remove_sidecar_leftovers().unwrap();
+
+    assert_dir!(d, []);
+    assert_trash!(d, ["image.jpg.xmp"]);
+  }
+
+  #[test]
+  fn removes_leftover_sidecar_to_external_trash() {
+    let d = test_dir!(
+      "catalog/image.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(
+      d.get_path("catalog"),
+      d.some_trash(),
+      &ScanConfig::default(),
+    )
+    .unwrap();
     o.remove_sidecar_leftovers().unwrap();
 
     assert_dir!(d, []);
@@ -340,10 +1139,371 @@ mod test_remove_sidecar_leftovers {
       "image_01.jpg.xmp": {},
     );
 
-    let mut o = Organizer::load_catalog(d.root(), d.some_trash()).unwrap();
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
     o.remove_sidecar_leftovers().unwrap();
 
     assert_dir!(d, []);
     assert_trash!(d, ["image_01.jpg.xmp"]);
   }
 }
+
+#[cfg(test)]
+mod test_confirm_removals {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  /// Answers with `accept`, recording the files asked about.
+  struct Scripted {
+    accept: bool,
+    asked:  Vec<PathBuf>,
+  }
+
+  impl Confirm for Scripted {
+    fn confirm(&mut self, files: &[PathBuf]) -> bool {
+      self.asked.extend_from_slice(files);
+      self.accept
+    }
+  }
+
+  #[test]
+  fn removes_if_accepted() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+      "leftover.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_confirm_removals();
+    o.remove_sidecar_leftovers().unwrap();
+
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp", "leftover.jpg.xmp"]);
+
+    let mut confirm = Scripted {
+      accept: true,
+      asked:  Vec::new(),
+    };
+    o.confirm_removals(&mut confirm).unwrap();
+
+    assert_eq!(confirm.asked, [d.get_path("leftover.jpg.xmp")]);
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp"]);
+    assert_trash!(d, ["leftover.jpg.xmp"]);
+  }
+
+  #[test]
+  fn keeps_if_declined() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+      "leftover.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_confirm_removals();
+    o.remove_sidecar_leftovers().unwrap();
+
+    let mut confirm = Scripted {
+      accept: false,
+      asked:  Vec::new(),
+    };
+    o.confirm_removals(&mut confirm).unwrap();
+
+    assert_eq!(confirm.asked, [d.get_path("leftover.jpg.xmp")]);
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp", "leftover.jpg.xmp"]);
+    assert_trash!(d, []);
+  }
+
+  #[test]
+  fn skips_asking_if_nothing_to_remove() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_confirm_removals();
+    o.remove_sidecar_leftovers().unwrap();
+
+    let mut confirm = Scripted {
+      accept: false,
+      asked:  Vec::new(),
+    };
+    o.confirm_removals(&mut confirm).unwrap();
+
+    assert!(confirm.asked.is_empty());
+  }
+}
+
+#[cfg(test)]
+mod test_set_trash_layout {
+  use std::fs;
+
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  /// Loads the catalog in `d`, then trashes its leftover sidecars.
+  fn trash_leftovers(d: &TestDir, layout: io::TrashLayout) {
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.set_trash_layout(layout).unwrap();
+    o.remove_sidecar_leftovers().unwrap();
+  }
+
+  #[test]
+  fn trashes_directly_if_flat() {
+    let d = test_dir!(
+      "image.jpg.xmp": {},
+    );
+
+    trash_leftovers(&d, io::TrashLayout::Flat);
+
+    assert_dir!(d, []);
+    assert_trash!(d, ["image.jpg.xmp"]);
+  }
+
+  #[test]
+  fn trashes_same_name_across_runs_if_timestamped() {
+    let d = test_dir!(
+      "image.jpg.xmp": {},
+    );
+
+    trash_leftovers(&d, io::TrashLayout::Timestamped);
+    let trashed = d.files_trash().into_iter().next().unwrap();
+    fs::copy(&trashed, d.get_path("image.jpg.xmp")).unwrap();
+    trash_leftovers(&d, io::TrashLayout::Timestamped);
+
+    let trashed = d.files_trash();
+    let runs = trashed
+      .iter()
+      .map(|p| p.parent().unwrap())
+      .collect::<HashSet<_>>();
+
+    assert_dir!(d, []);
+    assert_eq!(trashed.len(), 2);
+    assert_eq!(runs.len(), 2);
+    assert!(trashed.iter().all(|p| p.ends_with("image.jpg.xmp")));
+    assert!(runs.iter().all(|r| r.parent() == Some(d.trash())));
+  }
+}
+
+#[cfg(test)]
+mod test_remove_identical_media {
+  use std::fs;
+
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn removes_identical_media_with_sidecars() {
+    let d = test_dir!(
+      "a/image.jpg": {},
+      "a/image.jpg.xmp": { "Rating": "1" },
+      "b/image.jpg.xmp": { "Rating": "1" },
+    );
+    fs::create_dir_all(d.get_path("b")).unwrap();
+    fs::copy(d.get_path("a/image.jpg"), d.get_path("b/image.jpg")).unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.remove_identical_media().unwrap();
+
+    assert_eq!(o.take_warnings(), [Warning::IdenticalMedia(
+      PathBuf::from("./b/image.jpg"),
+      PathBuf::from("./a/image.jpg"),
+    )]);
+    assert_dir!(d, ["a/image.jpg", "a/image.jpg.xmp"]);
+    assert_trash!(d, ["b/image.jpg", "b/image.jpg.xmp"]);
+  }
+
+  #[test]
+  fn keeps_identical_media_with_edits() {
+    let d = test_dir!(
+      "a/image.jpg": {},
+      "b/image.jpg.xmp": { "HistoryOperation": "exposure" },
+      "b/image_01.jpg.xmp": {},
+    );
+    fs::create_dir_all(d.get_path("b")).unwrap();
+    fs::copy(d.get_path("a/image.jpg"), d.get_path("b/image.jpg")).unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.remove_identical_media().unwrap();
+
+    assert_eq!(o.take_warnings(), [Warning::IdenticalMedia(
+      PathBuf::from("./a/image.jpg"),
+      PathBuf::from("./b/image.jpg"),
+    )]);
+    assert_dir!(d, ["b/image.jpg", "b/image.jpg.xmp", "b/image_01.jpg.xmp"]);
+    assert_trash!(d, ["a/image.jpg"]);
+  }
+
+  #[test]
+  fn keeps_identical_media_with_differing_edits() {
+    let d = test_dir!(
+      "a/image.jpg": {},
+      "a/image.jpg.xmp": { "Rating": "1" },
+      "b/image.jpg.xmp": { "Rating": "5" },
+    );
+    fs::create_dir_all(d.get_path("b")).unwrap();
+    fs::copy(d.get_path("a/image.jpg"), d.get_path("b/image.jpg")).unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.remove_identical_media().unwrap();
+
+    assert!(o.take_warnings().is_empty());
+    assert_trash!(d, []);
+  }
+
+  #[test]
+  fn keeps_different_media() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:01" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.remove_identical_media().unwrap();
+
+    assert_dir!(d, ["image1.jpg", "image2.jpg"]);
+  }
+
+  #[test]
+  fn keeps_same_media_as_trashing_identical_when_moving() {
+    for remove_first in [true, false] {
+      let d = test_dir!(
+        "b/image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      );
+      fs::create_dir(d.get_path("a")).unwrap();
+      fs::copy(d.get_path("b/image.jpg"), d.get_path("a/image.jpg")).unwrap();
+
+      let mut o =
+        Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+      if remove_first {
+        o.remove_identical_media().unwrap();
+      }
+      o.enable_trash_identical_collisions();
+      o.move_and_rename_files(d.root(), true).unwrap();
+
+      assert_dir!(d, ["2000/01/000101_000000000.jpg"]);
+      assert_trash!(d, ["b/image.jpg"]);
+    }
+  }
+}
+
+#[cfg(test)]
+mod test_dedupe_across_formats {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn keeps_raw_and_trashes_jpeg_with_same_stem() {
+    let d = test_dir!(
+      "image.dng": {},
+      "image.dng.xmp": {},
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.dedupe_across_formats(FormatToKeep::Raw).unwrap();
+
+    assert_eq!(o.take_warnings(), [Warning::PairedInOtherFormat(
+      PathBuf::from("./image.jpg"),
+      PathBuf::from("./image.dng"),
+    )]);
+    assert_dir!(d, ["image.dng", "image.dng.xmp"]);
+    assert_trash!(d, ["image.jpg", "image.jpg.xmp"]);
+  }
+
+  #[test]
+  fn pairs_by_capture_time_from_same_camera() {
+    let d = test_dir!(
+      "DSC_0001.dng": { "DateTimeOriginal": "2000-01-01T00:00:00", "Make": "Make", "Model": "A" },
+      "IMG_0001.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "Make": "Make", "Model": "A" },
+      "IMG_0002.jpg": { "DateTimeOriginal": "2000-01-01T00:00:01", "Make": "Make", "Model": "A" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.dedupe_across_formats(FormatToKeep::Raw).unwrap();
+
+    assert_dir!(d, ["DSC_0001.dng", "IMG_0002.jpg"]);
+    assert_trash!(d, ["IMG_0001.jpg"]);
+  }
+
+  #[test]
+  fn keeps_images_at_same_time_from_other_camera_or_dir() {
+    let d = test_dir!(
+      "raw/DSC_0001.dng": { "DateTimeOriginal": "2000-01-01T00:00:00", "Make": "Make", "Model": "A" },
+      "raw/IMG_0001.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "Make": "Make", "Model": "B" },
+      "jpg/DSC_0001_edit.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "Make": "Make", "Model": "A" },
+      "raw/DSC_0002.dng": { "DateTimeOriginal": "2000-01-01T00:00:01" },
+      "raw/DSC_0003.jpg": { "DateTimeOriginal": "2000-01-01T00:00:01" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.dedupe_across_formats(FormatToKeep::Raw).unwrap();
+
+    assert_trash!(d, []);
+  }
+
+  #[test]
+  fn keeps_jpeg_if_set() {
+    let d = test_dir!(
+      "image.dng": {},
+      "image.jpg": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.dedupe_across_formats(FormatToKeep::Jpeg).unwrap();
+
+    assert_dir!(d, ["image.jpg"]);
+    assert_trash!(d, ["image.dng"]);
+  }
+
+  #[test]
+  fn pairs_each_image_once() {
+    let d = test_dir!(
+      "image1.dng": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image2.dng": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.dedupe_across_formats(FormatToKeep::Jpeg).unwrap();
+
+    assert_dir!(d, ["image1.jpg", "image2.dng"]);
+    assert_trash!(d, ["image1.dng"]);
+  }
+}
+
+#[cfg(test)]
+mod test_relocate_trash {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn restores_removed_file() {
+    let d = test_dir!(
+      "dir/image.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.remove_sidecar_leftovers().unwrap();
+    assert_trash!(d, ["dir/image.jpg.xmp"]);
+
+    Organizer::relocate_trash(d.root(), d.trash(), "dir/image.jpg.xmp").unwrap();
+
+    assert_dir!(d, ["dir/image.jpg.xmp"]);
+    assert_trash!(d, []);
+  }
+
+  #[test]
+  fn errors_if_restored_path_exists() {
+    let d = test_dir!(
+      "image.jpg.xmp": {},
+    );
+    std::fs::copy(d.get_path("image.jpg.xmp"), d.get_trash("image.jpg.xmp")).unwrap();
+
+    assert_err!(
+      Organizer::relocate_trash(d.root(), d.trash(), "image.jpg.xmp"),
+      "name collision"
+    );
+  }
+}
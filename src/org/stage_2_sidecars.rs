@@ -2,15 +2,57 @@
 
 //! Organizer Stage 2: Automatic sidecar creation.
 
+use std::path::PathBuf;
+
 use super::Organizer;
-use crate::{io, org, prim::SidecarInitial};
+use crate::{error::CatalogError, io, org, prim::SidecarInitial};
 
 impl Organizer {
+  /// Seeds sidecars created by `create_missing_sidecars` with tags from
+  /// `template`. `Copyright` is formatted as set by `set_copyright_format`, if
+  /// set, so that it matches what metadata updates and validation expect.
+  pub fn set_sidecar_template(&mut self, template: io::SidecarTemplate) {
+    log::info!("Setting sidecar template.");
+    self.sidecar_template = template;
+  }
+
+  /// Treats media of `file_types` (by `ExifTool`'s `FileType`, e.g. `PNG`) as
+  /// sidecar-only: Metadata updates and synchronization never write into them,
+  /// only their sidecars. This suits formats `ExifTool` cannot write, or which
+  /// should never be modified.
+  pub fn set_sidecar_only_types(&mut self, file_types: impl IntoIterator<Item = String>) {
+    self.sidecar_only_types = file_types.into_iter().collect();
+    if !self.sidecar_only_types.is_empty() {
+      log::info!(
+        "Writing metadata only to sidecars for {}.",
+        self.sidecar_only_types.join(", ")
+      );
+    }
+  }
+
+  /// Returns the paths of media files without an initial sidecar, i.e. those
+  /// `create_missing_sidecars` would create one for.
+  #[must_use]
+  pub fn report_missing_sidecars(&self) -> Vec<PathBuf> {
+    self
+      .media
+      .iter_data()
+      .filter(|m| m.is_missing_sidecar())
+      .map(|m| org::to_abs_path(&self.source, m))
+      .collect()
+  }
+
   /// Creates a new XMP sidecar for any file without one, and loads it into the
   /// organizer for future stages.
-  pub fn create_missing_sidecars(&mut self) -> Result<(), String> {
+  pub fn create_missing_sidecars(&mut self) -> Result<(), CatalogError> {
     log::info!("Creating XMP sidecars for media files without.");
 
+    let template = io::SidecarTemplate {
+      copyright_format: (self.copyright_format.clone())
+        .or_else(|| self.sidecar_template.copyright_format.clone()),
+      ..self.sidecar_template.clone()
+    };
+
     for media in self.media.iter_data_mut() {
       if !media.is_missing_sidecar() {
         continue;
@@ -18,23 +60,70 @@ impl Organizer {
 
       log::debug!("{media}: Creating XMP sidecar.");
 
-      let metadata = io::create_xmp(org::to_abs_path(
-        &self.source,
-        &media.get_metadata().source_file,
-      ))?;
+      let Some(metadata) = self.warnings.check(io::create_xmp(
+        &self.exiftool,
+        org::to_abs_path(&self.source, &media.get_metadata().source_file),
+        &template,
+      ))?
+      else {
+        continue;
+      };
 
       let path = metadata.as_ref().to_path_buf();
       self.sidecars.insert(path, SidecarInitial::new(metadata)?);
+      self.report.sidecars_created += 1;
     }
 
     Ok(())
   }
 }
 
+#[cfg(test)]
+mod test_report_missing_sidecars {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn reports_media_without_sidecar() {
+    let d = test_dir!(
+      "image1.jpg": {},
+      "image1.jpg.xmp": {},
+      "image2.jpg": {},
+      "a/image3.jpg": {},
+      "a/image4.jpg": {},
+      "a/image4.jpg.xmp": {},
+    );
+
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let mut missing = o.report_missing_sidecars();
+    missing.sort();
+
+    assert_eq!(missing, vec![
+      d.get_path("a/image3.jpg"),
+      d.get_path("image2.jpg")
+    ]);
+  }
+
+  #[test]
+  fn does_not_create_sidecars() {
+    let d = test_dir!(
+      "image.jpg": {},
+    );
+
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let _ = o.report_missing_sidecars();
+
+    assert_dir!(d, ["image.jpg"]);
+  }
+}
+
 #[cfg(test)]
 mod test_create_missing_sidecars {
   use super::*;
-  use crate::testing::*;
+  use crate::{
+    org::{CopyrightFormat, ScanConfig},
+    testing::*,
+  };
 
   #[test]
   fn copies_metadata_from_media() {
@@ -42,7 +131,7 @@ mod test_create_missing_sidecars {
       "image.jpg": { "Creator": "Creator" }
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.create_missing_sidecars().unwrap();
 
     assert_tag!(d, "image.jpg.xmp", "Creator", "Creator");
@@ -54,12 +143,71 @@ mod test_create_missing_sidecars {
       "image.jpg": {},
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.create_missing_sidecars().unwrap();
 
     assert_dir!(d, ["image.jpg", "image.jpg.xmp"]);
   }
 
+  #[test]
+  fn creates_sidecar_for_sidecar_only_media() {
+    let d = test_dir!(
+      "image.png": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_sidecar_only_types(["PNG".to_string()]);
+    o.create_missing_sidecars().unwrap();
+
+    assert_dir!(d, ["image.png", "image.png.xmp"]);
+  }
+
+  #[test]
+  fn seeds_sidecar_from_template() {
+    let d = test_dir!(
+      "image.jpg": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_sidecar_template(io::SidecarTemplate {
+      creator: Some("Creator".to_string()),
+      ..Default::default()
+    });
+    o.create_missing_sidecars().unwrap();
+
+    assert_tag!(d, "image.jpg.xmp", "Creator", "Creator");
+    assert_tag!(d, "image.jpg.xmp", "Copyright", "Copyright Creator");
+  }
+
+  #[test]
+  fn seeds_copyright_as_updated_and_validated() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2024-06-01T12:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_sidecar_template(io::SidecarTemplate {
+      creator: Some("Creator".to_string()),
+      ..Default::default()
+    });
+    o.set_copyright_format(
+      CopyrightFormat::new("© ${Year} ${Creator}, All Rights Reserved").unwrap(),
+    );
+    o.create_missing_sidecars().unwrap();
+    o.enable_set_copyrights_from_creator();
+    o.apply_metadata_updates().unwrap();
+    o.enable_attribution_validation();
+    o.validate();
+
+    assert_tag!(
+      d,
+      "image.jpg.xmp",
+      "Copyright",
+      "© 2024 Creator, All Rights Reserved"
+    );
+    assert_eq!(o.valid_media.len(), 1);
+  }
+
   #[test]
   fn skips_if_sidecar_already_exists() {
     let d = test_dir!(
@@ -67,7 +215,7 @@ mod test_create_missing_sidecars {
       "image.jpg.xmp": { "Creator": "Creator" }
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.create_missing_sidecars().unwrap();
 
     assert_tag!(d, "image.jpg.xmp", "Creator", "Creator");
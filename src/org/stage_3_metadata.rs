@@ -2,25 +2,113 @@
 
 //! Organizer Stage 3: Automatic metadata updates.
 
-use std::ffi::OsStr;
+use std::{ffi::OsStr, path::Path, str::FromStr};
 
-use tzf_rs::{Finder, r#gen::tzf::v1::Timezones};
+use chrono::{NaiveDate, NaiveDateTime};
+use regex::Regex;
+use tzf_rs::Finder;
 
-use super::Organizer;
+use super::{Organizer, geocode::Geocoder};
 use crate::{
+  error::CatalogError,
   io,
   org,
-  prim::{self, FileCategory, Sidecar},
+  prim::{self, CopyrightFormat, FileCategory, FileMap, Media, Metadata, Sidecar, SidecarInitial},
 };
 
+/// Pattern for a date and time within file names, for files without any in
+/// their metadata (e.g. `IMG_20230115_143000.jpg`).
+#[derive(Clone)]
+pub struct FileNameDatePattern {
+  /// Matches the date and time in the file name, as its first capture group.
+  regex:  Regex,
+  /// `chrono` format of the capture (e.g. `%Y%m%d_%H%M%S`). Dates without a
+  /// time are taken as midnight.
+  format: String,
+}
+
+impl FileNameDatePattern {
+  /// Creates a pattern capturing the date and time with `regex`, to be parsed
+  /// with `format`.
+  pub fn new(regex: &str, format: impl Into<String>) -> Result<Self, CatalogError> {
+    let regex = Regex::new(regex)
+      .map_err(|e| CatalogError::Parse(format!("{regex}: Invalid file name pattern ({e}).")))?;
+
+    if regex.captures_len() < 2 {
+      return Err(CatalogError::Parse(format!(
+        "{regex}: File name pattern has no capture group."
+      )));
+    }
+
+    Ok(Self {
+      regex,
+      format: format.into(),
+    })
+  }
+
+  /// Patterns for common cameras, phones and apps: `IMG_20230115_143000`,
+  /// `PXL_20230115_143000123`, and `IMG-20230115-WA0001` from `WhatsApp`
+  /// (with only the date).
+  #[must_use]
+  pub fn defaults() -> Vec<Self> {
+    [
+      (r"(?:^|[^\d])(\d{8}_\d{6})", "%Y%m%d_%H%M%S"),
+      (r"(?:^|[^\d])(\d{8})-WA\d+", "%Y%m%d"),
+    ]
+    .into_iter()
+    .map(|(regex, format)| Self::new(regex, format).unwrap())
+    .collect()
+  }
+
+  /// Parses the date and time from `file_name`, if it matches.
+  fn parse(&self, file_name: &str) -> Option<NaiveDateTime> {
+    let capture = self.regex.captures(file_name)?.get(1)?.as_str();
+
+    NaiveDateTime::parse_from_str(capture, &self.format)
+      .or_else(|_| {
+        NaiveDate::parse_from_str(capture, &self.format).map(|d| d.and_hms_opt(0, 0, 0).unwrap())
+      })
+      .ok()
+  }
+}
+
+/// Parses `REGEX=FORMAT`, as given on the command line.
+impl FromStr for FileNameDatePattern {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (regex, format) = s
+      .rsplit_once('=')
+      .ok_or_else(|| format!("{s}: Expected file name pattern as REGEX=FORMAT."))?;
+
+    Ok(Self::new(regex, format)?)
+  }
+}
+
+/// Whether any tag `move_file` could name a file by is set.
+fn has_date_time(metadata: &Metadata) -> bool {
+  [
+    &metadata.sub_sec_date_time_original,
+    &metadata.date_time_original,
+    &metadata.sub_sec_create_date,
+    &metadata.create_date,
+    &metadata.media_create_date,
+    &metadata.track_create_date,
+  ]
+  .into_iter()
+  .any(Option::is_some)
+}
+
 /// Holds which metadata update passes are enabled.
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Default)]
 pub struct MetadataUpdateConfig {
-  align_mwg_tags:             bool,
+  align_mwg_tags: bool,
   set_copyright_from_creator: bool,
-  set_location_from_gps:      bool,
-  set_time_zone_from_gps:     bool,
+  set_location_from_gps: bool,
+  set_time_zone_from_gps: bool,
+  /// Finds locations offline instead of with `ExifTool`, if set.
+  geocoder: Option<Geocoder>,
 }
 
 impl MetadataUpdateConfig {
@@ -44,29 +132,185 @@ impl Organizer {
   }
 
   /// Automatically writes the `Copyright` tag from `Creator`, if `Creator` is
-  /// set and `Copyright` not.
+  /// set and `Copyright` not, as formatted by `set_copyright_format`.
   pub fn enable_set_copyrights_from_creator(&mut self) {
     log::info!("Enabling automatic copyright.");
     self.metadata_updates.set_copyright_from_creator = true;
   }
 
+  /// Formats `Copyright` as `format` (instead of `Copyright ${Creator}`), both
+  /// when written by `enable_set_copyrights_from_creator` and when validated
+  /// by `enable_attribution_validation`, which then requires it.
+  pub fn set_copyright_format(&mut self, format: CopyrightFormat) {
+    log::info!("Formatting copyrights as \"{format}\".");
+    self.copyright_format = Some(format);
+  }
+
   /// Overwrites the `City`, `State`, and `Country` tags from GPS coordinates,
-  /// if GPS coordinate tags are set.
+  /// if GPS coordinate tags are set. Without `set_geocoder`, files already
+  /// matching are only skipped if scanned with geolocation (see
+  /// `io::ExifToolConfig::enable_geolocation`).
   pub fn enable_set_location_from_gps(&mut self) {
     log::info!("Enabling automatic location.");
     self.metadata_updates.set_location_from_gps = true;
   }
 
+  /// Finds locations for `enable_set_location_from_gps` with `geocoder`,
+  /// offline, instead of with `ExifTool`'s `-geolocate`.
+  pub fn set_geocoder(&mut self, geocoder: Geocoder) {
+    log::info!("Using offline geocoding database for automatic location.");
+    self.metadata_updates.geocoder = Some(geocoder);
+  }
+
   /// Sets time zone based on the location, date and time of each file.
   pub fn enable_set_time_zone_from_gps(&mut self) {
     log::info!("Enabling automatic time zone.");
     self.metadata_updates.set_time_zone_from_gps = true;
   }
 
+  /// Writes GPS coordinates from the GPX track log `track`, matched by
+  /// `DateTimeOriginal` (using `OffsetTimeOriginal`, if set). Unless
+  /// `overwrite` is true, only files without `GPSPosition` are written.
+  /// Metadata is written to the sidecar, if present.
+  pub fn geotag_from_gpx(
+    &mut self,
+    track: impl AsRef<Path>,
+    overwrite: bool,
+  ) -> Result<(), CatalogError> {
+    if !track.as_ref().exists() {
+      return Err(CatalogError::PathNotFound(
+        track.as_ref().to_path_buf(),
+        "GPX track",
+      ));
+    }
+
+    log::info!("{}: Geotagging from GPX track.", track.as_ref().display());
+
+    let targets = self
+      .media
+      .iter_data_indexed()
+      .filter_map(|(h, m)| {
+        let metadata = get_writable_metadata(m, &self.sidecars, &self.sidecar_only_types)?;
+        Some((h, metadata))
+      })
+      .filter(|(_, m)| {
+        m.date_time_original
+          .as_deref()
+          .is_some_and(|d| !prim::is_date_time_unset(d))
+          && (overwrite || m.gps_position.is_none())
+      })
+      .map(|(h, m)| (h, org::to_abs_path(&self.source, &m.source_file)))
+      .collect::<Vec<_>>();
+
+    if targets.is_empty() {
+      log::debug!("No files to geotag. Skipping.");
+      return Ok(());
+    }
+
+    // The last source available takes precedence, so the sub-second time (which
+    // includes the offset) is preferred.
+    let mut args = Vec::from([
+      OsStr::new("-geotag"),
+      track.as_ref().as_os_str(),
+      OsStr::new("-Geotime<DateTimeOriginal"),
+      OsStr::new("-Geotime<SubSecDateTimeOriginal"),
+    ]);
+    args.extend(targets.iter().map(|(_, p)| p.as_os_str()));
+
+    // Handled by the error policy, as for single files.
+    if self
+      .warnings
+      .check(io::run_exiftool(&self.exiftool, Some(&self.source), args))?
+      .is_none()
+    {
+      return Ok(());
+    }
+
+    for (handle, path) in targets {
+      let Some(metadata) = self
+        .warnings
+        .check(io::read_metadata(&self.exiftool, &path))?
+      else {
+        continue;
+      };
+      let media = &mut self.media[handle];
+
+      if let Some(sidecar) = media.get_sidecar().map(|h| &mut self.sidecars[h]) {
+        sidecar.update_metadata(metadata);
+      } else {
+        media.update_metadata(metadata);
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Writes `DateTimeOriginal` from the file name of media without any date
+  /// and time tags, using the first of `patterns` which matches. Metadata is
+  /// written to the sidecar, if present.
+  pub fn infer_dates_from_filename(
+    &mut self,
+    patterns: &[FileNameDatePattern],
+  ) -> Result<(), CatalogError> {
+    log::info!("Inferring dates from file names.");
+
+    let targets = self
+      .media
+      .iter_data_indexed()
+      .filter_map(|(h, m)| {
+        let metadata = get_writable_metadata(m, &self.sidecars, &self.sidecar_only_types)?;
+        if has_date_time(metadata) {
+          return None;
+        }
+
+        let file_name = m.get_metadata().source_file.file_name()?.to_str()?;
+        let date_time = patterns.iter().find_map(|p| p.parse(file_name))?;
+
+        Some((
+          h,
+          org::to_abs_path(&self.source, &metadata.source_file),
+          date_time,
+        ))
+      })
+      .collect::<Vec<_>>();
+
+    for (handle, path, date_time) in targets {
+      log::trace!("{}: Writing date from file name.", path.display());
+
+      let arg = format!(
+        "-DateTimeOriginal={}",
+        date_time.format("%Y:%m:%d %H:%M:%S")
+      );
+      let updated = self.warnings.check(
+        io::run_exiftool(&self.exiftool, Some(&self.source), [
+          OsStr::new(&arg),
+          path.as_os_str(),
+        ])
+        .and_then(|_| io::read_metadata(&self.exiftool, &path)),
+      )?;
+
+      let Some(metadata) = updated else {
+        continue;
+      };
+      let media = &mut self.media[handle];
+
+      if let Some(sidecar) = media.get_sidecar().map(|h| &mut self.sidecars[h]) {
+        sidecar.update_metadata(metadata);
+      } else {
+        media.update_metadata(metadata);
+      }
+    }
+
+    Ok(())
+  }
+
   /// Runs metadata updates, as enabled by `enable_*` methods. Operations are
   /// batched into this call for performance reasons (i.e. reducing the number
   /// of calls to `ExifTool`).
-  pub fn apply_metadata_updates(&mut self) -> Result<(), String> {
+  ///
+  /// Files whose copyright, location and time zone already match what would be
+  /// written are skipped, so re-running this does not modify them again.
+  pub fn apply_metadata_updates(&mut self) -> Result<(), CatalogError> {
     if !self.metadata_updates.enabled() {
       log::debug!("No metadata updates enabled. Skipping.");
       return Ok(());
@@ -75,12 +319,7 @@ impl Organizer {
     log::info!("Applying metadata updates.");
 
     let finder = if self.metadata_updates.set_time_zone_from_gps {
-      Finder::from_pb(
-        Timezones::try_from(
-          include_bytes!("../../third_party/tzf-rel/combined-with-oceans.bin").to_vec(),
-        )
-        .unwrap(),
-      )
+      org::load_time_zone_finder()
     } else {
       Finder::new()
     };
@@ -88,36 +327,36 @@ impl Organizer {
     for media in self.media.iter_data_mut() {
       // Main pass (copyright, location & time zone).
       {
-        let metadata = media
-          .get_sidecar()
-          .map_or(media.get_metadata(), |h| self.sidecars[h].get_metadata());
+        let Some(metadata) = get_writable_metadata(media, &self.sidecars, &self.sidecar_only_types)
+        else {
+          continue;
+        };
 
         let mut args = Vec::new();
 
-        if self.metadata_updates.set_copyright_from_creator
-          && metadata.creator.is_some()
-          && metadata.copyright.is_none()
-        {
-          args.push(OsStr::new("-Copyright<Copyright ${Creator}"));
-        }
+        let copyright_arg = if self.metadata_updates.set_copyright_from_creator {
+          make_copyright_arg(metadata, self.copyright_format.as_ref())
+        } else {
+          None
+        };
+        args.extend(copyright_arg.iter().map(OsStr::new));
 
-        if self.metadata_updates.set_location_from_gps
-          && metadata.gps_latitude.is_some()
-          && metadata.gps_longitude.is_some()
-        {
-          args.push(OsStr::new("-geolocate<GPSPosition"));
-        }
+        let location_args = if self.metadata_updates.set_location_from_gps {
+          make_location_args(metadata, self.metadata_updates.geocoder.as_ref())
+        } else {
+          Vec::new()
+        };
+        args.extend(location_args.iter().map(OsStr::new));
 
         let time_zone_args;
 
         if self.metadata_updates.set_time_zone_from_gps
-          && let Some(lat_lon) = metadata.get_lat_lon()
+          && let Some(coord) = metadata.get_gps_coord()
           && let Some((date_time, _)) = metadata.get_date_time_original()
+          && let time_zone = finder.get_tz_name(coord.lon, coord.lat)
+          && let offset = prim::get_offset_for_time_zone(&date_time, time_zone)
+          && !metadata.has_time_zone(offset)
         {
-          let time_zone = finder.get_tz_name(f64::from(lat_lon.1), f64::from(lat_lon.0));
-
-          let offset = prim::get_offset_for_time_zone(&date_time, time_zone);
-
           let date_time_new = date_time.and_local_timezone(offset).unwrap();
 
           time_zone_args = Vec::from([
@@ -135,14 +374,18 @@ impl Organizer {
           let path = org::to_abs_path(&self.source, &metadata.source_file);
           args.push(path.as_os_str());
 
-          io::run_exiftool(Some(&self.source), args)?;
-
-          let metadata = io::read_metadata(&path)?;
-
-          if let Some(sidecar) = media.get_sidecar().map(|h| &mut self.sidecars[h]) {
-            sidecar.update_metadata(metadata.clone());
-          } else {
-            media.update_metadata(metadata);
+          let updated = self.warnings.check(
+            io::run_exiftool(&self.exiftool, Some(&self.source), args)
+              .and_then(|_| io::read_metadata(&self.exiftool, &path)),
+          )?;
+
+          if let Some(metadata) = updated {
+            self.report.metadata_updated += 1;
+            if let Some(sidecar) = media.get_sidecar().map(|h| &mut self.sidecars[h]) {
+              sidecar.update_metadata(metadata.clone());
+            } else {
+              media.update_metadata(metadata);
+            }
           }
         }
       }
@@ -164,10 +407,12 @@ impl Organizer {
         {
           log::trace!("{}: Aligning MWG tags.", metadata.source_file.display());
 
-          io::run_exiftool(Some(&self.source), vec![
-            OsStr::new("-MWG:all<MWG:all"),
-            path.as_os_str(),
-          ])?;
+          self
+            .warnings
+            .check(io::run_exiftool(&self.exiftool, Some(&self.source), vec![
+              OsStr::new("-MWG:all<MWG:all"),
+              path.as_os_str(),
+            ]))?;
         }
       }
     }
@@ -176,10 +421,84 @@ impl Organizer {
   }
 }
 
+/// Gets the `ExifTool` argument to write `Copyright` to `metadata` from its
+/// `Creator` as `format` (else `Copyright ${Creator}`), if not already set.
+/// Media without a capture year are skipped if `format` needs it.
+fn make_copyright_arg(metadata: &Metadata, format: Option<&CopyrightFormat>) -> Option<String> {
+  let creator = metadata.creator.as_ref()?;
+  if metadata.copyright.is_some() {
+    return None;
+  }
+
+  let Some(copyright) = format
+    .cloned()
+    .unwrap_or_default()
+    .render_for(creator, metadata)
+  else {
+    log::debug!("{metadata}: No capture year for `Copyright`. Skipping.");
+    return None;
+  };
+
+  Some(format!("-Copyright={copyright}"))
+}
+
+/// Makes `ExifTool` arguments to set the `City`, `State` and `Country` tags of
+/// `metadata` from its GPS coordinates, found by `geocoder` if set, else by
+/// `ExifTool`. None if there are no coordinates, or the tags already match.
+fn make_location_args(metadata: &Metadata, geocoder: Option<&Geocoder>) -> Vec<String> {
+  let Some(geocoder) = geocoder else {
+    return if metadata.gps_latitude.is_some()
+      && metadata.gps_longitude.is_some()
+      && !metadata.is_location_from_gps()
+    {
+      vec!["-geolocate<GPSPosition".to_string()]
+    } else {
+      Vec::new()
+    };
+  };
+
+  let Some(place) = metadata
+    .get_gps_coord()
+    .and_then(|c| geocoder.find(c.lat, c.lon))
+  else {
+    return Vec::new();
+  };
+
+  if metadata.city.as_ref() == Some(&place.city)
+    && metadata.state == place.state
+    && metadata.country.as_ref() == Some(&place.country)
+  {
+    return Vec::new();
+  }
+
+  vec![
+    format!("-City={}", place.city),
+    format!("-State={}", place.state.as_deref().unwrap_or_default()),
+    format!("-Country={}", place.country),
+  ]
+}
+
+/// Gets the metadata to update for `media`: its sidecar's if it has one, else
+/// its own, unless its file type is in `sidecar_only_types`.
+fn get_writable_metadata<'a>(
+  media: &'a Media,
+  sidecars: &'a FileMap<SidecarInitial>,
+  sidecar_only_types: &[String],
+) -> Option<&'a Metadata> {
+  match media.get_sidecar() {
+    Some(handle) => Some(sidecars[handle].get_metadata()),
+    None if org::is_sidecar_only(media, sidecar_only_types) => {
+      log::debug!("{media}: Sidecar-only file without sidecar, not writing metadata.");
+      None
+    }
+    None => Some(media.get_metadata()),
+  }
+}
+
 #[cfg(test)]
 mod test_align_mwg_tags {
   use super::*;
-  use crate::testing::*;
+  use crate::{org::ScanConfig, testing::*};
 
   #[test]
   fn aligns_existing_tags() {
@@ -191,7 +510,7 @@ mod test_align_mwg_tags {
       },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_align_mwg_tags();
     o.apply_metadata_updates().unwrap();
 
@@ -204,7 +523,7 @@ mod test_align_mwg_tags {
 #[cfg(test)]
 mod test_apply_metadata_updates {
   use super::*;
-  use crate::testing::*;
+  use crate::{org::ScanConfig, testing::*};
 
   #[test]
   fn writes_to_media_if_no_sidecar() {
@@ -228,7 +547,7 @@ mod test_apply_metadata_updates {
       },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_align_mwg_tags();
     o.enable_set_copyrights_from_creator();
     o.enable_set_location_from_gps();
@@ -240,6 +559,38 @@ mod test_apply_metadata_updates {
     assert_tag!(d, "image.jpg", "OffsetTimeOriginal", "-08:00");
   }
 
+  #[test]
+  fn skips_sidecar_only_media_without_sidecar() {
+    let d = test_dir!(
+      "image.png": { "Creator": "Creator" },
+      "image.jpg": { "Creator": "Creator" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_sidecar_only_types(["PNG".to_string()]);
+    o.enable_set_copyrights_from_creator();
+    o.apply_metadata_updates().unwrap();
+
+    assert_tag!(d, "image.png", "Copyright", None);
+    assert_tag!(d, "image.jpg", "Copyright", "Copyright Creator");
+  }
+
+  #[test]
+  fn writes_to_sidecar_of_sidecar_only_media() {
+    let d = test_dir!(
+      "image.png": { "Creator": "Media" },
+      "image.png.xmp": { "Creator": "Creator" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_sidecar_only_types(["PNG".to_string()]);
+    o.enable_set_copyrights_from_creator();
+    o.apply_metadata_updates().unwrap();
+
+    assert_tag!(d, "image.png", "Copyright", None);
+    assert_tag!(d, "image.png.xmp", "Copyright", "Copyright Creator");
+  }
+
   #[test]
   fn writes_to_sidecar() {
     let d = test_dir!(
@@ -256,7 +607,7 @@ mod test_apply_metadata_updates {
       },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_align_mwg_tags();
     o.enable_set_copyrights_from_creator();
     o.enable_set_location_from_gps();
@@ -285,7 +636,7 @@ mod test_apply_metadata_updates {
       "image_01.jpg.xmp": { "Creator": "Dupe" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_set_copyrights_from_creator();
     o.apply_metadata_updates().unwrap();
 
@@ -301,7 +652,7 @@ mod test_apply_metadata_updates {
       "image_01.jpg.xmp": { "Creator": "Dupe" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_set_copyrights_from_creator();
     o.apply_metadata_updates().unwrap();
 
@@ -312,7 +663,7 @@ mod test_apply_metadata_updates {
 #[cfg(test)]
 mod test_set_copyright_from_creator {
   use super::*;
-  use crate::testing::*;
+  use crate::{org::ScanConfig, testing::*};
 
   #[test]
   fn preserves_existing_copyright() {
@@ -321,7 +672,7 @@ mod test_set_copyright_from_creator {
       "image.jpg.xmp": { "Copyright": "Copyright", "Creator": "Creator" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_set_copyrights_from_creator();
     o.apply_metadata_updates().unwrap();
 
@@ -335,19 +686,96 @@ mod test_set_copyright_from_creator {
       "image.jpg.xmp": { "Creator": "Creator" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_set_copyrights_from_creator();
     o.apply_metadata_updates().unwrap();
 
     assert_tag!(d, "image.jpg.xmp", "Copyright", "Copyright Creator");
   }
+
+  #[test]
+  fn sets_copyright_with_format() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {
+        "Creator": "Creator",
+        "DateTimeOriginal": "2024-06-01T12:00:00",
+      },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_set_copyrights_from_creator();
+    o.set_copyright_format(
+      CopyrightFormat::new("© ${Year} ${Creator}, All Rights Reserved").unwrap(),
+    );
+    o.apply_metadata_updates().unwrap();
+
+    assert_tag!(
+      d,
+      "image.jpg.xmp",
+      "Copyright",
+      "© 2024 Creator, All Rights Reserved"
+    );
+  }
 }
 
 #[cfg(test)]
-mod test_set_location_from_gps {
+mod test_make_copyright_arg {
   use super::*;
   use crate::testing::*;
 
+  #[test]
+  fn formats_creator_by_default() {
+    let metadata = metadata!(
+      "Creator": "Creator",
+    );
+
+    assert_eq!(
+      make_copyright_arg(&metadata, None).as_deref(),
+      Some("-Copyright=Copyright Creator")
+    );
+  }
+
+  #[test]
+  fn formats_year() {
+    let metadata = metadata!(
+      "Creator": "Creator",
+      "DateTimeOriginal": "2024-06-01T12:00:00",
+    );
+    let format = CopyrightFormat::new("© ${Year} ${Creator}").unwrap();
+
+    assert_eq!(
+      make_copyright_arg(&metadata, Some(&format)).as_deref(),
+      Some("-Copyright=© 2024 Creator")
+    );
+  }
+
+  #[test]
+  fn skips_without_year_if_needed() {
+    let metadata = metadata!(
+      "Creator": "Creator",
+    );
+    let format = CopyrightFormat::new("© ${Year} ${Creator}").unwrap();
+
+    assert!(make_copyright_arg(&metadata, Some(&format)).is_none());
+  }
+
+  #[test]
+  fn skips_existing_copyright() {
+    let metadata = metadata!(
+      "Creator": "Creator",
+      "Copyright": "Copyright",
+    );
+
+    assert!(make_copyright_arg(&metadata, None).is_none());
+  }
+}
+
+#[cfg(test)]
+mod test_set_location_from_gps {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
   #[test]
   fn overwrites_existing_location() {
     let d = test_dir!(
@@ -361,7 +789,7 @@ mod test_set_location_from_gps {
       },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_set_location_from_gps();
     o.apply_metadata_updates().unwrap();
 
@@ -380,7 +808,7 @@ mod test_set_location_from_gps {
       },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_set_location_from_gps();
     o.apply_metadata_updates().unwrap();
 
@@ -388,13 +816,121 @@ mod test_set_location_from_gps {
     assert_tag!(d, "image.jpg.xmp", "State", "Washington");
     assert_tag!(d, "image.jpg.xmp", "Country", "United States");
   }
+
+  #[test]
+  fn skips_location_already_set_on_rerun() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {
+        "GPSLatitude": "47.6061 N",
+        "GPSLongitude": "122.3328 W",
+      },
+    );
+    let mut scan = ScanConfig::default();
+    scan.exiftool.enable_geolocation();
+
+    let mut o = Organizer::import(&[d.root()], &scan).unwrap();
+    o.enable_set_location_from_gps();
+    o.apply_metadata_updates().unwrap();
+    assert_eq!(o.report.metadata_updated, 1);
+
+    let mut o = Organizer::import(&[d.root()], &scan).unwrap();
+    o.enable_set_location_from_gps();
+    o.apply_metadata_updates().unwrap();
+    assert_eq!(o.report.metadata_updated, 0);
+    assert_tag!(d, "image.jpg.xmp", "City", "Seattle");
+  }
+
+  #[test]
+  fn sets_location_with_geocoder() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {
+        "GPSLatitude": "47.6061 N",
+        "GPSLongitude": "122.3328 W",
+      },
+    );
+
+    let geocoder = Geocoder::from_geonames(
+      "5809844\tSeattle\tSeattle\t\t47.60621\t-122.33207\tP\tPPLA2\tUS\t\tWA".as_bytes(),
+      "US.WA\tWashington\tWashington\t5815135".as_bytes(),
+      "US\tUSA\t840\tUS\tUnited States".as_bytes(),
+    )
+    .unwrap();
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_set_location_from_gps();
+    o.set_geocoder(geocoder);
+    o.apply_metadata_updates().unwrap();
+
+    assert_tag!(d, "image.jpg.xmp", "City", "Seattle");
+    assert_tag!(d, "image.jpg.xmp", "State", "Washington");
+    assert_tag!(d, "image.jpg.xmp", "Country", "United States");
+  }
 }
 
 #[cfg(test)]
-mod test_set_time_zone_from_location {
+mod test_make_location_args {
   use super::*;
   use crate::testing::*;
 
+  fn geocoder() -> Geocoder {
+    Geocoder::from_geonames(
+      "5809844\tSeattle\tSeattle\t\t47.60621\t-122.33207\tP\tPPLA2\tUS\t\tWA".as_bytes(),
+      "US.WA\tWashington\tWashington\t5815135".as_bytes(),
+      "US\tUSA\t840\tUS\tUnited States".as_bytes(),
+    )
+    .unwrap()
+  }
+
+  #[test]
+  fn resolves_city_with_geocoder() {
+    let metadata = metadata!(
+      "GPSPosition": "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W",
+    );
+
+    assert_eq!(make_location_args(&metadata, Some(&geocoder())), [
+      "-City=Seattle",
+      "-State=Washington",
+      "-Country=United States",
+    ]);
+  }
+
+  #[test]
+  fn skips_matching_location_with_geocoder() {
+    let metadata = metadata!(
+      "GPSPosition": "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W",
+      "City": "Seattle",
+      "State": "Washington",
+      "Country": "United States",
+    );
+
+    assert!(make_location_args(&metadata, Some(&geocoder())).is_empty());
+  }
+
+  #[test]
+  fn skips_without_gps_with_geocoder() {
+    assert!(make_location_args(&metadata!(), Some(&geocoder())).is_empty());
+  }
+
+  #[test]
+  fn geolocates_with_exiftool_without_geocoder() {
+    let metadata = metadata!(
+      "GPSLatitude": "47 deg 36' 21.96\" N",
+      "GPSLongitude": "122 deg 19' 58.08\" W",
+    );
+
+    assert_eq!(make_location_args(&metadata, None), [
+      "-geolocate<GPSPosition"
+    ]);
+  }
+}
+
+#[cfg(test)]
+mod test_set_time_zone_from_location {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
   #[test]
   fn overwrites_time_zone_exif() {
     let d = test_dir!(
@@ -408,7 +944,7 @@ mod test_set_time_zone_from_location {
       }
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_set_time_zone_from_gps();
     o.apply_metadata_updates().unwrap();
 
@@ -428,7 +964,7 @@ mod test_set_time_zone_from_location {
       }
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_set_time_zone_from_gps();
     o.apply_metadata_updates().unwrap();
 
@@ -451,7 +987,7 @@ mod test_set_time_zone_from_location {
       }
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_set_time_zone_from_gps();
     o.apply_metadata_updates().unwrap();
 
@@ -475,7 +1011,7 @@ mod test_set_time_zone_from_location {
       }
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_set_time_zone_from_gps();
     o.apply_metadata_updates().unwrap();
 
@@ -493,7 +1029,7 @@ mod test_set_time_zone_from_location {
       }
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_set_time_zone_from_gps();
     o.apply_metadata_updates().unwrap();
 
@@ -516,7 +1052,7 @@ mod test_set_time_zone_from_location {
       }
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.enable_set_time_zone_from_gps();
     o.apply_metadata_updates().unwrap();
 
@@ -528,3 +1064,198 @@ mod test_set_time_zone_from_location {
     );
   }
 }
+
+#[cfg(test)]
+mod test_geotag_from_gpx {
+  use std::fs;
+
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  const GPX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+  <trk><trkseg>
+    <trkpt lat="47.6061" lon="-122.3328"><time>2000-01-01T00:00:00Z</time></trkpt>
+    <trkpt lat="47.6061" lon="-122.3328"><time>2000-01-01T00:10:00Z</time></trkpt>
+  </trkseg></trk>
+</gpx>
+"#;
+
+  #[test]
+  fn errors_if_track_does_not_exist() {
+    let d = test_dir!();
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    assert_err!(
+      o.geotag_from_gpx("/path/does/not/exist.gpx", false),
+      "GPX track does not exist."
+    );
+  }
+
+  #[test]
+  fn writes_gps_to_files_without() {
+    let d = test_dir!(
+      "image.jpg": {
+        "DateTimeOriginal": "2000-01-01T00:05:00",
+        "OffsetTimeOriginal": "+00:00",
+      },
+      "image_gps.jpg": {
+        "DateTimeOriginal": "2000-01-01T00:05:00",
+        "OffsetTimeOriginal": "+00:00",
+        "GPSLatitude": "1",
+        "GPSLatitudeRef": "S",
+        "GPSLongitude": "1",
+        "GPSLongitudeRef": "E",
+      },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let track = d.trash().join("track.gpx");
+    fs::write(&track, GPX).unwrap();
+    o.geotag_from_gpx(&track, false).unwrap();
+
+    assert_tag!(d, "image.jpg", "GPSLatitudeRef", "North");
+    assert_tag!(d, "image.jpg", "GPSLongitudeRef", "West");
+    assert_tag!(d, "image_gps.jpg", "GPSLatitudeRef", "South");
+  }
+
+  #[test]
+  fn overwrites_existing_gps() {
+    let d = test_dir!(
+      "image.jpg": {
+        "DateTimeOriginal": "2000-01-01T00:05:00",
+        "OffsetTimeOriginal": "+00:00",
+        "GPSLatitude": "1",
+        "GPSLatitudeRef": "S",
+        "GPSLongitude": "1",
+        "GPSLongitudeRef": "E",
+      },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let track = d.trash().join("track.gpx");
+    fs::write(&track, GPX).unwrap();
+    o.geotag_from_gpx(&track, true).unwrap();
+
+    assert_tag!(d, "image.jpg", "GPSLatitudeRef", "North");
+  }
+
+  #[test]
+  fn writes_gps_to_sidecar() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:05:00+00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let track = d.trash().join("track.gpx");
+    fs::write(&track, GPX).unwrap();
+    o.geotag_from_gpx(&track, false).unwrap();
+
+    assert_tag!(d, "image.jpg.xmp", "GPSLatitudeRef", "North");
+    assert_tag!(d, "image.jpg", "GPSLatitudeRef", None);
+  }
+}
+
+#[cfg(test)]
+mod test_file_name_date_pattern {
+  use super::*;
+
+  fn parse(file_name: &str) -> Option<String> {
+    FileNameDatePattern::defaults()
+      .iter()
+      .find_map(|p| p.parse(file_name))
+      .map(|d| d.to_string())
+  }
+
+  #[test]
+  fn parses_default_patterns() {
+    assert_eq!(
+      parse("IMG_20230115_143000.jpg").as_deref(),
+      Some("2023-01-15 14:30:00")
+    );
+    assert_eq!(
+      parse("PXL_20230115_143000123.jpg").as_deref(),
+      Some("2023-01-15 14:30:00")
+    );
+    assert_eq!(
+      parse("IMG-20230115-WA0001.jpg").as_deref(),
+      Some("2023-01-15 00:00:00")
+    );
+  }
+
+  #[test]
+  fn skips_invalid_dates() {
+    assert_eq!(parse("IMG_20231315_143000.jpg"), None);
+    assert_eq!(parse("IMG_0001.jpg"), None);
+  }
+
+  #[test]
+  fn parses_from_command_line() {
+    let pattern: FileNameDatePattern = r"^scan_(\d{4}-\d{2}-\d{2})=%Y-%m-%d".parse().unwrap();
+
+    assert_eq!(
+      pattern
+        .parse("scan_1985-06-01_03.tif")
+        .map(|d| d.to_string())
+        .as_deref(),
+      Some("1985-06-01 00:00:00")
+    );
+    assert!("no_format".parse::<FileNameDatePattern>().is_err());
+    assert!("no_capture=%Y".parse::<FileNameDatePattern>().is_err());
+  }
+}
+
+#[cfg(test)]
+mod test_infer_dates_from_filename {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn writes_date_from_file_name() {
+    let d = test_dir!(
+      "IMG_20230115_143000.jpg": {},
+      "IMG_20230115_143000.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.infer_dates_from_filename(&FileNameDatePattern::defaults())
+      .unwrap();
+
+    assert_tag!(d, "IMG_20230115_143000.jpg", "DateTimeOriginal", None);
+    assert_tag!(
+      d,
+      "IMG_20230115_143000.jpg.xmp",
+      "DateTimeOriginal",
+      "2023-01-15T14:30:00"
+    );
+  }
+
+  #[test]
+  fn skips_files_with_date() {
+    let d = test_dir!(
+      "IMG_20230115_143000.jpg": {},
+      "IMG_20230115_143000.jpg.xmp": { "CreateDate": "2000-01-01T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.infer_dates_from_filename(&FileNameDatePattern::defaults())
+      .unwrap();
+
+    assert_tag!(d, "IMG_20230115_143000.jpg.xmp", "DateTimeOriginal", None);
+  }
+
+  #[test]
+  fn skips_files_not_matching() {
+    let d = test_dir!(
+      "IMG_0001.jpg": {},
+      "IMG_0001.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.infer_dates_from_filename(&FileNameDatePattern::defaults())
+      .unwrap();
+
+    assert_tag!(d, "IMG_0001.jpg.xmp", "DateTimeOriginal", None);
+  }
+}
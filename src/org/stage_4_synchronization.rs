@@ -2,29 +2,87 @@
 
 //! Organizer Stage 4: Metadata synchronization.
 
-use std::fmt::Write;
+use std::{num::NonZeroUsize, path::PathBuf};
 
-use super::Organizer;
-use crate::{io, org, prim::Sidecar};
+use chrono::TimeDelta;
+
+use super::{Organizer, Warning, par_map};
+use crate::{
+  error::CatalogError,
+  io,
+  org,
+  prim::{Codec, Metadata, Sidecar},
+};
 
 impl Organizer {
+  /// Sets the number of metadata copies to run at once when synchronizing.
+  /// Each runs its own `ExifTool` process. Also bounds the files hashed at once
+  /// by `index_hashes`.
+  pub fn set_workers(&mut self, workers: NonZeroUsize) {
+    log::info!("Synchronizing metadata with {workers} worker(s).");
+    self.workers = workers.get();
+  }
+
+  /// Limits `sync_live_photo_metadata` to copying only `tags` (by `ExifTool`
+  /// name, e.g. `DateTimeOriginal`), rather than all tags. Others (e.g.
+  /// `Creator`) are left unchanged on videos. Without any tags, all are copied.
+  pub fn set_live_photo_sync_tags(&mut self, tags: impl IntoIterator<Item = String>) {
+    let tags = tags.into_iter().collect::<Vec<_>>();
+    if tags.is_empty() {
+      self.live_photo_sync_tags = None;
+      return;
+    }
+
+    log::info!(
+      "Synchronizing only {} across Live Photo components.",
+      tags.join(", ")
+    );
+    self.live_photo_sync_tags = Some(tags);
+  }
+
+  /// Warns in `sync_live_photo_metadata` about Live Photo images and videos
+  /// captured more than `tolerance` apart, as likely paired by mistake (e.g.
+  /// reused `ContentIdentifier`s). Videos start shortly before their images,
+  /// so a few seconds apart is expected.
+  pub fn enable_live_photo_time_check(&mut self, tolerance: TimeDelta) {
+    log::info!(
+      "Checking Live Photo capture times within {}s.",
+      tolerance.num_seconds()
+    );
+    self.live_photo_time_tolerance = Some(tolerance);
+  }
+
   /// Synchronizes metadata from Live Photo images to their corresponding
   /// videos. This means that any manual changes only need to be made for the
   /// image, and it can be copied here automatically.
-  pub fn sync_live_photo_metadata(&mut self) -> Result<(), String> {
+  pub fn sync_live_photo_metadata(&mut self) -> Result<(), CatalogError> {
     log::info!("Synchronizing metadata across Live Photo components.");
 
+    let mut handles = Vec::new();
+    let mut copies = Vec::new();
+
     for l in self.live_photo_map.values_mut() {
       if !l.is_pair() {
-        log::warn!(
-          "Cannot synchronize Live Photo with duplicates:{}",
+        self.warnings.push(Warning::UnsyncedLivePhoto(
           l.drain()
-            .map(|h| { &self.media[h] })
-            .fold(String::new(), |mut s, d| {
-              write!(s, " {d}").unwrap();
-              s
-            })
-        );
+            .map(|h| self.media[h].as_ref().to_path_buf())
+            .collect(),
+        ));
+      }
+
+      let image = &self.media[l.get_image_best()];
+      let video = &self.media[l.get_video_best()];
+      if let Some(tolerance) = self.live_photo_time_tolerance
+        && let Some(difference) = video
+          .get_metadata()
+          .get_capture_time_difference(image.get_metadata())
+        && difference.abs() > tolerance
+      {
+        self.warnings.push(Warning::LivePhotoTimeMismatch(
+          image.as_ref().to_path_buf(),
+          video.as_ref().to_path_buf(),
+          difference.num_seconds(),
+        ));
       }
 
       let Some(handle_image_sidecar) = self.media[l.get_image_best()].get_sidecar() else {
@@ -34,7 +92,7 @@ impl Organizer {
         );
         continue;
       };
-      let image_sidecar_path = self.sidecars[handle_image_sidecar].as_ref().to_path_buf();
+      let image_sidecar = &self.sidecars[handle_image_sidecar];
 
       let Some(handle_video_sidecar) = self.media[l.get_video_best()].get_sidecar() else {
         log::debug!(
@@ -43,31 +101,47 @@ impl Organizer {
         );
         continue;
       };
-      let video_sidecar = &mut self.sidecars[handle_video_sidecar];
-
-      log::trace!(
-        "{} -> {}: Synchronizing metadata.",
-        image_sidecar_path.display(),
-        video_sidecar
-      );
-
-      let metadata = io::copy_metadata(
-        org::to_abs_path(&self.source, image_sidecar_path),
-        org::to_abs_path(&self.source, &video_sidecar),
-      )?;
-      video_sidecar.update_metadata(metadata);
+      let video_sidecar = &self.sidecars[handle_video_sidecar];
+
+      log::trace!("{image_sidecar} -> {video_sidecar}: Synchronizing metadata.");
+
+      handles.push(handle_video_sidecar);
+      copies.push((
+        org::to_abs_path(&self.source, image_sidecar),
+        org::to_abs_path(&self.source, video_sidecar),
+      ));
     }
 
-    Ok(())
+    let mut errors = Vec::new();
+    for (handle, metadata) in handles.into_iter().zip(copy_metadata_all(
+      &self.exiftool,
+      &copies,
+      self.live_photo_sync_tags.as_deref(),
+      self.workers,
+    )) {
+      match metadata {
+        Ok(metadata) => {
+          self.sidecars[handle].update_metadata(metadata);
+          self.report.synchronized += 1;
+        }
+        Err(e) => errors.push(e),
+      }
+    }
+
+    self.warnings.check_all(errors)
   }
 
   /// Synchronizes metadata from initial (base/main) sidecars to duplicate
   /// sidecars, as made by darktable. Manual changes only need to be applied
   /// to the initial sidecar, and this function will propagate changes to the
-  /// duplicates.
-  pub fn sync_dupe_metadata(&mut self) -> Result<(), String> {
+  /// duplicates. A failure with one duplicate does not stop the others, and all
+  /// failures are reported together once every duplicate has been attempted.
+  pub fn sync_dupe_metadata(&mut self) -> Result<(), CatalogError> {
     log::info!("Synchronizing metadata from initial sidecars to duplicates.");
 
+    let mut handles = Vec::new();
+    let mut copies = Vec::new();
+
     for sidecar in self.sidecars.iter_data() {
       let Some(handle_media) = sidecar.get_media_handle() else {
         log::debug!("{sidecar}: Leftover sidecar, cannot synchronize to duplicates.");
@@ -77,53 +151,185 @@ impl Organizer {
       let media = &self.media[handle_media];
 
       for handle_dupe in media.iter_dupes() {
-        let dupe = &mut self.dupes[handle_dupe];
+        let dupe = &self.dupes[handle_dupe];
 
         log::trace!("{sidecar} -> {dupe}: Synchronizing metadata.");
 
-        let metadata = io::copy_metadata(
+        handles.push(handle_dupe);
+        copies.push((
           org::to_abs_path(&self.source, sidecar),
-          org::to_abs_path(&self.source, &dupe),
-        )?;
-        dupe.update_metadata(metadata);
+          org::to_abs_path(&self.source, dupe),
+        ));
       }
     }
 
-    Ok(())
+    let mut errors = Vec::new();
+    for (handle, metadata) in handles.into_iter().zip(copy_metadata_all(
+      &self.exiftool,
+      &copies,
+      None,
+      self.workers,
+    )) {
+      match metadata {
+        Ok(metadata) => {
+          self.dupes[handle].update_metadata(metadata);
+          self.report.synchronized += 1;
+        }
+        Err(e) => errors.push(e),
+      }
+    }
+
+    self.warnings.check_all(errors)
   }
 
   /// Synchronizes metadata from initial sidecars to their associated media
   /// files. This is useful in keeping metadata changes in case XMP files are
   /// lost or overwritten erroneously, but some prefer to never update media
-  /// metadata files directly for some formats (e.g. raw files).
-  pub fn sync_media_metadata(&mut self) -> Result<(), String> {
+  /// metadata files directly for some formats (e.g. raw files), so media with
+  /// any of the codecs in `skip` (e.g. `Codec::Raw`) are left untouched, as
+  /// are sidecar-only media (see `set_sidecar_only_types`).
+  pub fn sync_media_metadata(&mut self, skip: &[Codec]) -> Result<(), CatalogError> {
     log::info!("Synchronizing metadata from initial sidecars to media.");
 
-    for media in self.media.iter_data_mut() {
+    let mut handles = Vec::new();
+    let mut copies = Vec::new();
+
+    for (handle_media, media) in self.media.iter_data_indexed() {
       let Some(handle_sidecar) = media.get_sidecar() else {
         log::debug!("{media}: Missing sidecar, cannot synchronize.");
         continue;
       };
 
+      if skip.contains(&media.get_codec()) {
+        log::debug!(
+          "{media}: Skipping synchronization for {}.",
+          media.get_codec()
+        );
+        continue;
+      }
+
+      if org::is_sidecar_only(media, &self.sidecar_only_types) {
+        log::debug!("{media}: Skipping synchronization for sidecar-only file.");
+        continue;
+      }
+
       let sidecar = &self.sidecars[handle_sidecar];
 
       log::trace!("{sidecar} -> {media}: Synchronizing metadata.");
 
-      let metadata = io::copy_metadata(
+      handles.push(handle_media);
+      copies.push((
         org::to_abs_path(&self.source, sidecar),
-        org::to_abs_path(&self.source, &media),
-      )?;
-      media.update_metadata(metadata);
+        org::to_abs_path(&self.source, media),
+      ));
+    }
+
+    let mut errors = Vec::new();
+    for (handle, metadata) in handles.into_iter().zip(copy_metadata_all(
+      &self.exiftool,
+      &copies,
+      None,
+      self.workers,
+    )) {
+      match metadata {
+        Ok(metadata) => {
+          self.media[handle].update_metadata(metadata);
+          self.report.synchronized += 1;
+        }
+        Err(e) => errors.push(e),
+      }
     }
 
-    Ok(())
+    self.warnings.check_all(errors)
+  }
+}
+
+/// Runs `io::copy_metadata` (or `io::copy_metadata_tags`, if only copying
+/// `tags`) with `exiftool` for each pair of source and destination paths in
+/// `copies`, using up to `workers` threads. Results are in the same order as
+/// `copies`. Each destination must be distinct, so that copies are independent.
+fn copy_metadata_all(
+  exiftool: &io::ExifToolConfig,
+  copies: &[(PathBuf, PathBuf)],
+  tags: Option<&[String]>,
+  workers: usize,
+) -> Vec<Result<Metadata, CatalogError>> {
+  par_map(copies, workers, |(src, dst)| match tags {
+    None => io::copy_metadata(exiftool, src, dst),
+    Some(tags) => io::copy_metadata_tags(exiftool, src, dst, tags),
+  })
+}
+
+#[cfg(test)]
+mod test_set_workers {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  /// Runs all synchronization on a fresh catalog with `workers`, returning the
+  /// resulting `Creator` of each file.
+  fn sync_with_workers(workers: usize) -> Vec<(&'static str, Option<String>)> {
+    let d = test_dir!(
+      "image1.jpg": { "Creator": "Media1" },
+      "image1.jpg.xmp": { "Creator": "Sidecar1" },
+      "image1_01.jpg.xmp": { "Creator": "Dupe1" },
+      "image1_02.jpg.xmp": { "Creator": "Dupe2" },
+      "image2.jpg": { "Creator": "Media2" },
+      "image2.jpg.xmp": { "Creator": "Sidecar2" },
+      "image2_01.jpg.xmp": { "Creator": "Dupe3" },
+      "image3.heic": { "ContentIdentifier": "ID", "Creator": "Image" },
+      "image3.heic.xmp": { "Creator": "ImageSidecar" },
+      "video3.mov": {
+        "CompressorID": "avc1",
+        "ContentIdentifier": "ID",
+        "Creator": "Video"
+      },
+      "video3.mov.xmp": { "Creator": "VideoSidecar" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_workers(NonZeroUsize::new(workers).unwrap());
+    o.sync_live_photo_metadata().unwrap();
+    o.sync_dupe_metadata().unwrap();
+    o.sync_media_metadata(&[]).unwrap();
+
+    [
+      "image1.jpg",
+      "image1.jpg.xmp",
+      "image1_01.jpg.xmp",
+      "image1_02.jpg.xmp",
+      "image2.jpg",
+      "image2.jpg.xmp",
+      "image2_01.jpg.xmp",
+      "image3.heic",
+      "image3.heic.xmp",
+      "video3.mov",
+      "video3.mov.xmp",
+    ]
+    .into_iter()
+    .map(|f| (f, read_tag(d.root(), f, None, "Creator")))
+    .collect()
+  }
+
+  #[test]
+  fn syncs_same_as_serial() {
+    let serial = sync_with_workers(1);
+
+    assert_eq!(
+      serial[2],
+      ("image1_01.jpg.xmp", Some("Sidecar1".to_string()))
+    );
+    assert_eq!(
+      serial[10],
+      ("video3.mov.xmp", Some("ImageSidecar".to_string()))
+    );
+    assert_eq!(sync_with_workers(4), serial);
   }
 }
 
 #[cfg(test)]
 mod test_sync_live_photo_metadata {
   use super::*;
-  use crate::testing::*;
+  use crate::{org::ScanConfig, testing::*};
 
   #[test]
   fn overwrites_video_with_image_metadata() {
@@ -138,13 +344,90 @@ mod test_sync_live_photo_metadata {
       "video.mov.xmp": { "Creator": "Video" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.sync_live_photo_metadata().unwrap();
 
     assert_tag!(d, "image.heic.xmp", "Creator", "Image");
     assert_tag!(d, "video.mov.xmp", "Creator", "Image");
   }
 
+  #[test]
+  fn copies_only_sync_tags_if_set() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID" },
+      "image.heic.xmp": {
+        "Creator": "Image",
+        "Subject": "Keyword",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      },
+      "video.mov": {
+        "CompressorID": "avc1",
+        "ContentIdentifier": "ID",
+      },
+      "video.mov.xmp": { "Creator": "Video" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_live_photo_sync_tags(["DateTimeOriginal".to_string()]);
+    o.sync_live_photo_metadata().unwrap();
+
+    assert_tag!(d, "video.mov.xmp", "Creator", "Video");
+    assert_tag!(d, "video.mov.xmp", "Subject", None);
+    assert!(
+      o.sidecars
+        .iter_data()
+        .all(|s| s.get_metadata().date_time_original.is_some())
+    );
+  }
+
+  #[test]
+  fn warns_if_capture_times_diverge() {
+    let d = test_dir!(
+      "image.heic": {
+        "ContentIdentifier": "ID",
+        "DateTimeOriginal": "2000-01-01T00:01:00",
+        "OffsetTimeOriginal": "+00:00",
+      },
+      "video.mov": {
+        "CompressorID": "avc1",
+        "ContentIdentifier": "ID",
+        "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
+      },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_live_photo_time_check(TimeDelta::seconds(10));
+    o.sync_live_photo_metadata().unwrap();
+
+    assert_eq!(o.take_warnings(), [Warning::LivePhotoTimeMismatch(
+      PathBuf::from("./image.heic"),
+      PathBuf::from("./video.mov"),
+      -60,
+    )]);
+  }
+
+  #[test]
+  fn skips_warning_if_capture_times_within_tolerance() {
+    let d = test_dir!(
+      "image.heic": {
+        "ContentIdentifier": "ID",
+        "DateTimeOriginal": "2000-01-01T00:00:02",
+        "OffsetTimeOriginal": "+00:00",
+      },
+      "video.mov": {
+        "CompressorID": "avc1",
+        "ContentIdentifier": "ID",
+        "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
+      },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_live_photo_time_check(TimeDelta::seconds(10));
+    o.sync_live_photo_metadata().unwrap();
+
+    assert!(o.take_warnings().is_empty());
+  }
+
   #[test]
   fn skips_if_missing_image_sidecar() {
     let d = test_dir!(
@@ -157,7 +440,7 @@ mod test_sync_live_photo_metadata {
       "video.mov.xmp": { "Creator": "Sidecar" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.sync_live_photo_metadata().unwrap();
 
     assert_tag!(d, "video.mov", "Creator", "Video");
@@ -176,7 +459,7 @@ mod test_sync_live_photo_metadata {
       },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.sync_live_photo_metadata().unwrap();
 
     assert_tag!(d, "video.mov", "Creator", "Video");
@@ -203,7 +486,7 @@ mod test_sync_live_photo_metadata {
       "video2.mov.xmp": { "Creator": "VideoSidecar2" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.sync_live_photo_metadata().unwrap();
 
     assert_tag!(d, "video1.mov.xmp", "Creator", "ImageSidecar1");
@@ -225,7 +508,7 @@ mod test_sync_live_photo_metadata {
       "video_01.mov.xmp": { "Creator": "VideoDuplicate" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.sync_live_photo_metadata().unwrap();
 
     assert_tag!(d, "video.mov.xmp", "Creator", "ImageSidecar");
@@ -239,8 +522,13 @@ mod test_sync_live_photo_metadata {
 
 #[cfg(test)]
 mod test_sync_dupe_metadata {
+  use std::fs;
+
   use super::*;
-  use crate::testing::*;
+  use crate::{
+    org::{ErrorPolicy, ScanConfig},
+    testing::*,
+  };
 
   #[test]
   fn overwrites_dupe_with_sidecar_metadata() {
@@ -251,13 +539,61 @@ mod test_sync_dupe_metadata {
       "image_02.jpg.xmp": { "Creator": "Dupe2" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.sync_dupe_metadata().unwrap();
 
     assert_tag!(d, "image_01.jpg.xmp", "Creator", "Sidecar");
     assert_tag!(d, "image_02.jpg.xmp", "Creator", "Sidecar");
   }
 
+  #[test]
+  fn syncs_other_dupes_if_one_fails() {
+    let d = test_dir!(
+      "image.jpg": { "Creator": "Media" },
+      "image.jpg.xmp": { "Creator": "Sidecar" },
+      "image_01.jpg.xmp": { "Creator": "Dupe1" },
+      "image_02.jpg.xmp": { "Creator": "Dupe2" },
+      "image_03.jpg.xmp": { "Creator": "Dupe3" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    fs::remove_file(d.get_path("image_02.jpg.xmp")).unwrap();
+
+    assert_err!(o.sync_dupe_metadata(), "image_02.jpg.xmp");
+    assert_tag!(d, "image_01.jpg.xmp", "Creator", "Sidecar");
+    assert_tag!(d, "image_03.jpg.xmp", "Creator", "Sidecar");
+    assert_eq!(
+      o.dupes
+        .iter_data()
+        .filter(|dupe| dupe.get_metadata().creator.as_deref() == Some("Sidecar"))
+        .count(),
+      2
+    );
+  }
+
+  #[test]
+  fn records_each_failed_dupe_if_continuing() {
+    let d = test_dir!(
+      "image.jpg": { "Creator": "Media" },
+      "image.jpg.xmp": { "Creator": "Sidecar" },
+      "image_01.jpg.xmp": { "Creator": "Dupe1" },
+      "image_02.jpg.xmp": { "Creator": "Dupe2" },
+      "image_03.jpg.xmp": { "Creator": "Dupe3" },
+    );
+    let scan = ScanConfig {
+      on_error: ErrorPolicy::Continue,
+      ..Default::default()
+    };
+
+    let mut o = Organizer::import(&[d.root()], &scan).unwrap();
+    fs::remove_file(d.get_path("image_01.jpg.xmp")).unwrap();
+    fs::remove_file(d.get_path("image_03.jpg.xmp")).unwrap();
+    o.sync_dupe_metadata().unwrap();
+
+    assert_tag!(d, "image_02.jpg.xmp", "Creator", "Sidecar");
+    assert_eq!(o.take_warnings().len(), 2);
+  }
+
   #[test]
   fn skips_if_missing_sidecar() {
     let d = test_dir!(
@@ -266,7 +602,7 @@ mod test_sync_dupe_metadata {
       "image_02.jpg.xmp": { "Creator": "Dupe2" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.sync_dupe_metadata().unwrap();
 
     assert_tag!(d, "image.jpg", "Creator", "Media");
@@ -274,6 +610,27 @@ mod test_sync_dupe_metadata {
     assert_tag!(d, "image_02.jpg.xmp", "Creator", "Dupe2");
   }
 
+  #[test]
+  fn syncs_label_to_dupes() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": { "Label": "Red" },
+      "image_01.jpg.xmp": {},
+      "image_02.jpg.xmp": { "Label": "Blue" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.sync_dupe_metadata().unwrap();
+
+    assert_tag!(d, "image_01.jpg.xmp", "Label", "Red");
+    assert_tag!(d, "image_02.jpg.xmp", "Label", "Red");
+    assert!(
+      o.dupes
+        .iter_data()
+        .all(|s| s.get_metadata().label.as_deref() == Some("Red"))
+    );
+  }
+
   #[test]
   fn syncs_per_dupe_group() {
     let d = test_dir!(
@@ -285,7 +642,7 @@ mod test_sync_dupe_metadata {
       "image2_01.jpg.xmp": { "Creator": "Dupe2" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.sync_dupe_metadata().unwrap();
 
     assert_tag!(d, "image1_01.jpg.xmp", "Creator", "Sidecar1");
@@ -300,7 +657,7 @@ mod test_sync_dupe_metadata {
       "image_01.jpg.xmp": { "Creator": "Dupe" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     o.sync_dupe_metadata().unwrap();
 
     assert_tag!(d, "image.jpg", "Creator", "Media");
@@ -312,7 +669,7 @@ mod test_sync_dupe_metadata {
 #[cfg(test)]
 mod test_sync_media_metadata {
   use super::*;
-  use crate::testing::*;
+  use crate::{org::ScanConfig, testing::*};
 
   #[test]
   fn overwrites_media_with_sidecar_metadata() {
@@ -322,12 +679,42 @@ mod test_sync_media_metadata {
       "image_01.jpg.xmp": { "Creator": "Dupe" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
-    o.sync_media_metadata().unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.sync_media_metadata(&[]).unwrap();
+
+    assert_tag!(d, "image.jpg", "Creator", "Sidecar");
+  }
+
+  #[test]
+  fn skips_skipped_codecs() {
+    let d = test_dir!(
+      "image.dng": { "Creator": "Raw" },
+      "image.dng.xmp": { "Creator": "RawSidecar" },
+      "image.jpg": { "Creator": "Media" },
+      "image.jpg.xmp": { "Creator": "Sidecar" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.sync_media_metadata(&[Codec::Raw]).unwrap();
 
+    assert_tag!(d, "image.dng", "Creator", "Raw");
     assert_tag!(d, "image.jpg", "Creator", "Sidecar");
   }
 
+  #[test]
+  fn skips_sidecar_only_media() {
+    let d = test_dir!(
+      "image.png": { "Creator": "Media" },
+      "image.png.xmp": { "Creator": "Sidecar" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_sidecar_only_types(["png".to_string()]);
+    o.sync_media_metadata(&[]).unwrap();
+
+    assert_tag!(d, "image.png", "Creator", "Media");
+  }
+
   #[test]
   fn skips_if_missing_sidecar() {
     let d = test_dir!(
@@ -335,8 +722,8 @@ mod test_sync_media_metadata {
       "image_01.jpg.xmp": { "Creator": "Sidecar" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
-    o.sync_media_metadata().unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.sync_media_metadata(&[]).unwrap();
 
     assert_tag!(d, "image.jpg", "Creator", None);
   }
@@ -350,8 +737,8 @@ mod test_sync_media_metadata {
       "image2.jpg.xmp": { "Creator": "Sidecar2" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
-    o.sync_media_metadata().unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.sync_media_metadata(&[]).unwrap();
 
     assert_tag!(d, "image1.jpg", "Creator", "Sidecar1");
     assert_tag!(d, "image2.jpg", "Creator", "Sidecar2");
@@ -365,8 +752,8 @@ mod test_sync_media_metadata {
       "image_01.jpg.xmp": { "Creator": "Dupe" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
-    o.sync_media_metadata().unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.sync_media_metadata(&[]).unwrap();
 
     assert_tag!(d, "image.jpg", "Creator", "Sidecar");
     assert_tag!(d, "image.jpg.xmp", "Creator", "Sidecar");
@@ -2,23 +2,65 @@
 
 //! Organizer Stage 5: Metadata validation.
 
-use super::Organizer;
-use crate::prim::{self, FileMap, Handle, Media, Metadata, Sidecar, SidecarInitial};
+use std::borrow::Cow;
+
+use chrono::{DateTime, Datelike, Local, TimeDelta, Utc};
+use tzf_rs::Finder;
+
+use super::{CopyrightFormat, Organizer, Warning, Warnings};
+use crate::{
+  org,
+  prim::{self, FileMap, Handle, Media, Metadata, Sidecar, SidecarInitial},
+};
 
 /// Stores which validation checks are enabled.
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Default)]
 pub struct ValidationConfig {
-  pub attribution: bool,
-  pub camera:      bool,
-  pub date_time:   bool,
-  pub location:    bool,
+  pub attribution:       bool,
+  /// Whether `Copyright` must include the capture year, as `Copyright <year>
+  /// <creator>`.
+  pub copyright_year:    bool,
+  pub camera:            bool,
+  pub label:             bool,
+  pub date_time:         bool,
+  /// How far after now capture dates may be, if checked.
+  pub future_tolerance:  Option<TimeDelta>,
+  /// How far apart the capture dates of media and their sidecars may be, if
+  /// checked.
+  pub sidecar_tolerance: Option<TimeDelta>,
+  pub location:          bool,
+  pub time_zone:         bool,
+  /// Minimum image resolution, if checked. Videos are exempt.
+  pub min_megapixels:    Option<f32>,
+  /// Additional tags which must be set, by `ExifTool` name.
+  pub required_tags:     Vec<String>,
+  /// Whether to check the sidecar and media together, rather than only the
+  /// sidecar if present.
+  pub merged:            bool,
+  /// Whether to check media extensions match their detected file types.
+  pub format:            bool,
+  /// Whether mismatched extensions pass, as they are corrected when moving.
+  pub format_correct:    bool,
+  /// Whether images must not be rotated or mirrored by their `Orientation`.
+  pub orientation:       bool,
 }
 
 impl ValidationConfig {
   /// If any check is enabled.
-  pub fn enabled(&mut self) -> bool {
-    self.attribution || self.camera || self.date_time || self.location
+  pub fn enabled(&self) -> bool {
+    self.attribution
+      || self.camera
+      || self.label
+      || self.date_time
+      || self.future_tolerance.is_some()
+      || self.sidecar_tolerance.is_some()
+      || self.location
+      || self.time_zone
+      || self.format
+      || self.orientation
+      || self.min_megapixels.is_some()
+      || !self.required_tags.is_empty()
   }
 }
 
@@ -29,12 +71,29 @@ impl Organizer {
     self.validation.attribution = true;
   }
 
+  /// Validates attribution as `enable_attribution_validation`, additionally
+  /// requiring the capture year in `Copyright` (e.g. `Copyright 2023 Creator`
+  /// for media from 2023). Media without a capture date are not checked for
+  /// the year.
+  pub fn enable_copyright_year_validation(&mut self) {
+    log::info!("Copyright year metadata validation enabled.");
+    self.validation.attribution = true;
+    self.validation.copyright_year = true;
+  }
+
   /// Validates whether camera tags (e.g. `Make`, `Model`) are set as expected.
   pub fn enable_camera_validation(&mut self) {
     log::info!("Camera hardware metadata validation enabled.");
     self.validation.camera = true;
   }
 
+  /// Validates whether a color label (`Label`, e.g. `Red` from darktable) is
+  /// set.
+  pub fn enable_label_validation(&mut self) {
+    log::info!("Color label metadata validation enabled.");
+    self.validation.label = true;
+  }
+
   /// Validates whether date and time tags (e.g. `DateTimeOriginal`) are set as
   /// expected.
   pub fn enable_date_time_validation(&mut self) {
@@ -42,6 +101,29 @@ impl Organizer {
     self.validation.date_time = true;
   }
 
+  /// Validates whether capture dates are no later than now plus `tolerance`.
+  /// This catches cameras with dead clocks, which may stamp dates decades
+  /// ahead. Dates without a time zone are taken as local.
+  pub fn enable_future_date_validation(&mut self, tolerance: TimeDelta) {
+    log::info!(
+      "Future date validation enabled (tolerance {}s).",
+      tolerance.num_seconds()
+    );
+    self.validation.future_tolerance = Some(tolerance);
+  }
+
+  /// Validates whether the capture dates of media and their initial sidecars
+  /// are within `tolerance` of each other. Files are named by the sidecar's
+  /// date, so a stale or mistyped one would misfile the media. Media or
+  /// sidecars without a capture date are not checked.
+  pub fn enable_sidecar_date_validation(&mut self, tolerance: TimeDelta) {
+    log::info!(
+      "Sidecar date validation enabled (tolerance {}s).",
+      tolerance.num_seconds()
+    );
+    self.validation.sidecar_tolerance = Some(tolerance);
+  }
+
   /// Validates whether GPS and location tags (e.g. `GPSLatitude`, `City`) are
   /// set as expected.
   pub fn enable_location_validation(&mut self) {
@@ -49,6 +131,66 @@ impl Organizer {
     self.validation.location = true;
   }
 
+  /// Validates whether the stored time zone agrees with the one expected at
+  /// the GPS coordinates, within an hour. This catches cameras left set to the
+  /// wrong time zone, without changing anything.
+  pub fn enable_time_zone_validation(&mut self) {
+    log::info!("Time zone metadata validation enabled.");
+    self.validation.time_zone = true;
+  }
+
+  /// Validates whether images have at least `min_megapixels` resolution.
+  /// Videos are exempt.
+  pub fn enable_resolution_validation(&mut self, min_megapixels: f32) {
+    log::info!("Resolution validation enabled (minimum {min_megapixels} MP).");
+    self.validation.min_megapixels = Some(min_megapixels);
+  }
+
+  /// Validates whether each of `tags` (by `ExifTool` name, e.g. `Label`) is
+  /// set. May be called repeatedly to require more tags.
+  pub fn enable_required_tags_validation(&mut self, tags: impl IntoIterator<Item = String>) {
+    let tags = tags.into_iter().collect::<Vec<_>>();
+    if tags.is_empty() {
+      return;
+    }
+
+    log::info!("Required tags validation enabled ({}).", tags.join(", "));
+    self.validation.required_tags.extend(tags);
+  }
+
+  /// Validates whether the extension of each media file matches the type
+  /// `ExifTool` detects from its contents (e.g. a PNG named `image.jpg`).
+  pub fn enable_format_validation(&mut self) {
+    log::info!("File format validation enabled.");
+    self.validation.format = true;
+  }
+
+  /// Validates whether images are stored upright, rather than relying on their
+  /// `Orientation` tag to be rotated or mirrored when shown.
+  pub fn enable_orientation_validation(&mut self) {
+    log::info!("Orientation validation enabled.");
+    self.validation.orientation = true;
+  }
+
+  /// Lets media with mismatched extensions pass format validation, as
+  /// `move_and_rename_files` names them by their detected type (e.g.
+  /// `image.jpg` holding a PNG is moved as `.png`, along with its sidecars).
+  /// Enables format validation.
+  pub fn enable_format_correction(&mut self) {
+    log::info!("Correcting mismatched extensions when moving.");
+    self.validation.format = true;
+    self.validation.format_correct = true;
+  }
+
+  /// Validates tags of a media file's sidecar and the media itself together,
+  /// with tags missing from the sidecar taken from the media (e.g. GPS from the
+  /// camera, with attribution added in darktable). Otherwise, only the sidecar
+  /// is checked if present.
+  pub fn enable_merged_validation(&mut self) {
+    log::info!("Validating sidecar and media metadata together.");
+    self.validation.merged = true;
+  }
+
   /// Actually runs validation. This batches all operations enabled via calls to
   /// `enable_*_validation` to reduce the number of calls to `ExifTool`.
   pub fn validate(&mut self) {
@@ -58,9 +200,23 @@ impl Organizer {
 
     log::info!("Validating metadata.");
 
-    self
-      .valid_media
-      .extend(validate(&self.media, &self.sidecars, &self.validation));
+    let finder = if self.validation.time_zone {
+      org::load_time_zone_finder()
+    } else {
+      Finder::new()
+    };
+
+    self.valid_media.extend(validate(
+      &self.media,
+      &self.sidecars,
+      &self.validation,
+      self.copyright_format.as_ref(),
+      &finder,
+      &mut self.warnings,
+    ));
+
+    self.report.valid = self.valid_media.len();
+    self.report.invalid = self.media.iter_data().count() - self.report.valid;
   }
 }
 
@@ -70,37 +226,85 @@ fn validate<'a>(
   media: &'a FileMap<Media>,
   sidecars: &'a FileMap<SidecarInitial>,
   config: &'a ValidationConfig,
+  copyright_format: Option<&'a CopyrightFormat>,
+  finder: &'a Finder,
+  warnings: &'a mut Warnings,
 ) -> impl Iterator<Item = Handle<Media>> + 'a {
+  let now = Utc::now();
+
   media
     .iter_data_indexed()
     .map(|(handle_media, media)| {
-      (
-        handle_media,
-        media
-          .get_sidecar()
-          .map_or(media.get_metadata(), |h| sidecars[h].get_metadata()),
-      )
+      let metadata = match media.get_sidecar() {
+        None => Cow::Borrowed(media.get_metadata()),
+        Some(h) if config.merged => Cow::Owned(
+          sidecars[h]
+            .get_metadata()
+            .with_fallback(media.get_metadata()),
+        ),
+        Some(h) => Cow::Borrowed(sidecars[h].get_metadata()),
+      };
+      (handle_media, media, metadata)
     })
-    .filter_map(|(handle_media, metadata)| {
+    .filter_map(move |(handle_media, media, metadata)| {
+      let metadata = &*metadata;
       // Only run each validation if enabled, but make sure all run even if already
       // invalid.
-      let mut valid = !config.attribution || validate_attribution(metadata);
-      valid = (!config.camera || validate_camera(metadata)) && valid;
-      valid = (!config.date_time || validate_date_time(metadata)) && valid;
-      valid = (!config.location || validate_location(metadata)) && valid;
+      let mut valid = !config.attribution
+        || validate_attribution(metadata, config.copyright_year, copyright_format, warnings);
+      valid = (!config.camera || validate_camera(metadata, warnings)) && valid;
+      valid = (!config.label || validate_label(metadata, warnings)) && valid;
+      valid = (!config.date_time || validate_date_time(metadata, warnings)) && valid;
+      valid = config
+        .future_tolerance
+        .is_none_or(|tolerance| validate_not_future(metadata, now, tolerance, warnings))
+        && valid;
+      valid = config
+        .sidecar_tolerance
+        .zip(media.get_sidecar())
+        .is_none_or(|(tolerance, h)| {
+          validate_sidecar_date(
+            media.get_metadata(),
+            sidecars[h].get_metadata(),
+            tolerance,
+            warnings,
+          )
+        })
+        && valid;
+      valid = (!config.location || validate_location(metadata, warnings)) && valid;
+      valid = (!config.time_zone || validate_time_zone(metadata, finder, warnings)) && valid;
+      valid = validate_required_tags(metadata, &config.required_tags, warnings) && valid;
+      // Dimensions and format are properties of the media file itself, not the
+      // sidecar.
+      valid = (!config.format
+        || validate_format(media.get_metadata(), config.format_correct, warnings))
+        && valid;
+      valid = config
+        .min_megapixels
+        .is_none_or(|min| validate_resolution(media.get_metadata(), min, warnings))
+        && valid;
+      valid =
+        (!config.orientation || validate_orientation(media.get_metadata(), warnings)) && valid;
 
       valid.then_some(handle_media)
     })
 }
 
-/// Validates attribution tags in `metadata`.
-fn validate_attribution(metadata: &Metadata) -> bool {
+/// Validates attribution tags in `metadata`, with `Copyright` as `format` if
+/// set, else with the capture year if `year` (e.g. `Copyright 2023 Creator`).
+/// Media without a capture date are not checked for the year.
+fn validate_attribution(
+  metadata: &Metadata,
+  year: bool,
+  format: Option<&CopyrightFormat>,
+  warnings: &mut Warnings,
+) -> bool {
   let creator = metadata.creator.as_ref().ok_or_else(|| {
-    log::warn!("{metadata}: Missing `Creator` tag.");
+    warnings.push(missing_tag(metadata, "Creator"));
   });
 
   let copyright = metadata.copyright.as_ref().ok_or_else(|| {
-    log::warn!("{metadata}: Missing `Copyright` tag.");
+    warnings.push(missing_tag(metadata, "Copyright"));
   });
 
   let Ok(creator) = creator else {
@@ -111,7 +315,25 @@ fn validate_attribution(metadata: &Metadata) -> bool {
     return false;
   };
 
-  if *copyright != format!("Copyright {creator}") {
+  let year_captured = metadata
+    .get_date_time_original()
+    .map(|(date_time, _)| date_time.year());
+  let expected = match format {
+    Some(format) => format.render(creator, year_captured),
+    None if year => year_captured.map(|y| format!("Copyright {y} {creator}")),
+    None => None,
+  };
+
+  if let Some(expected) = expected {
+    if *copyright != expected {
+      warnings.push(invalid_tag(
+        metadata,
+        "Copyright",
+        format!("expected \"{expected}\""),
+      ));
+      return false;
+    }
+  } else if format.is_none() && *copyright != format!("Copyright {creator}") {
     log::debug!("{metadata}: Unexpected `Copyright` format (\"{copyright}\").");
   }
 
@@ -119,40 +341,55 @@ fn validate_attribution(metadata: &Metadata) -> bool {
 }
 
 /// Validates camera tags in `metadata`.
-fn validate_camera(metadata: &Metadata) -> bool {
+fn validate_camera(metadata: &Metadata, warnings: &mut Warnings) -> bool {
   let mut valid = true;
 
   if metadata.make.is_none() {
-    log::warn!("{metadata}: Missing `Make` tag.");
+    warnings.push(missing_tag(metadata, "Make"));
     valid = false;
   }
 
   if metadata.model.is_none() {
-    log::warn!("{metadata}: Missing `Model` tag.");
+    warnings.push(missing_tag(metadata, "Model"));
     valid = false;
   }
 
   valid
 }
 
+/// Validates a color label is set in `metadata`.
+fn validate_label(metadata: &Metadata, warnings: &mut Warnings) -> bool {
+  if metadata.label.as_deref().is_none_or(str::is_empty) {
+    warnings.push(missing_tag(metadata, "Label"));
+    return false;
+  }
+
+  true
+}
+
 /// Validates date and time tags in `metadata`.
 /// This checks that all expected tags are set, as well as their time zones.
-fn validate_date_time(metadata: &Metadata) -> bool {
+fn validate_date_time(metadata: &Metadata, warnings: &mut Warnings) -> bool {
   let date_time_original = metadata
     .date_time_original
     .as_deref()
+    .filter(|d| !prim::is_date_time_unset(d))
     .ok_or_else(|| {
-      log::warn!("{metadata}: Missing `DateTimeOriginal` tag.");
+      warnings.push(missing_tag(metadata, "DateTimeOriginal"));
     })
     .and_then(|_| {
       metadata.get_date_time_original().ok_or_else(|| {
-        log::warn!("{metadata}: Unable to parse `DateTimeOriginal` tag.");
+        warnings.push(invalid_tag(metadata, "DateTimeOriginal", "unable to parse"));
       })
     })
     .and_then(|(d, t)| {
       t.and_then(|t| d.and_local_timezone(t).single())
         .ok_or_else(|| {
-          log::warn!("{metadata}: `DateTimeOriginal` tag is missing time zone.");
+          warnings.push(invalid_tag(
+            metadata,
+            "DateTimeOriginal",
+            "missing time zone",
+          ));
         })
     });
 
@@ -161,17 +398,27 @@ fn validate_date_time(metadata: &Metadata) -> bool {
     .as_deref()
     .or(metadata.create_date.as_deref())
     .ok_or_else(|| {
-      log::warn!("{metadata}: Missing `CreateDate` tag.");
+      warnings.push(missing_tag(metadata, "CreateDate"));
     })
-    .and_then(|d| {
-      prim::parse_date_time(d).map_err(|e| {
-        log::warn!("{metadata}: Unable to parse `CreateDate` tag ({e}).");
-      })
+    .and_then(|d| match prim::parse_date_time(d) {
+      Ok(Some(d)) => Ok(d),
+      Ok(None) => {
+        warnings.push(missing_tag(metadata, "CreateDate"));
+        Err(())
+      }
+      Err(e) => {
+        warnings.push(invalid_tag(
+          metadata,
+          "CreateDate",
+          format!("unable to parse: {e}"),
+        ));
+        Err(())
+      }
     })
     .and_then(|(d, t)| {
       t.and_then(|t| d.and_local_timezone(t).single())
         .ok_or_else(|| {
-          log::warn!("{metadata}: `CreateDate` tag is missing time zone.");
+          warnings.push(invalid_tag(metadata, "CreateDate", "missing time zone"));
         })
     });
 
@@ -184,10 +431,74 @@ fn validate_date_time(metadata: &Metadata) -> bool {
   };
 
   if create_date < date_time_original {
-    log::warn!(
-      "{metadata}: `CreateDate` is before `DateTimeOriginal` ({create_date} < \
-       {date_time_original})."
-    );
+    warnings.push(invalid_tag(
+      metadata,
+      "CreateDate",
+      format!("before `DateTimeOriginal`: {create_date} < {date_time_original}"),
+    ));
+    return false;
+  }
+
+  true
+}
+
+/// Validates the capture date in `metadata` is no later than `now` plus
+/// `tolerance`. Media without a capture date pass, as `validate_date_time`
+/// checks for it.
+fn validate_not_future(
+  metadata: &Metadata,
+  now: DateTime<Utc>,
+  tolerance: TimeDelta,
+  warnings: &mut Warnings,
+) -> bool {
+  let Some((date, time_zone)) = metadata.get_date_time_original() else {
+    return true;
+  };
+
+  let date_time = match time_zone {
+    Some(time_zone) => date
+      .and_local_timezone(time_zone)
+      .single()
+      .map(|d| d.to_utc()),
+    None => date
+      .and_local_timezone(Local)
+      .earliest()
+      .map(|d| d.to_utc()),
+  };
+
+  if let Some(date_time) = date_time
+    && date_time > now + tolerance
+  {
+    warnings.push(invalid_tag(
+      metadata,
+      "DateTimeOriginal",
+      format!("in the future: {date_time}"),
+    ));
+    return false;
+  }
+
+  true
+}
+
+/// Validates the capture date in `sidecar` is within `tolerance` of that in
+/// `media`. Passes if either has no capture date, as `validate_date_time`
+/// checks for it.
+fn validate_sidecar_date(
+  media: &Metadata,
+  sidecar: &Metadata,
+  tolerance: TimeDelta,
+  warnings: &mut Warnings,
+) -> bool {
+  let Some(difference) = sidecar.get_capture_time_difference(media) else {
+    return true;
+  };
+
+  if difference.abs() > tolerance {
+    warnings.push(invalid_tag(
+      sidecar,
+      "DateTimeOriginal",
+      format!("{}s apart from media", difference.num_seconds()),
+    ));
     return false;
   }
 
@@ -195,29 +506,149 @@ fn validate_date_time(metadata: &Metadata) -> bool {
 }
 
 /// Validates GPS and location tags in `metadata` are set.
-fn validate_location(metadata: &Metadata) -> bool {
+fn validate_location(metadata: &Metadata, warnings: &mut Warnings) -> bool {
   let mut valid = true;
 
   if metadata.gps_position.is_none() {
-    log::warn!("{metadata}: Missing `GPSPosition` tag.");
+    warnings.push(missing_tag(metadata, "GPSPosition"));
     valid = false;
   }
   if metadata.city.is_none() {
-    log::warn!("{metadata}: Missing `City` tag.");
+    warnings.push(missing_tag(metadata, "City"));
     valid = false;
   }
   if metadata.state.is_none() {
-    log::warn!("{metadata}: Missing `State` tag.");
+    warnings.push(missing_tag(metadata, "State"));
     valid = false;
   }
   if metadata.country.is_none() {
-    log::warn!("{metadata}: Missing `Country` tag.");
+    warnings.push(missing_tag(metadata, "Country"));
     valid = false;
   }
 
   valid
 }
 
+/// Validates each of `tags` is set in `metadata`.
+fn validate_required_tags(metadata: &Metadata, tags: &[String], warnings: &mut Warnings) -> bool {
+  let mut valid = true;
+
+  for tag in tags {
+    if !metadata.has_tag(tag) {
+      warnings.push(missing_tag(metadata, tag));
+      valid = false;
+    }
+  }
+
+  valid
+}
+
+/// Validates the time zone stored in `metadata` is within an hour of that
+/// expected at its GPS coordinates, on its date. Passes if either is missing.
+fn validate_time_zone(metadata: &Metadata, finder: &Finder, warnings: &mut Warnings) -> bool {
+  let (Some(coord), Some((date_time, _)), Some(offset)) = (
+    metadata.get_gps_coord(),
+    metadata.get_date_time_original(),
+    metadata.get_time_zone(),
+  ) else {
+    return true;
+  };
+
+  let time_zone = finder.get_tz_name(coord.lon, coord.lat);
+  let offset_expected = prim::get_offset_for_time_zone(&date_time, time_zone);
+
+  if (offset.local_minus_utc() - offset_expected.local_minus_utc()).abs() > 3600 {
+    warnings.push(invalid_tag(
+      metadata,
+      "OffsetTimeOriginal",
+      format!("contradicts GPS: {offset} != {offset_expected} in {time_zone}"),
+    ));
+    return false;
+  }
+
+  true
+}
+
+/// Validates images in `metadata` have at least `min_megapixels` resolution.
+/// Videos pass regardless.
+fn validate_resolution(metadata: &Metadata, min_megapixels: f32, warnings: &mut Warnings) -> bool {
+  if metadata.is_video() {
+    return true;
+  }
+
+  let Some(megapixels) = metadata.get_megapixels() else {
+    warnings.push(missing_tag(metadata, "ImageSize"));
+    return false;
+  };
+
+  if megapixels < min_megapixels {
+    warnings.push(invalid_tag(
+      metadata,
+      "ImageSize",
+      format!("resolution too low: {megapixels:.1} < {min_megapixels} MP"),
+    ));
+    return false;
+  }
+
+  true
+}
+
+/// Validates the image in `metadata` is stored upright, i.e. its `Orientation`
+/// is unset or `Horizontal (normal)`.
+fn validate_orientation(metadata: &Metadata, warnings: &mut Warnings) -> bool {
+  match &metadata.orientation {
+    Some(orientation) if !orientation.starts_with("Horizontal") => {
+      warnings.push(invalid_tag(
+        metadata,
+        "Orientation",
+        format!("stored rotated: {orientation}"),
+      ));
+      false
+    }
+    _ => true,
+  }
+}
+
+/// Extensions of the same file type, as `ExifTool`'s extension for it and
+/// another in use.
+const EXTENSION_ALIASES: [(&str, &str); 3] = [("jpg", "jpeg"), ("jpg", "jpe"), ("tif", "tiff")];
+
+/// Validates the extension of the file in `metadata` matches its type as
+/// detected by `ExifTool` (`FileTypeExtension`). Mismatches pass if `correct`,
+/// as moving names files by their detected type.
+fn validate_format(metadata: &Metadata, correct: bool, warnings: &mut Warnings) -> bool {
+  let ext = metadata
+    .source_file
+    .extension()
+    .map(|e| e.to_string_lossy().to_lowercase())
+    .unwrap_or_default();
+  let ext_detected = metadata.file_type_extension.to_lowercase();
+
+  if ext == ext_detected || EXTENSION_ALIASES.contains(&(ext_detected.as_str(), ext.as_str())) {
+    return true;
+  }
+
+  if correct {
+    log::info!("{metadata}: Extension will be corrected to `.{ext_detected}` when moved.");
+    return true;
+  }
+
+  warnings.push(invalid_tag(
+    metadata,
+    "FileType",
+    format!("{} file with extension `.{ext}`", metadata.file_type),
+  ));
+  false
+}
+
+fn missing_tag(metadata: &Metadata, tag: impl Into<String>) -> Warning {
+  Warning::MissingTag(metadata.source_file.clone(), tag.into())
+}
+
+fn invalid_tag(metadata: &Metadata, tag: &'static str, reason: impl Into<String>) -> Warning {
+  Warning::InvalidTag(metadata.source_file.clone(), tag, reason.into())
+}
+
 #[cfg(test)]
 mod test_validation {
   use super::*;
@@ -252,19 +683,41 @@ mod test_validation {
     let mut media = FileMap::new();
     media.insert(
       "image.jpg",
-      Media::new(io::read_metadata(d.get_path("image.jpg")).unwrap()).unwrap(),
+      Media::new(
+        io::read_metadata(&io::ExifToolConfig::default(), d.get_path("image.jpg")).unwrap(),
+      )
+      .unwrap(),
     );
     let handle_media = media.find("image.jpg").unwrap();
 
     let sidecars = FileMap::new();
 
     let config = ValidationConfig {
-      attribution: true,
-      camera:      true,
-      date_time:   true,
-      location:    true,
+      attribution:       true,
+      copyright_year:    false,
+      camera:            true,
+      label:             false,
+      date_time:         true,
+      future_tolerance:  None,
+      sidecar_tolerance: None,
+      location:          true,
+      time_zone:         false,
+      min_megapixels:    None,
+      required_tags:     Vec::new(),
+      merged:            false,
+      format:            false,
+      format_correct:    false,
+      orientation:       false,
     };
-    let valid_handles: Vec<_> = validate(&media, &sidecars, &config).collect();
+    let valid_handles: Vec<_> = validate(
+      &media,
+      &sidecars,
+      &config,
+      None,
+      &Finder::new(),
+      &mut Warnings::default(),
+    )
+    .collect();
 
     assert_eq!(valid_handles, vec![handle_media]);
   }
@@ -295,14 +748,20 @@ mod test_validation {
     let mut media = FileMap::new();
     media.insert(
       "image.jpg",
-      Media::new(io::read_metadata(d.get_path("image.jpg")).unwrap()).unwrap(),
+      Media::new(
+        io::read_metadata(&io::ExifToolConfig::default(), d.get_path("image.jpg")).unwrap(),
+      )
+      .unwrap(),
     );
     let handle_media = media.find("image.jpg").unwrap();
 
     let mut sidecars = FileMap::new();
     sidecars.insert(
       "image.jpg.xmp",
-      SidecarInitial::new(io::read_metadata(d.get_path("image.jpg.xmp")).unwrap()).unwrap(),
+      SidecarInitial::new(
+        io::read_metadata(&io::ExifToolConfig::default(), d.get_path("image.jpg.xmp")).unwrap(),
+      )
+      .unwrap(),
     );
     let handle_sidecar = sidecars.find("image.jpg.xmp").unwrap();
 
@@ -318,19 +777,148 @@ mod test_validation {
       .set_media_handle(handle_media);
 
     let config = ValidationConfig {
-      attribution: true,
-      camera:      true,
-      date_time:   true,
-      location:    true,
+      attribution:       true,
+      copyright_year:    false,
+      camera:            true,
+      label:             false,
+      date_time:         true,
+      future_tolerance:  None,
+      sidecar_tolerance: None,
+      location:          true,
+      time_zone:         false,
+      min_megapixels:    None,
+      required_tags:     Vec::new(),
+      merged:            false,
+      format:            false,
+      format_correct:    false,
+      orientation:       false,
     };
-    let valid_handles: Vec<_> = validate(&media, &sidecars, &config).collect();
+    let valid_handles: Vec<_> = validate(
+      &media,
+      &sidecars,
+      &config,
+      None,
+      &Finder::new(),
+      &mut Warnings::default(),
+    )
+    .collect();
 
     assert_eq!(valid_handles, vec![handle_media]);
   }
+
+  #[test]
+  fn validates_sidecar_and_media_together_if_merged() {
+    let d = test_dir!(
+      "image.jpg": {
+        // Location.
+        "GPSLatitude": "47.6061",
+        "GPSLatitudeRef": "N",
+        "GPSLongitude": "122.3328",
+        "GPSLongitudeRef": "W",
+        "City": "Seattle",
+        "State": "Washington",
+        "Country": "United States",
+      },
+      "image.jpg.xmp": {
+        // Attribution.
+        "Creator": "Creator",
+        "Copyright": "Copyright",
+      },
+    );
+
+    let mut media = FileMap::new();
+    media.insert(
+      "image.jpg",
+      Media::new(
+        io::read_metadata(&io::ExifToolConfig::default(), d.get_path("image.jpg")).unwrap(),
+      )
+      .unwrap(),
+    );
+    let handle_media = media.find("image.jpg").unwrap();
+
+    let mut sidecars = FileMap::new();
+    sidecars.insert(
+      "image.jpg.xmp",
+      SidecarInitial::new(
+        io::read_metadata(&io::ExifToolConfig::default(), d.get_path("image.jpg.xmp")).unwrap(),
+      )
+      .unwrap(),
+    );
+    let handle_sidecar = sidecars.find("image.jpg.xmp").unwrap();
+
+    media
+      .get_entry_mut(handle_media)
+      .as_mut()
+      .unwrap()
+      .set_sidecar(handle_sidecar);
+
+    let mut config = ValidationConfig {
+      attribution: true,
+      location: true,
+      ..Default::default()
+    };
+    let valid_handles: Vec<_> = validate(
+      &media,
+      &sidecars,
+      &config,
+      None,
+      &Finder::new(),
+      &mut Warnings::default(),
+    )
+    .collect();
+    assert!(valid_handles.is_empty());
+
+    config.merged = true;
+    let valid_handles: Vec<_> = validate(
+      &media,
+      &sidecars,
+      &config,
+      None,
+      &Finder::new(),
+      &mut Warnings::default(),
+    )
+    .collect();
+    assert_eq!(valid_handles, vec![handle_media]);
+  }
+
+  #[test]
+  fn is_invalid_if_media_rotated() {
+    let d = test_dir!(
+      "image.jpg": { "Orientation": "Rotate 90 CW" },
+    );
+
+    let mut media = FileMap::new();
+    media.insert(
+      "image.jpg",
+      Media::new(
+        io::read_metadata(&io::ExifToolConfig::default(), d.get_path("image.jpg")).unwrap(),
+      )
+      .unwrap(),
+    );
+
+    let sidecars = FileMap::new();
+
+    let config = ValidationConfig {
+      orientation: true,
+      ..Default::default()
+    };
+    let valid_handles: Vec<_> = validate(
+      &media,
+      &sidecars,
+      &config,
+      None,
+      &Finder::new(),
+      &mut Warnings::default(),
+    )
+    .collect();
+    assert!(valid_handles.is_empty());
+  }
 }
 
 #[cfg(test)]
 mod test_validate_attribution {
+  use std::path::PathBuf;
+
   use super::*;
   use crate::testing::*;
 
@@ -340,7 +928,12 @@ mod test_validate_attribution {
       "Creator": "Creator",
     );
 
-    assert!(!validate_attribution(&metadata));
+    assert!(!validate_attribution(
+      &metadata,
+      false,
+      None,
+      &mut Warnings::default()
+    ));
   }
 
   #[test]
@@ -349,7 +942,12 @@ mod test_validate_attribution {
       "Copyright": "Copyright",
     );
 
-    assert!(!validate_attribution(&metadata));
+    assert!(!validate_attribution(
+      &metadata,
+      false,
+      None,
+      &mut Warnings::default()
+    ));
   }
 
   #[test]
@@ -359,7 +957,120 @@ mod test_validate_attribution {
       "Copyright": "Copyright",
     );
 
-    assert!(validate_attribution(&metadata));
+    assert!(validate_attribution(
+      &metadata,
+      false,
+      None,
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn is_invalid_if_copyright_year_mismatched() {
+    let metadata = metadata!(
+      "SourceFile": "./image.jpg",
+      "Creator": "Creator",
+      "Copyright": "Copyright 2022 Creator",
+      "DateTimeOriginal": "2023-06-01T12:00:00",
+    );
+
+    let mut warnings = Warnings::default();
+
+    assert!(!validate_attribution(&metadata, true, None, &mut warnings));
+    assert_eq!(warnings.take(), [Warning::InvalidTag(
+      PathBuf::from("./image.jpg"),
+      "Copyright",
+      "expected \"Copyright 2023 Creator\"".to_string(),
+    )]);
+  }
+
+  #[test]
+  fn is_invalid_if_copyright_year_missing() {
+    let metadata = metadata!(
+      "Creator": "Creator",
+      "Copyright": "Copyright Creator",
+      "DateTimeOriginal": "2023-06-01T12:00:00",
+    );
+
+    assert!(!validate_attribution(
+      &metadata,
+      true,
+      None,
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn passes_matching_copyright_year() {
+    let metadata = metadata!(
+      "Creator": "Creator",
+      "Copyright": "Copyright 2023 Creator",
+      "DateTimeOriginal": "2023-06-01T12:00:00",
+    );
+
+    assert!(validate_attribution(
+      &metadata,
+      true,
+      None,
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn passes_without_capture_date_if_checking_year() {
+    let metadata = metadata!(
+      "Creator": "Creator",
+      "Copyright": "Copyright Creator",
+    );
+
+    assert!(validate_attribution(
+      &metadata,
+      true,
+      None,
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn passes_matching_format() {
+    let metadata = metadata!(
+      "Creator": "Creator",
+      "Copyright": "© 2023 Creator, All Rights Reserved",
+      "DateTimeOriginal": "2023-06-01T12:00:00",
+    );
+    let format = CopyrightFormat::new("© ${Year} ${Creator}, All Rights Reserved").unwrap();
+
+    assert!(validate_attribution(
+      &metadata,
+      false,
+      Some(&format),
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn is_invalid_if_format_mismatched() {
+    let metadata = metadata!(
+      "SourceFile": "./image.jpg",
+      "Creator": "Creator",
+      "Copyright": "Copyright Creator",
+      "DateTimeOriginal": "2023-06-01T12:00:00",
+    );
+    let format = CopyrightFormat::new("© ${Year} ${Creator}, All Rights Reserved").unwrap();
+
+    let mut warnings = Warnings::default();
+
+    assert!(!validate_attribution(
+      &metadata,
+      false,
+      Some(&format),
+      &mut warnings
+    ));
+    assert_eq!(warnings.take(), [Warning::InvalidTag(
+      PathBuf::from("./image.jpg"),
+      "Copyright",
+      "expected \"© 2023 Creator, All Rights Reserved\"".to_string(),
+    )]);
   }
 }
 
@@ -374,7 +1085,7 @@ mod test_validate_camera {
       "Model": "Model",
     );
 
-    assert!(!validate_camera(&metadata));
+    assert!(!validate_camera(&metadata, &mut Warnings::default()));
   }
 
   #[test]
@@ -383,7 +1094,7 @@ mod test_validate_camera {
       "Make": "Make",
     );
 
-    assert!(!validate_camera(&metadata));
+    assert!(!validate_camera(&metadata, &mut Warnings::default()));
   }
 
   #[test]
@@ -393,7 +1104,42 @@ mod test_validate_camera {
       "Model": "Model",
     );
 
-    assert!(validate_camera(&metadata));
+    assert!(validate_camera(&metadata, &mut Warnings::default()));
+  }
+}
+
+#[cfg(test)]
+mod test_validate_label {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn is_invalid_if_no_label() {
+    let metadata = metadata!(
+      "Rating": 3,
+    );
+
+    let mut warnings = Warnings::default();
+    assert!(!validate_label(&metadata, &mut warnings));
+    assert_eq!(warnings.take().len(), 1);
+  }
+
+  #[test]
+  fn is_invalid_if_empty_label() {
+    let metadata = metadata!(
+      "Label": "",
+    );
+
+    assert!(!validate_label(&metadata, &mut Warnings::default()));
+  }
+
+  #[test]
+  fn passes_valid() {
+    let metadata = metadata!(
+      "Label": "Red",
+    );
+
+    assert!(validate_label(&metadata, &mut Warnings::default()));
   }
 }
 
@@ -409,7 +1155,7 @@ mod test_validate_date_time {
       "CreateDate": "2000-01-01T00:00:00+00:00",
     );
 
-    assert!(!validate_date_time(&metadata));
+    assert!(!validate_date_time(&metadata, &mut Warnings::default()));
   }
 
   #[test]
@@ -418,7 +1164,7 @@ mod test_validate_date_time {
       "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
     );
 
-    assert!(!validate_date_time(&metadata));
+    assert!(!validate_date_time(&metadata, &mut Warnings::default()));
   }
 
   #[test]
@@ -427,7 +1173,7 @@ mod test_validate_date_time {
       "CreateDate": "2000-01-01T00:00:00+00:00",
     );
 
-    assert!(!validate_date_time(&metadata));
+    assert!(!validate_date_time(&metadata, &mut Warnings::default()));
   }
 
   #[test]
@@ -437,7 +1183,7 @@ mod test_validate_date_time {
       "CreateDate": "2000-01-01T00:00:00",
     );
 
-    assert!(!validate_date_time(&metadata));
+    assert!(!validate_date_time(&metadata, &mut Warnings::default()));
   }
 
   #[test]
@@ -447,7 +1193,37 @@ mod test_validate_date_time {
       "CreateDate": "2000-01-01T00:00:00+00:00",
     );
 
-    assert!(!validate_date_time(&metadata));
+    assert!(!validate_date_time(&metadata, &mut Warnings::default()));
+  }
+
+  #[test]
+  fn treats_unset_date_time_original_as_missing() {
+    let metadata = metadata!(
+      "DateTimeOriginal": "0000:00:00 00:00:00",
+      "CreateDate": "2000-01-01T00:00:00+00:00",
+    );
+    let mut warnings = Warnings::default();
+
+    assert!(!validate_date_time(&metadata, &mut warnings));
+    assert_eq!(warnings.take(), [Warning::MissingTag(
+      std::path::PathBuf::from("-"),
+      "DateTimeOriginal".to_string()
+    )]);
+  }
+
+  #[test]
+  fn treats_unset_create_date_as_missing() {
+    let metadata = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
+      "CreateDate": "0000:00:00 00:00:00",
+    );
+    let mut warnings = Warnings::default();
+
+    assert!(!validate_date_time(&metadata, &mut warnings));
+    assert_eq!(warnings.take(), [Warning::MissingTag(
+      std::path::PathBuf::from("-"),
+      "CreateDate".to_string()
+    )]);
   }
 
   #[test]
@@ -460,7 +1236,7 @@ mod test_validate_date_time {
       "OffsetTimeDigitized": "+00:00",
       "SubSecCreateDate": "2000-01-01T00:00:00+00:00",
     );
-    assert!(validate_date_time(&metadata));
+    assert!(validate_date_time(&metadata, &mut Warnings::default()));
   }
 
   #[test]
@@ -469,7 +1245,217 @@ mod test_validate_date_time {
       "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
       "CreateDate": "2000-01-01T00:00:00+00:00",
     );
-    assert!(validate_date_time(&metadata));
+    assert!(validate_date_time(&metadata, &mut Warnings::default()));
+  }
+}
+
+#[cfg(test)]
+mod test_validate_not_future {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn is_invalid_if_in_future() {
+    let metadata = metadata!(
+      "DateTimeOriginal": "2100-01-01T00:00:00+00:00",
+    );
+
+    assert!(!validate_not_future(
+      &metadata,
+      Utc::now(),
+      TimeDelta::minutes(5),
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn passes_present_date() {
+    let now = Utc::now();
+    let metadata = metadata!(
+      "DateTimeOriginal": (now.format("%Y-%m-%dT%H:%M:%S+00:00").to_string()),
+    );
+
+    assert!(validate_not_future(
+      &metadata,
+      now,
+      TimeDelta::minutes(5),
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn passes_within_tolerance() {
+    let metadata = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:04:00+00:00",
+    );
+    let now = DateTime::parse_from_rfc3339("2000-01-01T00:00:00+00:00")
+      .unwrap()
+      .to_utc();
+
+    assert!(validate_not_future(
+      &metadata,
+      now,
+      TimeDelta::minutes(5),
+      &mut Warnings::default()
+    ));
+    assert!(!validate_not_future(
+      &metadata,
+      now,
+      TimeDelta::minutes(3),
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn compares_in_time_zone() {
+    let metadata = metadata!(
+      "DateTimeOriginal": "2000-01-01T08:00:00+08:00",
+    );
+    let now = DateTime::parse_from_rfc3339("2000-01-01T00:00:00+00:00")
+      .unwrap()
+      .to_utc();
+
+    assert!(validate_not_future(
+      &metadata,
+      now,
+      TimeDelta::zero(),
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn passes_without_date() {
+    let metadata = metadata!();
+
+    assert!(validate_not_future(
+      &metadata,
+      Utc::now(),
+      TimeDelta::zero(),
+      &mut Warnings::default()
+    ));
+  }
+}
+
+#[cfg(test)]
+mod test_validate_sidecar_date {
+  use std::path::PathBuf;
+
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn passes_matching_dates() {
+    let media = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
+    );
+    let sidecar = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
+    );
+
+    assert!(validate_sidecar_date(
+      &media,
+      &sidecar,
+      TimeDelta::zero(),
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn is_invalid_if_dates_mismatched() {
+    let media = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
+    );
+    let sidecar = metadata!(
+      "SourceFile": "image.jpg.xmp",
+      "DateTimeOriginal": "2001-01-01T00:00:00+00:00",
+    );
+    let mut warnings = Warnings::default();
+
+    assert!(!validate_sidecar_date(
+      &media,
+      &sidecar,
+      TimeDelta::minutes(1),
+      &mut warnings
+    ));
+    assert_eq!(warnings.take(), [Warning::InvalidTag(
+      PathBuf::from("image.jpg.xmp"),
+      "DateTimeOriginal",
+      "31622400s apart from media".to_string(),
+    )]);
+  }
+
+  #[test]
+  fn passes_within_tolerance() {
+    let media = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
+    );
+    let sidecar = metadata!(
+      "DateTimeOriginal": "1999-12-31T23:59:30+00:00",
+    );
+
+    assert!(validate_sidecar_date(
+      &media,
+      &sidecar,
+      TimeDelta::minutes(1),
+      &mut Warnings::default()
+    ));
+    assert!(!validate_sidecar_date(
+      &media,
+      &sidecar,
+      TimeDelta::seconds(10),
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn compares_in_time_zone() {
+    let media = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
+    );
+    let sidecar = metadata!(
+      "DateTimeOriginal": "2000-01-01T08:00:00+08:00",
+    );
+
+    assert!(validate_sidecar_date(
+      &media,
+      &sidecar,
+      TimeDelta::zero(),
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn passes_without_date() {
+    let media = metadata!();
+    let sidecar = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
+    );
+
+    assert!(validate_sidecar_date(
+      &media,
+      &sidecar,
+      TimeDelta::zero(),
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn skips_media_without_sidecar() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00+00:00" },
+      "image1.jpg.xmp": { "DateTimeOriginal": "2001-01-01T00:00:00+00:00" },
+      "image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00+00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &org::ScanConfig::default()).unwrap();
+    o.enable_sidecar_date_validation(TimeDelta::zero());
+    o.validate();
+
+    assert_eq!(o.valid_media.len(), 1);
+    assert!(
+      o.valid_media
+        .contains(&o.media.find(d.get_path("image2.jpg")).unwrap())
+    );
   }
 }
 
@@ -486,7 +1472,7 @@ mod test_validate_location {
       "Country": "United States",
     );
 
-    assert!(!validate_location(&metadata));
+    assert!(!validate_location(&metadata, &mut Warnings::default()));
   }
 
   #[test]
@@ -499,7 +1485,7 @@ mod test_validate_location {
       "GPSPosition": "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W",
     );
 
-    assert!(!validate_location(&metadata));
+    assert!(!validate_location(&metadata, &mut Warnings::default()));
   }
 
   #[test]
@@ -515,7 +1501,7 @@ mod test_validate_location {
       "Country": "United States",
     );
 
-    assert!(validate_location(&metadata));
+    assert!(validate_location(&metadata, &mut Warnings::default()));
   }
 
   #[test]
@@ -529,6 +1515,259 @@ mod test_validate_location {
       "Country": "United States",
     );
 
-    assert!(validate_location(&metadata));
+    assert!(validate_location(&metadata, &mut Warnings::default()));
+  }
+}
+
+#[cfg(test)]
+mod test_validate_required_tags {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn is_invalid_if_custom_tag_missing() {
+    let metadata = metadata!(
+      "Label": "Red",
+    );
+    let mut warnings = Warnings::default();
+
+    assert!(!validate_required_tags(
+      &metadata,
+      &["Label".to_string(), "Title".to_string()],
+      &mut warnings
+    ));
+    assert_eq!(warnings.take(), [Warning::MissingTag(
+      std::path::PathBuf::from("-"),
+      "Title".to_string()
+    )]);
+  }
+
+  #[test]
+  fn passes_if_custom_tags_present() {
+    let metadata = metadata!(
+      "Label": "Red",
+      "Title": "Title",
+    );
+
+    assert!(validate_required_tags(
+      &metadata,
+      &["Label".to_string(), "Title".to_string()],
+      &mut Warnings::default()
+    ));
+  }
+}
+
+#[cfg(test)]
+mod test_validate_resolution {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn is_invalid_if_below_minimum() {
+    let metadata = metadata!(
+      "MIMEType": "image/jpeg",
+      "ImageWidth": 1000,
+      "ImageHeight": 999,
+    );
+
+    assert!(!validate_resolution(
+      &metadata,
+      1.0,
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn is_invalid_if_missing_dimensions() {
+    let metadata = metadata!(
+      "MIMEType": "image/jpeg",
+    );
+
+    assert!(!validate_resolution(
+      &metadata,
+      1.0,
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn passes_at_or_above_minimum() {
+    let metadata = metadata!(
+      "MIMEType": "image/jpeg",
+      "ImageWidth": 1000,
+      "ImageHeight": 1000,
+    );
+
+    assert!(validate_resolution(
+      &metadata,
+      1.0,
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn passes_video() {
+    let metadata = metadata!(
+      "MIMEType": "video/quicktime",
+      "ImageWidth": 10,
+      "ImageHeight": 10,
+    );
+
+    assert!(validate_resolution(
+      &metadata,
+      1.0,
+      &mut Warnings::default()
+    ));
+  }
+}
+
+#[cfg(test)]
+mod test_validate_orientation {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn is_invalid_if_rotated() {
+    let metadata = metadata!(
+      "Orientation": "Rotate 90 CW",
+    );
+
+    assert!(!validate_orientation(&metadata, &mut Warnings::default()));
+  }
+
+  #[test]
+  fn is_invalid_if_mirrored() {
+    let metadata = metadata!(
+      "Orientation": "Mirror horizontal",
+    );
+
+    assert!(!validate_orientation(&metadata, &mut Warnings::default()));
+  }
+
+  #[test]
+  fn passes_upright() {
+    let metadata = metadata!(
+      "Orientation": "Horizontal (normal)",
+    );
+
+    assert!(validate_orientation(&metadata, &mut Warnings::default()));
+  }
+
+  #[test]
+  fn passes_if_missing() {
+    let metadata = metadata!();
+
+    assert!(validate_orientation(&metadata, &mut Warnings::default()));
+  }
+}
+
+#[cfg(test)]
+mod test_validate_format {
+  use std::path::PathBuf;
+
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn is_invalid_if_extension_mismatched() {
+    let metadata = metadata!(
+      "SourceFile": "./image.jpg",
+      "FileType": "PNG",
+      "FileTypeExtension": "PNG",
+    );
+    let mut warnings = Warnings::default();
+
+    assert!(!validate_format(&metadata, false, &mut warnings));
+    assert_eq!(warnings.take(), [Warning::InvalidTag(
+      PathBuf::from("./image.jpg"),
+      "FileType",
+      "PNG file with extension `.jpg`".to_string(),
+    )]);
+  }
+
+  #[test]
+  fn passes_mismatched_extension_if_correcting() {
+    let metadata = metadata!(
+      "SourceFile": "./image.jpg",
+      "FileType": "PNG",
+      "FileTypeExtension": "PNG",
+    );
+    let mut warnings = Warnings::default();
+
+    assert!(validate_format(&metadata, true, &mut warnings));
+    assert!(warnings.take().is_empty());
+  }
+
+  #[test]
+  fn passes_matching_extension_in_any_case_or_alias() {
+    for (file, ext) in [
+      ("./image.jpg", "JPG"),
+      ("./image.JPG", "JPG"),
+      ("./image.jpeg", "JPG"),
+      ("./image.tiff", "TIF"),
+    ] {
+      let metadata = metadata!(
+        "SourceFile": file,
+        "FileTypeExtension": ext,
+      );
+
+      assert!(
+        validate_format(&metadata, false, &mut Warnings::default()),
+        "{file}"
+      );
+    }
+  }
+}
+
+#[cfg(test)]
+mod test_validate_time_zone {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn is_invalid_if_offset_contradicts_gps() {
+    let metadata = metadata!(
+      "FileType": "JPEG",
+      "DateTimeOriginal": "2000-01-01T00:00:00+09:00",
+      "OffsetTimeOriginal": "+09:00",
+      "GPSPosition": "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W",
+    );
+
+    assert!(!validate_time_zone(
+      &metadata,
+      &org::load_time_zone_finder(),
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn passes_valid() {
+    let metadata = metadata!(
+      "FileType": "JPEG",
+      "DateTimeOriginal": "2000-01-01T00:00:00-08:00",
+      "OffsetTimeOriginal": "-08:00",
+      "GPSPosition": "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W",
+    );
+
+    assert!(validate_time_zone(
+      &metadata,
+      &org::load_time_zone_finder(),
+      &mut Warnings::default()
+    ));
+  }
+
+  #[test]
+  fn passes_if_no_gps() {
+    let metadata = metadata!(
+      "FileType": "JPEG",
+      "DateTimeOriginal": "2000-01-01T00:00:00+09:00",
+      "OffsetTimeOriginal": "+09:00",
+    );
+
+    assert!(validate_time_zone(
+      &metadata,
+      &Finder::new(),
+      &mut Warnings::default()
+    ));
   }
 }
@@ -3,56 +3,582 @@
 //! Stage 6: Moving and renaming of files.
 
 use std::{
+  collections::{HashMap, HashSet},
   ffi::OsString,
+  fs,
+  iter,
+  mem,
   path::{Path, PathBuf},
 };
 
-use super::Organizer;
+use chrono::{Datelike, FixedOffset};
+
+use super::{Organizer, Warning, stage_1_cleanup::remove_by_path, warnings::Warnings};
 use crate::{
+  error::CatalogError,
   io,
   org,
-  prim::{FileMap, Handle, Media, SidecarDupe, SidecarInitial},
+  prim::{
+    Adjustment,
+    FileMap,
+    Handle,
+    LivePhotoID,
+    Media,
+    Metadata,
+    Sidecar,
+    SidecarDupe,
+    SidecarInitial,
+  },
 };
 
+/// Pre-flight check in `move_and_rename_files` that the destination's drive
+/// has room for the files moved onto it.
+pub struct FreeSpaceCheck {
+  /// Bytes to leave free after moving.
+  headroom:        u64,
+  /// Gets the bytes available on the drive of a directory.
+  available_space: fn(&Path) -> Result<u64, CatalogError>,
+}
+
+/// Extensions to give moved media (and their sidecars), by `ExifTool`'s
+/// `FileTypeExtension` (e.g. `jpg`), which is used if not overridden.
+#[derive(Clone, Default)]
+pub struct ExtensionMap {
+  overrides: HashMap<String, String>,
+  lowercase: bool,
+}
+
+impl ExtensionMap {
+  /// Gives media with extension `from` (in any case) `to` instead.
+  pub fn insert(&mut self, from: &str, to: impl Into<String>) {
+    self.overrides.insert(from.to_lowercase(), to.into());
+  }
+
+  /// Forces extensions to lowercase, including overrides.
+  pub fn enable_lowercase(&mut self) {
+    self.lowercase = true;
+  }
+
+  /// Gets the extension to give media with extension `ext`.
+  fn get(&self, ext: &str) -> String {
+    let ext = self
+      .overrides
+      .get(&ext.to_lowercase())
+      .map_or(ext, String::as_str);
+
+    if self.lowercase {
+      ext.to_lowercase()
+    } else {
+      ext.to_string()
+    }
+  }
+}
+
+/// Kind of media, by which `move_and_rename_files` may move media into separate
+/// directories (see `MediaDirs`).
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum MediaKind {
+  #[default]
+  Photo,
+  Video,
+}
+
+impl MediaKind {
+  /// Gets the kind of `media`, by its MIME type.
+  pub(crate) fn of(media: &Media) -> Self {
+    if media.get_metadata().is_video() {
+      Self::Video
+    } else {
+      Self::Photo
+    }
+  }
+}
+
+/// Directories under the destination to move photos and videos into separately
+/// (e.g. `Photos` and `Videos`), each organized as `yyyy/mm` as usual.
+#[derive(Clone)]
+pub struct MediaDirs {
+  pub photos:      PathBuf,
+  pub videos:      PathBuf,
+  /// Directory Live Photos are moved into, with their images and videos kept
+  /// together.
+  pub live_photos: MediaKind,
+}
+
+impl MediaDirs {
+  /// Gets the directory for media of `kind`.
+  fn get(&self, kind: MediaKind) -> &Path {
+    match kind {
+      MediaKind::Photo => &self.photos,
+      MediaKind::Video => &self.videos,
+    }
+  }
+}
+
+/// Directory under the destination of `move_and_rename_files` that media
+/// without a date are moved into, by `MissingDatePolicy::MoveToUndated`.
+const UNDATED_DIR: &str = "undated";
+
+/// What `move_and_rename_files` does with media without a date to name it by
+/// (e.g. scans, or images stripped of metadata). Live Photos follow their main
+/// image.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum MissingDatePolicy {
+  /// Leave the media and its sidecars in place, with a warning.
+  #[default]
+  Skip,
+  /// Move the media and its sidecars into `undated` under the destination,
+  /// keeping their names (adding a counter if taken).
+  MoveToUndated,
+  /// Name the media and its sidecars by the media's `FileModifyDate`.
+  UseFileModifyDate,
+}
+
+impl MissingDatePolicy {
+  /// Gets the policy to apply to media named by `metadata`, or `None` if it has
+  /// a date to be named by in `format`.
+  fn applies_to(self, metadata: &Metadata, format: io::FileNameFormat) -> Option<Self> {
+    io::format_file_name_date(metadata, format)
+      .is_err()
+      .then_some(self)
+  }
+}
+
 impl Organizer {
-  /// Moves loaded files to `dst`, organizing them into subdirectories and
-  /// renaming them based on their timestamps.
-  /// Unless `force` is true, this will only touch validated files.
-  pub fn move_and_rename_files(mut self, dst: impl AsRef<Path>, force: bool) -> Result<(), String> {
-    if dst.as_ref().is_relative() {
-      return Err(format!(
-        "{}: Destination path is not absolute.",
-        dst.as_ref().display()
-      ));
+  /// Writes `digits` digits of sub-seconds into file names, instead of the
+  /// default of milliseconds.
+  pub fn set_sub_sec_digits(&mut self, digits: u8) {
+    log::info!("Using {digits} sub-second digits in file names.");
+    self.file_name_format.sub_sec_digits = digits;
+  }
+
+  /// Names (and folders) files by the date and time in their own time zone,
+  /// instead of UTC.
+  pub fn enable_local_time_file_names(&mut self) {
+    log::info!("Using local time in file names.");
+    self.file_name_format.local_time = true;
+  }
+
+  /// Writes the counter for files at the same time in `style`, instead of the
+  /// default of letters.
+  pub fn set_counter_style(&mut self, style: io::CounterStyle) {
+    self.file_name_format.counter = style;
+  }
+
+  /// Names files by the date tag of `source` (e.g. `CreateDate` for scanned
+  /// film), instead of the default of `DateTimeOriginal`.
+  pub fn set_rename_date_source(&mut self, source: io::RenameDateSource) {
+    self.file_name_format.date_source = source;
+  }
+
+  /// Renames files in `move_and_rename_files` within the directories they are
+  /// already in, rather than moving them under `yyyy/mm` directories in the
+  /// destination. Collisions still get counters, and sidecars follow their
+  /// media. Live Photos are renamed into their main image's directory, and
+  /// media directories (see `set_media_dirs`) are not used.
+  pub fn enable_rename_only(&mut self) {
+    log::info!("Renaming files in place, without moving into date directories.");
+    self.rename_only = true;
+    self.file_name_format.date_dirs = false;
+  }
+
+  /// Keeps the modify date (`FileModifyDate`) of files moved by
+  /// `move_and_rename_files` and `flatten_directory`, which could otherwise be
+  /// reset to the time of the move (e.g. when moving to another drive).
+  pub fn enable_preserve_modify_dates(&mut self) {
+    log::info!("Preserving modify dates of moved files.");
+    self.preserve_modify_dates = true;
+  }
+
+  /// Removes directories under the source (and merged catalogs) left empty
+  /// after `move_and_rename_files` moves files out, along with parents left
+  /// empty in turn. Directories already empty, trash, excluded directories, and
+  /// the destination if a subdirectory of the source, are kept.
+  pub fn enable_remove_empty_dirs(&mut self) {
+    log::info!("Removing empty directories after moving files.");
+    self.remove_empty_dirs = true;
+  }
+
+  /// Before `move_and_rename_files` adds a counter to media named the same as
+  /// another file (i.e. with the same capture time), compares their contents,
+  /// and moves the media and its sidecars to trash instead if identical. Live
+  /// Photos are moved as groups, so are not compared.
+  pub fn enable_trash_identical_collisions(&mut self) {
+    log::info!("Trashing media identical to others at the same time.");
+    self.trash_identical = true;
+  }
+
+  /// Checks in `move_and_rename_files`, before moving anything, that the
+  /// destination's drive has room for all files from other drives (which are
+  /// copied, rather than renamed), leaving at least `headroom` bytes free.
+  /// Nothing is moved otherwise.
+  pub fn enable_free_space_check(&mut self, headroom: u64) {
+    log::info!("Checking free space before moving ({headroom} bytes headroom).");
+    self.free_space_check = Some(FreeSpaceCheck {
+      headroom,
+      available_space: |dir| io::get_available_space(dir),
+    });
+  }
+
+  /// Moves Live Photos and other media in order of path in
+  /// `move_and_rename_files` (and plans them so in `rename_preview`), so that
+  /// files at the same time get the same counters on every run. Otherwise,
+  /// they're moved in the order loaded, which may vary.
+  pub fn enable_ordered_moves(&mut self) {
+    log::info!("Moving files in order of path.");
+    self.ordered_moves = true;
+  }
+
+  /// Moves photos and videos into separate directories under the destination
+  /// of `move_and_rename_files`, as set by `dirs`, instead of together.
+  pub fn set_media_dirs(&mut self, dirs: MediaDirs) {
+    log::info!(
+      "Moving photos into {} and videos into {}.",
+      dirs.photos.display(),
+      dirs.videos.display()
+    );
+    self.media_dirs = Some(dirs);
+  }
+
+  /// Handles media without a date to name it by in `move_and_rename_files` (and
+  /// `rename_preview`) per `policy`, instead of the default of leaving it in
+  /// place.
+  pub fn set_missing_date_policy(&mut self, policy: MissingDatePolicy) {
+    self.missing_date = policy;
+  }
+
+  /// Gives moved files extensions from `extensions`, instead of only those from
+  /// `ExifTool`.
+  pub fn set_extension_map(&mut self, extensions: ExtensionMap) {
+    self.extension_map = extensions;
+  }
+
+  /// Calls `callback` as `move_and_rename_files` moves each group of files,
+  /// for reporting progress.
+  pub fn set_progress_callback(&mut self, callback: impl Fn(usize, usize, &Path) + 'static) {
+    self.progress = Some(Box::new(callback));
+  }
+
+  /// Moves all files out of their subdirectories (e.g. the `yyyy/mm` structure
+  /// from `move_and_rename_files`) into `dst`, keeping their names, then
+  /// removes subdirectories left empty. Media are moved along with their
+  /// sidecars, adding the same-time counter to names already taken.
+  /// Returns warnings not yet taken, including any files skipped after errors.
+  pub fn flatten_directory(mut self, dst: impl AsRef<Path>) -> Result<Vec<Warning>, CatalogError> {
+    let dst = dst.as_ref();
+
+    check_destination(dst)?;
+
+    log::info!("Flattening into {}.", dst.display());
+
+    let counter = self.file_name_format.counter;
+    let preserve = self.preserve_modify_dates;
+    let mut dirs_vacated = HashSet::new();
+
+    for entry in self.media.iter_entries_mut() {
+      let media = entry.take().unwrap();
+      let sidecar = take_sidecar(&media, &mut self.sidecars);
+      let dupes = take_dupes(&media, &mut self.dupes);
+      let adjustment = take_adjustment(&media, &mut self.adjustments);
+
+      let result = group_files(&media, sidecar.as_ref(), &dupes, adjustment.as_ref()).and_then(
+        |(stem, files)| {
+          flatten_files(
+            &self.source,
+            dst,
+            &files,
+            stem,
+            counter,
+            preserve,
+            &mut dirs_vacated,
+          )
+        },
+      );
+      self.warnings.check(result)?;
+    }
+
+    log::info!("Flattening leftover sidecars.");
+
+    let leftovers = self
+      .sidecars
+      .iter_entries_mut()
+      .map(|e| e.take().unwrap().as_ref().to_path_buf())
+      .chain(
+        self
+          .dupes
+          .iter_entries_mut()
+          .map(|e| e.take().unwrap().as_ref().to_path_buf()),
+      )
+      .chain(
+        self
+          .adjustments
+          .iter_entries_mut()
+          .map(|e| e.take().unwrap().as_ref().to_path_buf()),
+      )
+      .collect::<Vec<_>>();
+
+    for file in leftovers {
+      let result = split_file_name(&file).and_then(|(stem, ext)| {
+        let files = [(file.clone(), ext.to_string())];
+        flatten_files(
+          &self.source,
+          dst,
+          &files,
+          stem,
+          counter,
+          preserve,
+          &mut dirs_vacated,
+        )
+      });
+      self.warnings.check(result)?;
+    }
+
+    for dir in dirs_vacated {
+      for dir in dir
+        .ancestors()
+        .take_while(|d| d.starts_with(&self.source) && *d != self.source && *d != dst)
+      {
+        // Only succeeds if empty.
+        if fs::remove_dir(dir).is_err() {
+          break;
+        }
+        log::debug!("{}: Removed empty directory.", dir.display());
+      }
     }
 
-    if !dst.as_ref().exists() {
-      return Err(format!(
-        "{}: Destination path does not exist.",
-        dst.as_ref().display()
+    Ok(self.warnings.take())
+  }
+
+  /// Moves media which did not pass validation, along with their sidecars,
+  /// under `dir`, maintaining their directory structure. Live Photos are
+  /// moved as a group, based on the main image, as in
+  /// `move_and_rename_files`. Must be run after `validate`.
+  pub fn quarantine_invalid(&mut self, dir: impl AsRef<Path>) -> Result<(), CatalogError> {
+    if dir.as_ref().is_relative() {
+      return Err(CatalogError::PathNotAbsolute(
+        dir.as_ref().to_path_buf(),
+        "Quarantine path",
       ));
     }
 
-    if !self.validation.enabled() && !force {
-      log::warn!("Skipping move and rename: Validation disabled.");
+    if !self.validation.enabled() {
+      log::warn!("Skipping quarantine: Validation disabled.");
       return Ok(());
     }
 
-    log::info!("Moving and renaming Live Photos.");
+    log::info!("Quarantining invalid media.");
+
+    fs::create_dir_all(&dir).map_err(|e| {
+      CatalogError::Io(format!(
+        "{}: Unable to create quarantine directory ({e}).",
+        dir.as_ref().display()
+      ))
+    })?;
+
+    let mut invalid = Vec::new();
+    let mut linked = HashSet::new();
+
+    // Leftover videos are not moved as a group, so are treated as other media.
+    self.live_photo_map.retain(|_, link| {
+      if link.is_leftover_videos() {
+        return true;
+      }
+
+      let handles = link
+        .iter_images()
+        .chain(link.iter_videos())
+        .collect::<Vec<_>>();
+      linked.extend(handles.iter().copied());
+
+      if self.valid_media.contains(&link.get_image_best()) {
+        true
+      } else {
+        invalid.extend(handles);
+        false
+      }
+    });
+
+    invalid.extend(
+      self
+        .media
+        .iter_data_indexed()
+        .map(|(h, _)| h)
+        .filter(|h| !linked.contains(h) && !self.valid_media.contains(h)),
+    );
+
+    for handle in invalid {
+      let media = take_media(handle, &mut self.media);
+      let sidecar = take_sidecar(&media, &mut self.sidecars);
+      let dupes = take_dupes(&media, &mut self.dupes);
+      let adjustment = take_adjustment(&media, &mut self.adjustments);
+
+      self
+        .warnings
+        .push(Warning::FailedValidation(media.as_ref().to_path_buf()));
+      log::info!("{media}: Moving to quarantine.");
+
+      for path in dupes
+        .iter()
+        .map(|d| org::to_abs_path(&self.source, d))
+        .chain(sidecar.iter().map(|s| org::to_abs_path(&self.source, s)))
+        .chain(adjustment.iter().map(|a| org::to_abs_path(&self.source, a)))
+        .chain([org::to_abs_path(&self.source, &media)])
+      {
+        self.warnings.check(io::remove_file(
+          org::get_root(&self.source, &self.merged, &path),
+          &dir,
+          &path,
+        ))?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Moves each media file, along with its sidecars, under a directory per
+  /// year of capture in `dst` (e.g. `dst/2023/`), maintaining its directory
+  /// structure, so that years can be archived separately. Live Photos are
+  /// moved as a group, by the year of the main image. Media without a capture
+  /// date, and leftover sidecars, are left in place.
+  /// Returns warnings not yet taken, including any files skipped after errors.
+  pub fn split_by_year(mut self, dst: impl AsRef<Path>) -> Result<Vec<Warning>, CatalogError> {
+    let dst = dst.as_ref();
+
+    check_destination(dst)?;
+
+    log::info!("Splitting by year into {}.", dst.display());
 
-    for mut link in self.live_photo_map.into_values() {
+    let dirs_loaded = self.remove_empty_dirs.then(|| self.get_loaded_dirs());
+    let mut groups = Vec::new();
+    let mut linked = HashSet::new();
+
+    // Leftover videos are not moved as a group, so are treated as other media.
+    for link in mem::take(&mut self.live_photo_map).into_values() {
       if link.is_leftover_videos() {
         continue;
       }
 
+      let handles = link
+        .iter_images()
+        .chain(link.iter_videos())
+        .collect::<Vec<_>>();
+      linked.extend(handles.iter().copied());
+      groups.push((get_year(&self.media[link.get_image_best()]), handles));
+    }
+
+    groups.extend(
+      self
+        .media
+        .iter_data_indexed()
+        .filter(|(h, _)| !linked.contains(h))
+        .map(|(h, m)| (get_year(m), vec![h])),
+    );
+
+    for (year, handles) in groups {
+      for handle in handles {
+        let media = take_media(handle, &mut self.media);
+
+        let Some(year) = year else {
+          self.warnings.push(Warning::MissingTag(
+            media.as_ref().to_path_buf(),
+            "DateTimeOriginal".to_string(),
+          ));
+          continue;
+        };
+
+        let sidecar = take_sidecar(&media, &mut self.sidecars);
+        let dupes = take_dupes(&media, &mut self.dupes);
+        let adjustment = take_adjustment(&media, &mut self.adjustments);
+        let dir_year = dst.join(year.to_string());
+
+        log::debug!("{media}: Moving to {}.", dir_year.display());
+
+        self
+          .warnings
+          .check(fs::create_dir_all(&dir_year).map_err(|e| {
+            CatalogError::Io(format!(
+              "{}: Unable to create year directory ({e}).",
+              dir_year.display()
+            ))
+          }))?;
+
+        for path in dupes
+          .iter()
+          .map(|d| org::to_abs_path(&self.source, d))
+          .chain(sidecar.iter().map(|s| org::to_abs_path(&self.source, s)))
+          .chain(adjustment.iter().map(|a| org::to_abs_path(&self.source, a)))
+          .chain([org::to_abs_path(&self.source, &media)])
+          .filter(|p| !p.starts_with(&dir_year))
+        {
+          self.warnings.check(io::remove_file(
+            org::get_root(&self.source, &self.merged, &path),
+            &dir_year,
+            &path,
+          ))?;
+        }
+      }
+    }
+
+    if let Some(dirs_loaded) = dirs_loaded {
+      self.remove_empty_dirs(dst, dirs_loaded);
+    }
+
+    Ok(self.take_warnings())
+  }
+
+  /// Moves loaded files to `dst`, organizing them into subdirectories and
+  /// renaming them based on their timestamps.
+  /// Unless `force` is true, this will only touch validated files.
+  /// Returns warnings not yet taken, including any files skipped after errors.
+  /// All files are taken out of the `Organizer`, leaving only what was done for
+  /// `into_report`.
+  pub fn move_and_rename_files(
+    &mut self,
+    dst: impl AsRef<Path>,
+    force: bool,
+  ) -> Result<Vec<Warning>, CatalogError> {
+    check_destination(dst.as_ref())?;
+
+    if !self.validation.enabled() && !force {
+      log::warn!("Skipping move and rename: Validation disabled.");
+      return Ok(self.take_warnings());
+    }
+
+    self.check_free_space(dst.as_ref())?;
+
+    let dirs_loaded = self.remove_empty_dirs.then(|| self.get_loaded_dirs());
+
+    if self.trash_identical {
+      self.trash_identical_collisions(dst.as_ref(), force)?;
+    }
+
+    let mut report_progress = self.take_progress_reporter(force);
+    let extensions = mem::take(&mut self.extension_map);
+    let settings = MoveSettings {
+      exiftool:     &self.exiftool,
+      dir_src:      &self.source,
+      format:       self.file_name_format,
+      preserve:     self.preserve_modify_dates,
+      extensions:   &extensions,
+      missing_date: self.missing_date,
+    };
+
+    log::info!("Moving and renaming Live Photos.");
+
+    for id in self.live_photos_to_move() {
+      let mut link = self.live_photo_map.remove(&id).unwrap();
+
       let handle_main = link.get_image_best();
 
       let image_main = take_media(handle_main, &mut self.media);
       let sidecar_main = take_sidecar(&image_main, &mut self.sidecars);
       let dupes_main = take_dupes(&image_main, &mut self.dupes);
-      let metadata_source = pick_source(&image_main, sidecar_main.as_ref());
+      let adjustment_main = take_adjustment(&image_main, &mut self.adjustments);
+      let naming = pick_naming(&image_main, sidecar_main.as_ref(), &settings);
 
-      let should_move = force || self.valid_media.contains(&handle_main);
+      let should_move = (force || self.valid_media.contains(&handle_main))
+        && check_named(&mut self.warnings, &image_main, &naming);
+      let dst_group = self.get_group_dst(&image_main, dst.as_ref(), None);
 
       for handle in link.drain() {
         if handle == handle_main {
@@ -62,21 +588,33 @@ impl Organizer {
         let media = take_media(handle, &mut self.media);
         let sidecar = take_sidecar(&media, &mut self.sidecars);
         let dupes = take_dupes(&media, &mut self.dupes);
-
-        if should_move {
-          move_media_with_deps(&self.source, &dst, &metadata_source, media, sidecar, dupes)?;
+        let adjustment = take_adjustment(&media, &mut self.adjustments);
+
+        if should_move
+          && let Some(files_dst) = self.warnings.check(move_media_with_deps(
+            &dst_group, &naming, media, sidecar, dupes, adjustment, &settings,
+          ))?
+        {
+          self.report.record_move(files_dst);
         }
       }
 
       if should_move {
-        move_media_with_deps(
-          &self.source,
-          &dst,
-          &metadata_source,
+        let path = org::to_abs_path(&self.source, &image_main);
+        if let Some(files_dst) = self.warnings.check(move_media_with_deps(
+          &dst_group,
+          &naming,
           image_main,
           sidecar_main,
           dupes_main,
-        )?;
+          adjustment_main,
+          &settings,
+        ))? {
+          self.report.record_move(files_dst);
+        }
+        report_progress(&path);
+      } else if force || self.valid_media.contains(&handle_main) {
+        log::warn!("{image_main}: Not moving or renaming. File has no date.");
       } else {
         log::warn!("{image_main}: Not moving or renaming. File did not pass validation.");
       }
@@ -84,109 +622,2416 @@ impl Organizer {
 
     log::info!("Moving and renaming all other media files.");
 
-    for (handle, entry) in self.media.iter_entries_mut_indexed() {
-      let media = entry.take().unwrap();
+    for handle in self.media_to_move() {
+      let media = take_media(handle, &mut self.media);
       let sidecar = take_sidecar(&media, &mut self.sidecars);
       let dupes = take_dupes(&media, &mut self.dupes);
-      let metadata_source = pick_source(&media, sidecar.as_ref());
+      let adjustment = take_adjustment(&media, &mut self.adjustments);
+      let naming = pick_naming(&media, sidecar.as_ref(), &settings);
 
-      if force || self.valid_media.contains(&handle) {
-        move_media_with_deps(&self.source, &dst, &metadata_source, media, sidecar, dupes)?;
-      } else {
+      if !(force || self.valid_media.contains(&handle)) {
         log::warn!("{media}: Not moving or renaming. File did not pass validation.");
+      } else if check_named(&mut self.warnings, &media, &naming) {
+        let path = org::to_abs_path(&self.source, &media);
+        let kind = MediaKind::of(&media);
+        let dst_media = self.get_group_dst(&media, dst.as_ref(), Some(kind));
+        if let Some(files_dst) = self.warnings.check(move_media_with_deps(
+          &dst_media, &naming, media, sidecar, dupes, adjustment, &settings,
+        ))? {
+          self.report.record_move(files_dst);
+        }
+        report_progress(&path);
+      } else {
+        log::warn!("{media}: Not moving or renaming. File has no date.");
+      }
+    }
+
+    self.live_photo_map.clear();
+    self.burst_map.clear();
+    self.debug_assert_moved();
+
+    if let Some(dirs_loaded) = dirs_loaded {
+      self.remove_empty_dirs(dst.as_ref(), dirs_loaded);
+    }
+
+    Ok(self.warnings.take())
+  }
+
+  /// Gets the Live Photos `move_and_rename_files` moves (i.e. not leftover
+  /// videos), in order of their components' first path if moving in order (see
+  /// `enable_ordered_moves`).
+  fn live_photos_to_move(&self) -> Vec<LivePhotoID> {
+    let mut ids = self
+      .live_photo_map
+      .iter()
+      .filter(|(_, l)| !l.is_leftover_videos())
+      .map(|(id, _)| id.clone())
+      .collect::<Vec<_>>();
+    if self.ordered_moves {
+      ids.sort_by_cached_key(|id| {
+        let link = &self.live_photo_map[id];
+        link
+          .iter_images()
+          .chain(link.iter_videos())
+          .map(|h| self.media[h].as_ref().to_path_buf())
+          .min()
+      });
+    }
+    ids
+  }
+
+  /// Gets all media, in order of path if moving in order.
+  fn media_to_move(&self) -> Vec<Handle<Media>> {
+    let mut handles = self
+      .media
+      .iter_data_indexed()
+      .map(|(h, _)| h)
+      .collect::<Vec<_>>();
+    if self.ordered_moves {
+      handles.sort_by(|a, b| self.media[*a].as_ref().cmp(self.media[*b].as_ref()));
+    }
+    handles
+  }
+
+  /// Errors if the drive of `dst` lacks room for the files to be copied onto
+  /// it, plus headroom, if checked (see `enable_free_space_check`).
+  fn check_free_space(&self, dst: &Path) -> Result<(), CatalogError> {
+    let Some(check) = &self.free_space_check else {
+      return Ok(());
+    };
+
+    let device = fs::metadata(dst).map(|m| drive_id(&m)).map_err(|e| {
+      CatalogError::Io(format!(
+        "{}: Unable to read destination directory ({e}).",
+        dst.display()
+      ))
+    })?;
+    let files = (self.media.iter_data().map(AsRef::as_ref))
+      .chain(self.sidecars.iter_data().map(AsRef::as_ref))
+      .chain(self.dupes.iter_data().map(AsRef::as_ref))
+      .chain(self.adjustments.iter_data().map(AsRef::as_ref));
+
+    // Files on the same drive are renamed in place, so need no more space.
+    let mut needed = 0;
+    for file in files {
+      let path = org::to_abs_path(&self.source, file);
+      let metadata = fs::metadata(&path).map_err(|e| {
+        CatalogError::Io(format!(
+          "{}: Unable to read file size ({e}).",
+          path.display()
+        ))
+      })?;
+      if device.is_none() || drive_id(&metadata) != device {
+        needed += metadata.len();
       }
     }
 
+    let available = (check.available_space)(dst)?;
+    if available < needed.saturating_add(check.headroom) {
+      return Err(CatalogError::Io(format!(
+        "{}: Not enough free space to move files ({needed} bytes to copy, leaving {} bytes free, \
+         but only {available} available).",
+        dst.display(),
+        check.headroom
+      )));
+    }
+
+    log::debug!("{needed} bytes to copy, with {available} available.");
     Ok(())
   }
-}
 
-fn take_media(handle: Handle<Media>, media_map: &mut FileMap<Media>) -> Media {
-  media_map.get_entry_mut(handle).take().unwrap()
-}
+  /// Checks in debug builds that `move_and_rename_files` took every media file,
+  /// leaving no handles to them.
+  fn debug_assert_moved(&self) {
+    debug_assert_eq!(self.media.count_entries().0, 0, "Media left after moving.");
+    if cfg!(debug_assertions) {
+      self.assert_consistent();
+    }
+  }
 
-fn take_sidecar(
-  media: &Media,
-  sidecar_map: &mut FileMap<SidecarInitial>,
-) -> Option<SidecarInitial> {
-  media
-    .get_sidecar()
-    .map(|h| sidecar_map.get_entry_mut(h).take().unwrap())
-}
+  /// Moves media which `move_and_rename_files` would move to `dst` under the
+  /// same name as an identical file (there already, or other media moved
+  /// first) to trash, along with its sidecars. Media with edits the identical
+  /// file lacks (see `Organizer::is_redundant_with`) is kept. Nothing is
+  /// removed without trash.
+  fn trash_identical_collisions(&mut self, dst: &Path, force: bool) -> Result<(), CatalogError> {
+    let Some(trash) = self.trash.clone() else {
+      log::warn!("Not trashing identical media: No trash.");
+      return Ok(());
+    };
+
+    log::info!("Trashing media identical to others at the same time.");
+
+    let grouped = self
+      .live_photo_map
+      .values()
+      .filter(|l| !l.is_leftover_videos())
+      .flat_map(|l| l.iter_images().chain(l.iter_videos()))
+      .collect::<HashSet<_>>();
+    let mut handles = self
+      .media
+      .iter_data_indexed()
+      .map(|(h, _)| h)
+      .filter(|h| !grouped.contains(h) && (force || self.valid_media.contains(h)))
+      .collect::<Vec<_>>();
+    // Kept in order of path, as by `remove_identical_media`.
+    handles.sort_by(|a, b| self.media[*a].as_ref().cmp(self.media[*b].as_ref()));
+
+    // Media kept so far, by the name (before any counter) it is moved to.
+    let mut kept = HashMap::<PathBuf, Vec<(Handle<Media>, PathBuf)>>::new();
+    let counter = self.file_name_format.counter;
+
+    for handle in handles {
+      let media = &self.media[handle];
+      let path = org::to_abs_path(&self.source, media);
+      let metadata = media
+        .get_sidecar()
+        .map_or(media.get_metadata(), |h| self.sidecars[h].get_metadata());
+      let ext = self
+        .extension_map
+        .get(&media.get_metadata().file_type_extension);
+      let dst = self.get_group_dst(media, dst, Some(MediaKind::of(media)));
+
+      // Media without a name fails when moved instead.
+      let Ok(name) = io::format_file_name_date(metadata, self.file_name_format) else {
+        continue;
+      };
+
+      let mut taken = Vec::new();
+      let mut copy = 0;
+      loop {
+        let file = dst.join(format!("{name}{}.{ext}", io::format_counter(counter, copy)));
+        if !file.exists() {
+          break;
+        }
+        if file != path {
+          taken.push(file);
+        }
+        copy += 1;
+      }
+      let kept = kept.entry(dst.join(format!("{name}.{ext}"))).or_default();
+
+      let Some(identical) = self.warnings.check(self.hash_index.find_identical(
+        handle,
+        &path,
+        &taken,
+        kept.iter(),
+      ))?
+      else {
+        continue;
+      };
+      let redundant = match &identical {
+        Some((Some(identical), _)) => Some(self.is_redundant_with(handle, *identical)),
+        Some((None, identical)) => self
+          .warnings
+          .check(self.is_redundant_with_file(handle, identical))?,
+        None => None,
+      };
+      let (Some(true), Some((_, identical))) = (redundant, identical) else {
+        kept.push((handle, path));
+        continue;
+      };
 
-fn take_dupes(media: &Media, dupe_map: &mut FileMap<SidecarDupe>) -> Vec<SidecarDupe> {
-  media
-    .iter_dupes()
-    .map(|h| dupe_map.get_entry_mut(h).take().unwrap())
-    .collect()
-}
+      let media = self.media.get_entry_mut(handle).take().unwrap();
+      self.report.removed += 1;
+      let sidecar = take_sidecar(&media, &mut self.sidecars);
+      let dupes = take_dupes(&media, &mut self.dupes);
+      let adjustment = take_adjustment(&media, &mut self.adjustments);
 
-fn pick_source(media: &Media, sidecar: Option<&SidecarInitial>) -> PathBuf {
-  sidecar
-    .as_ref()
-    .map_or(media.as_ref(), std::convert::AsRef::as_ref)
-    .to_path_buf()
-}
+      self.warnings.push(Warning::IdenticalAtSameTime(
+        media.as_ref().to_path_buf(),
+        identical,
+      ));
 
-fn move_media_with_deps(
-  dir_src: impl AsRef<Path>,
-  dir_dst: impl AsRef<Path>,
-  metadata_source: impl AsRef<Path>,
-  media: Media,
-  sidecar: Option<SidecarInitial>,
-  dupes: impl IntoIterator<Item = SidecarDupe>,
-) -> Result<(), String> {
-  log::trace!("{media}: Moving and renaming.");
+      let files = iter::once(media.as_ref().to_path_buf())
+        .chain(sidecar.iter().map(|s| s.as_ref().to_path_buf()))
+        .chain(dupes.iter().map(|d| d.as_ref().to_path_buf()))
+        .chain(adjustment.iter().map(|a| a.as_ref().to_path_buf()));
+      for file in files {
+        self.warnings.check(remove_by_path(
+          &self.source,
+          &self.merged,
+          file,
+          Some(&trash),
+          None,
+        ))?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Whether removing media `handle` in favor of `file` (not media) with the
+  /// same contents loses no edits, as for `Organizer::is_redundant_with`,
+  /// comparing with the sidecar beside `file`, if any.
+  fn is_redundant_with_file(
+    &self,
+    handle: Handle<Media>,
+    file: &Path,
+  ) -> Result<bool, CatalogError> {
+    let media = &self.media[handle];
+    if media.iter_dupes().next().is_some() || media.get_adjustment().is_some() {
+      return Ok(false);
+    }
+    let Some(sidecar) = media.get_sidecar() else {
+      return Ok(true);
+    };
+
+    let mut sidecar_file = file.as_os_str().to_owned();
+    sidecar_file.push(".xmp");
+    let sidecar_file = PathBuf::from(sidecar_file);
+    if !sidecar_file.exists() {
+      return Ok(false);
+    }
+
+    Ok(
+      io::read_metadata(&self.exiftool, sidecar_file)?
+        .has_same_tags(self.sidecars[sidecar].get_metadata()),
+    )
+  }
+
+  /// Gets the directories of all files still loaded, which moving them out
+  /// may leave empty.
+  fn get_loaded_dirs(&self) -> HashSet<PathBuf> {
+    self
+      .media
+      .iter_data()
+      .map(AsRef::<Path>::as_ref)
+      .chain(self.sidecars.iter_data().map(AsRef::as_ref))
+      .chain(self.dupes.iter_data().map(AsRef::as_ref))
+      .chain(self.adjustments.iter_data().map(AsRef::as_ref))
+      .filter_map(|f| {
+        org::to_abs_path(&self.source, f)
+          .parent()
+          .map(Path::to_path_buf)
+      })
+      .collect()
+  }
+
+  /// Removes those of `dirs` left empty under the source and merged catalogs,
+  /// and their parents left empty in turn, except trash, excluded directories,
+  /// and `dst` if a subdirectory of either.
+  fn remove_empty_dirs(&self, dst: &Path, dirs: HashSet<PathBuf>) {
+    log::info!("Removing empty directories.");
+
+    let keep = self
+      .trash
+      .iter()
+      .chain(&self.excluded)
+      .map(PathBuf::as_path)
+      .collect::<Vec<_>>();
+
+    for dir in dirs {
+      let root = org::get_root(&self.source, &self.merged, &dir);
+      let keep_dst = dst.starts_with(root) && dst != root;
+      for dir in dir.ancestors().take_while(|d| {
+        d.starts_with(root)
+          && *d != root
+          && !(keep_dst && d.starts_with(dst))
+          && !keep.iter().any(|k| d.starts_with(k))
+      }) {
+        // Only succeeds if empty.
+        if fs::remove_dir(dir).is_err() {
+          break;
+        }
+        log::debug!("{}: Removed empty directory.", dir.display());
+      }
+    }
+  }
+
+  /// Plans where `move_and_rename_files` would move each file under `dst`,
+  /// returning pairs of current and new absolute paths, in the order they would
+  /// be moved. Nothing is moved, but `dst` is checked for existing files, which
+  /// same-time counters skip over as `ExifTool` does.
+  pub fn rename_preview(
+    &self,
+    dst: impl AsRef<Path>,
+    force: bool,
+  ) -> Result<Vec<(PathBuf, PathBuf)>, CatalogError> {
+    if dst.as_ref().is_relative() {
+      return Err(CatalogError::PathNotAbsolute(
+        dst.as_ref().to_path_buf(),
+        "Destination path",
+      ));
+    }
+
+    let mut plan = RenamePlan::new(&self.source, self.file_name_format);
+
+    if !self.validation.enabled() && !force {
+      return Ok(plan.renames);
+    }
+
+    let mut linked = HashSet::new();
+
+    for id in self.live_photos_to_move() {
+      let link = &self.live_photo_map[&id];
+
+      let handles = link
+        .iter_images()
+        .chain(link.iter_videos())
+        .collect::<Vec<_>>();
+      linked.extend(handles.iter().copied());
+
+      let handle_main = link.get_image_best();
+      if !force && !self.valid_media.contains(&handle_main) {
+        continue;
+      }
+
+      let group = handles
+        .into_iter()
+        .filter(|h| *h != handle_main)
+        .chain([handle_main])
+        .map(|h| &self.media[h])
+        .collect::<Vec<_>>();
+      let dst_group = self.get_group_dst(&self.media[handle_main], dst.as_ref(), None);
+      plan.add_group(self, &group, &dst_group)?;
+    }
+
+    for handle in self.media_to_move() {
+      if linked.contains(&handle) || (!force && !self.valid_media.contains(&handle)) {
+        continue;
+      }
+
+      let media = &self.media[handle];
+
+      plan.add_group(
+        self,
+        &[media],
+        &self.get_group_dst(media, dst.as_ref(), Some(MediaKind::of(media))),
+      )?;
+    }
+
+    Ok(plan.renames)
+  }
+
+  /// Checks that each file planned by `rename_preview` exists at its
+  /// destination (e.g. after `move_and_rename_files`), returning the
+  /// destinations missing. Media trashed as identical to others (see
+  /// `enable_trash_identical_collisions`) are among those missing.
+  #[must_use]
+  pub fn verify_moves(renames: &[(PathBuf, PathBuf)]) -> Vec<PathBuf> {
+    renames
+      .iter()
+      .filter(|(_, dst)| !dst.exists())
+      .inspect(|(src, dst)| {
+        log::debug!(
+          "{}: Missing at {} after move.",
+          src.display(),
+          dst.display()
+        );
+      })
+      .map(|(_, dst)| dst.clone())
+      .collect()
+  }
+
+  /// Takes the progress callback, if set, as one for `move_and_rename_files` to
+  /// call as each group of files is moved.
+  fn take_progress_reporter(&mut self, force: bool) -> impl FnMut(&Path) + use<> {
+    let total = self.count_groups_to_move(force);
+    let progress = self.progress.take();
+    let mut moved = 0;
+
+    move |path| {
+      moved += 1;
+      if let Some(progress) = &progress {
+        progress(moved, total, path);
+      }
+    }
+  }
+
+  /// Counts the groups of files `move_and_rename_files` would move: Each Live
+  /// Photo (but not leftover videos), and each other media file, with their
+  /// sidecars.
+  fn count_groups_to_move(&self, force: bool) -> usize {
+    let should_move = |h: &Handle<Media>| force || self.valid_media.contains(h);
+
+    let mut linked = HashSet::new();
+    let mut count = 0;
+
+    for link in self.live_photo_map.values() {
+      if link.is_leftover_videos() {
+        continue;
+      }
+
+      linked.extend(link.iter_images().chain(link.iter_videos()));
+      if should_move(&link.get_image_best()) {
+        count += 1;
+      }
+    }
+
+    count
+      + self
+        .media
+        .iter_data_indexed()
+        .filter(|(h, _)| !linked.contains(h) && should_move(h))
+        .count()
+  }
+
+  /// Gets the directory `move_and_rename_files` moves `media` (leading a group
+  /// of `kind`, as in `get_media_dst`) into: its own directory if renaming only
+  /// (see `enable_rename_only`), else under `dst`.
+  fn get_group_dst(&self, media: &Media, dst: &Path, kind: Option<MediaKind>) -> PathBuf {
+    if self.rename_only {
+      let path = media.as_ref();
+      let path = self.source.join(path.strip_prefix(".").unwrap_or(path));
+      return path.parent().unwrap_or(dst).to_path_buf();
+    }
+
+    get_media_dst(self.media_dirs.as_ref(), dst, kind)
+  }
+
+  /// Gets the metadata files are named by, from the sidecar where present, as
+  /// in `pick_source`.
+  fn get_metadata_source<'a>(&'a self, media: &'a Media) -> &'a Metadata {
+    media
+      .get_sidecar()
+      .map_or(media.get_metadata(), |h| self.sidecars[h].get_metadata())
+  }
+}
+
+/// Planned renames for `Organizer::rename_preview`, tracking which paths are
+/// taken as each file is moved in turn.
+struct RenamePlan<'a> {
+  dir_src: &'a Path,
+  format:  io::FileNameFormat,
+  renames: Vec<(PathBuf, PathBuf)>,
+  planned: HashSet<PathBuf>,
+  vacated: HashSet<PathBuf>,
+}
+
+impl<'a> RenamePlan<'a> {
+  fn new(dir_src: &'a Path, format: io::FileNameFormat) -> Self {
+    Self {
+      dir_src,
+      format,
+      renames: Vec::new(),
+      planned: HashSet::new(),
+      vacated: HashSet::new(),
+    }
+  }
+
+  /// Plans moving each of `group` (ending with its main media) and their
+  /// sidecars into `dir_dst`, named by the main media as in
+  /// `move_and_rename_files`, including if it has no date.
+  fn add_group(
+    &mut self,
+    organizer: &Organizer,
+    group: &[&Media],
+    dir_dst: &Path,
+  ) -> Result<(), CatalogError> {
+    let main = group.last().unwrap();
+    let metadata_source = organizer.get_metadata_source(main);
+
+    let (metadata_source, format) = match organizer
+      .missing_date
+      .applies_to(metadata_source, self.format)
+    {
+      None => (metadata_source, self.format),
+      Some(MissingDatePolicy::UseFileModifyDate) => {
+        (main.get_metadata(), file_modify_date_format(self.format))
+      }
+      Some(MissingDatePolicy::MoveToUndated) => {
+        for media in group {
+          self.add_undated(organizer, media, &dir_dst.join(UNDATED_DIR))?;
+        }
+        return Ok(());
+      }
+      Some(MissingDatePolicy::Skip) => return Ok(()),
+    };
+
+    for media in group {
+      self.add_media_with_deps(organizer, media, metadata_source, format, dir_dst)?;
+    }
+
+    Ok(())
+  }
+
+  /// Plans moving `media` and its sidecars into `dir_undated`, keeping their
+  /// names, as `move_undated`.
+  fn add_undated(
+    &mut self,
+    organizer: &Organizer,
+    media: &Media,
+    dir_undated: &Path,
+  ) -> Result<(), CatalogError> {
+    let (stem, files) = group_files(
+      media,
+      media.get_sidecar().map(|h| &organizer.sidecars[h]),
+      media.iter_dupes().map(|h| &organizer.dupes[h]),
+      media.get_adjustment().map(|h| &organizer.adjustments[h]),
+    )?;
+
+    let files = files
+      .into_iter()
+      .map(|(file, ending)| {
+        (
+          self.dir_src.join(file.strip_prefix(".").unwrap_or(&file)),
+          ending,
+        )
+      })
+      .collect::<Vec<_>>();
+
+    // As `flatten_files`, the group takes the first counter free for all files.
+    let mut copy = 0;
+    let dsts = loop {
+      let counter = io::format_counter(self.format.counter, copy);
+      let dsts = files
+        .iter()
+        .map(|(_, ending)| dir_undated.join(format!("{stem}{counter}{ending}")))
+        .collect::<Vec<_>>();
+
+      if files
+        .iter()
+        .zip(&dsts)
+        .all(|((src, _), dst)| *src == *dst || !self.is_taken(dst))
+      {
+        break dsts;
+      }
+
+      copy += 1;
+    };
+
+    for ((src, _), dst) in files.into_iter().zip(dsts) {
+      self.insert(src, dst);
+    }
+
+    Ok(())
+  }
+
+  /// Plans moving `media` and its sidecars into `dir_dst`, named by
+  /// `metadata_source` in `format`, in the same order as
+  /// `move_media_with_deps`.
+  fn add_media_with_deps(
+    &mut self,
+    organizer: &Organizer,
+    media: &Media,
+    metadata_source: &Metadata,
+    format: io::FileNameFormat,
+    dir_dst: &Path,
+  ) -> Result<(), CatalogError> {
+    let name = io::format_file_name_date(metadata_source, format)?;
+    let ext = organizer
+      .extension_map
+      .get(&media.get_metadata().file_type_extension);
+
+    for handle in media.iter_dupes() {
+      let dupe = &organizer.dupes[handle];
+      let ending = format!("_{}.{ext}.xmp", dupe.get_dupe_number().display());
+      self.add(dupe.as_ref(), dir_dst, &name, &ending);
+    }
+
+    self.add(media.as_ref(), dir_dst, &name, &format!(".{ext}"));
+
+    if let Some(handle) = media.get_sidecar() {
+      self.add(
+        organizer.sidecars[handle].as_ref(),
+        dir_dst,
+        &name,
+        &format!(".{ext}.xmp"),
+      );
+    }
+
+    if let Some(handle) = media.get_adjustment() {
+      let adjustment = &organizer.adjustments[handle];
+      let ext = organizer
+        .extension_map
+        .get(&adjustment.get_metadata().file_type_extension);
+      self.add(adjustment.as_ref(), dir_dst, &name, &format!(".{ext}"));
+    }
+
+    Ok(())
+  }
+
+  /// Plans moving `file` into `dir_dst` as the first `name` with counter and
+  /// `ending` not already taken. The file's own path is never considered taken.
+  fn add(&mut self, file: &Path, dir_dst: &Path, name: &str, ending: &str) {
+    let src = self.dir_src.join(file.strip_prefix(".").unwrap_or(file));
+
+    let mut copy = 0;
+    let dst = loop {
+      let counter = io::format_counter(self.format.counter, copy);
+      let dst = dir_dst.join(format!("{name}{counter}{ending}"));
+
+      if dst == src || !self.is_taken(&dst) {
+        break dst;
+      }
+
+      copy += 1;
+    };
+
+    self.insert(src, dst);
+  }
+
+  /// Checks whether `dst` is planned, or exists and is not moved away.
+  fn is_taken(&self, dst: &Path) -> bool {
+    self.planned.contains(dst) || (dst.exists() && !self.vacated.contains(dst))
+  }
+
+  /// Plans moving `src` to `dst`.
+  fn insert(&mut self, src: PathBuf, dst: PathBuf) {
+    self.planned.insert(dst.clone());
+    if dst != src {
+      self.vacated.insert(src.clone());
+    }
+    self.renames.push((src, dst));
+  }
+}
+
+/// Gets the directory under `dst` to move media of `kind` into, or Live Photos
+/// if `None`, by `dirs` as set by `Organizer::set_media_dirs`.
+fn get_media_dst(dirs: Option<&MediaDirs>, dst: &Path, kind: Option<MediaKind>) -> PathBuf {
+  dirs.map_or_else(
+    || dst.to_path_buf(),
+    |d| dst.join(d.get(kind.unwrap_or(d.live_photos))),
+  )
+}
+
+/// Splits the name of `file` at its first `.`, into its stem and extension(s)
+/// (e.g. `image` and `.jpg.xmp`).
+fn split_file_name(file: &impl AsRef<Path>) -> Result<(&str, &str), CatalogError> {
+  let name = file
+    .as_ref()
+    .file_name()
+    .and_then(|n| n.to_str())
+    .ok_or_else(|| {
+      CatalogError::InvalidFile(format!("{}: Invalid file name.", file.as_ref().display()))
+    })?;
+
+  Ok(name.split_at(name.find('.').unwrap_or(name.len())))
+}
+
+/// Checks that `dst`, to move files to, is absolute and exists.
+/// Identifies the drive holding a file, for telling whether it can be renamed
+/// onto another drive in place. Unknown off Unix, where every file is assumed
+/// to need copying, so that `check_free_space` errs towards needing more.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)]
+fn drive_id(metadata: &fs::Metadata) -> Option<u64> {
+  Some(std::os::unix::fs::MetadataExt::dev(metadata))
+}
+
+#[cfg(not(unix))]
+fn drive_id(_metadata: &fs::Metadata) -> Option<u64> {
+  None
+}
+
+fn check_destination(dst: &Path) -> Result<(), CatalogError> {
+  if dst.is_relative() {
+    return Err(CatalogError::PathNotAbsolute(
+      dst.to_path_buf(),
+      "Destination path",
+    ));
+  }
+
+  if !dst.exists() {
+    return Err(CatalogError::PathNotFound(
+      dst.to_path_buf(),
+      "Destination path",
+    ));
+  }
+
+  Ok(())
+}
+
+/// Moves `files` (relative to `dir_src`, each with the ending of its name) into
+/// `dir_dst` as a group named by `stem`, adding the first counter for which no
+/// name is taken, and keeping modify dates if `preserve`. Records the
+/// directories moved out of in `dirs_vacated`, and returns the new path of each
+/// file.
+fn flatten_files(
+  dir_src: &Path,
+  dir_dst: &Path,
+  files: &[(PathBuf, String)],
+  stem: &str,
+  counter: io::CounterStyle,
+  preserve: bool,
+  dirs_vacated: &mut HashSet<PathBuf>,
+) -> Result<Vec<PathBuf>, CatalogError> {
+  let files = files
+    .iter()
+    .map(|(file, ending)| (org::to_abs_path(dir_src, file), ending))
+    .collect::<Vec<_>>();
+
+  let mut copy = 0;
+  let dsts = loop {
+    let counter = io::format_counter(counter, copy);
+    let dsts = files
+      .iter()
+      .map(|(_, ending)| dir_dst.join(format!("{stem}{counter}{ending}")))
+      .collect::<Vec<_>>();
+
+    // A file already in place keeps its name.
+    if files
+      .iter()
+      .zip(&dsts)
+      .all(|((src, _), dst)| src == dst || !dst.exists())
+    {
+      break dsts;
+    }
+
+    copy += 1;
+  };
+
+  for ((src, _), dst) in files.iter().zip(&dsts) {
+    if src == dst {
+      continue;
+    }
+
+    log::trace!("{}: Moving to {}.", src.display(), dst.display());
+    let modify_time = preserve.then(|| io::get_modify_time(src)).transpose()?;
+    io::rename_file(src, dst)?;
+    if let Some(modify_time) = modify_time {
+      io::set_modify_time(dst, modify_time)?;
+    }
+    dirs_vacated.insert(src.parent().unwrap().to_path_buf());
+  }
+
+  Ok(dsts)
+}
+
+/// Gets the year `media` was captured, if known.
+fn get_year(media: &Media) -> Option<i32> {
+  media
+    .get_metadata()
+    .get_date_time_original()
+    .map(|(date_time, _)| date_time.year())
+}
+
+fn take_media(handle: Handle<Media>, media_map: &mut FileMap<Media>) -> Media {
+  media_map.get_entry_mut(handle).take().unwrap()
+}
+
+pub fn take_sidecar(
+  media: &Media,
+  sidecar_map: &mut FileMap<SidecarInitial>,
+) -> Option<SidecarInitial> {
+  media
+    .get_sidecar()
+    .map(|h| sidecar_map.get_entry_mut(h).take().unwrap())
+}
+
+pub fn take_dupes(media: &Media, dupe_map: &mut FileMap<SidecarDupe>) -> Vec<SidecarDupe> {
+  media
+    .iter_dupes()
+    .map(|h| dupe_map.get_entry_mut(h).take().unwrap())
+    .collect()
+}
+
+pub fn take_adjustment(
+  media: &Media,
+  adjustment_map: &mut FileMap<Adjustment>,
+) -> Option<Adjustment> {
+  media
+    .get_adjustment()
+    .map(|h| adjustment_map.get_entry_mut(h).take().unwrap())
+}
+
+/// How `move_media_with_deps` moves a group of media, by whether it has a date
+/// to be named by.
+enum Naming {
+  /// Named by the date in this file (the sidecar where present), in this
+  /// format, with this file's time zone for naming in local time.
+  ByDate(PathBuf, io::FileNameFormat, Option<FixedOffset>),
+  /// Moved into `UNDATED_DIR`, keeping the names of its files.
+  Undated,
+  /// Left in place.
+  Skip,
+}
+
+/// Picks how to move `media`, and any group it leads, named by the date of its
+/// sidecar where present, else per `settings.missing_date`.
+fn pick_naming(media: &Media, sidecar: Option<&SidecarInitial>, settings: &MoveSettings) -> Naming {
+  let metadata = sidecar.map_or(media.get_metadata(), Sidecar::get_metadata);
+
+  match settings.missing_date.applies_to(metadata, settings.format) {
+    None => Naming::ByDate(
+      sidecar.map_or(media.as_ref(), AsRef::as_ref).to_path_buf(),
+      settings.format,
+      metadata.get_time_zone(),
+    ),
+    Some(MissingDatePolicy::UseFileModifyDate) => Naming::ByDate(
+      media.as_ref().to_path_buf(),
+      file_modify_date_format(settings.format),
+      media.get_metadata().get_time_zone(),
+    ),
+    Some(MissingDatePolicy::MoveToUndated) => Naming::Undated,
+    Some(MissingDatePolicy::Skip) => Naming::Skip,
+  }
+}
+
+/// Checks that `media` is to be moved by `naming`, warning that it has no date
+/// if skipped instead (see `MissingDatePolicy::Skip`).
+fn check_named(warnings: &mut Warnings, media: &Media, naming: &Naming) -> bool {
+  if matches!(naming, Naming::Skip) {
+    warnings.push(Warning::MissingTag(
+      media.as_ref().to_path_buf(),
+      "DateTimeOriginal".to_string(),
+    ));
+    false
+  } else {
+    true
+  }
+}
+
+/// Gets `format` naming by `FileModifyDate` alone, for
+/// `MissingDatePolicy::UseFileModifyDate`.
+fn file_modify_date_format(format: io::FileNameFormat) -> io::FileNameFormat {
+  io::FileNameFormat {
+    date_source: io::RenameDateSource::FileModify,
+    ..format
+  }
+}
+
+/// Files moved together, each with the ending of its name after a shared stem.
+type FileEndings = Vec<(PathBuf, String)>;
+
+/// Gets the files of `media` and its sidecars, each with the ending of its name
+/// after `media`'s stem (e.g. `.jpg.xmp`), and that stem, as moved together by
+/// `flatten_files`.
+fn group_files<'a, 'b>(
+  media: &'a Media,
+  sidecar: Option<&SidecarInitial>,
+  dupes: impl IntoIterator<Item = &'b SidecarDupe>,
+  adjustment: Option<&Adjustment>,
+) -> Result<(&'a str, FileEndings), CatalogError> {
+  let (stem, ext) = split_file_name(media)?;
+
+  let mut files = vec![(media.as_ref().to_path_buf(), ext.to_string())];
+  files.extend(
+    sidecar
+      .iter()
+      .map(|s| (s.as_ref().to_path_buf(), format!("{ext}.xmp"))),
+  );
+  files.extend(dupes.into_iter().map(|d| {
+    (
+      d.as_ref().to_path_buf(),
+      format!("_{}{ext}.xmp", d.get_dupe_number().display()),
+    )
+  }));
+  if let Some(adjustment) = adjustment {
+    let (_, ext) = split_file_name(adjustment)?;
+    files.push((adjustment.as_ref().to_path_buf(), ext.to_string()));
+  }
+
+  Ok((stem, files))
+}
+
+/// Where `move_media_with_deps` moves files from, how it names them (with
+/// `ExifTool` run as `exiftool`), and whether it keeps their modify dates.
+struct MoveSettings<'a> {
+  exiftool:     &'a io::ExifToolConfig,
+  dir_src:      &'a Path,
+  format:       io::FileNameFormat,
+  preserve:     bool,
+  extensions:   &'a ExtensionMap,
+  missing_date: MissingDatePolicy,
+}
+
+/// Moves `media` and its sidecars into `dir_dst` by `naming`, returning the new
+/// path of each file in the order moved.
+fn move_media_with_deps(
+  dir_dst: impl AsRef<Path>,
+  naming: &Naming,
+  media: Media,
+  sidecar: Option<SidecarInitial>,
+  dupes: impl IntoIterator<Item = SidecarDupe>,
+  adjustment: Option<Adjustment>,
+  settings: &MoveSettings,
+) -> Result<Vec<PathBuf>, CatalogError> {
+  let dir_src = settings.dir_src;
+
+  let (metadata_source, format, time_zone) = match naming {
+    Naming::ByDate(metadata_source, format, time_zone) => (metadata_source, *format, *time_zone),
+    Naming::Undated => {
+      let dupes = dupes.into_iter().collect::<Vec<_>>();
+      return move_undated(
+        &dir_dst.as_ref().join(UNDATED_DIR),
+        &media,
+        sidecar.as_ref(),
+        &dupes,
+        adjustment.as_ref(),
+        settings,
+      );
+    }
+    // Reported by the caller.
+    Naming::Skip => return Ok(Vec::new()),
+  };
+
+  log::trace!("{media}: Moving and renaming.");
+
+  // Sidecars are named after the media, so get the same extension.
+  let media_file_ext = settings
+    .extensions
+    .get(&media.get_metadata().file_type_extension);
+  let metadata_source = org::to_abs_path(dir_src, metadata_source);
+
+  let mut moved = Vec::new();
+
+  // Keeps the modify date if `preserve`, as it is read before moving.
+  let mut move_file = |file: PathBuf, ending: OsString| {
+    let modify_time = settings
+      .preserve
+      .then(|| io::get_modify_time(&file))
+      .transpose()?;
+    let file_dst = io::move_file(
+      settings.exiftool,
+      file,
+      Some(&metadata_source),
+      &dir_dst,
+      ending,
+      format,
+      time_zone,
+    )?;
+    if let Some(modify_time) = modify_time {
+      io::set_modify_time(&file_dst, modify_time)?;
+    }
+    moved.push(file_dst);
+    Ok::<_, CatalogError>(())
+  };
+  for dupe in dupes {
+    let mut dupe_ending = OsString::from("_");
+    dupe_ending.push(dupe.get_dupe_number());
+    dupe_ending.push(".");
+    dupe_ending.push(&media_file_ext);
+    dupe_ending.push(".xmp");
+
+    move_file(org::to_abs_path(dir_src, dupe), dupe_ending)?;
+  }
+
+  move_file(
+    org::to_abs_path(dir_src, media),
+    format!(".{media_file_ext}").into(),
+  )?;
+
+  if let Some(sidecar) = sidecar {
+    move_file(
+      org::to_abs_path(dir_src, sidecar),
+      format!(".{media_file_ext}.xmp").into(),
+    )?;
+  }
+
+  // Adjustment sidecars replace the media's extension, rather than adding to
+  // it.
+  if let Some(adjustment) = adjustment {
+    let ext = settings
+      .extensions
+      .get(&adjustment.get_metadata().file_type_extension);
+    move_file(
+      org::to_abs_path(dir_src, adjustment),
+      format!(".{ext}").into(),
+    )?;
+  }
+
+  Ok(moved)
+}
+
+/// Moves `media` and its sidecars into `dir_undated`, keeping their names
+/// unless taken, as for `MissingDatePolicy::MoveToUndated`. Returns the new
+/// path of each file.
+fn move_undated(
+  dir_undated: &Path,
+  media: &Media,
+  sidecar: Option<&SidecarInitial>,
+  dupes: &[SidecarDupe],
+  adjustment: Option<&Adjustment>,
+  settings: &MoveSettings,
+) -> Result<Vec<PathBuf>, CatalogError> {
+  log::trace!("{media}: Moving to {}.", dir_undated.display());
+
+  fs::create_dir_all(dir_undated).map_err(|e| {
+    CatalogError::Io(format!(
+      "{}: Unable to create undated directory ({e}).",
+      dir_undated.display()
+    ))
+  })?;
+
+  let (stem, files) = group_files(media, sidecar, dupes, adjustment)?;
+  flatten_files(
+    settings.dir_src,
+    dir_undated,
+    &files,
+    stem,
+    settings.format.counter,
+    settings.preserve,
+    &mut HashSet::new(),
+  )
+}
+
+#[cfg(test)]
+mod test_flatten_directory {
+  use std::time::{Duration, SystemTime};
+
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn errors_if_destination_path_is_relative() {
+    let d = test_dir!();
+
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    assert_err!(
+      o.flatten_directory("relative/path"),
+      "Destination path is not absolute."
+    );
+  }
+
+  #[test]
+  fn flattens_date_directories() {
+    let d = test_dir!(
+      "2000/01/000101_000000000.jpg": {},
+      "2000/01/000101_000000000.jpg.xmp": {},
+      "2000/01/000101_000000000_01.jpg.xmp": {},
+      "2000/02/000201_000000000.heic": {},
+      "2001/01/010101_000000000.mov": { "CompressorID": "avc1" },
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.flatten_directory(d.root()).unwrap();
+
+    assert_dir!(d, [
+      "000101_000000000.jpg",
+      "000101_000000000.jpg.xmp",
+      "000101_000000000_01.jpg.xmp",
+      "000201_000000000.heic",
+      "010101_000000000.mov",
+    ]);
+    assert!(!d.get_path("2000").exists());
+    assert!(!d.get_path("2001").exists());
+    assert!(d.trash().exists());
+  }
+
+  #[test]
+  fn adds_counter_on_name_clash() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "a/image.jpg": {},
+      "a/image.jpg.xmp": {},
+      "b/image.jpg": {},
+      "b/image_01.jpg.xmp": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.flatten_directory(d.root()).unwrap();
+
+    assert_eq!(d.files_good().len(), 5);
+    assert!(d.get_path("image.jpg").exists());
+    assert!(!d.get_path("a").exists());
+    assert!(!d.get_path("b").exists());
+
+    // Whichever is moved first, each sidecar keeps the name of its media.
+    let names = d
+      .files_good()
+      .into_iter()
+      .map(|p| p.file_name().unwrap().to_str().unwrap().to_string())
+      .collect::<HashSet<_>>();
+    for sidecar in names.iter().filter_map(|n| n.strip_suffix(".xmp")) {
+      let media = sidecar.replace("_01.", ".");
+      assert!(names.contains(&media), "{sidecar}.xmp without {media}");
+    }
+  }
+
+  #[test]
+  fn moves_leftover_sidecars() {
+    let d = test_dir!(
+      "2000/01/image.jpg.xmp": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.flatten_directory(d.root()).unwrap();
+
+    assert_dir!(d, ["image.jpg.xmp"]);
+    assert!(!d.get_path("2000").exists());
+  }
+
+  #[test]
+  fn keeps_non_empty_directories() {
+    let d = test_dir!(
+      "2000/01/image1.jpg": {},
+      "2000/excluded/image2.jpg": {},
+    );
+
+    let scan = ScanConfig {
+      exclude: vec![PathBuf::from("2000/excluded")],
+      ..Default::default()
+    };
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &scan).unwrap();
+    o.flatten_directory(d.root()).unwrap();
+
+    assert_dir!(d, ["image1.jpg", "2000/excluded/image2.jpg"]);
+    assert!(!d.get_path("2000/01").exists());
+  }
+
+  #[test]
+  fn preserves_modify_date_if_enabled() {
+    let d = test_dir!(
+      "dir/image.jpg": {},
+    );
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(978_307_201);
+    io::set_modify_time(d.get_path("dir/image.jpg"), time).unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_preserve_modify_dates();
+    o.flatten_directory(d.root()).unwrap();
+
+    assert_eq!(io::get_modify_time(d.get_path("image.jpg")).unwrap(), time);
+  }
+}
+
+#[cfg(test)]
+mod test_extension_map {
+  use super::*;
+
+  #[test]
+  fn defaults_to_exiftool_extension() {
+    assert_eq!(ExtensionMap::default().get("jpg"), "jpg");
+    assert_eq!(ExtensionMap::default().get("JPG"), "JPG");
+  }
+
+  #[test]
+  fn overrides_in_any_case() {
+    let mut extensions = ExtensionMap::default();
+    extensions.insert("JPEG", "jpg");
+    extensions.insert("tiff", "TIF");
+
+    assert_eq!(extensions.get("jpeg"), "jpg");
+    assert_eq!(extensions.get("Tiff"), "TIF");
+    assert_eq!(extensions.get("heic"), "heic");
+  }
+
+  #[test]
+  fn lowercases_if_enabled() {
+    let mut extensions = ExtensionMap::default();
+    extensions.insert("tiff", "TIF");
+    extensions.enable_lowercase();
+
+    assert_eq!(extensions.get("tiff"), "tif");
+    assert_eq!(extensions.get("HEIC"), "heic");
+  }
+}
+
+#[cfg(test)]
+mod test_move_and_rename_files {
+  use std::time::{Duration, SystemTime};
+
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn errors_if_destination_path_does_not_exist() {
+    let d = test_dir!();
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    assert_err!(
+      o.move_and_rename_files("/path/does/not/exist", false),
+      "Destination path does not exist."
+    );
+  }
+
+  #[test]
+  fn errors_if_destination_path_is_relative() {
+    let d = test_dir!();
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    assert_err!(
+      o.move_and_rename_files("relative/path", false),
+      "Destination path is not absolute."
+    );
+  }
+
+  #[test]
+  fn aborts_if_not_enough_free_space() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_free_space_check(1024);
+    o.free_space_check.as_mut().unwrap().available_space = |_| Ok(1000);
+
+    assert_err!(
+      o.move_and_rename_files(d.root(), true),
+      "Not enough free space to move files"
+    );
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp"]);
+  }
+
+  #[test]
+  fn moves_if_enough_free_space() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_free_space_check(1024);
+    o.free_space_check.as_mut().unwrap().available_space = |_| Ok(1024);
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, ["2000/01/000101_000000000.jpg"]);
+  }
+
+  #[test]
+  fn moves_raw_and_jpg_at_same_time() {
+    let d = test_dir!(
+      "image.DNG": {},
+      "image.DNG.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.dng",
+      "2000/01/000101_000000000.dng.xmp",
+      "2000/01/000101_000000000.jpg",
+    ]);
+  }
+
+  #[test]
+  fn skips_unset_date_time_original() {
+    let d = test_dir!(
+      "image.jpg": {
+        "DateTimeOriginal": "0000:00:00 00:00:00",
+        "CreateDate": "2000-01-01T00:00:00",
+      },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, ["2000/01/000101_000000000.jpg"]);
+  }
+
+  #[test]
+  fn normalizes_extension_of_media_and_sidecars() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+    fs::rename(d.get_path("image.jpg"), d.get_path("image.jpeg")).unwrap();
+    fs::rename(d.get_path("image.jpg.xmp"), d.get_path("image.jpeg.xmp")).unwrap();
+
+    let mut extensions = ExtensionMap::default();
+    extensions.insert("jpeg", "jpg");
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_extension_map(extensions);
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000.jpg.xmp",
+    ]);
+  }
+
+  #[test]
+  fn overrides_extension_of_media_and_sidecars() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image_01.jpg.xmp": {},
+    );
+
+    let mut extensions = ExtensionMap::default();
+    extensions.insert("jpg", "JPEG");
+    extensions.enable_lowercase();
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_extension_map(extensions);
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpeg",
+      "2000/01/000101_000000000.jpeg.xmp",
+      "2000/01/000101_000000000_01.jpeg.xmp",
+    ]);
+  }
+
+  #[test]
+  fn moves_video_by_media_create_date() {
+    let d = test_dir!(
+      "video.mov": {
+        "CompressorID": "avc1",
+        "DateTimeOriginal": "",
+        "CreateDate": "",
+        "TrackCreateDate": "",
+        "MediaCreateDate": "2000-01-01T00:00:00",
+      },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    assert_eq!(o.rename_preview(d.root(), true).unwrap(), [(
+      d.get_path("video.mov"),
+      d.get_path("2000/01/000101_000000000.mov")
+    )]);
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, ["2000/01/000101_000000000.mov"]);
+  }
+
+  #[test]
+  fn moves_file_and_sidecars_as_group() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image_01.jpg.xmp": { "DateTimeOriginal": "2025-01-01T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000.jpg.xmp",
+      "2000/01/000101_000000000_01.jpg.xmp",
+    ]);
+  }
+
+  #[test]
+  fn moves_file_and_dupe_as_group() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image_01.jpg.xmp": { "DateTimeOriginal": "2025-01-01T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000_01.jpg.xmp",
+    ]);
+  }
+
+  #[test]
+  fn assigns_counters_in_order_of_path_if_enabled() {
+    let d = test_dir!(
+      "c.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "b.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "b.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "a.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_ordered_moves();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000_b.jpg",
+      "2000/01/000101_000000000_b.jpg.xmp",
+      "2000/01/000101_000000000_c.jpg",
+    ]);
+  }
+
+  #[test]
+  fn moves_groups_at_same_time_separately() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "A" },
+      "image1.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "A" },
+      "image1_01.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "A" },
+      "image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "B" },
+      "image2.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "B" },
+      "image2_01.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "B" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000.jpg.xmp",
+      "2000/01/000101_000000000_01.jpg.xmp",
+      "2000/01/000101_000000000_b.jpg",
+      "2000/01/000101_000000000_b.jpg.xmp",
+      "2000/01/000101_000000000_b_01.jpg.xmp",
+    ]);
+
+    let creator_image =
+      read_tag(d.root(), "2000/01/000101_000000000.jpg", None, "Creator").unwrap();
+    let creator_sidecar = read_tag(
+      d.root(),
+      "2000/01/000101_000000000.jpg.xmp",
+      None,
+      "Creator",
+    )
+    .unwrap();
+    let creator_dupe = read_tag(
+      d.root(),
+      "2000/01/000101_000000000_01.jpg.xmp",
+      None,
+      "Creator",
+    )
+    .unwrap();
+    let creator_image_b =
+      read_tag(d.root(), "2000/01/000101_000000000_b.jpg", None, "Creator").unwrap();
+    let creator_sidecar_b = read_tag(
+      d.root(),
+      "2000/01/000101_000000000_b.jpg.xmp",
+      None,
+      "Creator",
+    )
+    .unwrap();
+    let creator_dupe_b = read_tag(
+      d.root(),
+      "2000/01/000101_000000000_b_01.jpg.xmp",
+      None,
+      "Creator",
+    )
+    .unwrap();
+
+    let creator_exp = if creator_image == "A" { "A" } else { "B" };
+
+    assert_eq!(creator_image, creator_exp);
+    assert_eq!(creator_sidecar, creator_exp);
+    assert_eq!(creator_dupe, creator_exp);
+
+    let creator_exp_b = if creator_image == "A" { "B" } else { "A" };
+
+    assert_eq!(creator_image_b, creator_exp_b);
+    assert_eq!(creator_sidecar_b, creator_exp_b);
+    assert_eq!(creator_dupe_b, creator_exp_b);
+  }
+
+  #[test]
+  fn moves_live_photo_if_image_valid() {
+    let d = test_dir!(
+      "image.heic": {
+        "ContentIdentifier": "ID",
+        "CreateDate": "2000-01-01T00:00:00",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      },
+      "image.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" }
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_date_time_validation();
+    o.validate();
+    o.move_and_rename_files(d.root(), false).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.heic",
+      "2000/01/000101_000000000.mov",
+    ]);
+  }
+
+  #[test]
+  fn moves_live_photo_as_group() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID", "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.heic.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "video.mov": {
+        "ContentIdentifier": "ID",
+        "CompressorID": "hvc1",
+        "DateTimeOriginal": "2025-01-01T00:00:00",
+      },
+      "video.mov.xmp": { "DateTimeOriginal": "2025-01-01T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.heic",
+      "2000/01/000101_000000000.heic.xmp",
+      "2000/01/000101_000000000.mov",
+      "2000/01/000101_000000000.mov.xmp",
+    ]);
+  }
+
+  #[test]
+  fn moves_live_photo_with_dupes_as_group() {
+    let d = test_dir!(
+      "image1.heic": { "ContentIdentifier": "ID", "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image2.jpg": { "ContentIdentifier": "ID", "DateTimeOriginal": "2025-01-01T00:00:00" },
+      "video1.mov": {
+        "CompressorID": "hvc1",
+        "ContentIdentifier": "ID",
+        "DateTimeOriginal": "2000-01-01T00:00:00"
+      },
+      "video2.mov": {
+        "CompressorID": "avc1",
+        "ContentIdentifier": "ID",
+        "DateTimeOriginal": "2025-01-01T00:00:00"
+      }
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.heic",
+      "2000/01/000101_000000000.mov",
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000_b.mov",
+    ]);
+  }
+
+  #[test]
+  fn moves_live_photo_leftovers_separately() {
+    let d = test_dir!(
+      "video1.mov": {
+        "CompressorID": "hvc1",
+        "ContentIdentifier": "ID",
+        "DateTimeOriginal": "2000-01-01T00:00:00"
+      },
+      "video2.mov": {
+        "CompressorID": "avc1",
+        "ContentIdentifier": "ID",
+        "DateTimeOriginal": "2025-01-01T00:00:00"
+      }
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.mov",
+      "2025/01/250101_000000000.mov",
+    ]);
+  }
+
+  #[test]
+  fn renames_by_date_time_from_sidecar() {
+    let d = test_dir!(
+      "image.jpg": {
+        "DateTimeOriginal": "2025-01-01T00:00:00",
+        "OffsetTimeOriginal": "+00:00",
+        "SubSecTimeOriginal": "0",
+      },
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00.999-08:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_080000999.jpg",
+      "2000/01/000101_080000999.jpg.xmp",
+    ]);
+  }
+
+  #[test]
+  fn renames_by_date_time_from_media_if_missing_initial_sidecar() {
+    let d = test_dir!(
+      "image.jpg": {
+        "DateTimeOriginal": "2025-01-01T00:00:00",
+        "OffsetTimeOriginal": "-08:00",
+        "SubSecTimeOriginal": "999",
+      },
+      "image_01.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00+00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2025/01/250101_080000999.jpg",
+      "2025/01/250101_080000999_01.jpg.xmp",
+    ]);
+  }
+
+  #[test]
+  fn skips_invalid_files() {
+    let d = test_dir!(
+      "image1.jpg": {},
+      "image1.jpg.xmp": {
+        "CreateDate": "2000-01-01T00:00:00",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      },
+      "image2.jpg": {},
+      "image2.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_date_time_validation();
+    o.validate();
+    o.move_and_rename_files(d.root(), false).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000.jpg.xmp",
+      "image2.jpg",
+      "image2.jpg.xmp",
+    ]);
+  }
+
+  #[test]
+  fn skips_leftover_sidecars() {
+    let d = test_dir!(
+      "image1.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00+00:00" },
+      "image1_01.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00+00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, ["image1.jpg.xmp", "image1_01.jpg.xmp",]);
+  }
+
+  #[test]
+  fn skips_live_photo_if_image_invalid() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID" },
+      "image.mov": {
+        "ContentIdentifier": "ID",
+        "CompressorID": "hvc1",
+        "CreateDate": "2000-01-01T00:00:00",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      }
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_date_time_validation();
+    o.validate();
+    o.move_and_rename_files(d.root(), false).unwrap();
+
+    assert_dir!(d, ["image.heic", "image.mov",]);
+  }
+
+  #[test]
+  fn preserves_modify_date_if_enabled() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(978_307_201);
+    io::set_modify_time(d.get_path("image.jpg"), time).unwrap();
+    io::set_modify_time(d.get_path("image.jpg.xmp"), time).unwrap();
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_preserve_modify_dates();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    for file in [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000.jpg.xmp",
+    ] {
+      assert_eq!(io::get_modify_time(d.get_path(file)).unwrap(), time);
+    }
+  }
+
+  #[test]
+  fn keeps_different_media_at_same_time_if_trashing_identical() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "A" },
+      "image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "B" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_trash_identical_collisions();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000_b.jpg",
+    ]);
+    assert_trash!(d, []);
+  }
+
+  #[test]
+  fn trashes_media_identical_to_file_at_new_name() {
+    let d = test_dir!(
+      "2000/01/000101_000000000.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "2000/01/000101_000000000.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+    fs::copy(
+      d.get_path("2000/01/000101_000000000.jpg"),
+      d.get_path("image.jpg"),
+    )
+    .unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_trash_identical_collisions();
+    let warnings = o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_eq!(warnings, [Warning::IdenticalAtSameTime(
+      PathBuf::from("./image.jpg"),
+      d.get_path("2000/01/000101_000000000.jpg"),
+    )]);
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000.jpg.xmp",
+    ]);
+    assert_trash!(d, ["image.jpg", "image.jpg.xmp"]);
+  }
+
+  #[test]
+  fn keeps_identical_media_with_edits_file_at_new_name_lacks() {
+    let d = test_dir!(
+      "2000/01/000101_000000000.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00", "Rating": "5" },
+    );
+    fs::copy(
+      d.get_path("2000/01/000101_000000000.jpg"),
+      d.get_path("image.jpg"),
+    )
+    .unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_trash_identical_collisions();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000000000_b.jpg",
+      "2000/01/000101_000000000_b.jpg.xmp",
+    ]);
+    assert_trash!(d, []);
+  }
+
+  #[test]
+  fn trashes_one_of_identical_media_at_same_time() {
+    let d = test_dir!(
+      "a/image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+    fs::create_dir(d.get_path("b")).unwrap();
+    fs::copy(d.get_path("a/image.jpg"), d.get_path("b/image.jpg")).unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_trash_identical_collisions();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, ["2000/01/000101_000000000.jpg"]);
+    assert_eq!(d.files_trash().len(), 1);
+  }
+
+  #[test]
+  fn skips_mislabeled_file_if_format_validated() {
+    let d = test_dir!(
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+    fs::write(d.get_path("image.jpg"), PNG).unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_format_validation();
+    o.validate();
+    let warnings = o.move_and_rename_files(d.root(), false).unwrap();
+
+    assert_eq!(warnings, [Warning::InvalidTag(
+      PathBuf::from("./image.jpg"),
+      "FileType",
+      "PNG file with extension `.jpg`".to_string(),
+    )]);
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp"]);
+  }
+
+  #[test]
+  fn corrects_extension_of_mislabeled_file() {
+    let d = test_dir!(
+      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+    fs::write(d.get_path("image.jpg"), PNG).unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_format_correction();
+    o.validate();
+    o.move_and_rename_files(d.root(), false).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.png",
+      "2000/01/000101_000000000.png.xmp",
+    ]);
+  }
+
+  #[test]
+  fn removes_empty_dirs_if_enabled() {
+    let d = test_dir!(
+      "a/b/image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "c/image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:01" },
+      "c/excluded/image3.jpg": {},
+    );
+
+    let scan = ScanConfig {
+      exclude: vec![PathBuf::from("c/excluded")],
+      ..Default::default()
+    };
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &scan).unwrap();
+    o.enable_remove_empty_dirs();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.jpg",
+      "2000/01/000101_000001000.jpg",
+      "c/excluded/image3.jpg",
+    ]);
+    assert!(!d.get_path("a").exists());
+    assert!(d.trash().exists());
+    assert!(d.root().exists());
+  }
+
+  #[test]
+  fn keeps_dirs_already_empty_or_excluded() {
+    let d = test_dir!(
+      "a/image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+    fs::create_dir_all(d.get_path("a/empty")).unwrap();
+    fs::create_dir_all(d.get_path("b/excluded/empty")).unwrap();
+
+    let scan = ScanConfig {
+      exclude: vec![PathBuf::from("b/excluded")],
+      ..Default::default()
+    };
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &scan).unwrap();
+    o.enable_remove_empty_dirs();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, ["2000/01/000101_000000000.jpg"]);
+    assert!(d.get_path("a/empty").exists());
+    assert!(d.get_path("b/excluded/empty").exists());
+  }
+
+  #[test]
+  fn keeps_empty_destination_within_source() {
+    let d = test_dir!(
+      "a/image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+    fs::create_dir_all(d.get_path("sorted/empty")).unwrap();
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.enable_remove_empty_dirs();
+    o.move_and_rename_files(d.get_path("sorted"), true).unwrap();
+
+    assert_dir!(d, ["sorted/2000/01/000101_000000000.jpg"]);
+    assert!(!d.get_path("a").exists());
+    assert!(d.get_path("sorted/empty").exists());
+  }
+
+  #[test]
+  fn keeps_empty_dirs_by_default() {
+    let d = test_dir!(
+      "a/image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, ["2000/01/000101_000000000.jpg"]);
+    assert!(d.get_path("a").exists());
+  }
+
+  #[test]
+  fn moves_adjustment_with_image() {
+    let d = test_dir!(
+      "IMG_0001.heic": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "IMG_0001.aae": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "2000/01/000101_000000000.heic",
+      "2000/01/000101_000000000.aae",
+    ]);
+  }
+
+  #[test]
+  fn leaves_leftover_adjustment() {
+    let d = test_dir!(
+      "IMG_0001.heic": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "IMG_0002.aae": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, ["2000/01/000101_000000000.heic", "IMG_0002.aae"]);
+  }
+
+  #[test]
+  fn skips_media_without_date_by_default() {
+    let d = test_dir!(
+      "a/image.jpg": {},
+      "a/image.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let warnings = o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, ["a/image.jpg", "a/image.jpg.xmp"]);
+    assert!(matches!(
+      warnings.as_slice(),
+      [Warning::MissingTag(_, tag)] if tag == "DateTimeOriginal"
+    ));
+  }
+
+  #[test]
+  fn moves_media_without_date_to_undated_if_set() {
+    let d = test_dir!(
+      "a/image.jpg": {},
+      "a/image.jpg.xmp": {},
+      "a/image_01.jpg.xmp": {},
+      "b/image.jpg": {},
+      "dated.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_missing_date_policy(MissingDatePolicy::MoveToUndated);
+    let warnings = o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert!(warnings.is_empty());
+    assert_eq!(d.files_good().len(), 5);
+    assert!(d.get_path("2000/01/000101_000000000.jpg").exists());
+    assert!(d.get_path("undated/image.jpg").exists());
+    assert!(d.get_path("undated/image_b.jpg").exists());
+    assert!(!d.get_path("a/image.jpg").exists());
+    assert!(!d.get_path("b/image.jpg").exists());
+
+    // Whichever is moved first, sidecars keep the name of their media.
+    let a_name = if d.get_path("undated/image.jpg.xmp").exists() {
+      "image"
+    } else {
+      "image_b"
+    };
+    assert!(d.get_path(format!("undated/{a_name}.jpg.xmp")).exists());
+    assert!(d.get_path(format!("undated/{a_name}_01.jpg.xmp")).exists());
+  }
+
+  #[test]
+  fn names_media_without_date_by_file_modify_date_if_set() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+    );
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(978_307_201);
+    io::set_modify_time(d.get_path("image.jpg"), time).unwrap();
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_missing_date_policy(MissingDatePolicy::UseFileModifyDate);
+    let warnings = o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert!(warnings.is_empty());
+    assert_dir!(d, [
+      "2001/01/010101_000001000.jpg",
+      "2001/01/010101_000001000.jpg.xmp",
+    ]);
+  }
+
+  #[test]
+  fn takes_every_entry() {
+    let d = test_dir!(
+      "burst1.jpg": { "BurstUUID": "ID", "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "burst2.jpg": { "BurstUUID": "ID", "DateTimeOriginal": "2000-01-01T00:00:01" },
+      "image.heic": { "ContentIdentifier": "ID", "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.heic.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image_01.heic.xmp": {},
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+      "other.jpg": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.assert_consistent();
+    o.enable_date_time_validation();
+    o.validate();
+
+    // Checked by `debug_assert_moved`, including media skipped as invalid.
+    o.move_and_rename_files(d.root(), false).unwrap();
+  }
+}
+
+#[cfg(test)]
+mod test_set_media_dirs {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  fn media_dirs(live_photos: MediaKind) -> MediaDirs {
+    MediaDirs {
+      photos: PathBuf::from("Photos"),
+      videos: PathBuf::from("Videos"),
+      live_photos,
+    }
+  }
+
+  #[test]
+  fn moves_photos_and_videos_apart() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.jpg.xmp": {},
+      "video.mov": { "DateTimeOriginal": "2000-01-02T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_media_dirs(media_dirs(MediaKind::Photo));
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "Photos/2000/01/000101_000000000.jpg",
+      "Photos/2000/01/000101_000000000.jpg.xmp",
+      "Videos/2000/01/000102_000000000.mov",
+    ]);
+  }
+
+  #[test]
+  fn keeps_live_photos_together() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID", "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_media_dirs(media_dirs(MediaKind::Video));
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "Videos/2000/01/000101_000000000.heic",
+      "Videos/2000/01/000101_000000000.mov",
+    ]);
+  }
+
+  #[test]
+  fn previews_renames_into_media_dirs() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "video.mov": { "DateTimeOriginal": "2000-01-02T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_media_dirs(media_dirs(MediaKind::Photo));
+    let mut renames = o.rename_preview(d.root(), true).unwrap();
+    renames.sort();
+
+    assert_eq!(renames, vec![
+      (
+        d.get_path("image.jpg"),
+        d.get_path("Photos/2000/01/000101_000000000.jpg")
+      ),
+      (
+        d.get_path("video.mov"),
+        d.get_path("Videos/2000/01/000102_000000000.mov")
+      ),
+    ]);
+  }
+}
+
+#[cfg(test)]
+mod test_enable_rename_only {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn renames_within_current_directories() {
+    let d = test_dir!(
+      "image.jpg": { "DateTimeOriginal": "2000-01-03T00:00:00" },
+      "a/image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "a/image.jpg.xmp": {},
+      "a/image_01.jpg.xmp": {},
+      "a/b/video.mov": { "DateTimeOriginal": "2000-01-02T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_rename_only();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "000103_000000000.jpg",
+      "a/000101_000000000.jpg",
+      "a/000101_000000000.jpg.xmp",
+      "a/000101_000000000_01.jpg.xmp",
+      "a/b/000102_000000000.mov",
+    ]);
+  }
+
+  #[test]
+  fn adds_counter_on_name_clash_within_directory() {
+    let d = test_dir!(
+      "a/image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "a/image1.jpg.xmp": {},
+      "a/image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "a/image2.jpg.xmp": {},
+      "b/image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_rename_only();
+    o.enable_ordered_moves();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, [
+      "a/000101_000000000.jpg",
+      "a/000101_000000000.jpg.xmp",
+      "a/000101_000000000_b.jpg",
+      "a/000101_000000000_b.jpg.xmp",
+      "b/000101_000000000.jpg",
+    ]);
+  }
+
+  #[test]
+  fn keeps_live_photo_together_in_image_directory() {
+    let d = test_dir!(
+      "a/image.heic": {
+        "ContentIdentifier": "ID",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      },
+      "a/video.mov": {
+        "CompressorID": "avc1",
+        "ContentIdentifier": "ID",
+        "DateTimeOriginal": "2000-01-01T00:00:01",
+      },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_rename_only();
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_dir!(d, ["a/000101_000000000.heic", "a/000101_000000000.mov"]);
+  }
+}
+
+#[cfg(test)]
+mod test_set_progress_callback {
+  use std::{cell::RefCell, rc::Rc};
+
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn reports_each_moved_group_in_order() {
+    let d = test_dir!(
+      "image1.heic": { "ContentIdentifier": "ID", "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image1.heic.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "video1.mov": {
+        "ContentIdentifier": "ID",
+        "CompressorID": "hvc1",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      },
+      "image2.jpg": { "DateTimeOriginal": "2000-01-02T00:00:00" },
+      "image3.jpg": { "DateTimeOriginal": "2000-01-03T00:00:00" },
+    );
+    let calls = Rc::new(RefCell::new(Vec::new()));
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let calls_callback = Rc::clone(&calls);
+    o.set_progress_callback(move |moved, total, path| {
+      calls_callback
+        .borrow_mut()
+        .push((moved, total, path.to_path_buf()));
+    });
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    let calls = calls.borrow();
+    assert_eq!(
+      calls.iter().map(|(m, t, _)| (*m, *t)).collect::<Vec<_>>(),
+      [(1, 3), (2, 3), (3, 3)]
+    );
+    assert_eq!(calls[0].2, d.get_path("image1.heic"));
+  }
+
+  #[test]
+  fn skips_groups_not_moved() {
+    let d = test_dir!(
+      "image1.jpg": {
+        "CreateDate": "2000-01-01T00:00:00",
+        "DateTimeOriginal": "2000-01-01T00:00:00",
+      },
+      "image2.jpg": {},
+    );
+    let calls = Rc::new(RefCell::new(Vec::new()));
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let calls_callback = Rc::clone(&calls);
+    o.set_progress_callback(move |moved, total, path| {
+      calls_callback
+        .borrow_mut()
+        .push((moved, total, path.to_path_buf()));
+    });
+    o.enable_date_time_validation();
+    o.validate();
+    o.move_and_rename_files(d.root(), false).unwrap();
+
+    assert_eq!(*calls.borrow(), [(1, 1, d.get_path("image1.jpg"))]);
+  }
+}
+
+#[cfg(test)]
+mod test_quarantine_invalid {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn errors_if_quarantine_path_is_relative() {
+    let d = test_dir!();
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_attribution_validation();
+    o.validate();
+    assert_err!(
+      o.quarantine_invalid("relative/path"),
+      "Quarantine path is not absolute."
+    );
+  }
+
+  #[test]
+  fn moves_only_invalid_media() {
+    let d = test_dir!(
+      "dir/invalid.jpg": {},
+      "dir/invalid.jpg.xmp": {},
+      "dir/invalid_01.jpg.xmp": {},
+      "valid.jpg": {},
+      "valid.jpg.xmp": { "Creator": "Creator", "Copyright": "Copyright Creator" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_attribution_validation();
+    o.validate();
+    o.quarantine_invalid(d.get_path("quarantine")).unwrap();
+
+    assert_dir!(d, [
+      "quarantine/dir/invalid.jpg",
+      "quarantine/dir/invalid.jpg.xmp",
+      "quarantine/dir/invalid_01.jpg.xmp",
+      "valid.jpg",
+      "valid.jpg.xmp",
+    ]);
+  }
+
+  #[test]
+  fn moves_live_photo_as_group() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID" },
+      "video.mov": {
+        "ContentIdentifier": "ID",
+        "CompressorID": "hvc1",
+        "Creator": "Creator",
+        "Copyright": "Copyright Creator",
+      },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_attribution_validation();
+    o.validate();
+    o.quarantine_invalid(d.get_path("quarantine")).unwrap();
+
+    assert_dir!(d, ["quarantine/image.heic", "quarantine/video.mov"]);
+  }
+
+  #[test]
+  fn skips_if_validation_disabled() {
+    let d = test_dir!(
+      "image.jpg": {},
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.quarantine_invalid(d.get_path("quarantine")).unwrap();
+
+    assert_dir!(d, ["image.jpg"]);
+  }
+}
+
+#[cfg(test)]
+mod test_split_by_year {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn errors_if_destination_is_relative() {
+    let d = test_dir!();
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    assert_err!(
+      o.split_by_year("relative/path"),
+      "Destination path is not absolute."
+    );
+  }
+
+  #[test]
+  fn moves_media_with_sidecars_by_year() {
+    let d = test_dir!(
+      "dir/image1.jpg": { "DateTimeOriginal": "2022-06-01T00:00:00" },
+      "dir/image1.jpg.xmp": {},
+      "dir/image1_01.jpg.xmp": {},
+      "image2.jpg": { "DateTimeOriginal": "2023-06-01T00:00:00" },
+      "image2.jpg.xmp": {},
+    );
+
+    fs::create_dir(d.get_path("split")).unwrap();
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    assert!(o.split_by_year(d.get_path("split")).unwrap().is_empty());
+
+    assert_dir!(d, [
+      "split/2022/dir/image1.jpg",
+      "split/2022/dir/image1.jpg.xmp",
+      "split/2022/dir/image1_01.jpg.xmp",
+      "split/2023/image2.jpg",
+      "split/2023/image2.jpg.xmp",
+    ]);
+  }
 
-  let media_file_ext = media.get_metadata().file_type_extension.clone();
+  #[test]
+  fn splits_in_place() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2022-06-01T00:00:00" },
+      "image2.jpg": { "DateTimeOriginal": "2023-06-01T00:00:00" },
+    );
 
-  for dupe in dupes {
-    let mut dupe_ending = OsString::from("_");
-    dupe_ending.push(dupe.get_dupe_number());
-    dupe_ending.push(".");
-    dupe_ending.push(&media_file_ext);
-    dupe_ending.push(".xmp");
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.split_by_year(d.root()).unwrap();
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.split_by_year(d.root()).unwrap();
 
-    io::move_file(
-      org::to_abs_path(&dir_src, dupe),
-      Some(&org::to_abs_path(&dir_src, &metadata_source)),
-      &dir_dst,
-      dupe_ending,
-    )?;
+    assert_dir!(d, ["2022/image1.jpg", "2023/image2.jpg"]);
   }
 
-  io::move_file(
-    org::to_abs_path(&dir_src, media),
-    Some(&org::to_abs_path(&dir_src, &metadata_source)),
-    &dir_dst,
-    format!(".{media_file_ext}"),
-  )?;
+  #[test]
+  fn moves_live_photo_by_image_year() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID", "DateTimeOriginal": "2023-01-01T00:00:00" },
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+    );
 
-  if let Some(sidecar) = sidecar {
-    io::move_file(
-      org::to_abs_path(&dir_src, sidecar),
-      Some(&org::to_abs_path(dir_src, metadata_source)),
-      dir_dst,
-      format!(".{media_file_ext}.xmp"),
-    )?;
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.split_by_year(d.root()).unwrap();
+
+    assert_dir!(d, ["2023/image.heic", "2023/video.mov"]);
   }
 
-  Ok(())
+  #[test]
+  fn keeps_media_without_date() {
+    let d = test_dir!(
+      "image.jpg": {},
+      "image.jpg.xmp": {},
+    );
+
+    let o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    let warnings = o.split_by_year(d.root()).unwrap();
+
+    assert_dir!(d, ["image.jpg", "image.jpg.xmp"]);
+    assert!(matches!(
+      warnings.as_slice(),
+      [Warning::MissingTag(_, tag)] if tag == "DateTimeOriginal"
+    ));
+  }
 }
 
 #[cfg(test)]
-mod test_move_and_rename_files {
+mod test_rename_preview {
+  use std::collections::HashSet;
+
   use super::*;
-  use crate::testing::*;
+  use crate::{io::CounterStyle, org::ScanConfig, testing::*};
 
-  #[test]
-  fn errors_if_destination_path_does_not_exist() {
-    let d = test_dir!();
+  /// Asserts that the preview of `o` is exactly what moving its files produces.
+  fn assert_preview_matches_move(mut o: Organizer, d: &TestDir) {
+    let preview = o.rename_preview(d.root(), true).unwrap();
+    let files_before = d.files_good();
 
-    let o = Organizer::import(d.root()).unwrap();
-    assert_err!(
-      o.move_and_rename_files("/path/does/not/exist", false),
-      "Destination path does not exist."
+    o.move_and_rename_files(d.root(), true).unwrap();
+
+    assert_eq!(
+      preview
+        .iter()
+        .map(|(src, _)| src.clone())
+        .collect::<HashSet<_>>(),
+      files_before
+    );
+    assert_eq!(
+      preview
+        .into_iter()
+        .map(|(_, dst)| dst)
+        .collect::<HashSet<_>>(),
+      d.files_good()
     );
   }
 
@@ -194,309 +3039,207 @@ mod test_move_and_rename_files {
   fn errors_if_destination_path_is_relative() {
     let d = test_dir!();
 
-    let o = Organizer::import(d.root()).unwrap();
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
     assert_err!(
-      o.move_and_rename_files("relative/path", false),
+      o.rename_preview("relative/path", false),
       "Destination path is not absolute."
     );
   }
 
   #[test]
-  fn moves_file_and_sidecars_as_group() {
+  fn previews_nothing_if_validation_disabled() {
     let d = test_dir!(
-      "image.jpg": {},
-      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
-      "image_01.jpg.xmp": { "DateTimeOriginal": "2025-01-01T00:00:00" },
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
     );
 
-    let o = Organizer::import(d.root()).unwrap();
-    o.move_and_rename_files(d.root(), true).unwrap();
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
 
-    assert_dir!(d, [
-      "2000/01/000101_000000000.jpg",
-      "2000/01/000101_000000000.jpg.xmp",
-      "2000/01/000101_000000000_01.jpg.xmp",
-    ]);
+    assert_eq!(o.rename_preview(d.root(), false).unwrap(), []);
   }
 
   #[test]
-  fn moves_file_and_dupe_as_group() {
+  fn does_not_move_files() {
     let d = test_dir!(
       "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
-      "image_01.jpg.xmp": { "DateTimeOriginal": "2025-01-01T00:00:00" },
     );
 
-    let o = Organizer::import(d.root()).unwrap();
-    o.move_and_rename_files(d.root(), true).unwrap();
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
 
-    assert_dir!(d, [
-      "2000/01/000101_000000000.jpg",
-      "2000/01/000101_000000000_01.jpg.xmp",
-    ]);
+    assert_eq!(o.rename_preview(d.root(), true).unwrap(), [(
+      d.get_path("image.jpg"),
+      d.get_path("2000/01/000101_000000000.jpg")
+    )]);
+    assert_dir!(d, ["image.jpg"]);
   }
 
   #[test]
-  fn moves_groups_at_same_time_separately() {
+  fn matches_move_of_groups_at_same_time() {
     let d = test_dir!(
-      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "A" },
-      "image1.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "A" },
-      "image1_01.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "A" },
-      "image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "B" },
-      "image2.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "B" },
-      "image2_01.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00", "Creator": "B" },
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image1.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image1_01.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image3.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image3.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
     );
 
-    let o = Organizer::import(d.root()).unwrap();
-    o.move_and_rename_files(d.root(), true).unwrap();
-
-    assert_dir!(d, [
-      "2000/01/000101_000000000.jpg",
-      "2000/01/000101_000000000.jpg.xmp",
-      "2000/01/000101_000000000_01.jpg.xmp",
-      "2000/01/000101_000000000_b.jpg",
-      "2000/01/000101_000000000_b.jpg.xmp",
-      "2000/01/000101_000000000_b_01.jpg.xmp",
-    ]);
-
-    let creator_image =
-      read_tag(d.root(), "2000/01/000101_000000000.jpg", None, "Creator").unwrap();
-    let creator_sidecar = read_tag(
-      d.root(),
-      "2000/01/000101_000000000.jpg.xmp",
-      None,
-      "Creator",
-    )
-    .unwrap();
-    let creator_dupe = read_tag(
-      d.root(),
-      "2000/01/000101_000000000_01.jpg.xmp",
-      None,
-      "Creator",
-    )
-    .unwrap();
-    let creator_image_b =
-      read_tag(d.root(), "2000/01/000101_000000000_b.jpg", None, "Creator").unwrap();
-    let creator_sidecar_b = read_tag(
-      d.root(),
-      "2000/01/000101_000000000_b.jpg.xmp",
-      None,
-      "Creator",
-    )
-    .unwrap();
-    let creator_dupe_b = read_tag(
-      d.root(),
-      "2000/01/000101_000000000_b_01.jpg.xmp",
-      None,
-      "Creator",
-    )
-    .unwrap();
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
 
-    let creator_exp = if creator_image == "A" { "A" } else { "B" };
+    assert_preview_matches_move(o, &d);
+  }
 
-    assert_eq!(creator_image, creator_exp);
-    assert_eq!(creator_sidecar, creator_exp);
-    assert_eq!(creator_dupe, creator_exp);
+  #[test]
+  fn matches_move_with_numeric_counter() {
+    let d = test_dir!(
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+    );
 
-    let creator_exp_b = if creator_image == "A" { "B" } else { "A" };
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_counter_style(CounterStyle::Numeric);
 
-    assert_eq!(creator_image_b, creator_exp_b);
-    assert_eq!(creator_sidecar_b, creator_exp_b);
-    assert_eq!(creator_dupe_b, creator_exp_b);
+    assert_preview_matches_move(o, &d);
   }
 
   #[test]
-  fn moves_live_photo_if_image_valid() {
+  fn previews_same_names_every_run_if_ordered() {
     let d = test_dir!(
-      "image.heic": {
-        "ContentIdentifier": "ID",
-        "CreateDate": "2000-01-01T00:00:00",
-        "DateTimeOriginal": "2000-01-01T00:00:00",
-      },
-      "image.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" }
+      "c.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "b.heic": { "DateTimeOriginal": "2000-01-01T00:00:00", "ContentIdentifier": "B" },
+      "b.mov": { "ContentIdentifier": "B", "CompressorID": "hvc1" },
+      "a.heic": { "DateTimeOriginal": "2000-01-01T00:00:00", "ContentIdentifier": "A" },
+      "a.mov": { "ContentIdentifier": "A", "CompressorID": "hvc1" },
+      "a.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
-    o.enable_date_time_validation();
-    o.validate();
-    o.move_and_rename_files(d.root(), false).unwrap();
-
-    assert_dir!(d, [
-      "2000/01/000101_000000000.heic",
-      "2000/01/000101_000000000.mov",
-    ]);
+    let preview = || {
+      let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+      o.enable_ordered_moves();
+      o.rename_preview(d.root(), true).unwrap()
+    };
+    let first = preview();
+
+    assert_eq!(first, preview());
+    assert_eq!(
+      first.iter().map(|(src, _)| src.clone()).collect::<Vec<_>>(),
+      ["a.mov", "a.heic", "b.mov", "b.heic", "a.jpg", "c.jpg"].map(|f| d.get_path(f))
+    );
   }
 
   #[test]
-  fn moves_live_photo_as_group() {
+  fn matches_move_of_existing_files() {
     let d = test_dir!(
-      "image.heic": { "ContentIdentifier": "ID", "DateTimeOriginal": "2000-01-01T00:00:00" },
-      "image.heic.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
-      "video.mov": {
-        "ContentIdentifier": "ID",
-        "CompressorID": "hvc1",
-        "DateTimeOriginal": "2025-01-01T00:00:00",
-      },
-      "video.mov.xmp": { "DateTimeOriginal": "2025-01-01T00:00:00" },
+      "2000/01/000101_000000000.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
     );
 
-    let o = Organizer::import(d.root()).unwrap();
-    o.move_and_rename_files(d.root(), true).unwrap();
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
 
-    assert_dir!(d, [
-      "2000/01/000101_000000000.heic",
-      "2000/01/000101_000000000.heic.xmp",
-      "2000/01/000101_000000000.mov",
-      "2000/01/000101_000000000.mov.xmp",
-    ]);
+    assert_preview_matches_move(o, &d);
   }
 
   #[test]
-  fn moves_live_photo_with_dupes_as_group() {
+  fn matches_move_of_live_photo() {
     let d = test_dir!(
-      "image1.heic": { "ContentIdentifier": "ID", "DateTimeOriginal": "2000-01-01T00:00:00" },
-      "image2.jpg": { "ContentIdentifier": "ID", "DateTimeOriginal": "2025-01-01T00:00:00" },
-      "video1.mov": {
-        "CompressorID": "hvc1",
-        "ContentIdentifier": "ID",
-        "DateTimeOriginal": "2000-01-01T00:00:00"
-      },
-      "video2.mov": {
-        "CompressorID": "avc1",
-        "ContentIdentifier": "ID",
-        "DateTimeOriginal": "2025-01-01T00:00:00"
-      }
+      "image.heic": { "DateTimeOriginal": "2000-01-01T00:00:00", "ContentIdentifier": "ID" },
+      "image.heic.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+      "other.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
     );
 
-    let o = Organizer::import(d.root()).unwrap();
-    o.move_and_rename_files(d.root(), true).unwrap();
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
 
-    assert_dir!(d, [
-      "2000/01/000101_000000000.heic",
-      "2000/01/000101_000000000.mov",
-      "2000/01/000101_000000000.jpg",
-      "2000/01/000101_000000000_b.mov",
-    ]);
+    assert_preview_matches_move(o, &d);
   }
 
   #[test]
-  fn moves_live_photo_leftovers_separately() {
+  fn matches_move_of_undated_media() {
     let d = test_dir!(
-      "video1.mov": {
-        "CompressorID": "hvc1",
-        "ContentIdentifier": "ID",
-        "DateTimeOriginal": "2000-01-01T00:00:00"
-      },
-      "video2.mov": {
-        "CompressorID": "avc1",
-        "ContentIdentifier": "ID",
-        "DateTimeOriginal": "2025-01-01T00:00:00"
-      }
+      "a/image.jpg": {},
+      "a/image.jpg.xmp": {},
+      "b/image.jpg": {},
     );
 
-    let o = Organizer::import(d.root()).unwrap();
-    o.move_and_rename_files(d.root(), true).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.set_missing_date_policy(MissingDatePolicy::MoveToUndated);
 
-    assert_dir!(d, [
-      "2000/01/000101_000000000.mov",
-      "2025/01/250101_000000000.mov",
-    ]);
+    assert_preview_matches_move(o, &d);
   }
 
   #[test]
-  fn renames_by_date_time_from_sidecar() {
+  fn matches_move_of_adjustment() {
     let d = test_dir!(
-      "image.jpg": {
-        "DateTimeOriginal": "2025-01-01T00:00:00",
-        "OffsetTimeOriginal": "+00:00",
-        "SubSecTimeOriginal": "0",
-      },
-      "image.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00.999-08:00" },
+      "IMG_0001.heic": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "IMG_0001.aae": {},
     );
 
-    let o = Organizer::import(d.root()).unwrap();
-    o.move_and_rename_files(d.root(), true).unwrap();
+    let o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
 
-    assert_dir!(d, [
-      "2000/01/000101_080000999.jpg",
-      "2000/01/000101_080000999.jpg.xmp",
-    ]);
+    assert_preview_matches_move(o, &d);
   }
 
   #[test]
-  fn renames_by_date_time_from_media_if_missing_initial_sidecar() {
+  fn matches_rename_only() {
     let d = test_dir!(
-      "image.jpg": {
-        "DateTimeOriginal": "2025-01-01T00:00:00",
-        "OffsetTimeOriginal": "-08:00",
-        "SubSecTimeOriginal": "999",
-      },
-      "image_01.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00+00:00" },
+      "a/image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "a/image1.jpg.xmp": {},
+      "a/image2.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "b/image.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
     );
 
-    let o = Organizer::import(d.root()).unwrap();
-    o.move_and_rename_files(d.root(), true).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_rename_only();
 
-    assert_dir!(d, [
-      "2025/01/250101_080000999.jpg",
-      "2025/01/250101_080000999_01.jpg.xmp",
-    ]);
+    assert_preview_matches_move(o, &d);
   }
+}
+
+#[cfg(test)]
+mod test_verify_moves {
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
 
   #[test]
-  fn skips_invalid_files() {
+  fn finds_nothing_missing_after_move() {
     let d = test_dir!(
-      "image1.jpg": {},
-      "image1.jpg.xmp": {
-        "CreateDate": "2000-01-01T00:00:00",
-        "DateTimeOriginal": "2000-01-01T00:00:00",
-      },
-      "image2.jpg": {},
-      "image2.jpg.xmp": {},
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image1.jpg.xmp": {},
+      "image2.jpg": { "DateTimeOriginal": "2000-01-02T00:00:00" },
     );
 
-    let mut o = Organizer::import(d.root()).unwrap();
-    o.enable_date_time_validation();
-    o.validate();
-    o.move_and_rename_files(d.root(), false).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let renames = o.rename_preview(d.root(), true).unwrap();
+    o.move_and_rename_files(d.root(), true).unwrap();
 
-    assert_dir!(d, [
-      "2000/01/000101_000000000.jpg",
-      "2000/01/000101_000000000.jpg.xmp",
-      "image2.jpg",
-      "image2.jpg.xmp",
-    ]);
+    assert!(Organizer::verify_moves(&renames).is_empty());
   }
 
   #[test]
-  fn skips_leftover_sidecars() {
+  fn finds_destination_deleted_after_move() {
     let d = test_dir!(
-      "image1.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00+00:00" },
-      "image1_01.jpg.xmp": { "DateTimeOriginal": "2000-01-01T00:00:00+00:00" },
+      "image1.jpg": { "DateTimeOriginal": "2000-01-01T00:00:00" },
+      "image1.jpg.xmp": {},
+      "image2.jpg": { "DateTimeOriginal": "2000-01-02T00:00:00" },
     );
 
-    let o = Organizer::import(d.root()).unwrap();
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    let renames = o.rename_preview(d.root(), true).unwrap();
     o.move_and_rename_files(d.root(), true).unwrap();
+    fs::remove_file(d.get_path("2000/01/000101_000000000.jpg.xmp")).unwrap();
 
-    assert_dir!(d, ["image1.jpg.xmp", "image1_01.jpg.xmp",]);
+    assert_eq!(Organizer::verify_moves(&renames), vec![
+      d.get_path("2000/01/000101_000000000.jpg.xmp")
+    ]);
   }
 
   #[test]
-  fn skips_live_photo_if_image_invalid() {
-    let d = test_dir!(
-      "image.heic": { "ContentIdentifier": "ID" },
-      "image.mov": {
-        "ContentIdentifier": "ID",
-        "CompressorID": "hvc1",
-        "CreateDate": "2000-01-01T00:00:00",
-        "DateTimeOriginal": "2000-01-01T00:00:00",
-      }
-    );
+  fn finds_destinations_not_moved_to() {
+    let d = test_dir!();
 
-    let mut o = Organizer::import(d.root()).unwrap();
-    o.enable_date_time_validation();
-    o.validate();
-    o.move_and_rename_files(d.root(), false).unwrap();
+    let renames = vec![(d.get_path("image.jpg"), d.get_path("2000/01/image.jpg"))];
 
-    assert_dir!(d, ["image.heic", "image.mov",]);
+    assert_eq!(Organizer::verify_moves(&renames), vec![
+      d.get_path("2000/01/image.jpg")
+    ]);
   }
 }
@@ -0,0 +1,370 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Organizer warnings: Structured records of problems found while organizing,
+//! so that callers can collect them instead of only reading logs.
+
+use std::{
+  fmt::{self, Display, Formatter},
+  mem,
+  path::PathBuf,
+};
+
+use super::Organizer;
+use crate::error::CatalogError;
+
+/// A problem found while organizing, with the offending file's path relative to
+/// the scanned directory.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+  /// Tag required by validation is not set.
+  MissingTag(PathBuf, String),
+  /// Tag is set, but does not pass validation, with the reason.
+  InvalidTag(PathBuf, &'static str, String),
+  /// Sidecar without an associated media file, which is removed unless kept.
+  LeftoverSidecar(PathBuf),
+  /// Sidecar linked to media of the same name but a different extension (e.g.
+  /// after re-encoding), as the media it names is missing.
+  RelinkedSidecar(PathBuf, PathBuf),
+  /// Live Photo video without any associated image, which is removed.
+  LeftoverLivePhotoVideo(PathBuf),
+  /// Live Photo video without any associated image, linked instead to a
+  /// nearby image without a video (e.g. after converting the image).
+  RelinkedLivePhotoVideo(PathBuf, PathBuf),
+  /// Live Photo component removed in favor of a better duplicate.
+  DuplicateLivePhoto(PathBuf),
+  /// Duplicate sidecar removed for having the same tags as the initial sidecar.
+  RedundantDupe(PathBuf),
+  /// Burst frame removed in favor of another frame of the same burst.
+  DuplicateBurstFrame(PathBuf),
+  /// Media in a merged catalog at the same path and capture time as media
+  /// already loaded (e.g. a copy of the same file).
+  DuplicateInMergedCatalog(PathBuf, PathBuf),
+  /// Media removed for having the same contents as other media (e.g. imported
+  /// twice).
+  IdenticalMedia(PathBuf, PathBuf),
+  /// Media removed when moving, for having the same capture time and contents
+  /// as a file already at its new name (or moved there first).
+  IdenticalAtSameTime(PathBuf, PathBuf),
+  /// Raw or JPEG image removed in favor of the other of a pair captured
+  /// together (e.g. shooting RAW+JPEG), with the one kept.
+  PairedInOtherFormat(PathBuf, PathBuf),
+  /// Images which look alike (e.g. resized copies), with the one kept if
+  /// removing near duplicates first.
+  NearDuplicates(Vec<PathBuf>),
+  /// Live Photo image and video captured further apart than allowed, likely
+  /// paired by mistake, with the video and the difference in seconds.
+  LivePhotoTimeMismatch(PathBuf, PathBuf, i64),
+  /// Live Photo components which could not be synchronized due to duplicates.
+  UnsyncedLivePhoto(Vec<PathBuf>),
+  /// Media file whose checksum differs from the one stored in its sidecar,
+  /// which may be corrupt. The stored checksum is kept.
+  ChecksumMismatch(PathBuf),
+  /// Media file which did not pass validation.
+  FailedValidation(PathBuf),
+  /// Error with a single file, which was skipped to continue the run.
+  SkippedAfterError(CatalogError),
+}
+
+impl Display for Warning {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Self::MissingTag(p, tag) => write!(f, "{}: Missing `{tag}` tag.", p.display()),
+      Self::InvalidTag(p, tag, reason) => write!(f, "{}: Invalid `{tag}` ({reason}).", p.display()),
+      Self::LeftoverSidecar(p) => write!(f, "{}: Leftover sidecar.", p.display()),
+      Self::RelinkedSidecar(p, media) => write!(
+        f,
+        "{}: Linked to media with different extension ({}).",
+        p.display(),
+        media.display()
+      ),
+      Self::LeftoverLivePhotoVideo(p) => {
+        write!(f, "{}: Live Photo video without image.", p.display())
+      }
+      Self::RelinkedLivePhotoVideo(p, image) => write!(
+        f,
+        "{}: Linked Live Photo video to image without one ({}).",
+        p.display(),
+        image.display()
+      ),
+      Self::DuplicateLivePhoto(p) => write!(f, "{}: Duplicate Live Photo component.", p.display()),
+      Self::RedundantDupe(p) => write!(f, "{}: Duplicate sidecar same as initial.", p.display()),
+      Self::DuplicateBurstFrame(p) => write!(f, "{}: Duplicate burst frame.", p.display()),
+      Self::DuplicateInMergedCatalog(p, copy) => write!(
+        f,
+        "{}: Same path and capture time as {}.",
+        p.display(),
+        copy.display()
+      ),
+      Self::IdenticalMedia(p, copy) => {
+        write!(f, "{}: Same contents as {}.", p.display(), copy.display())
+      }
+      Self::IdenticalAtSameTime(p, copy) => write!(
+        f,
+        "{}: Same capture time and contents as {}.",
+        p.display(),
+        copy.display()
+      ),
+      Self::PairedInOtherFormat(p, kept) => write!(
+        f,
+        "{}: Same shot as {} in another format.",
+        p.display(),
+        kept.display()
+      ),
+      Self::LivePhotoTimeMismatch(p, video, seconds) => write!(
+        f,
+        "{}: Live Photo video captured {seconds}s apart ({}).",
+        p.display(),
+        video.display()
+      ),
+      Self::NearDuplicates(paths) => {
+        write!(f, "Near-duplicate images:")?;
+        for p in paths {
+          write!(f, " {}", p.display())?;
+        }
+        Ok(())
+      }
+      Self::UnsyncedLivePhoto(paths) => {
+        write!(f, "Cannot synchronize Live Photo with duplicates:")?;
+        for p in paths {
+          write!(f, " {}", p.display())?;
+        }
+        Ok(())
+      }
+      Self::ChecksumMismatch(p) => {
+        write!(
+          f,
+          "{}: Checksum differs from sidecar, may be corrupt.",
+          p.display()
+        )
+      }
+      Self::FailedValidation(p) => write!(f, "{}: File did not pass validation.", p.display()),
+      Self::SkippedAfterError(e) => write!(f, "Skipped after error: {e}"),
+    }
+  }
+}
+
+/// What to do on an error with a single file.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum ErrorPolicy {
+  /// Stop the run.
+  #[default]
+  FailFast,
+  /// Skip the file, recording the error as a warning.
+  Continue,
+}
+
+/// Warnings collected by an `Organizer`. Each is also logged when pushed.
+#[derive(Default)]
+pub struct Warnings {
+  warnings: Vec<Warning>,
+  policy:   ErrorPolicy,
+}
+
+impl Warnings {
+  pub fn push(&mut self, warning: Warning) {
+    log::warn!("{warning}");
+    self.warnings.push(warning);
+  }
+
+  /// Passes through `result` from processing a single file, unless an error
+  /// and the policy is to continue, in which case this is recorded as a warning
+  /// and `None` is returned.
+  pub fn check<T>(&mut self, result: Result<T, CatalogError>) -> Result<Option<T>, CatalogError> {
+    match (result, self.policy) {
+      (Ok(value), _) => Ok(Some(value)),
+      (Err(e), ErrorPolicy::FailFast) => Err(e),
+      (Err(e), ErrorPolicy::Continue) => {
+        self.push(Warning::SkippedAfterError(e));
+        Ok(None)
+      }
+    }
+  }
+
+  /// As `check`, but for the errors from a batch of files which have all been
+  /// attempted. If failing fast, these are returned together (or alone, if only
+  /// one), and otherwise each is recorded as a warning.
+  pub fn check_all(&mut self, mut errors: Vec<CatalogError>) -> Result<(), CatalogError> {
+    match (errors.len(), self.policy) {
+      (0, _) => Ok(()),
+      (1, ErrorPolicy::FailFast) => Err(errors.remove(0)),
+      (_, ErrorPolicy::FailFast) => Err(CatalogError::Multiple(errors)),
+      (_, ErrorPolicy::Continue) => {
+        for e in errors {
+          self.push(Warning::SkippedAfterError(e));
+        }
+        Ok(())
+      }
+    }
+  }
+
+  pub fn set_policy(&mut self, policy: ErrorPolicy) {
+    self.policy = policy;
+  }
+
+  pub fn take(&mut self) -> Vec<Warning> {
+    mem::take(&mut self.warnings)
+  }
+}
+
+impl Organizer {
+  /// Takes all warnings collected so far, leaving none behind.
+  pub fn take_warnings(&mut self) -> Vec<Warning> {
+    self.warnings.take()
+  }
+}
+
+#[cfg(test)]
+mod test_check {
+  use super::*;
+
+  #[test]
+  fn passes_through_ok() {
+    let mut warnings = Warnings::default();
+
+    assert_eq!(warnings.check(Ok(1)), Ok(Some(1)));
+    assert!(warnings.take().is_empty());
+  }
+
+  #[test]
+  fn passes_through_err_if_failing_fast() {
+    let mut warnings = Warnings::default();
+
+    assert_eq!(
+      warnings.check::<()>(Err(CatalogError::Io("Error.".to_string()))),
+      Err(CatalogError::Io("Error.".to_string()))
+    );
+    assert!(warnings.take().is_empty());
+  }
+
+  #[test]
+  fn records_err_if_continuing() {
+    let mut warnings = Warnings::default();
+    warnings.set_policy(ErrorPolicy::Continue);
+
+    assert_eq!(
+      warnings.check::<()>(Err(CatalogError::Io("Error.".to_string()))),
+      Ok(None)
+    );
+    assert_eq!(warnings.take(), [Warning::SkippedAfterError(
+      CatalogError::Io("Error.".to_string())
+    )]);
+  }
+}
+
+#[cfg(test)]
+mod test_check_all {
+  use super::*;
+
+  fn errors() -> Vec<CatalogError> {
+    vec![
+      CatalogError::Io("First.".to_string()),
+      CatalogError::Io("Second.".to_string()),
+    ]
+  }
+
+  #[test]
+  fn passes_through_single_err_if_failing_fast() {
+    let mut warnings = Warnings::default();
+
+    assert_eq!(
+      warnings.check_all(vec![CatalogError::Io("Error.".to_string())]),
+      Err(CatalogError::Io("Error.".to_string()))
+    );
+  }
+
+  #[test]
+  fn combines_errs_if_failing_fast() {
+    let mut warnings = Warnings::default();
+
+    assert_eq!(
+      warnings.check_all(errors()),
+      Err(CatalogError::Multiple(errors()))
+    );
+    assert!(warnings.take().is_empty());
+  }
+
+  #[test]
+  fn records_each_err_if_continuing() {
+    let mut warnings = Warnings::default();
+    warnings.set_policy(ErrorPolicy::Continue);
+
+    assert_eq!(warnings.check_all(errors()), Ok(()));
+    assert_eq!(
+      warnings.take(),
+      errors()
+        .into_iter()
+        .map(Warning::SkippedAfterError)
+        .collect::<Vec<_>>()
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_take_warnings {
+  use std::path::Path;
+
+  use super::*;
+  use crate::{org::ScanConfig, testing::*};
+
+  #[test]
+  fn records_leftovers() {
+    let d = test_dir!(
+      "image.jpg.xmp": {},
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.remove_live_photo_leftovers().unwrap();
+    o.remove_sidecar_leftovers().unwrap();
+
+    assert_eq!(o.take_warnings(), [
+      Warning::LeftoverLivePhotoVideo(Path::new("./video.mov").to_path_buf()),
+      Warning::LeftoverSidecar(Path::new("./image.jpg.xmp").to_path_buf()),
+    ]);
+  }
+
+  #[test]
+  fn records_live_photo_duplicates() {
+    let d = test_dir!(
+      "image.heic": { "ContentIdentifier": "ID" },
+      "image.jpg": { "ContentIdentifier": "ID" },
+      "video.mov": { "ContentIdentifier": "ID", "CompressorID": "hvc1" },
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.remove_live_photo_duplicates().unwrap();
+
+    assert_eq!(o.take_warnings(), [Warning::DuplicateLivePhoto(
+      Path::new("./image.jpg").to_path_buf()
+    )]);
+  }
+
+  #[test]
+  fn records_missing_tags() {
+    let d = test_dir!(
+      "image.jpg": { "Make": "Make" },
+    );
+
+    let mut o = Organizer::import(&[d.root()], &ScanConfig::default()).unwrap();
+    o.enable_camera_validation();
+    o.validate();
+
+    assert_eq!(o.take_warnings(), [Warning::MissingTag(
+      Path::new("./image.jpg").to_path_buf(),
+      "Model".to_string()
+    )]);
+  }
+
+  #[test]
+  fn clears_on_take() {
+    let d = test_dir!(
+      "image.jpg.xmp": {},
+    );
+
+    let mut o = Organizer::load_catalog(d.root(), d.some_trash(), &ScanConfig::default()).unwrap();
+    o.remove_sidecar_leftovers().unwrap();
+
+    assert_eq!(o.take_warnings().len(), 1);
+    assert_eq!(o.take_warnings(), []);
+  }
+}
@@ -0,0 +1,105 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Adjustment sidecar file handling, for edits made in Photos on iOS.
+
+use core::fmt;
+use std::{
+  fmt::{Display, Formatter},
+  path::{Path, PathBuf},
+};
+
+use super::{Handle, Media, Metadata};
+use crate::{error::CatalogError, prim::FileCategory};
+
+/// Holds metadata from an adjustment sidecar (`.aae`), and an optional handle
+/// to the associated media file. Unlike XMP sidecars, these replace the media's
+/// extension rather than adding to it (e.g. `IMG_0001.aae` for
+/// `IMG_0001.heic`).
+pub struct Adjustment {
+  metadata: Metadata,
+  media:    Option<Handle<Media>>,
+}
+
+impl Adjustment {
+  /// Creates a new adjustment sidecar with metadata but no linked media file.
+  pub fn new(metadata: Metadata) -> Result<Self, CatalogError> {
+    if metadata.get_file_category() != FileCategory::Adjustment {
+      return Err(CatalogError::InvalidFile(format!(
+        "{metadata}: Invalid adjustment file type ({}).",
+        metadata.file_type
+      )));
+    }
+
+    Ok(Self {
+      metadata,
+      media: None,
+    })
+  }
+
+  /// Gets the path to the media file for this sidecar, without its extension
+  /// (e.g. `dir/IMG_0001`), which is not known from the sidecar alone.
+  pub fn get_media_stem(&self) -> PathBuf {
+    self.metadata.source_file.with_extension("")
+  }
+
+  /// Get the metadata read from this sidecar file.
+  pub fn get_metadata(&self) -> &Metadata {
+    &self.metadata
+  }
+
+  /// Whether this sidecar has no associated media file.
+  pub fn is_leftover(&self) -> bool {
+    self.media.is_none()
+  }
+
+  /// Link this sidecar to a media file via its handle.
+  pub fn set_media_handle(&mut self, media: Handle<Media>) {
+    assert!(self.media.is_none());
+    self.media = Some(media);
+  }
+}
+
+impl AsRef<Path> for Adjustment {
+  fn as_ref(&self) -> &Path {
+    self.metadata.as_ref()
+  }
+}
+
+impl Display for Adjustment {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    write!(f, "{}", self.metadata)
+  }
+}
+
+#[cfg(test)]
+mod test_new {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn errors_if_not_aae() {
+    let metadata = metadata!(
+      "SourceFile": "IMG_0001.xmp",
+      "FileType": "XMP",
+    );
+
+    assert_err!(Adjustment::new(metadata), "Invalid adjustment file type");
+  }
+}
+
+#[cfg(test)]
+mod test_get_media_stem {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn strips_extension() {
+    let adjustment = Adjustment::new(metadata!(
+      "SourceFile": "dir/IMG_0001.aae",
+      "FileType": "AAE",
+    ))
+    .unwrap();
+
+    assert_eq!(adjustment.get_media_stem(), PathBuf::from("dir/IMG_0001"));
+  }
+}
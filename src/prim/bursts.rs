@@ -0,0 +1,9 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Types for managing bursts, which consist of multiple images shot in rapid
+//! succession.
+
+/// Holds the `BurstUUID` tag from `ExifTool`, which identifies which images are
+/// a part of the same burst.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct BurstID(pub String);
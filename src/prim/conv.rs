@@ -2,8 +2,10 @@ use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, Offset, TimeZone};
 use chrono_tz::Tz;
 use regex::Regex;
 
+use crate::error::CatalogError;
+
 /// Converts degrees, minutes and seconds to latitude and longitude.
-pub fn dms_to_lat_lon(deg: f32, min: f32, sec: f32) -> f32 {
+pub fn dms_to_lat_lon(deg: f64, min: f64, sec: f64) -> f64 {
   deg + (min / 60.0) + (sec / 3600.0)
 }
 
@@ -23,32 +25,48 @@ pub fn get_offset_local(date_time: &NaiveDateTime) -> FixedOffset {
   *Local.from_local_datetime(date_time).unwrap().offset()
 }
 
+/// Whether `date_time` is the value `ExifTool` reports for dates which are
+/// present but unset (e.g. `QuickTime:ModifyDate` once deleted), which cannot
+/// be formatted, so is kept as stored.
+pub fn is_date_time_unset(date_time: &str) -> bool {
+  date_time.starts_with("0000:00:00")
+}
+
 /// Converts a date & time string to a `NaiveDateTime` and an optional
 /// `FixedOffset`. Assumes RFC3339 format, but optionally without a time zone
-/// offset.
-pub fn parse_date_time(date_time: &str) -> Result<(NaiveDateTime, Option<FixedOffset>), String> {
+/// offset. Sub-seconds are kept up to nanosecond precision. Unset dates (see
+/// `is_date_time_unset`) are `None`, as if missing.
+pub fn parse_date_time(
+  date_time: &str,
+) -> Result<Option<(NaiveDateTime, Option<FixedOffset>)>, CatalogError> {
+  if is_date_time_unset(date_time) {
+    return Ok(None);
+  }
+
   let date_time = date_time.to_string();
 
   let re =
-    Regex::new(r"^(\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:.\d{1,3})?)([+-]\d{2}:\d{2})?$").unwrap();
+    Regex::new(r"^(\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:.\d{1,9})?)([+-]\d{2}:\d{2})?$").unwrap();
 
-  let caps = re.captures(&date_time).ok_or(format!(
+  let caps = re.captures(&date_time).ok_or(CatalogError::Parse(format!(
     "Date Time string `{date_time}` did not match regex."
-  ))?;
+  )))?;
 
   // If a time zone is present.
   if caps.get(2).is_some() {
-    let date_time_parsed = DateTime::parse_from_rfc3339(caps.get(0).unwrap().as_str())
-      .map_err(|e| format!("Unable to parse date & time `{date_time}` ({e})"))?;
-    return Ok((
+    let date_time_parsed =
+      DateTime::parse_from_rfc3339(caps.get(0).unwrap().as_str()).map_err(|e| {
+        CatalogError::Parse(format!("Unable to parse date & time `{date_time}` ({e})"))
+      })?;
+    return Ok(Some((
       date_time_parsed.naive_local(),
       Some(*date_time_parsed.offset()),
-    ));
+    )));
   }
 
   NaiveDateTime::parse_from_str(caps.get(1).unwrap().as_str(), "%Y-%m-%dT%H:%M:%S%.f")
-    .map_err(|e| format!("Unable to parse date & time `{date_time}` ({e})."))
-    .map(|d| (d, None))
+    .map_err(|e| CatalogError::Parse(format!("Unable to parse date & time `{date_time}` ({e}).")))
+    .map(|d| Some((d, None)))
 }
 
 #[cfg(test)]
@@ -91,7 +109,7 @@ mod test_parse_date_time {
   fn parses_string_without_subseconds_or_time_zone() {
     let date_time = "2000-01-01T00:00:00";
 
-    let parsed = parse_date_time(date_time).unwrap();
+    let parsed = parse_date_time(date_time).unwrap().unwrap();
 
     assert_eq!(parsed.0, make_date_naive(2000, 1, 1, 0, 0, 0, 0));
     assert!(parsed.1.is_none());
@@ -101,7 +119,7 @@ mod test_parse_date_time {
   fn parses_string_with_subseconds_and_time_zone() {
     let date_time = "2000-01-01T00:00:00.999-08:00";
 
-    let parsed = parse_date_time(date_time).unwrap();
+    let parsed = parse_date_time(date_time).unwrap().unwrap();
 
     assert_eq!(parsed.0, make_date_naive(2000, 1, 1, 0, 0, 0, 999));
     assert_eq!(parsed.1.unwrap(), FixedOffset::east_opt(-8 * 3600).unwrap());
@@ -111,19 +129,43 @@ mod test_parse_date_time {
   fn parses_string_with_subseconds_without_time_zone() {
     let date_time = "2000-01-01T00:00:00.999";
 
-    let parsed = parse_date_time(date_time).unwrap();
+    let parsed = parse_date_time(date_time).unwrap().unwrap();
 
     assert_eq!(parsed.0, make_date_naive(2000, 1, 1, 0, 0, 0, 999));
     assert!(parsed.1.is_none());
   }
 
+  #[test]
+  fn parses_string_with_microseconds() {
+    let first = parse_date_time("2000-01-01T00:00:00.123456-08:00")
+      .unwrap()
+      .unwrap();
+    let second = parse_date_time("2000-01-01T00:00:00.123789-08:00")
+      .unwrap()
+      .unwrap();
+
+    assert_eq!(first.0.and_utc().timestamp_subsec_micros(), 123_456);
+    assert!(first.0 < second.0);
+  }
+
   #[test]
   fn parses_string_without_subseconds_with_time_zone() {
     let date_time = "2000-01-01T00:00:00-08:00";
 
-    let parsed = parse_date_time(date_time).unwrap();
+    let parsed = parse_date_time(date_time).unwrap().unwrap();
 
     assert_eq!(parsed.0, make_date_naive(2000, 1, 1, 0, 0, 0, 0));
     assert_eq!(parsed.1.unwrap(), FixedOffset::east_opt(-8 * 3600).unwrap());
   }
+
+  #[test]
+  fn treats_unset_date_as_missing() {
+    assert_eq!(parse_date_time("0000:00:00 00:00:00").unwrap(), None);
+    assert_eq!(parse_date_time("0000:00:00 00:00:00+00:00").unwrap(), None);
+  }
+
+  #[test]
+  fn errors_if_not_date() {
+    assert!(parse_date_time("0000").is_err());
+  }
 }
@@ -0,0 +1,106 @@
+// Copyright 2023-5 Seth Pendergrass. See LICENSE.
+
+//! Format of the `Copyright` tag, shared by sidecar creation, metadata updates
+//! and validation so that each derives the same `Copyright` from `Creator`.
+
+use std::{
+  fmt::{self, Display, Formatter},
+  str::FromStr,
+};
+
+use chrono::Datelike;
+
+use super::Metadata;
+use crate::error::CatalogError;
+
+/// Format of the `Copyright` tag, with placeholders `${Creator}` and `${Year}`
+/// (of capture), e.g. `© ${Year} ${Creator}, All Rights Reserved`.
+#[derive(Clone, Debug)]
+pub struct CopyrightFormat {
+  template: String,
+}
+
+impl CopyrightFormat {
+  /// Placeholders replaced by `render`.
+  const PLACEHOLDERS: [&str; 2] = ["${Creator}", "${Year}"];
+
+  /// Creates a format from `template`, which may only contain known
+  /// placeholders.
+  pub fn new(template: impl Into<String>) -> Result<Self, CatalogError> {
+    let template = template.into();
+
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find("${") {
+      let end = rest[start..]
+        .find('}')
+        .map_or(rest.len(), |e| start + e + 1);
+      let placeholder = &rest[start..end];
+      if !Self::PLACEHOLDERS.contains(&placeholder) {
+        return Err(CatalogError::Parse(format!(
+          "{template}: Unknown copyright placeholder `{placeholder}`."
+        )));
+      }
+      rest = &rest[end..];
+    }
+
+    Ok(Self { template })
+  }
+
+  /// Gets the `Copyright` for `creator` and capture `year`, if the year is
+  /// known or not needed.
+  #[must_use]
+  pub fn render(&self, creator: &str, year: Option<i32>) -> Option<String> {
+    let copyright = self.template.replace("${Creator}", creator);
+    if !copyright.contains("${Year}") {
+      return Some(copyright);
+    }
+
+    year.map(|y| copyright.replace("${Year}", &y.to_string()))
+  }
+
+  /// Gets the `Copyright` for `creator` as `render`, with the capture year of
+  /// `metadata`.
+  #[must_use]
+  pub fn render_for(&self, creator: &str, metadata: &Metadata) -> Option<String> {
+    let year = metadata
+      .get_date_time_original()
+      .map(|(date_time, _)| date_time.year());
+    self.render(creator, year)
+  }
+}
+
+impl Default for CopyrightFormat {
+  fn default() -> Self {
+    Self {
+      template: "Copyright ${Creator}".to_string(),
+    }
+  }
+}
+
+impl Display for CopyrightFormat {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    f.write_str(&self.template)
+  }
+}
+
+impl FromStr for CopyrightFormat {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Ok(Self::new(s)?)
+  }
+}
+
+#[cfg(test)]
+mod test_copyright_format {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn errors_if_unknown_placeholder() {
+    assert_err!(
+      CopyrightFormat::new("© ${Month} ${Creator}"),
+      "Unknown copyright placeholder `${Month}`"
+    );
+  }
+}
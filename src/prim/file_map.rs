@@ -85,6 +85,8 @@ impl<T> PartialEq for Handle<T> {
 /// Holds a collection of files of the same type, each with a unique `Handle`.
 pub struct FileMap<T> {
   data:           Vec<Option<T>>,
+  /// Path each entry was inserted with, by handle, kept after removal.
+  paths:          Vec<PathBuf>,
   path_to_handle: HashMap<PathBuf, Handle<T>>,
 }
 
@@ -93,15 +95,41 @@ impl<T> FileMap<T> {
   pub fn new() -> Self {
     Self {
       data:           Vec::new(),
+      paths:          Vec::new(),
       path_to_handle: HashMap::new(),
     }
   }
 
+  /// Counts entries still holding their file, and those taken (e.g. when
+  /// moved), in that order. In debug builds, asserts every entry is reachable
+  /// by the path it was inserted with.
+  pub fn count_entries(&self) -> (usize, usize) {
+    debug_assert_eq!(
+      self.data.len(),
+      self.path_to_handle.len(),
+      "Entries inserted under the same path."
+    );
+
+    let present = self.data.iter().filter(|o| o.is_some()).count();
+    (present, self.data.len() - present)
+  }
+
   /// Finds the handle for `path`, if it exists.
   pub fn find(&self, path: impl AsRef<Path>) -> Option<Handle<T>> {
     self.path_to_handle.get(path.as_ref()).copied()
   }
 
+  /// Returns the stored data for `handle`, if not removed.
+  pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+    self.data[usize::from(handle)].as_ref()
+  }
+
+  /// Finds the path `handle` was inserted with. Unlike the data, this is
+  /// retained after removal.
+  pub fn get_path(&self, handle: Handle<T>) -> Option<&Path> {
+    self.paths.get(usize::from(handle)).map(PathBuf::as_path)
+  }
+
   /// Returns a reference to the stored data for `handle`, allowing the caller
   /// to delete it. Note: The caller should *not* use this to overwrite the
   /// entry with a different file.
@@ -112,6 +140,7 @@ impl<T> FileMap<T> {
   /// Adds a file to the map that was read from `path`.
   pub fn insert(&mut self, path: impl AsRef<Path>, data: T) {
     self.data.push(Some(data));
+    self.paths.push(path.as_ref().to_path_buf());
     self
       .path_to_handle
       .insert(path.as_ref().to_path_buf(), (self.data.len() - 1).into());
@@ -151,18 +180,6 @@ impl<T> FileMap<T> {
   pub fn iter_entries_mut(&mut self) -> impl Iterator<Item = &mut Option<T>> {
     self.data.iter_mut().filter(|o| o.is_some())
   }
-
-  /// Mutably iterates over all existing entries, alongside their handles,
-  /// allowing the caller to remove them. Note: The caller should *not* use
-  /// this to overwrite the entry with a different file.
-  pub fn iter_entries_mut_indexed(&mut self) -> impl Iterator<Item = (Handle<T>, &mut Option<T>)> {
-    self
-      .data
-      .iter_mut()
-      .enumerate()
-      .filter(|(_, o)| o.is_some())
-      .map(|(i, o)| (i.into(), o))
-  }
 }
 
 impl<T> Default for FileMap<T> {
@@ -210,6 +227,58 @@ mod test_find {
   }
 }
 
+#[cfg(test)]
+mod test_count_entries {
+  use super::*;
+
+  #[test]
+  fn counts_present_and_taken() {
+    let mut map = FileMap::new();
+    map.insert("image1.jpg", 0);
+    map.insert("image2.jpg", 1);
+    map.insert("image3.jpg", 2);
+
+    map.get_entry_mut(map.find("image2.jpg").unwrap()).take();
+
+    assert_eq!(map.count_entries(), (2, 1));
+  }
+
+  #[test]
+  fn counts_empty() {
+    let map = FileMap::<usize>::new();
+    assert_eq!(map.count_entries(), (0, 0));
+  }
+
+  #[test]
+  #[should_panic(expected = "Entries inserted under the same path.")]
+  #[cfg(debug_assertions)]
+  fn panics_if_path_inserted_twice() {
+    let mut map = FileMap::new();
+    map.insert("image.jpg", 0);
+    map.insert("image.jpg", 1);
+
+    map.count_entries();
+  }
+}
+
+#[cfg(test)]
+mod test_get_path {
+  use super::*;
+
+  #[test]
+  fn finds_path_of_removed_item() {
+    let mut map = FileMap::new();
+    map.insert("image1.jpg", 0);
+    map.insert("image2.jpg", 1);
+
+    let handle = map.find("image2.jpg").unwrap();
+    map.get_entry_mut(handle).take();
+
+    assert!(map.get(handle).is_none());
+    assert_eq!(map.get_path(handle), Some(Path::new("image2.jpg")));
+  }
+}
+
 #[cfg(test)]
 mod test_iter {
   use super::*;
@@ -63,6 +63,16 @@ impl LivePhotoLinker {
     self.videos.len() > 1
   }
 
+  /// Iterate over all image `Handles`, in no particular order.
+  pub fn iter_images(&self) -> impl Iterator<Item = Handle<Media>> + '_ {
+    self.images.iter().map(LivePhotoLinkMetadata::handle)
+  }
+
+  /// Iterate over all video `Handles`, in no particular order.
+  pub fn iter_videos(&self) -> impl Iterator<Item = Handle<Media>> + '_ {
+    self.videos.iter().map(LivePhotoLinkMetadata::handle)
+  }
+
   /// Link image via `Handle`.
   pub fn insert_image(&mut self, handle: Handle<Media>, image: &Media) {
     self.images.push(LivePhotoLinkMetadata::new(handle, image));
@@ -197,6 +207,29 @@ mod test_live_photo_link_metadata {
     assert_eq!(to_sorted_vec(dupes), [0, 2, 1]);
   }
 
+  #[test]
+  fn orders_prores_over_avc() {
+    let dupes = heap(&[
+      metadata!(
+        "ModifyDate": "2025-01-01T00:00:00",
+        "FileType": "MOV",
+        "CompressorID": "avc1",
+      ),
+      metadata!(
+        "ModifyDate": "2000-01-01T00:00:00",
+        "FileType": "MOV",
+        "CompressorID": "apcn",
+      ),
+      metadata!(
+        "ModifyDate": "2000-01-01T00:00:00",
+        "FileType": "MOV",
+        "CompressorID": "hvc1",
+      ),
+    ]);
+
+    assert_eq!(to_sorted_vec(dupes), [1, 2, 0]);
+  }
+
   #[test]
   fn orders_by_format_before_date_time() {
     let dupes = heap(&[
@@ -13,13 +13,16 @@ use std::{
 
 use chrono::{DateTime, FixedOffset};
 
-use super::{Handle, LivePhotoID, Metadata, SidecarDupe, SidecarInitial};
-use crate::prim::FileCategory;
+use super::{Adjustment, BurstID, Handle, LivePhotoID, Metadata, SidecarDupe, SidecarInitial};
+use crate::{error::CatalogError, prim::FileCategory};
 
 static LIVE_PHOTO_IMAGE_EXTS: LazyLock<HashSet<&'static str>> =
   LazyLock::new(|| HashSet::from(["JPEG", "HEIC"]));
 static LIVE_PHOTO_VIDEO_EXTS: LazyLock<HashSet<&'static str>> =
   LazyLock::new(|| HashSet::from(["MOV"]));
+/// `ExifTool` file types of camera raw images.
+static RAW_FILE_TYPES: LazyLock<HashSet<&'static str>> =
+  LazyLock::new(|| HashSet::from(["ARW", "CR2", "CR3", "DNG", "NEF", "ORF", "RAF", "RW2"]));
 
 /// Live Photos are comprised of an image file and a video.
 #[derive(PartialEq)]
@@ -38,14 +41,25 @@ pub enum Codec {
   HEIC,
   HEVC,
   JPEG,
+  ProRes,
+  Raw,
   Other,
 }
 
 impl Codec {
+  /// Ranks codecs from most to least preferred:
+  /// 1. `ProRes`, being (near-)lossless for archival.
+  /// 2. HEIC and HEVC.
+  /// 3. JPEG and AVC.
+  /// 4. Raw.
+  /// 5. Other, unknown codecs.
   fn rank(self) -> u8 {
     match self {
-      Codec::HEIC | Codec::HEVC => u8::MAX,
-      Codec::JPEG | Codec::AVC => u8::MAX - 1,
+      Codec::ProRes => u8::MAX,
+      Codec::HEIC | Codec::HEVC => u8::MAX - 1,
+      Codec::JPEG | Codec::AVC => u8::MAX - 2,
+      // Never a Live Photo component, so only ranked above unknown codecs.
+      Codec::Raw => 1,
       Codec::Other => 0,
     }
   }
@@ -58,6 +72,8 @@ impl Display for Codec {
       Codec::HEIC => write!(f, "HEIC"),
       Codec::HEVC => write!(f, "HEVC"),
       Codec::JPEG => write!(f, "JPEG"),
+      Codec::ProRes => write!(f, "ProRes"),
+      Codec::Raw => write!(f, "Raw"),
       Codec::Other => write!(f, "Other"),
     }
   }
@@ -78,25 +94,27 @@ impl PartialOrd for Codec {
 /// Represents a single media file loaded from disk, including its metadata and,
 /// optionally, handles to associated sidecars.
 pub struct Media {
-  metadata: Metadata,
-  sidecar:  Option<Handle<SidecarInitial>>,
-  dupes:    HashSet<Handle<SidecarDupe>>,
+  metadata:   Metadata,
+  sidecar:    Option<Handle<SidecarInitial>>,
+  dupes:      HashSet<Handle<SidecarDupe>>,
+  adjustment: Option<Handle<Adjustment>>,
 }
 
 impl Media {
   /// Create from scanned `metadata`.
-  pub fn new(metadata: Metadata) -> Result<Self, String> {
+  pub fn new(metadata: Metadata) -> Result<Self, CatalogError> {
     let media = Self {
       metadata,
       sidecar: None,
       dupes: HashSet::new(),
+      adjustment: None,
     };
 
     if media.metadata.get_file_category() != FileCategory::Media {
-      return Err(format!(
+      return Err(CatalogError::InvalidFile(format!(
         "{}: Invalid media file type ({}).",
         media.metadata, media.metadata.file_type
-      ));
+      )));
     }
 
     let codec = media.get_codec();
@@ -104,26 +122,28 @@ impl Media {
     match media.get_live_photo_component_type() {
       Some(LivePhotoComponentType::Image) => {
         if codec != Codec::JPEG && codec != Codec::HEIC {
-          return Err(format!(
+          return Err(CatalogError::InvalidFile(format!(
             "{}: Unexpected Live Photo codec ({codec}).",
             media.metadata
-          ));
+          )));
         }
       }
       Some(LivePhotoComponentType::Video) => {
-        if codec != Codec::AVC && codec != Codec::HEVC {
-          return Err(format!(
+        if codec != Codec::AVC && codec != Codec::HEVC && codec != Codec::ProRes {
+          return Err(CatalogError::InvalidFile(format!(
             "{}: Unexpected Live Photo codec ({codec}).",
             media.metadata
-          ));
+          )));
         }
       }
+      // Raw images may share the identifier of the JPEG or HEIC captured
+      // alongside, but are never part of the Live Photo themselves.
       None => {
-        if media.metadata.content_identifier.is_some() {
-          return Err(format!(
+        if media.metadata.get_content_identifier().is_some() && codec != Codec::Raw {
+          return Err(CatalogError::InvalidFile(format!(
             "{}: Unexpected Live Photo file type ({}).",
             media.metadata, media.metadata.file_type
-          ));
+          )));
         }
       }
     }
@@ -131,20 +151,35 @@ impl Media {
     Ok(media)
   }
 
-  /// Gets the `ContentIdentifier` for this media file. Assumes this is a Live
-  /// Photo.
+  /// Gets the `ContentIdentifier` (or `MediaGroupUUID`) for this media file.
+  /// Assumes this is a Live Photo.
   pub fn content_id(&self) -> Option<LivePhotoID> {
     Some(LivePhotoID(
-      self.metadata.content_identifier.as_ref()?.clone(),
+      self.metadata.get_content_identifier()?.to_string(),
     ))
   }
 
+  /// Gets the `BurstUUID` for this media file, if part of a burst.
+  pub fn burst_id(&self) -> Option<BurstID> {
+    Some(BurstID(self.metadata.burst_uuid.as_ref()?.clone()))
+  }
+
   /// Adds a `Handle` to a duplicate sidecar, which holds metadata for
   /// additional edits to the same base media file in darktable.
   pub fn add_dupe(&mut self, sidecar: Handle<SidecarDupe>) {
     assert!(self.dupes.insert(sidecar));
   }
 
+  /// Removes a `Handle` to a duplicate sidecar, e.g. once it is deleted.
+  pub fn remove_dupe(&mut self, sidecar: Handle<SidecarDupe>) {
+    assert!(self.dupes.remove(&sidecar));
+  }
+
+  /// Returns the `Handle` to the adjustment sidecar from iOS, if it exists.
+  pub fn get_adjustment(&self) -> Option<Handle<Adjustment>> {
+    self.adjustment
+  }
+
   /// Gets the `Codec` this media file is encodec with.
   pub fn get_codec(&self) -> Codec {
     match self.metadata.file_type.as_str() {
@@ -153,8 +188,10 @@ impl Media {
       "MOV" => match self.metadata.compressor_id.as_deref() {
         Some("avc1") => Codec::AVC,
         Some("hev1" | "hvc1") => Codec::HEVC,
+        Some("apco" | "apcs" | "apcn" | "apch" | "ap4h" | "ap4x") => Codec::ProRes,
         _ => Codec::Other,
       },
+      t if RAW_FILE_TYPES.contains(t) => Codec::Raw,
       _ => Codec::Other,
     }
   }
@@ -162,7 +199,7 @@ impl Media {
   /// Gets whether this media file is the image or video component of a Live
   /// Photo, or neither.
   pub fn get_live_photo_component_type(&self) -> Option<LivePhotoComponentType> {
-    if self.metadata.content_identifier.is_some() {
+    if self.metadata.get_content_identifier().is_some() {
       if LIVE_PHOTO_IMAGE_EXTS.contains(&self.metadata.file_type.as_str()) {
         Some(LivePhotoComponentType::Image)
       } else if LIVE_PHOTO_VIDEO_EXTS.contains(&self.metadata.file_type.as_str()) {
@@ -183,17 +220,18 @@ impl Media {
   /// Gets the most recent date of modification, either from the `ModifyDate`
   /// tag, if present, else the filesystem's modification timestamp.
   pub fn get_modify_date(&self) -> DateTime<FixedOffset> {
-    // filter is to clear out `QuickTime:ModifyDate`, which reports as `0000:00:00
-    // 00:00:00` when deleted.
-    let modify_date = self
-      .metadata
-      .sub_sec_modify_date
-      .as_deref()
-      .or(self.metadata.modify_date.as_deref())
-      .filter(|d| *d != "0000:00:00 00:00:00")
-      .unwrap_or(&self.metadata.file_modify_date);
-
-    let (date_time, tz) = super::parse_date_time(modify_date).unwrap();
+    // Unset dates are skipped, e.g. `QuickTime:ModifyDate`, which reports as
+    // `0000:00:00 00:00:00` when deleted.
+    let (date_time, tz) = [
+      self.metadata.sub_sec_modify_date.as_deref(),
+      self.metadata.modify_date.as_deref(),
+      Some(self.metadata.file_modify_date.as_str()),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|d| super::parse_date_time(d).transpose())
+    .unwrap()
+    .unwrap();
 
     date_time
       .and_local_timezone(tz.unwrap_or(super::get_offset_local(&date_time)))
@@ -216,6 +254,12 @@ impl Media {
     self.dupes.iter().copied()
   }
 
+  /// Link an adjustment sidecar to this media file by `Handle`.
+  pub fn set_adjustment(&mut self, adjustment: Handle<Adjustment>) {
+    assert!(self.adjustment.is_none());
+    self.adjustment = Some(adjustment);
+  }
+
   /// Link a sidecar to this media file by `Handle`.
   pub fn set_sidecar(&mut self, sidecar: Handle<SidecarInitial>) {
     assert!(self.sidecar.is_none());
@@ -279,6 +323,19 @@ mod test_new {
 
     assert_err!(Media::new(metadata), "Invalid media file type");
   }
+
+  #[test]
+  fn loads_raw_sharing_live_photo_id() {
+    let raw = Media::new(metadata!(
+      "SourceFile": "test.dng",
+      "FileType": "DNG",
+      "FileTypeExtension": "dng",
+      "ContentIdentifier": "ID",
+    ))
+    .unwrap();
+
+    assert!(raw.get_live_photo_component_type().is_none());
+  }
 }
 
 #[cfg(test)]
@@ -319,6 +376,23 @@ mod test_get_live_photo_component_type {
     );
   }
 
+  #[test]
+  fn identifies_live_image_by_media_group_uuid() {
+    let image = Media::new(metadata!(
+      "SourceFile": "test.heic",
+      "FileType": "HEIC",
+      "MediaGroupUUID": "ID",
+    ))
+    .unwrap();
+
+    assert!(
+      image
+        .get_live_photo_component_type()
+        .is_some_and(|t| t == LivePhotoComponentType::Image)
+    );
+    assert!(image.content_id() == Some(LivePhotoID("ID".to_string())));
+  }
+
   #[test]
   fn identifies_live_hevc() {
     let video = Media::new(metadata!(
@@ -446,6 +520,69 @@ mod test_get_codec {
 
     assert_eq!(media.get_codec(), Codec::JPEG);
   }
+
+  #[test]
+  fn identifies_prores() {
+    for compressor_id in ["apco", "apcs", "apcn", "apch", "ap4h", "ap4x"] {
+      let media = Media::new(metadata!(
+        "SourceFile": "test.mov",
+        "FileType": "MOV",
+        "CompressorID": compressor_id,
+      ))
+      .unwrap();
+
+      assert_eq!(media.get_codec(), Codec::ProRes);
+    }
+  }
+
+  #[test]
+  fn identifies_raw() {
+    for (file, file_type) in [
+      ("test.dng", "DNG"),
+      ("test.cr3", "CR3"),
+      ("test.nef", "NEF"),
+      ("test.arw", "ARW"),
+    ] {
+      let media = Media::new(metadata!(
+        "SourceFile": file,
+        "FileType": file_type,
+      ))
+      .unwrap();
+
+      assert_eq!(media.get_codec(), Codec::Raw);
+    }
+  }
+}
+
+#[cfg(test)]
+mod test_codec_rank {
+  use super::*;
+
+  #[test]
+  fn orders_by_preference() {
+    let mut codecs = [
+      Codec::Other,
+      Codec::JPEG,
+      Codec::ProRes,
+      Codec::Raw,
+      Codec::HEVC,
+    ];
+    codecs.sort();
+
+    assert_eq!(codecs, [
+      Codec::Other,
+      Codec::Raw,
+      Codec::JPEG,
+      Codec::HEVC,
+      Codec::ProRes,
+    ]);
+  }
+
+  #[test]
+  fn ranks_image_and_video_counterparts_equally() {
+    assert_eq!(Codec::HEIC.cmp(&Codec::HEVC), Ordering::Equal);
+    assert_eq!(Codec::JPEG.cmp(&Codec::AVC), Ordering::Equal);
+  }
 }
 
 #[cfg(test)]
@@ -7,20 +7,109 @@ use std::{
   ffi::OsStr,
   fmt::{Display, Formatter},
   path::{Path, PathBuf},
+  sync::{LazyLock, OnceLock},
 };
 
-use chrono::{FixedOffset, NaiveDateTime};
+use chrono::{FixedOffset, NaiveDateTime, TimeDelta};
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Tags with their own `Metadata` fields which `to_exiftool_args` writes back.
+/// Other fields are file properties or derived by `ExifTool` on reading (e.g.
+/// `SubSecDateTimeOriginal`).
+const WRITABLE_TAGS: [&str; 16] = [
+  "Creator",
+  "Copyright",
+  "Subject",
+  "Rating",
+  "Label",
+  "HistoryOperation",
+  "HistoryParams",
+  "Make",
+  "Model",
+  "CreateDate",
+  "DateTimeOriginal",
+  "OffsetTimeOriginal",
+  "GPSPosition",
+  "City",
+  "State",
+  "Country",
+];
+
+/// Tags `has_same_tags` ignores: file properties, and tags `ExifTool` generates
+/// on reading (e.g. reverse geolocation) or writes on any modification.
+const NON_USER_TAGS: [&str; 29] = [
+  "SourceFile",
+  "ExifToolVersion",
+  "Error",
+  "Warning",
+  "FileName",
+  "Directory",
+  "FileSize",
+  "FileModifyDate",
+  "FileAccessDate",
+  "FileInodeChangeDate",
+  "FilePermissions",
+  "FileType",
+  "FileTypeExtension",
+  "MIMEType",
+  "ModifyDate",
+  "SubSecModifyDate",
+  "MetadataDate",
+  "XMPToolkit",
+  "GeolocationCity",
+  "GeolocationRegion",
+  "GeolocationSubregion",
+  "GeolocationCountry",
+  "GeolocationCountryCode",
+  "GeolocationPosition",
+  "GeolocationDistance",
+  "GeolocationFeatureCode",
+  "GeolocationFeatureType",
+  "GeolocationPopulation",
+  "GeolocationTimeZone",
+];
+
+/// Matches `GPSPosition` as formatted by `ExifTool`, e.g. `47 deg 36' 21.96" N,
+/// 122 deg 19' 58.08" W`.
+static GPS_POSITION_RE: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(r#"^(\d+) deg (\d+)\' (\d+\.?\d*)" ([NnSs]), (\d+) deg (\d+)\' (\d+\.?\d*)" ([WwEe])"#)
+    .unwrap()
+});
+
+/// Most digits in darktable's duplicate numbers: `_01` to `_99`, then `_100`
+/// onwards. Longer numbers are taken as part of the stem, so that names given
+/// when moving (e.g. `yymmdd_hhmmssfff`) are never mistaken for duplicates.
+const DUPE_NUMBER_DIGITS_MAX: usize = 3;
+
+/// Matches file names as parsed by `Metadata::parse_file_name`, with the
+/// directory and stem, duplicate number and base extension captured.
+static FILE_NAME_RE: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(&format!(
+    r"^(?:./)?([^.]*?)(?:_(\d{{2}}|[1-9]\d{{2,{}}}))?\.([^.]*)(?:\.[Xx][Mm][Pp])?$",
+    DUPE_NUMBER_DIGITS_MAX - 1
+  ))
+  .unwrap()
+});
+
+/// GPS coordinates in decimal degrees, negative to the south and west, with
+/// altitude in meters (negative below sea level), if known.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsCoord {
+  pub lat: f64,
+  pub lon: f64,
+  pub alt: Option<f64>,
+}
 
 /// Represents whether a file is a media file or sidecar, and if a sidecar,
 /// whether the initial (i.e. base or primary) sidecar or a duplicate from
-/// darktable.
+/// darktable, or an adjustment sidecar (`.aae`) from iOS.
 #[derive(Debug, PartialEq, Eq)]
 pub enum FileCategory {
   Media,
   SidecarInitial,
   SidecarDupe,
+  Adjustment,
 }
 
 /// Holds the parsed components of a file name, used to determine file type and
@@ -35,23 +124,50 @@ pub struct ParsedFileName<'a> {
 /// Metadata for an image or video file.
 ///
 /// Names are from `ExifTool`'s tags: <https://exiftool.org/TagNames/>.
-#[derive(Default, Clone, Deserialize)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Metadata {
   // General.
   pub source_file:         PathBuf,
   pub file_type:           String,
   pub file_type_extension: String,
+  #[serde(rename = "MIMEType")]
+  pub mime_type:           Option<String>,
+
+  // Dimensions, in pixels.
+  pub image_width:  Option<u32>,
+  pub image_height: Option<u32>,
+  /// EXIF orientation, as printed by `ExifTool` (e.g. `Rotate 90 CW`).
+  pub orientation:  Option<String>,
 
   // For Live Photos.
   #[serde(rename = "CompressorID")]
   pub compressor_id:      Option<String>,
   pub content_identifier: Option<String>, // Live Photo images & videos.
+  #[serde(rename = "MediaGroupUUID")]
+  pub media_group_uuid:   Option<String>, // Live Photo images (older exports).
+
+  // For bursts.
+  #[serde(rename = "BurstUUID")]
+  pub burst_uuid: Option<String>,
 
   // Attribution.
   pub creator:   Option<String>,
   pub copyright: Option<String>,
 
+  // Description. `Subject` holds XMP keywords, and `Label` darktable's color
+  // labels (e.g. `Red`).
+  #[serde(default, deserialize_with = "deserialize_list")]
+  pub subject: Vec<String>,
+  pub rating:  Option<f32>,
+  pub label:   Option<String>,
+
+  // darktable edit history. Lists of each step's operation and parameters.
+  #[serde(default, deserialize_with = "deserialize_list")]
+  pub history_operation: Vec<String>,
+  #[serde(default, deserialize_with = "deserialize_list")]
+  pub history_params:    Vec<String>,
+
   // Camera.
   pub make:  Option<String>,
   pub model: Option<String>,
@@ -85,10 +201,15 @@ pub struct Metadata {
 
   // Date of media capture (e.g. actuating the shutter).
   pub date_time_original:         Option<String>,
-  // pub offset_time_original:       Option<String>,
+  pub offset_time_original:       Option<String>,
   // pub sub_sec_time_original:      Option<u32>,
   pub sub_sec_date_time_original: Option<String>,
 
+  // QuickTime dates of media and track creation, which some videos carry
+  // instead of the above.
+  pub media_create_date: Option<String>,
+  pub track_create_date: Option<String>,
+
   // GPS.
   //
   // Note that XMP metadata will have the GPS references (i.e. N/S and E/W) in
@@ -109,20 +230,270 @@ pub struct Metadata {
   #[serde(rename = "GPSPosition")]
   pub gps_position: Option<String>,
 
+  // Coordinates parsed from the above, on first use.
+  #[serde(skip)]
+  gps_coord: OnceLock<Option<GpsCoord>>,
+
   // Location.
   pub city:    Option<String>,
   pub state:   Option<String>,
   pub country: Option<String>,
+
+  // Reverse geolocation of the GPS coordinates, as computed by ExifTool when
+  // reading. These are not stored in the file.
+  pub geolocation_city:    Option<String>,
+  pub geolocation_region:  Option<String>,
+  pub geolocation_country: Option<String>,
+
+  // All other tags, by name, for checking arbitrary tags (e.g. required by
+  // validation).
+  #[serde(flatten)]
+  pub other_tags: serde_json::Map<String, serde_json::Value>,
+}
+
+// The cached coordinates follow from the GPS tags, so are not compared (and may
+// only be parsed on one side).
+impl PartialEq for Metadata {
+  fn eq(&self, other: &Self) -> bool {
+    self.source_file == other.source_file
+      && self.file_type == other.file_type
+      && self.file_type_extension == other.file_type_extension
+      && self.mime_type == other.mime_type
+      && self.image_width == other.image_width
+      && self.image_height == other.image_height
+      && self.orientation == other.orientation
+      && self.compressor_id == other.compressor_id
+      && self.content_identifier == other.content_identifier
+      && self.media_group_uuid == other.media_group_uuid
+      && self.burst_uuid == other.burst_uuid
+      && self.creator == other.creator
+      && self.copyright == other.copyright
+      && self.subject == other.subject
+      && self.rating == other.rating
+      && self.label == other.label
+      && self.history_operation == other.history_operation
+      && self.history_params == other.history_params
+      && self.make == other.make
+      && self.model == other.model
+      && self.file_modify_date == other.file_modify_date
+      && self.modify_date == other.modify_date
+      && self.sub_sec_modify_date == other.sub_sec_modify_date
+      && self.create_date == other.create_date
+      && self.sub_sec_create_date == other.sub_sec_create_date
+      && self.date_time_original == other.date_time_original
+      && self.offset_time_original == other.offset_time_original
+      && self.sub_sec_date_time_original == other.sub_sec_date_time_original
+      && self.media_create_date == other.media_create_date
+      && self.track_create_date == other.track_create_date
+      && self.gps_latitude == other.gps_latitude
+      && self.gps_longitude == other.gps_longitude
+      && self.gps_position == other.gps_position
+      && self.city == other.city
+      && self.state == other.state
+      && self.country == other.country
+      && self.geolocation_city == other.geolocation_city
+      && self.geolocation_region == other.geolocation_region
+      && self.geolocation_country == other.geolocation_country
+      && self.other_tags == other.other_tags
+  }
+}
+
+/// `ExifTool` reports list tags with a single item as a scalar, and numeric
+/// looking strings as numbers, so accept any of these as a list of strings.
+fn deserialize_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+  fn to_string(value: serde_json::Value) -> String {
+    match value {
+      serde_json::Value::String(s) => s,
+      v => v.to_string(),
+    }
+  }
+
+  Ok(match serde_json::Value::deserialize(deserializer)? {
+    serde_json::Value::Array(values) => values.into_iter().map(to_string).collect(),
+    serde_json::Value::Null => Vec::new(),
+    value => vec![to_string(value)],
+  })
+}
+
+/// Returns whether a tag's `value` is set (i.e. not null or empty).
+fn is_tag_set(value: &serde_json::Value) -> bool {
+  match value {
+    serde_json::Value::Null => false,
+    serde_json::Value::String(s) => !s.is_empty(),
+    serde_json::Value::Array(a) => !a.is_empty(),
+    _ => true,
+  }
+}
+
+/// Formats an `ExifTool` argument assigning `value` to `tag`.
+fn format_tag_arg(tag: &str, value: &serde_json::Value) -> String {
+  match value {
+    serde_json::Value::String(s) => format!("-{tag}={s}"),
+    v => format!("-{tag}={v}"),
+  }
 }
 
 impl Metadata {
+  /// Gets the date and time of capture, falling back to video-specific tags
+  /// for videos without `DateTimeOriginal`.
   pub fn get_date_time_original(&self) -> Option<(NaiveDateTime, Option<FixedOffset>)> {
-    let date_time_original = self
-      .sub_sec_date_time_original
+    [
+      self.sub_sec_date_time_original.as_deref(),
+      self.date_time_original.as_deref(),
+      self.get_video_create_date(),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|d| super::parse_date_time(d).transpose())?
+    .ok()
+  }
+
+  /// Gets `MediaCreateDate`, else `TrackCreateDate`, if this is a video.
+  pub fn get_video_create_date(&self) -> Option<&str> {
+    if !self.is_video() {
+      return None;
+    }
+
+    self
+      .media_create_date
       .as_deref()
-      .or(self.date_time_original.as_deref())?;
+      .or(self.track_create_date.as_deref())
+  }
+
+  /// Returns the time zone explicitly stored for `DateTimeOriginal`. EXIF
+  /// keeps this in the separate `OffsetTimeOriginal` tag, whereas XMP and
+  /// `QuickTime` embed it in `DateTimeOriginal` itself. This distinction
+  /// matters as `ExifTool` reports dates without a time zone in the local time
+  /// zone.
+  pub fn get_time_zone(&self) -> Option<FixedOffset> {
+    if ["XMP", "MOV", "MP4"].contains(&self.file_type.as_str()) {
+      self.get_date_time_original().and_then(|(_, o)| o)
+    } else {
+      self.offset_time_original.as_deref()?.parse().ok()
+    }
+  }
+
+  /// Gets how much later this was captured than `other`, comparing instants if
+  /// both have time zones, else local times as-is.
+  pub fn get_capture_time_difference(&self, other: &Metadata) -> Option<TimeDelta> {
+    let (time, offset) = self.get_date_time_original()?;
+    let (time_other, offset_other) = other.get_date_time_original()?;
+
+    Some(match (offset, offset_other) {
+      (Some(o), Some(o_other)) => {
+        time.and_local_timezone(o).single()? - time_other.and_local_timezone(o_other).single()?
+      }
+      _ => time - time_other,
+    })
+  }
+
+  /// Returns whether `offset` is explicitly stored as the time zone of
+  /// `DateTimeOriginal`.
+  pub fn has_time_zone(&self, offset: FixedOffset) -> bool {
+    self.get_time_zone() == Some(offset)
+  }
+
+  /// Gets the identifier linking Live Photo components, from
+  /// `ContentIdentifier`, else `MediaGroupUUID` (which some older exports carry
+  /// on the image only). Empty values are skipped.
+  pub fn get_content_identifier(&self) -> Option<&str> {
+    [&self.content_identifier, &self.media_group_uuid]
+      .into_iter()
+      .find_map(|id| id.as_deref().filter(|id| !id.is_empty()))
+  }
+
+  /// Returns whether `tag` (by `ExifTool` name, e.g. `Label`) is set to a
+  /// non-empty value.
+  pub fn has_tag(&self, tag: &str) -> bool {
+    if let Some(value) = self.other_tags.get(tag) {
+      return is_tag_set(value);
+    }
+
+    // Tags with their own fields are not in `other_tags`.
+    serde_json::to_value(self).is_ok_and(|m| m.get(tag).is_some_and(is_tag_set))
+  }
+
+  /// Returns a copy with tags not set here (as by `has_tag`) taken from
+  /// `fallback`, e.g. to view a sidecar together with its media. File
+  /// properties (e.g. `SourceFile`) are always set, so are kept.
+  pub fn with_fallback(&self, fallback: &Metadata) -> Metadata {
+    let (Ok(serde_json::Value::Object(mut tags)), Ok(serde_json::Value::Object(tags_fallback))) =
+      (serde_json::to_value(self), serde_json::to_value(fallback))
+    else {
+      unreachable!("Metadata serializes to an object.");
+    };
+
+    for (tag, value) in tags_fallback {
+      if !tags.get(&tag).is_some_and(is_tag_set) {
+        tags.insert(tag, value);
+      }
+    }
+
+    let Ok(metadata) = serde_json::from_value(serde_json::Value::Object(tags)) else {
+      unreachable!("Metadata deserializes from its own tags.");
+    };
+    metadata
+  }
+
+  /// Returns whether `other` holds the same tags, with their own fields or
+  /// not (e.g. `Title` or darktable's `HistoryEnd`), ignoring file properties
+  /// and tags generated by `ExifTool` (`NON_USER_TAGS`). Unset tags are the
+  /// same as missing ones.
+  pub fn has_same_tags(&self, other: &Metadata) -> bool {
+    let user_tags = |metadata: &Metadata| {
+      let Ok(serde_json::Value::Object(tags)) = serde_json::to_value(metadata) else {
+        unreachable!("Metadata serializes to an object.");
+      };
+      tags
+        .into_iter()
+        .filter(|(tag, value)| !NON_USER_TAGS.contains(&tag.as_str()) && is_tag_set(value))
+        .collect::<serde_json::Map<_, _>>()
+    };
+
+    user_tags(self) == user_tags(other)
+  }
 
-    super::parse_date_time(date_time_original).ok()
+  /// Returns the `ExifTool` arguments (e.g. `-Creator=Name`) to write tags
+  /// changed from `original` (i.e. as read from disk). Tags set in `original`
+  /// but not here are cleared (`-Tag=`), while unchanged tags are left out.
+  /// Besides those with their own fields, this covers all `other_tags`.
+  pub fn to_exiftool_args(&self, original: &Metadata) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(tags)), Ok(serde_json::Value::Object(tags_original))) =
+      (serde_json::to_value(self), serde_json::to_value(original))
+    else {
+      unreachable!("Metadata serializes to an object.");
+    };
+
+    let mut names = WRITABLE_TAGS
+      .iter()
+      .map(ToString::to_string)
+      .chain(self.other_tags.keys().cloned())
+      .chain(original.other_tags.keys().cloned())
+      .collect::<Vec<_>>();
+    names.sort_unstable();
+    names.dedup();
+
+    let mut args = Vec::new();
+
+    for name in names {
+      let value = tags.get(&name).unwrap_or(&serde_json::Value::Null);
+      let value_original = tags_original.get(&name).unwrap_or(&serde_json::Value::Null);
+
+      if value == value_original {
+        continue;
+      }
+
+      match value {
+        serde_json::Value::Array(values) if !values.is_empty() => {
+          // Assigning each item within one command replaces the whole list.
+          args.extend(values.iter().map(|v| format_tag_arg(&name, v)));
+        }
+        serde_json::Value::Array(_) | serde_json::Value::Null => args.push(format!("-{name}=")),
+        value => args.push(format_tag_arg(&name, value)),
+      }
+    }
+
+    args
   }
 
   /// Get the type of file this metadata represents.
@@ -136,90 +507,136 @@ impl Metadata {
       } else {
         FileCategory::SidecarInitial
       }
+    } else if self.file_type == "AAE" {
+      FileCategory::Adjustment
     } else {
       assert!(self.file_type != "-", "FileType is not set.");
       FileCategory::Media
     }
   }
 
-  /// Parses the GPS metadata values into a latitude and longitude, if possible.
-  pub fn get_lat_lon(&self) -> Option<(f32, f32)> {
-    let re = Regex::new(
-      r#"^(\d+) deg (\d+)\' (\d+\.?\d*)" ([NnSs]), (\d+) deg (\d+)\' (\d+\.?\d*)" ([WwEe])"#,
-    )
-    .unwrap();
+  /// Whether this is a video file, based on its MIME type.
+  pub fn is_video(&self) -> bool {
+    self
+      .mime_type
+      .as_deref()
+      .is_some_and(|m| m.starts_with("video/"))
+  }
 
-    let Some(caps) = re.captures(self.gps_position.as_deref()?) else {
-      log::warn!(
-        "Unable to parse GPSPosition: {}",
-        self.gps_position.as_deref()?
-      );
+  /// Gets the resolution in megapixels, if the dimensions are known.
+  pub fn get_megapixels(&self) -> Option<f32> {
+    #[allow(clippy::cast_precision_loss)]
+    Some((u64::from(self.image_width?) * u64::from(self.image_height?)) as f32 / 1_000_000.0)
+  }
+
+  /// Gets the GPS coordinates, in decimal degrees, parsed from `GPSPosition`
+  /// (and `GPSAltitude`, if set). Parsed once, then cached.
+  pub fn get_gps_coord(&self) -> Option<GpsCoord> {
+    *self.gps_coord.get_or_init(|| self.parse_gps_coord())
+  }
+
+  /// Parses the GPS metadata values into coordinates, if possible.
+  fn parse_gps_coord(&self) -> Option<GpsCoord> {
+    let gps_position = self.gps_position.as_deref()?;
+
+    let Some(caps) = GPS_POSITION_RE.captures(gps_position) else {
+      log::warn!("Unable to parse GPSPosition: {gps_position}");
       return None;
     };
 
     let (Some(lat_deg), Some(lat_min), Some(lat_sec), Some(lat_ref)) =
       (caps.get(1), caps.get(2), caps.get(3), caps.get(4))
     else {
-      log::warn!(
-        "Unable to parse latitude components: {}",
-        self.gps_position.as_deref()?
-      );
+      log::warn!("Unable to parse latitude components: {gps_position}");
       return None;
     };
 
     let (Ok(lat_deg), Ok(lat_min), Ok(lat_sec)) = (
-      lat_deg.as_str().parse::<f32>(),
-      lat_min.as_str().parse::<f32>(),
-      lat_sec.as_str().parse::<f32>(),
+      lat_deg.as_str().parse::<f64>(),
+      lat_min.as_str().parse::<f64>(),
+      lat_sec.as_str().parse::<f64>(),
     ) else {
-      log::warn!(
-        "Unable to parse latitude components to float: {}",
-        self.gps_position.as_deref()?
-      );
+      log::warn!("Unable to parse latitude components to float: {gps_position}");
       return None;
     };
 
-    let mut latitude = super::dms_to_lat_lon(lat_deg, lat_min, lat_sec);
+    let mut lat = super::dms_to_lat_lon(lat_deg, lat_min, lat_sec);
     if lat_ref.as_str() == "S" || lat_ref.as_str() == "s" {
-      latitude *= -1.0;
+      lat *= -1.0;
     }
 
     let (Some(lon_deg), Some(lon_min), Some(lon_sec), Some(lon_ref)) =
       (caps.get(5), caps.get(6), caps.get(7), caps.get(8))
     else {
-      log::warn!(
-        "Unable to parse longitude components: {}",
-        self.gps_position.as_deref()?
-      );
+      log::warn!("Unable to parse longitude components: {gps_position}");
       return None;
     };
 
     let (Ok(lon_deg), Ok(lon_min), Ok(lon_sec)) = (
-      lon_deg.as_str().parse::<f32>(),
-      lon_min.as_str().parse::<f32>(),
-      lon_sec.as_str().parse::<f32>(),
+      lon_deg.as_str().parse::<f64>(),
+      lon_min.as_str().parse::<f64>(),
+      lon_sec.as_str().parse::<f64>(),
     ) else {
-      log::warn!(
-        "Unable to parse longitude components to float: {}",
-        self.gps_position.as_deref()?
-      );
+      log::warn!("Unable to parse longitude components to float: {gps_position}");
       return None;
     };
 
-    let mut longitude = super::dms_to_lat_lon(lon_deg, lon_min, lon_sec);
+    let mut lon = super::dms_to_lat_lon(lon_deg, lon_min, lon_sec);
     if lon_ref.as_str() == "W" || lon_ref.as_str() == "w" {
-      longitude *= -1.0;
+      lon *= -1.0;
     }
 
-    Some((latitude, longitude))
+    Some(GpsCoord {
+      lat,
+      lon,
+      alt: self.parse_gps_altitude(),
+    })
+  }
+
+  /// Parses `GPSAltitude` in meters, e.g. `12.3 m Below Sea Level` as -12.3.
+  /// The reference may instead be in `GPSAltitudeRef` (e.g. for XMP).
+  fn parse_gps_altitude(&self) -> Option<f64> {
+    let below_sea_level = |v: &serde_json::Value| v.as_str().is_some_and(|s| s.contains("Below"));
+
+    let altitude = self.other_tags.get("GPSAltitude")?;
+    let meters = match altitude {
+      serde_json::Value::Number(n) => n.as_f64()?,
+      serde_json::Value::String(s) => s.split_whitespace().next()?.parse().ok()?,
+      _ => return None,
+    };
+
+    if below_sea_level(altitude)
+      || self
+        .other_tags
+        .get("GPSAltitudeRef")
+        .is_some_and(below_sea_level)
+    {
+      Some(-meters)
+    } else {
+      Some(meters)
+    }
+  }
+
+  /// Returns whether the `City`, `State` and `Country` tags already match the
+  /// location `ExifTool` derives from the GPS coordinates. Parts it derives
+  /// nothing for (e.g. a region) are not written, so are not compared, keeping
+  /// this true once written.
+  pub fn is_location_from_gps(&self) -> bool {
+    self.geolocation_city.is_some()
+      && [
+        (&self.city, &self.geolocation_city),
+        (&self.state, &self.geolocation_region),
+        (&self.country, &self.geolocation_country),
+      ]
+      .into_iter()
+      .all(|(tag, derived)| derived.is_none() || tag == derived)
   }
 
   /// Extract the components of the source file name (e.g.
-  /// `dir/image_01.jpg.xmp`).
+  /// `dir/image_01.jpg.xmp`). Duplicate numbers are two digits, or up to
+  /// `DUPE_NUMBER_DIGITS_MAX` without a leading zero (e.g. `_100`).
   pub fn parse_file_name(&self) -> Option<ParsedFileName> {
-    let re = Regex::new(r"^(?:./)?([^.]*?)(?:_(\d{2}))?\.([^.]*)(?:\.[Xx][Mm][Pp])?$").unwrap();
-
-    let caps = re.captures(self.source_file.to_str()?)?;
+    let caps = FILE_NAME_RE.captures(self.source_file.to_str()?)?;
 
     Some(ParsedFileName {
       parent_and_stem: OsStr::new(caps.get(1)?.as_str()),
@@ -278,6 +695,57 @@ mod test_get_date_time_original {
     assert_eq!(date_time, date_time_expected.naive_local());
     assert_eq!(time_zone.unwrap(), *date_time_expected.offset());
   }
+
+  #[test]
+  fn falls_back_to_media_create_date_for_video() {
+    let metadata = metadata!(
+      "SourceFile": "video.mov",
+      "MIMEType": "video/quicktime",
+      "MediaCreateDate": "2000-01-01T00:00:00",
+      "TrackCreateDate": "2010-01-01T00:00:00",
+    );
+
+    assert_eq!(
+      metadata.get_date_time_original(),
+      Some((make_date_naive(2000, 1, 1, 0, 0, 0, 0), None))
+    );
+  }
+
+  #[test]
+  fn ignores_video_dates_for_image() {
+    let metadata = metadata!(
+      "SourceFile": "test.jpg",
+      "MIMEType": "image/jpeg",
+      "MediaCreateDate": "2000-01-01T00:00:00",
+    );
+
+    assert_eq!(metadata.get_date_time_original(), None);
+  }
+
+  #[test]
+  fn treats_unset_date_as_missing() {
+    let metadata = metadata!(
+      "SourceFile": "test.jpg",
+      "DateTimeOriginal": "0000:00:00 00:00:00",
+    );
+
+    assert_eq!(metadata.get_date_time_original(), None);
+  }
+
+  #[test]
+  fn falls_back_past_unset_date_for_video() {
+    let metadata = metadata!(
+      "SourceFile": "video.mov",
+      "MIMEType": "video/quicktime",
+      "DateTimeOriginal": "0000:00:00 00:00:00",
+      "MediaCreateDate": "2000-01-01T00:00:00",
+    );
+
+    assert_eq!(
+      metadata.get_date_time_original(),
+      Some((make_date_naive(2000, 1, 1, 0, 0, 0, 0), None))
+    );
+  }
 }
 
 #[cfg(test)]
@@ -295,6 +763,26 @@ mod test_get_file_category {
     assert_eq!(metadata.get_file_category(), FileCategory::SidecarDupe);
   }
 
+  #[test]
+  fn identifies_dupe_with_three_digits() {
+    let metadata = metadata!(
+      "SourceFile": "image_100.jpg.xmp",
+      "FileType": "XMP",
+    );
+
+    assert_eq!(metadata.get_file_category(), FileCategory::SidecarDupe);
+  }
+
+  #[test]
+  fn identifies_sidecar_named_by_time() {
+    let metadata = metadata!(
+      "SourceFile": "230101_120000123.jpg.xmp",
+      "FileType": "XMP",
+    );
+
+    assert_eq!(metadata.get_file_category(), FileCategory::SidecarInitial);
+  }
+
   #[test]
   fn identifies_media() {
     let metadata = metadata!(
@@ -314,12 +802,31 @@ mod test_get_file_category {
 
     assert_eq!(metadata.get_file_category(), FileCategory::SidecarInitial);
   }
+
+  #[test]
+  fn identifies_adjustment() {
+    let metadata = metadata!(
+      "SourceFile": "IMG_0001.aae",
+      "FileType": "AAE",
+    );
+
+    assert_eq!(metadata.get_file_category(), FileCategory::Adjustment);
+  }
 }
 
 #[cfg(test)]
-mod test_get_lat_lon {
+mod test_get_gps_coord {
+  use super::*;
   use crate::testing::*;
 
+  /// Latitude and longitude as formerly returned by `get_lat_lon`, in `f32`.
+  #[allow(clippy::cast_possible_truncation)]
+  fn lat_lon(metadata: &Metadata) -> Option<(f32, f32)> {
+    metadata
+      .get_gps_coord()
+      .map(|c| (c.lat as f32, c.lon as f32))
+  }
+
   #[test]
   fn parses_from_exif() {
     let metadata = metadata!(
@@ -330,7 +837,7 @@ mod test_get_lat_lon {
       "GPSPosition": "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W",
     );
 
-    assert_eq!(metadata.get_lat_lon(), Some((47.6061, -122.3328)));
+    assert_eq!(lat_lon(&metadata), Some((47.6061, -122.3328)));
   }
 
   #[test]
@@ -341,7 +848,610 @@ mod test_get_lat_lon {
       "GPSPosition": "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W",
     );
 
-    assert_eq!(metadata.get_lat_lon(), Some((47.6061, -122.3328)));
+    assert_eq!(lat_lon(&metadata), Some((47.6061, -122.3328)));
+  }
+
+  #[test]
+  fn parses_southern_and_eastern() {
+    let metadata = metadata!(
+      "GPSPosition": "33 deg 52' 4.08\" S, 151 deg 12' 36.00\" E",
+    );
+
+    assert_eq!(lat_lon(&metadata), Some((-33.8678, 151.21)));
+  }
+
+  #[test]
+  fn parses_altitude() {
+    let metadata = metadata!(
+      "GPSPosition": "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W",
+      "GPSAltitude": "56.5 m Above Sea Level",
+    );
+    assert_eq!(metadata.get_gps_coord().unwrap().alt, Some(56.5));
+
+    let metadata = metadata!(
+      "GPSPosition": "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W",
+      "GPSAltitude": "12 m",
+      "GPSAltitudeRef": "Below Sea Level",
+    );
+    assert_eq!(metadata.get_gps_coord().unwrap().alt, Some(-12.0));
+  }
+
+  #[test]
+  fn returns_none_if_unparseable() {
+    assert_eq!(metadata!().get_gps_coord(), None);
+    assert_eq!(
+      metadata!("GPSPosition": "47.6061 N, 122.3328 W").get_gps_coord(),
+      None
+    );
+  }
+
+  #[test]
+  fn caches_after_first_parse() {
+    let metadata = metadata!(
+      "GPSPosition": "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W",
+    );
+
+    assert!(metadata.gps_coord.get().is_none());
+    let coord = metadata.get_gps_coord();
+    assert_eq!(metadata.gps_coord.get(), Some(&coord));
+    assert_eq!(metadata.get_gps_coord(), coord);
+  }
+}
+
+#[cfg(test)]
+mod test_get_content_identifier {
+  use crate::testing::*;
+
+  #[test]
+  fn falls_back_to_media_group_uuid() {
+    let metadata = metadata!(
+      "MediaGroupUUID": "GROUP",
+    );
+
+    assert_eq!(metadata.get_content_identifier(), Some("GROUP"));
+  }
+
+  #[test]
+  fn skips_empty_values() {
+    let metadata = metadata!(
+      "ContentIdentifier": "",
+      "MediaGroupUUID": "GROUP",
+    );
+    assert_eq!(metadata.get_content_identifier(), Some("GROUP"));
+
+    let metadata = metadata!(
+      "ContentIdentifier": "",
+      "MediaGroupUUID": "",
+    );
+    assert_eq!(metadata.get_content_identifier(), None);
+  }
+}
+
+#[cfg(test)]
+mod test_get_time_zone {
+  use chrono::FixedOffset;
+
+  use crate::testing::*;
+
+  #[test]
+  fn parses_exif_offset_time() {
+    let metadata = metadata!(
+      "FileType": "JPEG",
+      "DateTimeOriginal": "2000-01-01T00:00:00-08:00",
+      "OffsetTimeOriginal": "+09:00",
+    );
+
+    assert_eq!(metadata.get_time_zone(), FixedOffset::east_opt(9 * 3600));
+  }
+
+  #[test]
+  fn returns_none_if_exif_missing_offset_time() {
+    let metadata = metadata!(
+      "FileType": "JPEG",
+      "DateTimeOriginal": "2000-01-01T00:00:00-08:00",
+    );
+
+    assert_eq!(metadata.get_time_zone(), None);
+  }
+}
+
+#[cfg(test)]
+mod test_deserialize_list {
+  use crate::testing::*;
+
+  #[test]
+  fn reads_array() {
+    let metadata = metadata!(
+      "Subject": ["Family", "Holiday"],
+    );
+
+    assert_eq!(metadata.subject, ["Family", "Holiday"]);
+  }
+
+  #[test]
+  fn reads_number_as_string() {
+    let metadata = metadata!(
+      "Subject": 2000,
+    );
+
+    assert_eq!(metadata.subject, ["2000"]);
+  }
+
+  #[test]
+  fn reads_single_item() {
+    let metadata = metadata!(
+      "Subject": "Family",
+    );
+
+    assert_eq!(metadata.subject, ["Family"]);
+  }
+}
+
+#[cfg(test)]
+mod test_serialize {
+  use super::*;
+
+  #[test]
+  fn round_trips_all_fields() {
+    let metadata = Metadata {
+      source_file: PathBuf::from("dir/image.jpg"),
+      file_type: "JPEG".to_string(),
+      file_type_extension: "jpg".to_string(),
+      mime_type: Some("image/jpeg".into()),
+      image_width: Some(4032),
+      image_height: Some(3024),
+      orientation: Some("Rotate 90 CW".into()),
+      compressor_id: Some("hvc1".into()),
+      content_identifier: Some("ID".into()),
+      media_group_uuid: Some("GROUP".into()),
+      burst_uuid: Some("BURST".into()),
+      creator: Some("Creator".into()),
+      copyright: Some("Copyright".into()),
+      subject: vec!["Family".to_string(), "Holiday".to_string()],
+      rating: Some(3.0),
+      label: Some("Red".into()),
+      history_operation: vec!["exposure".to_string()],
+      history_params: vec!["params".to_string()],
+      make: Some("Apple".into()),
+      model: Some("iPhone".into()),
+      file_modify_date: "2000-01-01T00:00:00-08:00".to_string(),
+      modify_date: Some("2000-01-01T00:00:00".into()),
+      sub_sec_modify_date: Some("2000-01-01T00:00:00.000-08:00".into()),
+      create_date: Some("2000-01-01T00:00:00".into()),
+      sub_sec_create_date: Some("2000-01-01T00:00:00.000-08:00".into()),
+      date_time_original: Some("2000-01-01T00:00:00".into()),
+      offset_time_original: Some("-08:00".into()),
+      sub_sec_date_time_original: Some("2000-01-01T00:00:00.000-08:00".into()),
+      media_create_date: Some("2000-01-01T08:00:00".into()),
+      track_create_date: Some("2000-01-01T08:00:00".into()),
+      gps_latitude: Some("47 deg 36' 21.96\" N".into()),
+      gps_longitude: Some("122 deg 19' 58.08\" W".into()),
+      gps_position: Some("47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W".into()),
+      gps_coord: OnceLock::new(),
+      city: Some("Seattle".into()),
+      state: Some("Washington".into()),
+      country: Some("United States".into()),
+      geolocation_city: Some("Seattle".into()),
+      geolocation_region: Some("Washington".into()),
+      geolocation_country: Some("United States".into()),
+      other_tags: serde_json::Map::from_iter([("Lens".to_string(), "Lens".into())]),
+    };
+    let value = serde_json::to_value(&metadata).unwrap();
+
+    assert_eq!(value["MIMEType"], "image/jpeg");
+    assert_eq!(value["CompressorID"], "hvc1");
+    assert_eq!(
+      value["GPSPosition"],
+      "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W"
+    );
+    assert_eq!(value["Lens"], "Lens");
+    assert_eq!(
+      serde_json::from_value::<Metadata>(value.clone()).unwrap(),
+      metadata
+    );
+
+    // Parsing the coordinates caches them on one side only.
+    assert!(metadata.get_gps_coord().is_some());
+    assert_eq!(serde_json::from_value::<Metadata>(value).unwrap(), metadata);
+  }
+
+  #[test]
+  fn round_trips_default() {
+    let metadata = Metadata::default();
+    let value = serde_json::to_value(&metadata).unwrap();
+
+    assert_eq!(serde_json::from_value::<Metadata>(value).unwrap(), metadata);
+  }
+}
+
+#[cfg(test)]
+mod test_has_same_tags {
+  use crate::testing::*;
+
+  #[test]
+  fn ignores_file_properties() {
+    let a = metadata!(
+      "SourceFile": "image.jpg.xmp",
+      "FileModifyDate": "2000-01-01T00:00:00",
+      "Creator": "Creator",
+      "Subject": ["A", "B"],
+    );
+    let b = metadata!(
+      "SourceFile": "image_01.jpg.xmp",
+      "FileModifyDate": "2025-01-01T00:00:00",
+      "Creator": "Creator",
+      "Subject": ["A", "B"],
+    );
+
+    assert!(a.has_same_tags(&b));
+  }
+
+  #[test]
+  fn detects_differing_history() {
+    let a = metadata!(
+      "HistoryOperation": ["exposure"],
+    );
+    let b = metadata!(
+      "HistoryOperation": ["exposure", "crop"],
+    );
+
+    assert!(!a.has_same_tags(&b));
+  }
+
+  #[test]
+  fn detects_differing_tags() {
+    let a = metadata!(
+      "Rating": 1,
+    );
+    let b = metadata!(
+      "Rating": 5,
+    );
+
+    assert!(!a.has_same_tags(&b));
+  }
+
+  #[test]
+  fn detects_differing_other_tags() {
+    let a = metadata!(
+      "Title": "Title",
+      "HistoryEnd": 3,
+    );
+    let b = metadata!(
+      "Title": "Title",
+      "HistoryEnd": 2,
+    );
+
+    assert!(!a.has_same_tags(&b));
+  }
+
+  #[test]
+  fn detects_differing_orientation() {
+    let a = metadata!(
+      "Orientation": "Horizontal (normal)",
+    );
+    let b = metadata!(
+      "Orientation": "Rotate 90 CW",
+    );
+
+    assert!(!a.has_same_tags(&b));
+  }
+
+  #[test]
+  fn ignores_generated_tags() {
+    let a = metadata!(
+      "ExifToolVersion": 13.29,
+      "GeolocationCity": "Seattle",
+      "Title": "",
+    );
+    let b = metadata!(
+      "ExifToolVersion": 13.30,
+    );
+
+    assert!(a.has_same_tags(&b));
+  }
+
+  #[test]
+  fn detects_differing_label() {
+    let a = metadata!(
+      "Label": "Red",
+    );
+    let b = metadata!(
+      "Label": "Blue",
+    );
+
+    assert!(!a.has_same_tags(&b));
+  }
+}
+
+#[cfg(test)]
+mod test_has_tag {
+  use crate::testing::*;
+
+  #[test]
+  fn finds_custom_tag() {
+    let metadata = metadata!(
+      "Label": "Red",
+    );
+
+    assert!(metadata.has_tag("Label"));
+    assert!(!metadata.has_tag("Title"));
+  }
+
+  #[test]
+  fn finds_tag_with_own_field() {
+    let metadata = metadata!(
+      "Creator": "Creator",
+      "GPSPosition": "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W",
+    );
+
+    assert!(metadata.has_tag("Creator"));
+    assert!(metadata.has_tag("GPSPosition"));
+    assert!(!metadata.has_tag("Copyright"));
+  }
+
+  #[test]
+  fn ignores_empty_values() {
+    let metadata = metadata!(
+      "Title": "",
+      "Subject": [],
+    );
+
+    assert!(!metadata.has_tag("Title"));
+    assert!(!metadata.has_tag("Subject"));
+  }
+}
+
+#[cfg(test)]
+mod test_with_fallback {
+  use std::path::Path;
+
+  use crate::testing::*;
+
+  #[test]
+  fn takes_tags_missing_from_fallback() {
+    let sidecar = metadata!(
+      "SourceFile": "image.jpg.xmp",
+      "Creator": "Creator",
+      "Subject": [],
+    );
+    let media = metadata!(
+      "SourceFile": "image.jpg",
+      "Creator": "Camera Owner",
+      "Subject": ["Keyword"],
+      "GPSPosition": "47 deg 36' 21.96\" N, 122 deg 19' 58.08\" W",
+      "Label": "Red",
+    );
+
+    let merged = sidecar.with_fallback(&media);
+
+    assert_eq!(merged.source_file, Path::new("image.jpg.xmp"));
+    assert_eq!(merged.creator.as_deref(), Some("Creator"));
+    assert_eq!(merged.subject, ["Keyword"]);
+    assert!(merged.get_gps_coord().is_some());
+    assert!(merged.has_tag("Label"));
+  }
+}
+
+#[cfg(test)]
+mod test_get_capture_time_difference {
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn compares_instants_if_both_have_time_zones() {
+    let a = metadata!(
+      "DateTimeOriginal": "2000-01-01T01:00:05+01:00",
+    );
+    let b = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:00+00:00",
+    );
+
+    assert_eq!(
+      a.get_capture_time_difference(&b),
+      Some(TimeDelta::seconds(5))
+    );
+  }
+
+  #[test]
+  fn compares_local_times_otherwise() {
+    let a = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:00",
+    );
+    let b = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:05+01:00",
+    );
+
+    assert_eq!(
+      a.get_capture_time_difference(&b),
+      Some(TimeDelta::seconds(-5))
+    );
+  }
+
+  #[test]
+  fn is_none_without_dates() {
+    let a = metadata!(
+      "DateTimeOriginal": "2000-01-01T00:00:00",
+    );
+
+    assert_eq!(a.get_capture_time_difference(&metadata!()), None);
+  }
+}
+
+#[cfg(test)]
+mod test_has_time_zone {
+  use chrono::FixedOffset;
+
+  use crate::testing::*;
+
+  #[test]
+  fn is_false_if_exif_missing_offset_time() {
+    let metadata = metadata!(
+      "FileType": "JPEG",
+      "DateTimeOriginal": "2000-01-01T00:00:00-08:00",
+    );
+
+    assert!(!metadata.has_time_zone(FixedOffset::west_opt(8 * 3600).unwrap()));
+  }
+
+  #[test]
+  fn is_true_if_exif_offset_time_matches() {
+    let metadata = metadata!(
+      "FileType": "JPEG",
+      "DateTimeOriginal": "2000-01-01T00:00:00-08:00",
+      "OffsetTimeOriginal": "-08:00",
+    );
+
+    assert!(metadata.has_time_zone(FixedOffset::west_opt(8 * 3600).unwrap()));
+  }
+
+  #[test]
+  fn is_true_if_xmp_date_time_matches() {
+    let metadata = metadata!(
+      "FileType": "XMP",
+      "DateTimeOriginal": "2000-01-01T00:00:00-08:00",
+    );
+
+    assert!(metadata.has_time_zone(FixedOffset::west_opt(8 * 3600).unwrap()));
+  }
+}
+
+#[cfg(test)]
+mod test_is_location_from_gps {
+  use crate::testing::*;
+
+  #[test]
+  fn is_false_if_location_differs() {
+    let metadata = metadata!(
+      "City": "Unknown",
+      "State": "Washington",
+      "Country": "United States",
+      "GeolocationCity": "Seattle",
+      "GeolocationRegion": "Washington",
+      "GeolocationCountry": "United States",
+    );
+
+    assert!(!metadata.is_location_from_gps());
+  }
+
+  #[test]
+  fn is_false_if_no_geolocation() {
+    let metadata = metadata!(
+      "City": "Seattle",
+      "State": "Washington",
+      "Country": "United States",
+    );
+
+    assert!(!metadata.is_location_from_gps());
+  }
+
+  #[test]
+  fn is_true_if_no_region_derived() {
+    let metadata = metadata!(
+      "City": "Singapore",
+      "State": "Unknown",
+      "Country": "Singapore",
+      "GeolocationCity": "Singapore",
+      "GeolocationCountry": "Singapore",
+    );
+
+    assert!(metadata.is_location_from_gps());
+  }
+
+  #[test]
+  fn is_true_if_location_matches() {
+    let metadata = metadata!(
+      "City": "Seattle",
+      "State": "Washington",
+      "Country": "United States",
+      "GeolocationCity": "Seattle",
+      "GeolocationRegion": "Washington",
+      "GeolocationCountry": "United States",
+    );
+
+    assert!(metadata.is_location_from_gps());
+  }
+}
+
+#[cfg(test)]
+mod test_to_exiftool_args {
+  use crate::testing::*;
+
+  #[test]
+  fn is_empty_if_unchanged() {
+    let metadata = metadata!(
+      "Creator": "Creator",
+      "Label": "Red",
+    );
+
+    assert!(metadata.to_exiftool_args(&metadata.clone()).is_empty());
+  }
+
+  #[test]
+  fn sets_changed_tag() {
+    let original = metadata!(
+      "Creator": "Old",
+      "Copyright": "Copyright",
+    );
+    let mut metadata = original.clone();
+    metadata.creator = Some("New".to_string());
+
+    assert_eq!(metadata.to_exiftool_args(&original), ["-Creator=New"]);
+  }
+
+  #[test]
+  fn clears_unset_tag() {
+    let original = metadata!(
+      "Creator": "Creator",
+      "Copyright": "Copyright",
+    );
+    let mut metadata = original.clone();
+    metadata.copyright = None;
+
+    assert_eq!(metadata.to_exiftool_args(&original), ["-Copyright="]);
+  }
+
+  #[test]
+  fn sets_each_list_item() {
+    let original = metadata!(
+      "Subject": "Old",
+    );
+    let mut metadata = original.clone();
+    metadata.subject = vec!["A".to_string(), "B".to_string()];
+    metadata.rating = Some(3.0);
+
+    assert_eq!(metadata.to_exiftool_args(&original), [
+      "-Rating=3.0",
+      "-Subject=A",
+      "-Subject=B"
+    ]);
+  }
+
+  #[test]
+  fn sets_other_tags() {
+    let original = metadata!(
+      "Label": "Red",
+      "Title": "Title",
+    );
+    let mut metadata = original.clone();
+    metadata
+      .other_tags
+      .insert("Label".to_string(), "Blue".into());
+    metadata.other_tags.remove("Title");
+
+    assert_eq!(metadata.to_exiftool_args(&original), [
+      "-Label=Blue",
+      "-Title="
+    ]);
+  }
+
+  #[test]
+  fn ignores_file_properties() {
+    let original = metadata!();
+    let mut metadata = original.clone();
+    metadata.source_file = "other.jpg".into();
+    metadata.image_width = Some(100);
+
+    assert!(metadata.to_exiftool_args(&original).is_empty());
   }
 }
 
@@ -382,6 +1492,33 @@ mod test_parse_file_name {
     );
   }
 
+  #[test]
+  fn parses_dupe_with_three_digits() {
+    let metadata = metadata!(
+      "SourceFile": "dir/image_100.jpg.xmp",
+    );
+
+    assert_eq!(
+      metadata.parse_file_name(),
+      Some(ParsedFileName {
+        parent_and_stem: OsStr::new("dir/image"),
+        dupe_number:     Some(OsStr::new("100")),
+        base_ext:        OsStr::new("jpg"),
+      })
+    );
+  }
+
+  #[test]
+  fn parses_longer_or_zero_padded_numbers_as_stem() {
+    for file in ["dir/image_010.jpg.xmp", "dir/image_1000.jpg.xmp"] {
+      let metadata = metadata!(
+        "SourceFile": file,
+      );
+
+      assert_eq!(metadata.parse_file_name().unwrap().dupe_number, None);
+    }
+  }
+
   #[test]
   fn parses_media() {
     let metadata = metadata!(
@@ -3,7 +3,10 @@
 //! Primitive types for representing multimedia files and their metadata, and
 //! the relationships between them.
 
+mod adjustment;
+mod bursts;
 mod conv;
+mod copyright;
 mod file_map;
 mod live_photos;
 mod media;
@@ -13,7 +16,10 @@ mod sidecar_initial;
 
 use std::path::PathBuf;
 
+pub use adjustment::*;
+pub use bursts::*;
 pub use conv::*;
+pub use copyright::*;
 pub use file_map::*;
 pub use live_photos::*;
 pub use media::*;
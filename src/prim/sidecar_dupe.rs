@@ -11,7 +11,7 @@ use std::{
 };
 
 use super::{Handle, Media, Metadata, Sidecar};
-use crate::prim::FileCategory;
+use crate::{error::CatalogError, prim::FileCategory};
 
 /// Holds metadata from a duplicate XMP sidecar, and an optional handle to the
 /// associate media file.
@@ -22,20 +22,20 @@ pub struct SidecarDupe {
 
 impl SidecarDupe {
   /// Create a new duplicate sidecar from the given metadata.
-  pub fn new(metadata: Metadata) -> Result<Self, String> {
+  pub fn new(metadata: Metadata) -> Result<Self, CatalogError> {
     if metadata.get_file_category() != FileCategory::SidecarDupe {
-      return Err(format!(
+      return Err(CatalogError::InvalidFile(format!(
         "{metadata}: Invalid sidecar duplicate file type ({}).",
         metadata.file_type
-      ));
+      )));
     }
 
     let parsed_name = metadata.parse_file_name();
 
     if parsed_name.is_none_or(|p| p.base_ext.eq_ignore_ascii_case("xmp")) {
-      return Err(format!(
+      return Err(CatalogError::InvalidFile(format!(
         "{metadata}: Invalid sidecar duplicate file extension."
-      ));
+      )));
     }
 
     Ok(Self {
@@ -148,6 +148,17 @@ mod test_get_dupe_number {
 
     assert_eq!(sidecar.get_dupe_number(), "01");
   }
+
+  #[test]
+  fn extracts_three_digit_dupe_number() {
+    let sidecar = SidecarDupe::new(metadata!(
+      "SourceFile": "dir/image_100.jpg.xmp",
+      "FileType": "XMP",
+    ))
+    .unwrap();
+
+    assert_eq!(sidecar.get_dupe_number(), "100");
+  }
 }
 
 #[cfg(test)]
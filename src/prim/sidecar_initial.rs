@@ -10,7 +10,7 @@ use std::{
 };
 
 use super::{Handle, Media, Metadata, Sidecar};
-use crate::prim::FileCategory;
+use crate::{error::CatalogError, prim::FileCategory};
 
 /// Holds metadata from an XMP sidecar file on disk, and an optional handle to
 /// the associated media file.
@@ -21,18 +21,20 @@ pub struct SidecarInitial {
 
 impl SidecarInitial {
   /// Creates a new sidecar object with metadata but no linked media file.
-  pub fn new(metadata: Metadata) -> Result<Self, String> {
+  pub fn new(metadata: Metadata) -> Result<Self, CatalogError> {
     if metadata.get_file_category() != FileCategory::SidecarInitial {
-      return Err(format!(
+      return Err(CatalogError::InvalidFile(format!(
         "{metadata}: Invalid sidecar file type ({}).",
         metadata.file_type
-      ));
+      )));
     }
 
     let parsed_name = metadata.parse_file_name();
 
     if parsed_name.is_none_or(|p| p.base_ext.eq_ignore_ascii_case("xmp")) {
-      return Err(format!("{metadata}: Invalid sidecar file extension."));
+      return Err(CatalogError::InvalidFile(format!(
+        "{metadata}: Invalid sidecar file extension."
+      )));
     }
 
     Ok(Self {
@@ -2,35 +2,114 @@
 
 //! Program setup functions.
 
-use std::{fs, io::Write, path::PathBuf};
+use std::{
+  fs::{self, File},
+  io::Write,
+  path::{Path, PathBuf},
+};
 
-use env_logger::Builder;
-use log::LevelFilter;
+use env_logger::{Builder, Logger, Target, WriteStyle};
+use log::{LevelFilter, Log, Metadata, Record};
+use serde_json::Value;
+
+use crate::io;
 
 /// Sets up `env_logger` with the format "`ERROR_LEVEL` message" (e.g. "WARN
-/// something went wrong").
+/// something went wrong"). If `log_file` is `Some`, logs are also written
+/// there (without color), truncating any existing file.
 ///
 /// Log levels:
 /// Error: Program errors.
 /// Warn:  File removal and issues preventing work.
 /// Info:  General program flow.
 /// Debug: Per-file operations.
-/// Trace: Detailed per-file operations.
-pub fn configure_logging(verbosity: u8) {
+/// Trace: Detailed per-file operations. At verbosity 3 and above, also every
+///        `ExifTool` command line.
+pub fn configure_logging(verbosity: u8, log_file: Option<&Path>) -> Result<(), String> {
+  let logger = make_logger(verbosity, log_file)?;
+
+  log::set_max_level(logger.filter());
+  log::set_boxed_logger(Box::new(logger)).map_err(|e| format!("Unable to set up logging ({e})."))
+}
+
+/// Logs to each of its loggers.
+struct TeeLogger {
+  loggers: Vec<Logger>,
+}
+
+impl TeeLogger {
+  fn filter(&self) -> LevelFilter {
+    self
+      .loggers
+      .iter()
+      .map(Logger::filter)
+      .max()
+      .unwrap_or(LevelFilter::Off)
+  }
+}
+
+impl Log for TeeLogger {
+  fn enabled(&self, metadata: &Metadata) -> bool {
+    self.loggers.iter().any(|l| l.enabled(metadata))
+  }
+
+  fn log(&self, record: &Record) {
+    for logger in &self.loggers {
+      logger.log(record);
+    }
+  }
+
+  fn flush(&self) {
+    for logger in &self.loggers {
+      logger.flush();
+    }
+  }
+}
+
+/// Makes a logger to stderr, and also to `log_file` if `Some`.
+fn make_logger(verbosity: u8, log_file: Option<&Path>) -> Result<TeeLogger, String> {
+  let mut loggers = Vec::from([make_builder(verbosity).build()]);
+
+  if let Some(log_file) = log_file {
+    let file = File::create(log_file)
+      .map_err(|e| format!("{}: Unable to create log file ({e}).", log_file.display()))?;
+
+    loggers.push(
+      make_builder(verbosity)
+        .target(Target::Pipe(Box::new(file)))
+        .write_style(WriteStyle::Never)
+        .build(),
+    );
+  }
+
+  Ok(TeeLogger { loggers })
+}
+
+/// Makes a logger builder for `verbosity`, in the format described in
+/// `configure_logging`.
+fn make_builder(verbosity: u8) -> Builder {
   let level = match verbosity {
     0 => LevelFilter::Info,
     1 => LevelFilter::Debug,
     _ => LevelFilter::Trace,
   };
 
-  Builder::new()
+  let level_exiftool = if verbosity >= 3 {
+    LevelFilter::Trace
+  } else {
+    LevelFilter::Off
+  };
+
+  let mut builder = Builder::new();
+  builder
     .filter_level(level)
+    .filter_module(io::LOG_TARGET_EXIFTOOL, level_exiftool)
     .format(|f, r| {
       let level = r.level();
       let style = f.default_level_style(level);
       writeln!(f, "{style}{level}{style:#}\t{}", r.args())
-    })
-    .init();
+    });
+  builder
 }
 
 /// Get catalog root from `path`, if present, and write to
@@ -61,3 +140,257 @@ pub fn get_or_update_catalog_path(path: Option<PathBuf>) -> Result<PathBuf, Stri
     )),
   }
 }
+
+/// Reads default arguments from the config file
+/// `XDG_CONFIG_HOME/catalog_1a.json` if it exists, as by `load_config_args`.
+pub fn read_config_args(is_given: &dyn Fn(&str) -> bool) -> Result<Vec<String>, String> {
+  let xdg_dirs = xdg::BaseDirectories::new();
+  match xdg_dirs.find_config_file(concat!(env!("CARGO_PKG_NAME"), ".json")) {
+    Some(path) => load_config_args(&path, is_given),
+    None => Ok(Vec::new()),
+  }
+}
+
+/// Reads the JSON config file at `path`, holding defaults for global flags by
+/// their long names (e.g. `{ "min-megapixels": 12, "exclude": ["tmp"] }`), and
+/// converts them to arguments to parse before those on the command line. Flags
+/// `is_given` on the command line, by ID (e.g. `min_megapixels`), are skipped,
+/// so that those override the file.
+pub fn load_config_args(
+  path: &Path,
+  is_given: &dyn Fn(&str) -> bool,
+) -> Result<Vec<String>, String> {
+  let contents = fs::read_to_string(path)
+    .map_err(|e| format!("{}: Unable to read config ({e}).", path.display()))?;
+  let config = serde_json::from_str::<serde_json::Map<String, Value>>(&contents)
+    .map_err(|e| format!("{}: Unable to parse config ({e}).", path.display()))?;
+
+  let mut args = Vec::new();
+  for (flag, value) in config {
+    if is_given(&flag.replace('-', "_")) {
+      continue;
+    }
+
+    let values = match value {
+      Value::Array(values) => values,
+      value => vec![value],
+    };
+    for value in values {
+      match value {
+        Value::Null | Value::Bool(false) => {}
+        Value::Bool(true) => args.push(format!("--{flag}")),
+        Value::Number(n) => args.push(format!("--{flag}={n}")),
+        Value::String(s) => args.push(format!("--{flag}={s}")),
+        Value::Array(_) | Value::Object(_) => {
+          return Err(format!(
+            "{}: Invalid value for {flag} in config.",
+            path.display()
+          ));
+        }
+      }
+    }
+  }
+
+  Ok(args)
+}
+
+#[cfg(test)]
+mod test_make_logger {
+  use std::env;
+
+  use log::Level;
+
+  use super::*;
+  use crate::testing::*;
+
+  #[test]
+  fn writes_to_log_file_at_verbosity() {
+    let log_file = env::temp_dir().join(format!(
+      "{}_tests/setup_test_make_logger.log",
+      env!("CARGO_PKG_NAME")
+    ));
+    fs::create_dir_all(log_file.parent().unwrap()).unwrap();
+    fs::write(&log_file, "Old contents.").unwrap();
+
+    let logger = make_logger(0, Some(&log_file)).unwrap();
+    for (level, message) in [
+      (Level::Info, "Loading catalog."),
+      (Level::Debug, "Per-file."),
+    ] {
+      logger.log(
+        &Record::builder()
+          .level(level)
+          .target("c1a")
+          .args(format_args!("{message}"))
+          .build(),
+      );
+    }
+    logger.flush();
+
+    assert_eq!(
+      fs::read_to_string(&log_file).unwrap(),
+      "INFO\tLoading catalog.\n"
+    );
+  }
+
+  #[test]
+  fn errors_if_log_file_cannot_be_created() {
+    assert_err!(
+      make_logger(0, Some(Path::new("/path/does/not/exist/c1a.log"))),
+      "Unable to create log file"
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_make_builder {
+  use std::sync::{Mutex, OnceLock};
+
+  use log::Level;
+
+  use super::*;
+
+  /// Records lines logged at verbosity 3, with their targets, as the global
+  /// logger.
+  struct TestLogger {
+    logger: Logger,
+    lines:  Mutex<Vec<(String, String)>>,
+  }
+
+  impl Log for TestLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+      self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+      if self.logger.matches(record) {
+        self
+          .lines
+          .lock()
+          .unwrap()
+          .push((record.target().to_string(), record.args().to_string()));
+      }
+    }
+
+    fn flush(&self) {}
+  }
+
+  /// Installs the `TestLogger` on first use, shared by all tests.
+  fn test_logger() -> &'static TestLogger {
+    static LOGGER: OnceLock<&'static TestLogger> = OnceLock::new();
+    LOGGER.get_or_init(|| {
+      let logger = Box::leak(Box::new(TestLogger {
+        logger: make_builder(3).build(),
+        lines:  Mutex::new(Vec::new()),
+      }));
+      log::set_max_level(logger.logger.filter());
+      log::set_logger(logger).unwrap();
+      logger
+    })
+  }
+
+  #[test]
+  fn logs_exiftool_command_line_at_verbosity_3() {
+    let logger = test_logger();
+
+    // The command line is logged before running, so even if that fails.
+    let _ = io::run_exiftool(&io::ExifToolConfig::default(), None::<&Path>, [
+      "-ver",
+      "logs_command_line",
+    ]);
+
+    let lines = logger.lines.lock().unwrap();
+    assert!(lines.contains(&(
+      io::LOG_TARGET_EXIFTOOL.to_string(),
+      "exiftool -ver logs_command_line".to_string()
+    )));
+  }
+
+  #[test]
+  fn filters_exiftool_command_line_below_verbosity_3() {
+    let record = Record::builder()
+      .level(Level::Trace)
+      .target(io::LOG_TARGET_EXIFTOOL)
+      .args(format_args!("exiftool -ver"))
+      .build();
+
+    assert!(make_builder(3).build().matches(&record));
+    assert!(!make_builder(2).build().matches(&record));
+    assert!(
+      make_builder(2)
+        .build()
+        .matches(&Record::builder().level(Level::Trace).target("c1a").build())
+    );
+  }
+}
+
+#[cfg(test)]
+mod test_load_config_args {
+  use std::env;
+
+  use super::*;
+  use crate::testing::*;
+
+  fn write_config(name: &str, contents: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!(
+      "{}_tests/setup_test_load_config_args_{name}.json",
+      env!("CARGO_PKG_NAME")
+    ));
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, contents).unwrap();
+    path
+  }
+
+  #[test]
+  fn converts_flags_to_args() {
+    let path = write_config(
+      "converts",
+      r#"{
+        "copyright-year": true,
+        "require-label": false,
+        "min-megapixels": 12,
+        "counter": "numeric",
+        "exclude": ["tmp", "-dashed"],
+        "max-depth": null
+      }"#,
+    );
+
+    assert_eq!(load_config_args(&path, &|_| false).unwrap(), [
+      "--copyright-year",
+      "--counter=numeric",
+      "--exclude=tmp",
+      "--exclude=-dashed",
+      "--min-megapixels=12",
+    ]);
+  }
+
+  #[test]
+  fn skips_flags_given() {
+    let path = write_config("skips", r#"{ "min-megapixels": 12, "counter": "numeric" }"#);
+
+    assert_eq!(
+      load_config_args(&path, &|id| id == "min_megapixels").unwrap(),
+      ["--counter=numeric"]
+    );
+  }
+
+  #[test]
+  fn errors_if_invalid() {
+    let path = write_config("nested", r#"{ "exclude": [["tmp"]] }"#);
+    assert_err!(
+      load_config_args(&path, &|_| false),
+      "Invalid value for exclude"
+    );
+
+    let path = write_config("not_object", "[]");
+    assert_err!(
+      load_config_args(&path, &|_| false),
+      "Unable to parse config"
+    );
+
+    assert_err!(
+      load_config_args(Path::new("/path/does/not/exist.json"), &|_| false),
+      "Unable to read config"
+    );
+  }
+}
@@ -23,7 +23,7 @@ macro_rules! assert_err {
     };
 
     assert!(
-      e.contains($msg),
+      e.to_string().contains($msg),
       "Error message did not contain expected substring.\nActual:\n{e}\nExpected:\n{}",
       $msg
     );
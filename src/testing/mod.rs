@@ -35,7 +35,7 @@ pub fn read_tag(
     tag_str.as_str(),
     path.as_ref().to_str().unwrap(),
   ];
-  let stdout = io::run_exiftool(Some(working_dir), args).unwrap();
+  let stdout = io::run_exiftool(&io::ExifToolConfig::default(), Some(working_dir), args).unwrap();
 
   let metadata = serde_json::from_slice::<Vec<HashMap<String, Value>>>(&stdout).unwrap();
   metadata[0]
@@ -44,13 +44,22 @@ pub fn read_tag(
     .map(std::string::ToString::to_string)
 }
 
+/// A 1x1 pixel PNG, as there is no PNG asset to create files from.
+pub const PNG: &[u8] = &[
+  0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+  0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00, 0x00, 0x3a, 0x7e, 0x9b,
+  0x55, 0x00, 0x00, 0x00, 0x0a, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x60, 0x00, 0x00, 0x00,
+  0x02, 0x00, 0x01, 0x48, 0xaf, 0xa4, 0x71, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae,
+  0x42, 0x60, 0x82,
+];
+
 pub fn type_of<T>(_: T) -> &'static str {
   std::any::type_name::<T>()
 }
 
 #[macro_export]
 macro_rules! metadata {
-  ($($key:literal: $value:literal),* $(,)?) => {
+  ($($key:literal: $value:tt),* $(,)?) => {
     serde_json::from_value::<$crate::prim::Metadata>(
       serde_json::json!({
         "SourceFile": "-",
@@ -53,8 +53,11 @@ impl TestDir {
     traverse_dir(&self.root, Some(&self.trash))
   }
 
+  /// Files in trash, excluding records of when they were trashed.
   pub fn files_trash(&self) -> HashSet<PathBuf> {
-    traverse_dir(&self.trash, None::<&Path>)
+    let mut files = traverse_dir(&self.trash, None::<&Path>);
+    files.retain(|f| f.file_name().is_none_or(|n| n != io::TRASH_RECORD));
+    files
   }
 
   pub fn get_path(&self, file: impl AsRef<Path>) -> PathBuf {
@@ -101,18 +104,24 @@ fn create_file(working_dir: impl AsRef<Path>, path: impl AsRef<Path>, tags: Hash
     );
   }
   test_asset.push(".");
-  test_asset.push(ext);
+  test_asset.push(&ext);
   fs::copy(ASSET_ROOT.join(test_asset), &full_path).unwrap();
 
+  // `ExifTool` can't write to adjustment sidecars.
+  if ext == "aae" {
+    assert!(tags.is_empty(), "Tags can't be set on `.aae` files.");
+    return;
+  }
+
   // Create ExifTool commands to set tags.
   let mut args = tags
     .iter()
     .map(|(k, v)| OsString::from(format!("-{k}={v}")))
     .collect::<Vec<_>>();
-  // If `ContentIdentifier` isn't manually set, strip out the tag from the test
-  // file. Required because if the test file doesn't have the tag, ExifTool will
-  // not allow it to be added.
-  if !tags.contains_key("ContentIdentifier") {
+  // If `ContentIdentifier` or `BurstUUID` isn't manually set, strip out the
+  // Apple maker notes from the test file. Required because if the test file
+  // doesn't have the tags, ExifTool will not allow them to be added.
+  if !tags.contains_key("ContentIdentifier") && !tags.contains_key("BurstUUID") {
     args.push(OsString::from("-MakerNotes="));
   }
 
@@ -122,7 +131,7 @@ fn create_file(working_dir: impl AsRef<Path>, path: impl AsRef<Path>, tags: Hash
     path.as_ref().as_os_str().to_os_string(),
   ]);
 
-  io::run_exiftool(Some(working_dir), args).unwrap();
+  io::run_exiftool(&io::ExifToolConfig::default(), Some(working_dir), args).unwrap();
 }
 
 fn traverse_dir<P: AsRef<Path>, Q: AsRef<Path>>(root: P, exclude: Option<Q>) -> HashSet<PathBuf> {